@@ -0,0 +1,498 @@
+//! [`Index`] records every entry of an archive by path, so that the archive's
+//! headers only have to be read once.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
+    io,
+    path::{Component, Path, PathBuf},
+    time::{Duration, UNIX_EPOCH}
+};
+
+use crate::{FileType, Metadata, Permissions};
+
+/// The maximum number of symlinks that are followed while resolving a single
+/// path, matching Linux's limit.
+const MAX_SYMLINKS: usize = 40;
+
+/// The mode of directories that only exist because an entry is inside them.
+const IMPLIED_DIR_MODE: u32 = 0o755;
+
+pub(crate) fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such file or directory")
+}
+
+#[cfg(feature = "zip")]
+pub(crate) fn already_exists() -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, "file exists")
+}
+
+pub(crate) fn not_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "not a directory")
+}
+
+pub(crate) fn is_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "is a directory")
+}
+
+fn too_many_links() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "too many levels of symbolic links")
+}
+
+pub(crate) fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(unix)]
+pub(crate) fn os_string(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn os_string(bytes: Vec<u8>) -> OsString {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
+#[cfg(all(unix, feature = "zip"))]
+pub(crate) fn os_bytes(name: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    name.as_bytes().to_vec()
+}
+
+#[cfg(all(not(unix), feature = "zip"))]
+pub(crate) fn os_bytes(name: &std::ffi::OsStr) -> Vec<u8> {
+    name.to_string_lossy().into_owned().into_bytes()
+}
+
+/// Turns a name from an archive into an absolute path, resolving `.` and
+/// `..` lexically so that no entry can land outside the root.
+pub(crate) fn archive_path(name: &[u8]) -> PathBuf {
+    let mut path = PathBuf::from("/");
+    for component in name.split(|&b| b == b'/') {
+        match component {
+            b"" | b"." => {}
+            b".." => {
+                path.pop();
+            }
+            _ => path.push(os_string(component.to_vec()))
+        }
+    }
+    path
+}
+
+/// What an [`Entry`] is.
+#[derive(Debug, Clone)]
+pub(crate) enum Kind {
+    /// A regular file that is `len` bytes long.  `data` is where the format
+    /// finds its contents.
+    File { data: u64, len: u64 },
+
+    /// A directory, with the names of its entries.
+    Dir { children: BTreeSet<OsString> },
+
+    /// A symlink, with its target exactly as it was recorded.
+    Symlink(PathBuf),
+
+    /// A device or FIFO, which has no data.
+    Special(FileType)
+}
+
+impl Kind {
+    pub(crate) fn dir() -> Self {
+        Kind::Dir { children: BTreeSet::new() }
+    }
+}
+
+/// The attributes of an entry, as read from its headers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Attributes {
+    pub(crate) mode: u32,
+    pub(crate) uid: Option<u32>,
+    pub(crate) gid: Option<u32>,
+
+    /// Seconds since the Unix epoch.
+    pub(crate) mtime: Option<u64>
+}
+
+/// An entry of an archive.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub(crate) kind: Kind,
+    attributes: Attributes,
+
+    /// Shared by an entry and its hard links.
+    ino: u64,
+    nlink: u64
+}
+
+impl Entry {
+    fn implied_dir(ino: u64) -> Self {
+        Entry { kind: Kind::dir(),
+                attributes: Attributes { mode: IMPLIED_DIR_MODE,
+                                         uid: None,
+                                         gid: None,
+                                         mtime: None },
+                ino,
+                nlink: 1 }
+    }
+
+    pub(crate) fn metadata(&self) -> Metadata {
+        let (file_type, len) = match &self.kind {
+            Kind::File { len, .. } => (FileType::File, *len),
+            Kind::Dir { .. } => (FileType::Dir, 0),
+            Kind::Symlink(target) => {
+                (FileType::Symlink, target.as_os_str().len() as u64)
+            }
+            Kind::Special(file_type) => (*file_type, 0)
+        };
+        let Attributes { mode,
+                         uid,
+                         gid,
+                         mtime } = self.attributes;
+        let permissions = Permissions::from_mode(mode);
+        let mut metadata =
+            Metadata::new(file_type, len).with_permissions(permissions)
+                                         .with_ino(self.ino)
+                                         .with_nlink(self.nlink);
+        if let Some(uid) = uid {
+            metadata = metadata.with_uid(uid);
+        }
+        if let Some(gid) = gid {
+            metadata = metadata.with_gid(gid);
+        }
+        if let Some(mtime) = mtime {
+            metadata =
+                metadata.with_modified(UNIX_EPOCH + Duration::from_secs(mtime));
+        }
+        metadata
+    }
+}
+
+/// Every entry of an archive, by absolute path.
+///
+/// Directories that hold entries but have no entries of their own are implied,
+/// and an entry for a path that is already indexed replaces the earlier one,
+/// just like when the archive is extracted.
+#[derive(Debug)]
+pub(crate) struct Index {
+    entries: BTreeMap<PathBuf, Entry>,
+    next_ino: u64
+}
+
+impl Index {
+    pub(crate) fn new() -> Self {
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::from("/"), Entry::implied_dir(1));
+        Index { entries,
+                next_ino: 2 }
+    }
+
+    /// Adds an entry, replacing any earlier entry with the same path.
+    pub(crate) fn insert(&mut self,
+                         path: PathBuf,
+                         kind: Kind,
+                         attributes: Attributes)
+                         -> io::Result<()> {
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        let entry = Entry { kind,
+                            attributes,
+                            ino,
+                            nlink: 1 };
+        self.put(path, entry)
+    }
+
+    /// Adds a hard link to the entry at `target`.
+    #[cfg(feature = "tar")]
+    pub(crate) fn link(&mut self,
+                       path: PathBuf,
+                       target: &Path)
+                       -> io::Result<()> {
+        let entry = match self.entries.get(target) {
+            Some(entry) if !matches!(entry.kind, Kind::Dir { .. }) => {
+                entry.clone()
+            }
+            Some(_) => return Err(invalid_data("hard link to a directory")),
+            None => return Err(invalid_data("hard link to a missing entry"))
+        };
+        self.put(path, entry)
+    }
+
+    fn put(&mut self, path: PathBuf, mut entry: Entry) -> io::Result<()> {
+        let parent = match path.parent() {
+            Some(parent) => parent.to_owned(),
+            None => {
+                // An entry for the root itself only updates its attributes.
+                if let Kind::Dir { .. } = entry.kind {
+                    let root =
+                        self.entries.get_mut(&path).expect("root exists");
+                    entry.kind = root.kind.clone();
+                    entry.ino = root.ino;
+                    *root = entry;
+                }
+                return Ok(());
+            }
+        };
+        self.implied_dirs(&parent)?;
+        match self.entries.get_mut(&path) {
+            Some(old) => {
+                if let (Kind::Dir { children }, Kind::Dir { .. }) =
+                    (&old.kind, &entry.kind)
+                {
+                    entry.kind = Kind::Dir { children: children.clone() };
+                    entry.ino = old.ino;
+                } else {
+                    self.remove_children(&path);
+                }
+            }
+            None => {
+                if let Some(Kind::Dir { children }) =
+                    self.entries.get_mut(&parent).map(|parent| &mut parent.kind)
+                {
+                    let name = path.file_name().expect("not the root");
+                    children.insert(name.to_owned());
+                }
+            }
+        }
+        self.entries.insert(path, entry);
+        Ok(())
+    }
+
+    /// Creates the directories above an entry that have no entries of their
+    /// own.
+    fn implied_dirs(&mut self, dir: &Path) -> io::Result<()> {
+        match self.entries.get(dir).map(|entry| &entry.kind) {
+            Some(Kind::Dir { .. }) => return Ok(()),
+            Some(_) => {
+                return Err(invalid_data("archive entry is inside a \
+                                         non-directory"))
+            }
+            None => {}
+        }
+        let parent = dir.parent().expect("the root always exists");
+        self.implied_dirs(parent)?;
+        let entry = Entry::implied_dir(self.next_ino);
+        self.next_ino += 1;
+        if let Some(Kind::Dir { children }) =
+            self.entries.get_mut(parent).map(|parent| &mut parent.kind)
+        {
+            children.insert(dir.file_name().expect("not the root").to_owned());
+        }
+        self.entries.insert(dir.to_owned(), entry);
+        Ok(())
+    }
+
+    /// Removes everything below `dir`, when a directory is replaced by a
+    /// later entry that isn't one.
+    fn remove_children(&mut self, dir: &Path) {
+        let below = self.entries
+                        .range(dir.to_owned()..)
+                        .skip(1)
+                        .take_while(|(path, _)| path.starts_with(dir))
+                        .map(|(path, _)| path.clone())
+                        .collect::<Vec<_>>();
+        for path in below {
+            self.entries.remove(&path);
+        }
+    }
+
+    /// Sets the link count of every entry to the number of entries that
+    /// share its inode, once every hard link has been added.
+    #[cfg(feature = "tar")]
+    pub(crate) fn count_links(&mut self) {
+        let mut counts = BTreeMap::new();
+        for entry in self.entries.values() {
+            *counts.entry(entry.ino).or_insert(0) += 1;
+        }
+        for entry in self.entries.values_mut() {
+            entry.nlink = counts[&entry.ino];
+        }
+    }
+
+    /// Finds the entry at `path`, following symlinks along the way, and the
+    /// final one too if `follow` is set.  Returns the path of the entry with
+    /// every symlink resolved.
+    pub(crate) fn resolve(&self,
+                          path: &Path,
+                          follow: bool)
+                          -> io::Result<(PathBuf, &Entry)> {
+        let mut pending = Vec::new();
+        push_components(&mut pending, path);
+        let mut current = PathBuf::from("/");
+        let mut links = 0;
+        while let Some(component) = pending.pop() {
+            if component == ".." {
+                current.pop();
+                continue;
+            }
+            let next = current.join(&component);
+            let entry = self.entries.get(&next).ok_or_else(not_found)?;
+            match &entry.kind {
+                Kind::Symlink(target) if follow || !pending.is_empty() => {
+                    links += 1;
+                    if links > MAX_SYMLINKS {
+                        return Err(too_many_links());
+                    }
+                    if target.has_root() {
+                        current = PathBuf::from("/");
+                    }
+                    push_components(&mut pending, target);
+                }
+                Kind::Dir { .. } => current = next,
+                _ if pending.is_empty() => current = next,
+                _ => return Err(not_a_directory())
+            }
+        }
+        let entry = &self.entries[&current];
+        Ok((current, entry))
+    }
+
+    /// Returns the entries of the directory at `path`, which must already be
+    /// resolved, with their names.
+    pub(crate) fn children<'a>(
+        &'a self,
+        dir: &'a Path)
+        -> io::Result<impl Iterator<Item = (&'a OsString, &'a Entry)> + 'a>
+    {
+        let children = match self.entries.get(dir).map(|entry| &entry.kind) {
+            Some(Kind::Dir { children }) => children,
+            Some(_) => return Err(not_a_directory()),
+            None => return Err(not_found())
+        };
+        Ok(children.iter()
+                   .map(move |name| (name, &self.entries[&dir.join(name)])))
+    }
+}
+
+/// Pushes the components of `path` onto `pending` in reverse order, so that
+/// popping them returns them in order.  Root and `.` components are dropped.
+fn push_components(pending: &mut Vec<OsString>, path: &Path) {
+    for component in path.components().rev() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Normal(_) => {
+                pending.push(component.as_os_str().to_owned())
+            }
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTRIBUTES: Attributes = Attributes { mode: 0o644,
+                                                uid: Some(1000),
+                                                gid: None,
+                                                mtime: Some(1_000_000_000) };
+
+    fn file(len: u64) -> Kind {
+        Kind::File { data: 0, len }
+    }
+
+    fn len(index: &Index, path: &str) -> u64 {
+        index.resolve(Path::new(path), true)
+             .unwrap()
+             .1
+             .metadata()
+             .len()
+    }
+
+    #[test]
+    fn parents_are_implied() {
+        let mut index = Index::new();
+        index.insert("/a/b/c".into(), file(5), ATTRIBUTES).unwrap();
+        index.insert("/d".into(), Kind::dir(), ATTRIBUTES).unwrap();
+
+        let names = index.children(Path::new("/"))
+                         .unwrap()
+                         .map(|(name, _)| name.clone())
+                         .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "d"]);
+        let implied = index.resolve(Path::new("/a/b"), true).unwrap().1;
+        assert_eq!(implied.metadata().permissions().mode(), Some(0o755));
+        let metadata = index.resolve(Path::new("/a/b/c"), true)
+                            .unwrap()
+                            .1
+                            .metadata();
+        assert_eq!(metadata.len(), 5);
+        assert_eq!(metadata.uid(), Some(1000));
+        assert_eq!(metadata.gid(), None);
+        assert_eq!(metadata.modified().unwrap(),
+                   UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+    }
+
+    #[test]
+    fn later_entries_replace_earlier_ones() {
+        let mut index = Index::new();
+        index.insert("/dir/inner".into(), file(0), ATTRIBUTES)
+             .unwrap();
+        index.insert("/a".into(), file(3), ATTRIBUTES).unwrap();
+        index.insert("/a".into(), file(5), ATTRIBUTES).unwrap();
+        index.insert("/dir".into(), file(1), ATTRIBUTES).unwrap();
+        assert_eq!(len(&index, "/a"), 5);
+        assert_eq!(len(&index, "/dir"), 1);
+        assert!(index.resolve(Path::new("/dir/inner"), false).is_err());
+
+        let err = index.insert("/a/b".into(), file(0), ATTRIBUTES)
+                       .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn hard_links_share_an_inode() {
+        let mut index = Index::new();
+        index.insert("/a".into(), file(4), ATTRIBUTES).unwrap();
+        index.link("/b".into(), Path::new("/a")).unwrap();
+        index.count_links();
+        let a = index.resolve(Path::new("/a"), false).unwrap().1.metadata();
+        let b = index.resolve(Path::new("/b"), false).unwrap().1.metadata();
+        assert_eq!(a.ino(), b.ino());
+        assert_eq!(a.nlink(), Some(2));
+
+        let err = index.link("/c".into(), Path::new("/missing")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn symlinks_are_resolved() {
+        let mut index = Index::new();
+        index.insert("/real/file".into(), file(1), ATTRIBUTES)
+             .unwrap();
+        for (link, target) in
+            [("/rel", "real"), ("/abs", "/rel/file"), ("/loop", "loop")]
+        {
+            index.insert(link.into(), Kind::Symlink(target.into()), ATTRIBUTES)
+                 .unwrap();
+        }
+        let (path, _) = index.resolve(Path::new("/abs"), true).unwrap();
+        assert_eq!(path, Path::new("/real/file"));
+        let (path, entry) = index.resolve(Path::new("abs"), false).unwrap();
+        assert_eq!(path, Path::new("/abs"));
+        assert!(entry.metadata().is_symlink());
+        let err = index.resolve(Path::new("/loop"), true).unwrap_err();
+        assert_eq!(err.to_string(), "too many levels of symbolic links");
+        let err = index.resolve(Path::new("/real/file/x"), true).unwrap_err();
+        assert_eq!(err.to_string(), "not a directory");
+    }
+
+    #[test]
+    fn archive_paths_stay_inside_the_root() {
+        assert_eq!(archive_path(b"./a//b/"), Path::new("/a/b"));
+        assert_eq!(archive_path(b"../../etc/passwd"), Path::new("/etc/passwd"));
+        assert_eq!(archive_path(b"a/../../b"), Path::new("/b"));
+    }
+}
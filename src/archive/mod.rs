@@ -0,0 +1,20 @@
+//! Shared machinery for backends that serve the contents of an archive.
+//!
+//! An archive's entries are read once into an [`Index`], which maps every
+//! path to what is there and resolves symlinks between entries.  The data of
+//! a regular file is then read through a [`Region`] of the archive's bytes.
+//! How the index is built, and where each file's data is found, is up to the
+//! format.
+
+mod index;
+mod region;
+
+#[cfg(feature = "zip")]
+pub(crate) use index::{already_exists, not_a_directory, not_found, os_bytes};
+pub(crate) use index::{
+    archive_path, invalid_data, is_a_directory, os_string, Attributes, Index,
+    Kind
+};
+#[cfg(feature = "tar")]
+pub(crate) use region::truncated;
+pub(crate) use region::{Archive, Region, Source};
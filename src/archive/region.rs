@@ -0,0 +1,255 @@
+//! [`Region`] reads one entry's data out of the bytes of an archive.
+
+use std::{
+    fmt,
+    future::Future,
+    io::{self, SeekFrom},
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll}
+};
+
+use async_lock::Mutex as AsyncMutex;
+use futures_io::{AsyncRead, AsyncSeek};
+use futures_lite::{AsyncReadExt, AsyncSeekExt};
+
+/// The most that is read from the archive at once.
+const MAX_READ: usize = 64 * 1024;
+
+pub(crate) fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "archive is truncated")
+}
+
+/// A seekable reader of an archive.
+pub(crate) trait Archive: AsyncRead + AsyncSeek + Send + Unpin {}
+
+impl<T> Archive for T where T: AsyncRead + AsyncSeek + Send + Unpin {}
+
+type ReadFuture = Pin<Box<dyn Future<Output = io::Result<Vec<u8>>> + Send>>;
+
+/// The bytes of an archive.
+#[derive(Clone)]
+pub(crate) enum Source {
+    /// Bytes that were read, and possibly decompressed, into memory.
+    Memory(Arc<Vec<u8>>),
+
+    /// An archive that is read on demand, seeking to each read.
+    Reader(Arc<AsyncMutex<Box<dyn Archive>>>)
+}
+
+impl Source {
+    pub(crate) fn reader<R>(reader: R) -> Self
+        where R: Archive + 'static
+    {
+        Source::Reader(Arc::new(AsyncMutex::new(Box::new(reader))))
+    }
+
+    /// Reads `len` bytes at `offset`.
+    pub(crate) fn read_at(&self, offset: u64, len: usize) -> ReadFuture {
+        let source = self.clone();
+        Box::pin(async move {
+            match source {
+                Source::Memory(bytes) => {
+                    let start = usize::try_from(offset).unwrap_or(usize::MAX);
+                    start.checked_add(len)
+                         .and_then(|end| bytes.get(start..end))
+                         .map(<[u8]>::to_vec)
+                         .ok_or_else(truncated)
+                }
+                Source::Reader(reader) => {
+                    let mut reader = reader.lock_arc().await;
+                    reader.seek(SeekFrom::Start(offset)).await?;
+                    let mut data = vec![0; len];
+                    reader.read_exact(&mut data).await?;
+                    Ok(data)
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Memory(bytes) => {
+                f.debug_struct("Memory").field("len", &bytes.len()).finish()
+            }
+            Source::Reader(_) => {
+                f.debug_struct("Reader").finish_non_exhaustive()
+            }
+        }
+    }
+}
+
+/// The `len` bytes at `offset` of a [`Source`], as a seekable reader.
+///
+/// Every read goes straight to the source, so any part of the region can be
+/// read without reading what comes before it.
+pub(crate) struct Region {
+    source: Source,
+    offset: u64,
+    len: u64,
+    pos: u64,
+
+    /// A read from the source that hasn't finished yet.
+    ///
+    /// The future is kept behind a [`Mutex`] only so that the region stays
+    /// `Sync`; it is only ever reached through `&mut self`.
+    read: Mutex<Option<ReadFuture>>,
+
+    /// Bytes at `pos` that were read from the source, but didn't fit in the
+    /// caller's buffer.
+    leftover: Vec<u8>
+}
+
+impl Region {
+    pub(crate) fn new(source: Source, offset: u64, len: u64) -> Self {
+        Region { source,
+                 offset,
+                 len,
+                 pos: 0,
+                 read: Mutex::new(None),
+                 leftover: Vec::new() }
+    }
+
+    /// Hands out as much of `data` as fits in `buf`, and keeps the rest.
+    fn deliver(&mut self, mut data: Vec<u8>, buf: &mut [u8]) -> usize {
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.leftover = data.split_off(n);
+        self.pos += n as u64;
+        n
+    }
+}
+
+impl fmt::Debug for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Region")
+         .field("offset", &self.offset)
+         .field("len", &self.len)
+         .field("pos", &self.pos)
+         .finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for Region {
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if !this.leftover.is_empty() {
+            let leftover = std::mem::take(&mut this.leftover);
+            return Poll::Ready(Ok(this.deliver(leftover, buf)));
+        }
+        if this.pos >= this.len {
+            return Poll::Ready(Ok(0));
+        }
+        let slot = this.read.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let future = slot.get_or_insert_with(|| {
+                             let remaining = this.len - this.pos;
+                             let len = buf.len()
+                                          .min(MAX_READ)
+                                          .min(remaining.try_into()
+                                                        .unwrap_or(usize::MAX));
+                             this.source.read_at(this.offset + this.pos, len)
+                         });
+        let result = match Future::poll(future.as_mut(), cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending
+        };
+        *slot = None;
+        Poll::Ready(result.map(|data| this.deliver(data, buf)))
+    }
+}
+
+impl AsyncSeek for Region {
+    fn poll_seek(self: Pin<&mut Self>,
+                 _cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let (base, delta) = match pos {
+            SeekFrom::Start(pos) => (pos, 0),
+            SeekFrom::End(delta) => (this.len, delta),
+            SeekFrom::Current(delta) => (this.pos, delta)
+        };
+        let pos = match delta >= 0 {
+            true => base.checked_add(delta as u64),
+            false => base.checked_sub(delta.unsigned_abs())
+        };
+        let pos = match pos {
+            Some(pos) => pos,
+            None => {
+                let error = io::Error::new(io::ErrorKind::InvalidInput,
+                                           "invalid seek to a negative or \
+                                            overflowing position");
+                return Poll::Ready(Err(error));
+            }
+        };
+        // Whatever was read ahead was read at the old position.
+        *this.read.get_mut().unwrap_or_else(PoisonError::into_inner) = None;
+        this.leftover.clear();
+        this.pos = pos;
+        Poll::Ready(Ok(pos))
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, io::Cursor};
+
+    use super::*;
+
+    async fn read_at(region: &mut Region, pos: u64, len: usize) -> Vec<u8> {
+        region.seek(SeekFrom::Start(pos)).await.unwrap();
+        let mut buf = vec![0; len];
+        let read = region.read(&mut buf).await.unwrap();
+        buf.truncate(read);
+        buf
+    }
+
+    #[test]
+    fn regions_read_their_window() {
+        block_on(async {
+            let bytes = (0..=255u8).collect::<Vec<_>>();
+            let sources = [Source::Memory(Arc::new(bytes.clone())),
+                           Source::reader(Cursor::new(bytes))];
+            for source in sources {
+                let mut region = Region::new(source, 100, 50);
+                assert_eq!(read_at(&mut region, 0, 2).await, [100, 101]);
+                assert_eq!(read_at(&mut region, 48, 10).await, [148, 149]);
+                assert_eq!(read_at(&mut region, 50, 10).await, []);
+
+                let mut all = Vec::new();
+                region.seek(SeekFrom::End(-50)).await.unwrap();
+                region.read_to_end(&mut all).await.unwrap();
+                assert_eq!(all, (100..150).collect::<Vec<u8>>());
+                assert!(region.seek(SeekFrom::Current(-51)).await.is_err());
+            }
+        });
+    }
+
+    #[test]
+    fn truncated_sources_fail() {
+        block_on(async {
+            let mut region =
+                Region::new(Source::Memory(Arc::new(vec![0; 10])), 5, 10);
+            let mut all = Vec::new();
+            let err = region.read_to_end(&mut all).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        });
+    }
+}
@@ -0,0 +1,174 @@
+//! Directory types for [`async_std::fs`].
+//!
+//! [`AsyncStdDirBuilder`] creates directories, and [`AsyncStdReadDir`] streams
+//! the [`AsyncStdDirEntry`]s of an existing directory.
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::{ready, Stream};
+
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncReadDirTrait, FileType,
+    Metadata
+};
+
+/// A builder for creating directories through [`async_std::fs`].
+///
+/// This is a thin wrapper around [`async_std::fs::DirBuilder`].
+#[derive(Debug, Default)]
+pub struct AsyncStdDirBuilder {
+    inner: async_std::fs::DirBuilder
+}
+
+impl AsyncStdDirBuilder {
+    /// Creates a blank set of options.
+    ///
+    /// The [`recursive()`][1] option is initially set to `false`.
+    ///
+    /// [1]: AsyncDirBuilderTrait::recursive
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the mode to create new directories with.
+    ///
+    /// This option defaults to `0o777`, modified by the process's umask.
+    #[cfg(unix)]
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        use async_std::os::unix::fs::DirBuilderExt;
+
+        self.inner.mode(mode);
+        self
+    }
+}
+
+impl From<async_std::fs::DirBuilder> for AsyncStdDirBuilder {
+    fn from(inner: async_std::fs::DirBuilder) -> Self {
+        AsyncStdDirBuilder { inner }
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for AsyncStdDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.inner.recursive(recursive);
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.create(path.as_ref()).await
+    }
+}
+
+/// A stream of entries in a directory, as seen through [`async_std::fs`].
+#[derive(Debug)]
+pub struct AsyncStdReadDir {
+    inner: async_std::fs::ReadDir
+}
+
+impl From<async_std::fs::ReadDir> for AsyncStdReadDir {
+    fn from(inner: async_std::fs::ReadDir) -> Self {
+        AsyncStdReadDir { inner }
+    }
+}
+
+impl Stream for AsyncStdReadDir {
+    type Item = io::Result<AsyncStdDirEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let entry = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        Poll::Ready(entry.map(|entry| entry.map(AsyncStdDirEntry::from)))
+    }
+}
+
+impl AsyncReadDirTrait<AsyncStdDirEntry> for AsyncStdReadDir {}
+
+/// An entry in a directory, as seen through [`async_std::fs`].
+///
+/// [`path()`][1] and [`file_name()`][2] never touch the disk.
+///
+/// [1]: AsyncDirEntryTrait::path
+/// [2]: AsyncDirEntryTrait::file_name
+#[derive(Debug, Clone)]
+pub struct AsyncStdDirEntry {
+    inner: async_std::fs::DirEntry
+}
+
+impl From<async_std::fs::DirEntry> for AsyncStdDirEntry {
+    fn from(inner: async_std::fs::DirEntry) -> Self {
+        AsyncStdDirEntry { inner }
+    }
+}
+
+#[async_trait]
+impl AsyncDirEntryTrait for AsyncStdDirEntry {
+    async fn path(&self) -> PathBuf {
+        self.inner.path().into()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        // `async_std::fs::DirEntry::metadata()` doesn't follow symlinks, but
+        // this trait method is documented to, so go through the path instead.
+        Ok(async_std::fs::metadata(self.inner.path()).await?.into())
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.inner.file_type().await?.into())
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.inner.file_name()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{async_std_fs::AsyncStdFs, AsyncFsTrait};
+
+    #[test]
+    fn recursive_create_and_read_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+
+        let fs = AsyncStdFs::new();
+        block_on(async {
+            assert!(fs.dir_builder().create(&nested).await.is_err());
+            fs.dir_builder()
+              .recursive(true)
+              .create(&nested)
+              .await
+              .unwrap();
+
+            let mut entries = fs.read_dir(dir.path()).await.unwrap();
+            let entry = entries.next().await.unwrap().unwrap();
+            assert!(entries.next().await.is_none());
+
+            assert_eq!(entry.file_name().await, OsString::from("a"));
+            assert_eq!(entry.path().await, dir.path().join("a"));
+            assert!(entry.file_type().await.unwrap().is_dir());
+        });
+    }
+}
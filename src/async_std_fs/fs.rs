@@ -0,0 +1,230 @@
+//! [`AsyncStdFs`] implements [`AsyncFsTrait`] on top of [`async_std::fs`].
+
+use std::{
+    io,
+    path::{Path, PathBuf}
+};
+
+use async_trait::async_trait;
+
+use super::{
+    AsyncStdDirBuilder, AsyncStdDirEntry, AsyncStdOpenOptions, AsyncStdReadDir
+};
+use crate::{
+    metadata::to_std_permissions, AsyncFsTrait, AsyncSymLinkTrait, Metadata,
+    Permissions
+};
+
+/// The operating system's filesystem, as seen through [`async_std::fs`].
+///
+/// Every method is a thin wrapper around the function of the same name in
+/// [`async_std::fs`].  Relative paths are resolved against the process's
+/// current working directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AsyncStdFs;
+
+impl AsyncStdFs {
+    /// Creates a handle to the operating system's filesystem.
+    pub fn new() -> Self {
+        AsyncStdFs
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for AsyncStdFs {
+    type DirBuilder = AsyncStdDirBuilder;
+    type DirEntry = AsyncStdDirEntry;
+    type FileBuilder = AsyncStdOpenOptions;
+    type ReadDir = AsyncStdReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        AsyncStdDirBuilder::new()
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        AsyncStdOpenOptions::new()
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_std::fs::canonicalize(path.as_ref()).await?.into())
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        async_std::fs::rename(src.as_ref(), dst.as_ref()).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let current = async_std::fs::metadata(path).await?.permissions();
+        let std_perm = to_std_permissions(perm, || Ok(current))?;
+        async_std::fs::set_permissions(path, std_perm).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        async_std::fs::hard_link(src.as_ref(), dst.as_ref()).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_std::fs::read_link(path.as_ref()).await?.into())
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_std::fs::symlink_metadata(path.as_ref()).await?.into())
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_std::fs::metadata(path.as_ref()).await?.into())
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        async_std::fs::copy(src.as_ref(), dst.as_ref()).await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        async_std::fs::remove_file(path.as_ref()).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_std::fs::read_dir(path.as_ref()).await?.into())
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        async_std::fs::remove_dir(path.as_ref()).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        async_std::fs::remove_dir_all(path.as_ref()).await
+    }
+}
+
+#[async_trait]
+impl AsyncSymLinkTrait for AsyncStdFs {
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        symlink(src.as_ref().to_owned(), dst.as_ref().to_owned()).await
+    }
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// Note that the argument order follows [`AsyncSymLinkTrait::symlink()`],
+/// which is the reverse of the platform-specific functions in async-std.
+#[cfg(unix)]
+async fn symlink(src: PathBuf, dst: PathBuf) -> io::Result<()> {
+    async_std::os::unix::fs::symlink(dst, src).await
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// async-std only offers Windows symlinks behind its `unstable` feature, so
+/// the standard library's functions are run on async-std's blocking thread
+/// pool instead.  A relative `dst` is resolved against the parent of `src` to
+/// find out whether it is a directory; targets that don't exist are linked as
+/// files.
+#[cfg(windows)]
+async fn symlink(src: PathBuf, dst: PathBuf) -> io::Result<()> {
+    async_std::task::spawn_blocking(move || {
+        let target = match src.parent() {
+            Some(parent) => parent.join(&dst),
+            None => dst.clone()
+        };
+        if std::fs::metadata(target).map(|m| m.is_dir())
+                                    .unwrap_or(false)
+        {
+            std::os::windows::fs::symlink_dir(dst, src)
+        } else {
+            std::os::windows::fs::symlink_file(dst, src)
+        }
+    }).await
+}
+
+/// Symlinks aren't supported on this platform.
+#[cfg(not(any(unix, windows)))]
+async fn symlink(_src: PathBuf, _dst: PathBuf) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "symlinks are not supported on this platform"))
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    #[test]
+    fn rename_overwrites_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        std::fs::write(&src, b"new").unwrap();
+        std::fs::write(&dst, b"old").unwrap();
+
+        block_on(AsyncStdFs::new().rename(&src, &dst)).unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(std::fs::read(&dst).unwrap(), b"new");
+    }
+
+    #[test]
+    fn symlink_and_set_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        std::fs::write(&target, b"12345").unwrap();
+
+        let fs = AsyncStdFs::new();
+        block_on(async {
+            fs.symlink(&link, "target").await.unwrap();
+            assert_eq!(fs.read_link(&link).await.unwrap(),
+                       PathBuf::from("target"));
+            assert_eq!(fs.metadata(&link).await.unwrap().len(), 5);
+
+            fs.set_permissions(&target, Permissions::new(true))
+              .await
+              .unwrap();
+            let metadata = fs.metadata(&target).await.unwrap();
+            assert!(metadata.permissions().readonly());
+        });
+    }
+}
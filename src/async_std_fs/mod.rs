@@ -0,0 +1,35 @@
+//! A backend for the operating system's filesystem, built on
+//! [`async_std::fs`].
+//!
+//! async-std already uses the [`futures_io`] traits for its files, so this
+//! module is mostly a matter of converting between async-std's types and the
+//! ones used by this crate.
+//!
+//! The entry point is [`AsyncStdFs`], which implements [`AsyncFsTrait`][1] and
+//! [`AsyncSymLinkTrait`][2].  Everything else in this module is reached
+//! through it:
+//!
+//! - [`AsyncStdOpenOptions`] is returned by
+//!   [`AsyncStdFs::open_options()`][3], and opens [`AsyncStdFile`]s.
+//! - [`AsyncStdDirBuilder`] is returned by [`AsyncStdFs::dir_builder()`][4].
+//! - [`AsyncStdReadDir`] is returned by [`AsyncStdFs::read_dir()`][5], and
+//!   yields [`AsyncStdDirEntry`]s.
+//!
+//! This module is only available when the `async-std` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait
+//! [2]: crate::AsyncSymLinkTrait
+//! [3]: crate::AsyncFsTrait::open_options
+//! [4]: crate::AsyncFsTrait::dir_builder
+//! [5]: crate::AsyncFsTrait::read_dir
+
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{AsyncStdDirBuilder, AsyncStdDirEntry, AsyncStdReadDir};
+#[doc(inline)]
+pub use file::{AsyncStdFile, AsyncStdOpenOptions};
+#[doc(inline)]
+pub use fs::AsyncStdFs;
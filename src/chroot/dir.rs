@@ -0,0 +1,213 @@
+//! [`ChrootDirBuilder`] creates directories in a [`ChrootFs`], and
+//! [`ChrootReadDir`] lists them.
+
+use std::{
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+
+use super::ChrootFs;
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFsTrait, AsyncReadDirTrait,
+    FileType, Metadata
+};
+
+/// Creates directories in a [`ChrootFs`].
+pub struct ChrootDirBuilder<F>
+    where F: AsyncFsTrait
+{
+    inner: F::DirBuilder,
+    fs: ChrootFs<F>
+}
+
+impl<F> ChrootDirBuilder<F> where F: AsyncFsTrait
+{
+    pub(super) fn new(inner: F::DirBuilder, fs: ChrootFs<F>) -> Self {
+        ChrootDirBuilder { inner, fs }
+    }
+}
+
+impl<F> fmt::Debug for ChrootDirBuilder<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChrootDirBuilder")
+         .field("inner", &self.inner)
+         .field("root", &self.fs.root())
+         .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncDirBuilderTrait for ChrootDirBuilder<F> where F: AsyncFsTrait
+{
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.inner.recursive(recursive);
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.fs.locate(path.as_ref(), false).await?;
+        self.inner.create(path).await
+    }
+}
+
+/// A stream of the entries in a directory of a [`ChrootFs`].
+pub struct ChrootReadDir<F>
+    where F: AsyncFsTrait
+{
+    inner: F::ReadDir,
+
+    /// The resolved path of the directory inside the root.
+    dir: PathBuf
+}
+
+impl<F> ChrootReadDir<F> where F: AsyncFsTrait
+{
+    pub(super) fn new(inner: F::ReadDir, dir: PathBuf) -> Self {
+        ChrootReadDir { inner, dir }
+    }
+}
+
+impl<F> fmt::Debug for ChrootReadDir<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChrootReadDir")
+         .field("inner", &self.inner)
+         .field("dir", &self.dir)
+         .finish()
+    }
+}
+
+impl<F> Stream for ChrootReadDir<F> where F: AsyncFsTrait
+{
+    type Item = io::Result<ChrootDirEntry<F>>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let entry = match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(entry) => entry,
+            Poll::Pending => return Poll::Pending
+        };
+        Poll::Ready(entry.map(|entry| {
+                             entry.map(|inner| ChrootDirEntry { inner,
+                                                           dir: this.dir
+                                                                    .clone() })
+                         }))
+    }
+}
+
+#[async_trait]
+impl<F> AsyncReadDirTrait<ChrootDirEntry<F>> for ChrootReadDir<F>
+    where F: AsyncFsTrait
+{
+}
+
+/// An entry in a directory of a [`ChrootFs`].
+///
+/// [`path()`][1] joins the entry's name to the path of its directory inside
+/// the root, with any symlinks in the path passed to [`read_dir()`][2]
+/// resolved, so the root's own location never shows.
+///
+/// [1]: AsyncDirEntryTrait::path
+/// [2]: AsyncFsTrait::read_dir
+pub struct ChrootDirEntry<F>
+    where F: AsyncFsTrait
+{
+    inner: F::DirEntry,
+    dir: PathBuf
+}
+
+impl<F> Clone for ChrootDirEntry<F> where F: AsyncFsTrait
+{
+    fn clone(&self) -> Self {
+        ChrootDirEntry { inner: self.inner.clone(),
+                         dir: self.dir.clone() }
+    }
+}
+
+impl<F> fmt::Debug for ChrootDirEntry<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChrootDirEntry")
+         .field("inner", &self.inner)
+         .field("dir", &self.dir)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncDirEntryTrait for ChrootDirEntry<F> where F: AsyncFsTrait
+{
+    async fn path(&self) -> PathBuf {
+        self.dir.join(self.inner.file_name().await)
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.inner.metadata().await
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        self.inner.file_type().await
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.inner.file_name().await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{
+        chroot::ChrootLayer, layer::FsLayer, mem_fs::MemFs, AsyncSymLinkTrait
+    };
+
+    #[test]
+    fn entry_paths_are_inside_the_root() {
+        block_on(async {
+            let mem = MemFs::new();
+            mem.dir_builder()
+               .recursive(true)
+               .create("/jail/dir/sub")
+               .await
+               .unwrap();
+            let fs = ChrootLayer::new("/jail").layer(mem);
+            fs.symlink("/link", "dir").await.unwrap();
+            fs.dir_builder().create("/link/new").await.unwrap();
+
+            let mut paths = Vec::new();
+            let mut entries = fs.read_dir("/link").await.unwrap();
+            while let Some(entry) = entries.next().await {
+                let entry = entry.unwrap();
+                assert!(entry.metadata().await.unwrap().is_dir());
+                paths.push(entry.path().await);
+            }
+            paths.sort();
+            assert_eq!(paths, [Path::new("/dir/new"), Path::new("/dir/sub")]);
+
+            let err = fs.dir_builder().create("../out").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        });
+    }
+}
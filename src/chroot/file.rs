@@ -0,0 +1,144 @@
+//! [`ChrootOpenOptions`] opens files in a [`ChrootFs`][1].
+//!
+//! [1]: super::ChrootFs
+
+use std::{fmt, io, path::Path};
+
+use async_trait::async_trait;
+
+use super::ChrootFs;
+use crate::{layer::FileOf, AsyncFileBuilderTrait, AsyncFsTrait};
+
+/// Options for opening a file in a [`ChrootFs`].
+///
+/// The files it opens are the wrapped filesystem's own, since an open file
+/// has no path to escape through.  Like `O_EXCL`, [`create_new()`][1] doesn't
+/// follow a symlink in the final component of the path.
+///
+/// [1]: AsyncFileBuilderTrait::create_new
+pub struct ChrootOpenOptions<F>
+    where F: AsyncFsTrait
+{
+    inner: F::FileBuilder,
+    fs: ChrootFs<F>,
+    create_new: bool
+}
+
+impl<F> ChrootOpenOptions<F> where F: AsyncFsTrait
+{
+    pub(super) fn new(inner: F::FileBuilder, fs: ChrootFs<F>) -> Self {
+        ChrootOpenOptions { inner,
+                            fs,
+                            create_new: false }
+    }
+}
+
+impl<F> fmt::Debug for ChrootOpenOptions<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChrootOpenOptions")
+         .field("inner", &self.inner)
+         .field("root", &self.fs.root())
+         .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFileBuilderTrait for ChrootOpenOptions<F> where F: AsyncFsTrait
+{
+    type File = FileOf<F>;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.inner.read(read);
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.inner.write(write);
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.inner.append(append);
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.inner.truncate(truncate);
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.inner.create(create);
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.inner.create_new(create_new);
+        self.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.fs.locate(path.as_ref(), !self.create_new).await?;
+        self.inner.open(path).await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{
+        chroot::ChrootLayer, layer::FsLayer, mem_fs::MemFs,
+        AsyncDirBuilderTrait, AsyncSymLinkTrait
+    };
+
+    #[test]
+    fn files_are_opened_through_links_inside_the_root() {
+        block_on(async {
+            let mem = MemFs::new();
+            mem.dir_builder().create("/jail").await.unwrap();
+            let fs = ChrootLayer::new("/jail").layer(mem.clone());
+            fs.symlink("/link", "/target").await.unwrap();
+
+            let mut file = fs.open_options()
+                             .write(true)
+                             .create(true)
+                             .open("/link")
+                             .await
+                             .unwrap();
+            file.write_all(b"contents").await.unwrap();
+            let mut file = mem.open_options()
+                              .read(true)
+                              .open("/jail/target")
+                              .await
+                              .unwrap();
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, "contents");
+
+            fs.remove_file("/target").await.unwrap();
+            let err = fs.open_options()
+                        .write(true)
+                        .create_new(true)
+                        .open("/link")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+            assert!(mem.metadata("/jail/target").await.is_err());
+        });
+    }
+}
@@ -0,0 +1,459 @@
+//! [`ChrootLayer`] wraps a filesystem in a [`ChrootFs`].
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc
+};
+
+use async_trait::async_trait;
+
+use super::{ChrootDirBuilder, ChrootOpenOptions, ChrootReadDir};
+use crate::{
+    layer::FsLayer, AsyncFsTrait, AsyncSymLinkTrait, Metadata, Permissions
+};
+
+/// The maximum number of symlinks that are followed while resolving a single
+/// path, matching Linux's limit.
+const MAX_SYMLINKS: usize = 40;
+
+/// The error returned for paths that would leave the root.
+fn escape() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   "path escapes the root directory")
+}
+
+fn too_many_links() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "too many levels of symbolic links")
+}
+
+/// Pushes the components of `path` onto `pending` in reverse order, so that
+/// popping them returns them in order.  Root and `.` components are dropped.
+fn push_components(pending: &mut Vec<OsString>, path: &Path) -> io::Result<()> {
+    for component in path.components().rev() {
+        match component {
+            Component::Prefix(_) => return Err(escape()),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Normal(_) => {
+                pending.push(component.as_os_str().to_owned())
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A layer that confines a filesystem to one of its directories.
+#[derive(Debug, Clone)]
+pub struct ChrootLayer {
+    root: PathBuf
+}
+
+impl ChrootLayer {
+    /// Creates a layer that makes `root`, a directory of the wrapped
+    /// filesystem, the root of the new filesystem.
+    pub fn new<P>(root: P) -> Self
+        where P: Into<PathBuf>
+    {
+        ChrootLayer { root: root.into() }
+    }
+}
+
+impl<F> FsLayer<F> for ChrootLayer where F: AsyncFsTrait
+{
+    type Fs = ChrootFs<F>;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        ChrootFs::new(inner, self.root.clone())
+    }
+}
+
+/// A filesystem that exposes one directory of another filesystem as its root.
+///
+/// Paths given to this filesystem are resolved inside the root directory; see
+/// the [module documentation][1] for how escapes are prevented.  Resolving a
+/// path looks up the metadata of each of its components in the wrapped
+/// filesystem, to find the symlinks among them.  Paths this filesystem returns
+/// are absolute paths inside the root.
+///
+/// Symlinks created through [`symlink()`][2] with an absolute target are
+/// stored with a target relative to the link instead, so that they keep
+/// pointing inside the root when the wrapped filesystem follows them.
+///
+/// There is no way back to the wrapped filesystem, so code that is handed a
+/// `ChrootFs` can't reach outside the root through it:
+///
+/// ```compile_fail
+/// use async_fs_traits::{chroot::ChrootFs, AsyncFsTrait};
+///
+/// fn reach<F: AsyncFsTrait>(fs: &ChrootFs<F>) -> &F {
+///     fs.get_ref()
+/// }
+/// ```
+///
+/// [1]: super
+/// [2]: AsyncSymLinkTrait::symlink
+pub struct ChrootFs<F> {
+    inner: Arc<F>,
+    root: PathBuf
+}
+
+impl<F> ChrootFs<F> {
+    /// Wraps `inner`, making its directory `root` the root of the new
+    /// filesystem.
+    pub fn new<P>(inner: F, root: P) -> Self
+        where P: Into<PathBuf>
+    {
+        ChrootFs { inner: Arc::new(inner),
+                   root: root.into() }
+    }
+
+    /// Returns the directory of the wrapped filesystem that is this
+    /// filesystem's root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path in the wrapped filesystem of `path`, an absolute path
+    /// inside the root that contains no `..` components.
+    fn real(&self, path: &Path) -> PathBuf {
+        let mut real = self.root.clone();
+        real.extend(path.components()
+                        .filter(|component| {
+                            matches!(component, Component::Normal(_))
+                        }));
+        real
+    }
+}
+
+impl<F> ChrootFs<F> where F: AsyncFsTrait
+{
+    /// Resolves `path` to an absolute path inside the root, without `..`
+    /// components or symlinks.  If `follow` is false, a symlink in the final
+    /// component is left alone.
+    async fn resolve(&self, path: &Path, follow: bool) -> io::Result<PathBuf> {
+        let mut pending = Vec::new();
+        push_components(&mut pending, path)?;
+        let mut resolved = PathBuf::from("/");
+        let mut links = 0;
+        while let Some(name) = pending.pop() {
+            if name == ".." {
+                if !resolved.pop() {
+                    return Err(escape());
+                }
+                continue;
+            }
+            resolved.push(&name);
+            if pending.is_empty() && !follow {
+                break;
+            }
+
+            let real = self.real(&resolved);
+            match self.inner.symlink_metadata(&real).await {
+                Ok(metadata) if metadata.is_symlink() => {}
+                _ => continue
+            }
+            links += 1;
+            if links > MAX_SYMLINKS {
+                return Err(too_many_links());
+            }
+            let target = self.inner.read_link(&real).await?;
+            resolved.pop();
+            if target.is_absolute() {
+                let target =
+                    target.strip_prefix(&self.root).map_err(|_| escape())?;
+                resolved = PathBuf::from("/");
+                push_components(&mut pending, target)?;
+            } else {
+                push_components(&mut pending, &target)?;
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Resolves `path` like [`resolve()`][1], and returns its path in the
+    /// wrapped filesystem.
+    ///
+    /// [1]: ChrootFs::resolve
+    pub(super) async fn locate(&self,
+                               path: &Path,
+                               follow: bool)
+                               -> io::Result<PathBuf> {
+        let path = self.resolve(path, follow).await?;
+        Ok(self.real(&path))
+    }
+}
+
+impl<F> Clone for ChrootFs<F> {
+    fn clone(&self) -> Self {
+        ChrootFs { inner: Arc::clone(&self.inner),
+                   root: self.root.clone() }
+    }
+}
+
+impl<F> std::fmt::Debug for ChrootFs<F> where F: std::fmt::Debug
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChrootFs")
+         .field("inner", &self.inner)
+         .field("root", &self.root)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFsTrait for ChrootFs<F> where F: AsyncFsTrait
+{
+    type DirBuilder = ChrootDirBuilder<F>;
+    type DirEntry = super::ChrootDirEntry<F>;
+    type FileBuilder = ChrootOpenOptions<F>;
+    type ReadDir = ChrootReadDir<F>;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        ChrootDirBuilder::new(self.inner.dir_builder(), self.clone())
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        ChrootOpenOptions::new(self.inner.open_options(), self.clone())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.resolve(path.as_ref(), true).await?;
+        self.inner.metadata(self.real(&path)).await?;
+        Ok(path)
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = self.locate(src.as_ref(), false).await?;
+        let dst = self.locate(dst.as_ref(), false).await?;
+        self.inner.rename(src, dst).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.locate(path.as_ref(), true).await?;
+        self.inner.set_permissions(path, perm).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = self.locate(src.as_ref(), false).await?;
+        let dst = self.locate(dst.as_ref(), false).await?;
+        self.inner.hard_link(src, dst).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.locate(path.as_ref(), false).await?;
+        let target = self.inner.read_link(path).await?;
+        match target.strip_prefix(&self.root) {
+            Ok(target) => Ok(Path::new("/").join(target)),
+            _ => Ok(target)
+        }
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.locate(path.as_ref(), false).await?;
+        self.inner.symlink_metadata(path).await
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.locate(path.as_ref(), true).await?;
+        self.inner.metadata(path).await
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = self.locate(src.as_ref(), true).await?;
+        let dst = self.locate(dst.as_ref(), true).await?;
+        self.inner.copy(src, dst).await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.locate(path.as_ref(), false).await?;
+        self.inner.remove_file(path).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.resolve(path.as_ref(), true).await?;
+        let inner = self.inner.read_dir(self.real(&path)).await?;
+        Ok(ChrootReadDir::new(inner, path))
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.locate(path.as_ref(), false).await?;
+        self.inner.remove_dir(path).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.locate(path.as_ref(), false).await?;
+        self.inner.remove_dir_all(path).await
+    }
+}
+
+#[async_trait]
+impl<F> AsyncSymLinkTrait for ChrootFs<F>
+    where F: AsyncFsTrait + AsyncSymLinkTrait
+{
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = self.resolve(src.as_ref(), false).await?;
+        let dst = dst.as_ref();
+        let target = if dst.is_absolute() {
+            // Climb from the link's directory back up to the root.
+            let mut target = PathBuf::new();
+            for _ in src.parent().into_iter().flat_map(Path::components).skip(1)
+            {
+                target.push("..");
+            }
+            let mut pending = Vec::new();
+            push_components(&mut pending, dst)?;
+            target.extend(pending.iter().rev());
+            if target.as_os_str().is_empty() {
+                target.push(".");
+            }
+            target
+        } else {
+            dst.to_owned()
+        };
+        self.inner.symlink(self.real(&src), target).await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{mem_fs::MemFs, AsyncDirBuilderTrait, AsyncFileBuilderTrait};
+
+    async fn jail() -> (MemFs, ChrootFs<MemFs>) {
+        let mem = MemFs::new();
+        mem.dir_builder()
+           .recursive(true)
+           .create("/jail/dir")
+           .await
+           .unwrap();
+        mem.open_options()
+           .write(true)
+           .create(true)
+           .open("/secret")
+           .await
+           .unwrap();
+        (mem.clone(), ChrootLayer::new("/jail").layer(mem))
+    }
+
+    #[test]
+    fn paths_are_rewritten() {
+        block_on(async {
+            let (mem, fs) = jail().await;
+            fs.open_options()
+              .write(true)
+              .create(true)
+              .open("/dir/f")
+              .await
+              .unwrap();
+            assert!(mem.metadata("/jail/dir/f").await.unwrap().is_file());
+            assert!(fs.metadata("dir/f").await.unwrap().is_file());
+            assert!(fs.metadata("/dir/../dir/./f").await.unwrap().is_file());
+            assert_eq!(fs.canonicalize("dir/f").await.unwrap(),
+                       Path::new("/dir/f"));
+
+            fs.rename("/dir/f", "/g").await.unwrap();
+            assert!(mem.metadata("/jail/g").await.unwrap().is_file());
+            fs.remove_file("/g").await.unwrap();
+            assert!(mem.metadata("/jail/g").await.is_err());
+        });
+    }
+
+    #[test]
+    fn escapes_are_refused() {
+        block_on(async {
+            let (mem, fs) = jail().await;
+            mem.symlink("/jail/abs", "/secret").await.unwrap();
+            mem.symlink("/jail/dir/rel", "../../secret").await.unwrap();
+            mem.symlink("/jail/loop", "loop").await.unwrap();
+
+            for path in ["..",
+                         "/../secret",
+                         "dir/../../secret",
+                         "abs",
+                         "dir/rel",
+                         "dir/rel/x"]
+            {
+                let err = fs.metadata(path).await.unwrap_err();
+                assert_eq!(err.kind(),
+                           io::ErrorKind::PermissionDenied,
+                           "{}",
+                           path);
+            }
+            let err = fs.open_options()
+                        .write(true)
+                        .open("/abs")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            assert!(fs.metadata("/secret").await.is_err());
+            assert!(fs.metadata("/loop").await.is_err());
+
+            // Links themselves can still be looked at and removed.
+            assert_eq!(fs.read_link("/abs").await.unwrap(),
+                       Path::new("/secret"));
+            assert!(fs.symlink_metadata("/abs").await.unwrap().is_symlink());
+            fs.remove_file("/dir/rel").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn symlinks_stay_inside() {
+        block_on(async {
+            let (mem, fs) = jail().await;
+            fs.symlink("/dir/up", "/").await.unwrap();
+            fs.symlink("/dir/abs", "/dir").await.unwrap();
+            fs.symlink("/rel", "dir/abs/up").await.unwrap();
+            assert_eq!(mem.read_link("/jail/dir/abs").await.unwrap(),
+                       Path::new("../dir"));
+            assert_eq!(fs.canonicalize("/rel/dir/up/dir").await.unwrap(),
+                       Path::new("/dir"));
+            assert_eq!(mem.canonicalize("/jail/rel/dir").await.unwrap(),
+                       Path::new("/jail/dir"));
+        });
+    }
+}
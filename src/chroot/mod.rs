@@ -0,0 +1,45 @@
+//! A layer that confines a filesystem to one of its subdirectories.
+//!
+//! [`ChrootLayer`] wraps a filesystem in a [`ChrootFs`], which exposes a
+//! single directory of the wrapped filesystem as its root, much like
+//! `chroot(2)`.  Every path passed to the filesystem, to
+//! [`open()`][1], to [`create()`][2], and to [`symlink()`][3] is resolved
+//! inside that directory, and the directory's own location is stripped from
+//! the paths that come back out of [`canonicalize()`][4], [`read_link()`][5],
+//! and [`path()`][6].
+//!
+//! Paths are resolved one component at a time, following symlinks along the
+//! way, so none of these can reach outside the root:
+//!
+//! * Absolute paths, which start at the root rather than at the wrapped
+//!   filesystem's root.  Relative paths start there as well.
+//! * `..` components, which fail when they would leave the root.
+//! * Symlinks whose targets lie outside the root, whether the targets are
+//!   relative or absolute.
+//!
+//! Escapes fail with [`ErrorKind::PermissionDenied`][7].  The checks are made
+//! before the wrapped filesystem is asked to do anything, so they can't see a
+//! symlink that someone else swaps in while the operation is underway.  When
+//! that matters, the rest of the wrapped filesystem shouldn't be writable by
+//! anyone else.
+//!
+//! This module is only available when the `chroot` feature is enabled.
+//!
+//! [1]: crate::AsyncFileBuilderTrait::open
+//! [2]: crate::AsyncDirBuilderTrait::create
+//! [3]: crate::AsyncSymLinkTrait::symlink
+//! [4]: crate::AsyncFsTrait::canonicalize
+//! [5]: crate::AsyncFsTrait::read_link
+//! [6]: crate::AsyncDirEntryTrait::path
+//! [7]: std::io::ErrorKind::PermissionDenied
+
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{ChrootDirBuilder, ChrootDirEntry, ChrootReadDir};
+#[doc(inline)]
+pub use file::ChrootOpenOptions;
+#[doc(inline)]
+pub use fs::{ChrootFs, ChrootLayer};
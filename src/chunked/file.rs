@@ -0,0 +1,453 @@
+//! [`ChunkedFile`] drives a [`State`] from `AsyncRead`, `AsyncWrite`, and
+//! `AsyncSeek`.
+
+use std::{
+    fmt,
+    future::Future,
+    io::{self, SeekFrom},
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll}
+};
+
+use async_lock::{Mutex as AsyncMutex, MutexGuard, MutexGuardArc};
+
+use super::{FrameCodec, Inner, State};
+
+type OpFuture = Pin<Box<dyn Future<Output = io::Result<Output>> + Send>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpKind {
+    Read,
+    Write,
+    Seek,
+    Flush,
+    Close
+}
+
+enum Output {
+    Read(Vec<u8>),
+    Written(usize),
+    Seeked(u64),
+    Done
+}
+
+/// An operation started by one of the `poll_*` methods.
+///
+/// The future is kept behind a [`Mutex`] so that the file stays `Sync`, and
+/// so that [`ChunkedFile::state()`] can take it over.  The lock is only ever
+/// held briefly, never across an `.await`.
+struct Op {
+    kind: OpKind,
+    future: OpFuture
+}
+
+/// Returns the bytes that an operation that was given up on took from the
+/// state without handing them out.
+fn unseen(output: io::Result<Output>) -> Vec<u8> {
+    match output {
+        Ok(Output::Read(data)) => data,
+        _ => Vec::new()
+    }
+}
+
+/// An open chunked file.
+///
+/// Each `poll_*` method starts an operation on the shared [`State`] and keeps
+/// polling it until it finishes.  If a different kind of operation is
+/// requested first, the pending one is run to completion and its result is
+/// dropped, just like an abandoned write to a buffered file.  Bytes that a
+/// dropped read took from the state are put back, so the cursor doesn't move
+/// past them.
+pub(crate) struct ChunkedFile<T, C> {
+    state: Arc<AsyncMutex<State<T, C>>>,
+    op: Mutex<Option<Op>>,
+
+    /// Bytes that were read from the state, but didn't fit in the caller's
+    /// buffer.
+    leftover: Vec<u8>
+}
+
+impl<T, C> ChunkedFile<T, C>
+    where T: Inner,
+          C: FrameCodec
+{
+    pub(crate) fn new(state: State<T, C>) -> Self {
+        ChunkedFile { state: Arc::new(AsyncMutex::new(state)),
+                      op: Mutex::new(None),
+                      leftover: Vec::new() }
+    }
+
+    /// Locks the state.
+    ///
+    /// An operation that a `poll_*` method left pending may be holding the
+    /// lock, and nothing else would ever poll it again if the caller gave up
+    /// on it, so it is run to completion first and its result is dropped.
+    /// Bytes that an abandoned read took from the state are put back.
+    pub(crate) async fn state(&self) -> MutexGuard<'_, State<T, C>> {
+        let pending = self.op
+                          .lock()
+                          .unwrap_or_else(PoisonError::into_inner)
+                          .take();
+        let unread = match pending {
+            Some(op) => unseen(op.future.await).len(),
+            None => 0
+        };
+        let mut guard = self.state.lock().await;
+        guard.unread(unread);
+        guard
+    }
+
+    fn poll_op<G, Fut>(&mut self,
+                       cx: &mut Context<'_>,
+                       kind: OpKind,
+                       start: G)
+                       -> Poll<io::Result<Output>>
+        where G: FnOnce(MutexGuardArc<State<T, C>>) -> Fut + Send + 'static,
+              Fut: Future<Output = io::Result<Output>> + Send + 'static
+    {
+        let mut start = Some(start);
+        let slot = self.op.get_mut().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            match slot {
+                Some(op) => {
+                    let output = match op.future.as_mut().poll(cx) {
+                        Poll::Ready(output) => output,
+                        Poll::Pending => return Poll::Pending
+                    };
+                    let finished = op.kind;
+                    *slot = None;
+                    if finished == kind {
+                        return Poll::Ready(output);
+                    }
+                    // The bytes of a read that was given up on are unread
+                    // when the next operation starts, like any leftover.
+                    self.leftover = unseen(output);
+                }
+                None => {
+                    let start = match start.take() {
+                        Some(start) => start,
+                        None => unreachable!("operations are only started once")
+                    };
+                    let state = Arc::clone(&self.state);
+                    let unread = mem::take(&mut self.leftover).len();
+                    let future = async move {
+                        let mut guard = state.lock_arc().await;
+                        guard.unread(unread);
+                        start(guard).await
+                    };
+                    *slot = Some(Op { kind,
+                                      future: Box::pin(future) });
+                }
+            }
+        }
+    }
+
+    pub(crate) fn poll_read(&mut self,
+                            cx: &mut Context<'_>,
+                            buf: &mut [u8])
+                            -> Poll<io::Result<usize>> {
+        if self.leftover.is_empty() {
+            let len = buf.len();
+            let output =
+                self.poll_op(cx, OpKind::Read, move |mut s| async move {
+                        s.read(len).await.map(Output::Read)
+                    });
+            match output {
+                Poll::Ready(Ok(Output::Read(data))) => self.leftover = data,
+                Poll::Ready(Ok(_)) => {
+                    unreachable!("read returned another output")
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+
+        let len = buf.len().min(self.leftover.len());
+        buf[..len].copy_from_slice(&self.leftover[..len]);
+        self.leftover.drain(..len);
+        Poll::Ready(Ok(len))
+    }
+
+    pub(crate) fn poll_write(&mut self,
+                             cx: &mut Context<'_>,
+                             buf: &[u8])
+                             -> Poll<io::Result<usize>> {
+        let data = buf.to_vec();
+        self.poll_op(cx, OpKind::Write, move |mut s| async move {
+                s.write(&data).await.map(Output::Written)
+            })
+            .map_ok(|output| match output {
+                Output::Written(len) => len,
+                _ => unreachable!("write returned another output")
+            })
+    }
+
+    pub(crate) fn poll_flush(&mut self,
+                             cx: &mut Context<'_>)
+                             -> Poll<io::Result<()>> {
+        self.poll_op(cx, OpKind::Flush, |mut s| async move {
+                s.flush().await.map(|_| Output::Done)
+            })
+            .map_ok(|_| ())
+    }
+
+    pub(crate) fn poll_close(&mut self,
+                             cx: &mut Context<'_>)
+                             -> Poll<io::Result<()>> {
+        self.poll_op(cx, OpKind::Close, |mut s| async move {
+                s.close().await.map(|_| Output::Done)
+            })
+            .map_ok(|_| ())
+    }
+
+    pub(crate) fn poll_seek(&mut self,
+                            cx: &mut Context<'_>,
+                            pos: SeekFrom)
+                            -> Poll<io::Result<u64>> {
+        self.poll_op(cx, OpKind::Seek, move |mut s| async move {
+                s.seek(pos).map(Output::Seeked)
+            })
+            .map_ok(|output| match output {
+                Output::Seeked(pos) => pos,
+                _ => unreachable!("seek returned another output")
+            })
+    }
+}
+
+impl<T, C> fmt::Debug for ChunkedFile<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkedFile")
+         .field("leftover", &self.leftover.len())
+         .finish_non_exhaustive()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use async_trait::async_trait;
+    use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+    use futures_lite::future::{block_on, poll_fn, poll_once};
+
+    use super::*;
+    use crate::{
+        chunked::scan,
+        mem_fs::{MemFile, MemFs},
+        AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait, Metadata,
+        Permissions
+    };
+
+    #[derive(Debug)]
+    struct Plain;
+
+    impl FrameCodec for Plain {
+        fn encode(&self,
+                  _index: u64,
+                  _last: bool,
+                  data: &[u8])
+                  -> io::Result<Vec<u8>> {
+            Ok(data.to_vec())
+        }
+
+        fn decode(&self,
+                  _index: u64,
+                  _last: bool,
+                  frame: &[u8],
+                  _len: usize)
+                  -> io::Result<Vec<u8>> {
+            Ok(frame.to_vec())
+        }
+    }
+
+    /// A file that stops making progress on reads and writes while `stall` is
+    /// set.
+    #[derive(Debug)]
+    struct Stalling {
+        inner: MemFile,
+        stall: Arc<AtomicBool>
+    }
+
+    #[async_trait]
+    impl AsyncFileTrait for Stalling {
+        async fn sync_all(&self) -> io::Result<()> {
+            self.inner.sync_all().await
+        }
+
+        async fn sync_data(&self) -> io::Result<()> {
+            self.inner.sync_data().await
+        }
+
+        async fn set_len(&self, size: u64) -> io::Result<()> {
+            self.inner.set_len(size).await
+        }
+
+        async fn metadata(&self) -> io::Result<Metadata> {
+            self.inner.metadata().await
+        }
+
+        async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+            self.inner.set_permissions(perm).await
+        }
+    }
+
+    impl AsyncRead for Stalling {
+        fn poll_read(mut self: Pin<&mut Self>,
+                     cx: &mut Context<'_>,
+                     buf: &mut [u8])
+                     -> Poll<io::Result<usize>> {
+            if self.stall.load(Ordering::SeqCst) {
+                return Poll::Pending;
+            }
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for Stalling {
+        fn poll_write(mut self: Pin<&mut Self>,
+                      cx: &mut Context<'_>,
+                      buf: &[u8])
+                      -> Poll<io::Result<usize>> {
+            if self.stall.load(Ordering::SeqCst) {
+                return Poll::Pending;
+            }
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>,
+                      cx: &mut Context<'_>)
+                      -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>,
+                      cx: &mut Context<'_>)
+                      -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_close(cx)
+        }
+    }
+
+    impl AsyncSeek for Stalling {
+        fn poll_seek(mut self: Pin<&mut Self>,
+                     cx: &mut Context<'_>,
+                     pos: SeekFrom)
+                     -> Poll<io::Result<u64>> {
+            Pin::new(&mut self.inner).poll_seek(cx, pos)
+        }
+    }
+
+    #[test]
+    fn abandoned_writes_release_the_state() {
+        block_on(async {
+            let fs = MemFs::new();
+            let inner = fs.open_options()
+                          .write(true)
+                          .create(true)
+                          .open("/f")
+                          .await
+                          .unwrap();
+            let stall = Arc::new(AtomicBool::new(true));
+            let inner = Stalling { inner,
+                                   stall: Arc::clone(&stall) };
+            let state = State::new(inner, Plain, 0, 16, Vec::new(), false);
+            let mut file = ChunkedFile::new(state);
+
+            // Filling the first chunk stores it, which stalls with the state
+            // locked, and the write is then given up on.
+            let write = poll_fn(|cx| file.poll_write(cx, &[b'x'; 20]));
+            assert!(poll_once(write).await.is_none());
+
+            stall.store(false, Ordering::SeqCst);
+            let mut state = poll_once(file.state()).await
+                                                   .expect("the state is \
+                                                            still locked");
+            state.sync_all().await.unwrap();
+            assert_eq!(state.len(), 20);
+            drop(state);
+            let len = fs.metadata("/f").await.unwrap().len();
+            assert_eq!(len, 2 * 8 + 20);
+        });
+    }
+
+    #[test]
+    fn unread_bytes_dont_move_the_cursor() {
+        block_on(async {
+            let fs = MemFs::new();
+            let mut inner = fs.open_options()
+                              .read(true)
+                              .write(true)
+                              .create(true)
+                              .open("/f")
+                              .await
+                              .unwrap();
+            let frames = scan(&mut inner, 0, 16).await.unwrap();
+            let state = State::new(inner, Plain, 0, 16, frames, false);
+            let mut file = ChunkedFile::new(state);
+
+            poll_fn(|cx| file.poll_write(cx, b"0123456789")).await
+                                                            .unwrap();
+            poll_fn(|cx| file.poll_seek(cx, SeekFrom::Start(2))).await
+                                                                .unwrap();
+
+            // The state reads up to the end of the file, but only two bytes
+            // are handed out.
+            let mut buf = [0; 2];
+            poll_fn(|cx| file.poll_read(cx, &mut buf)).await.unwrap();
+            assert_eq!(&buf, b"23");
+            let pos = poll_fn(|cx| file.poll_seek(cx, SeekFrom::Current(0)));
+            assert_eq!(pos.await.unwrap(), 4);
+        });
+    }
+
+    #[test]
+    fn abandoned_reads_dont_move_the_cursor() {
+        block_on(async {
+            let fs = MemFs::new();
+            let mut writer = fs.open_options()
+                               .write(true)
+                               .create(true)
+                               .open("/f")
+                               .await
+                               .unwrap();
+            let frames = scan(&mut writer, 0, 16).await.unwrap();
+            let state = State::new(writer, Plain, 0, 16, frames, false);
+            let mut writer = ChunkedFile::new(state);
+            poll_fn(|cx| writer.poll_write(cx, b"0123456789")).await
+                                                              .unwrap();
+            poll_fn(|cx| writer.poll_close(cx)).await.unwrap();
+
+            let mut inner =
+                fs.open_options().read(true).open("/f").await.unwrap();
+            let frames = scan(&mut inner, 0, 16).await.unwrap();
+            let stall = Arc::new(AtomicBool::new(true));
+            let inner = Stalling { inner,
+                                   stall: Arc::clone(&stall) };
+            let state = State::new(inner, Plain, 0, 16, frames, false);
+            let mut file = ChunkedFile::new(state);
+
+            // Loading the chunk stalls, and the read is then given up on.
+            // It finishes when the seek starts, but its bytes were never
+            // handed out.
+            let mut buf = [0; 4];
+            let read = poll_fn(|cx| file.poll_read(cx, &mut buf));
+            assert!(poll_once(read).await.is_none());
+
+            stall.store(false, Ordering::SeqCst);
+            let pos = poll_fn(|cx| file.poll_seek(cx, SeekFrom::Current(0)));
+            assert_eq!(pos.await.unwrap(), 0);
+            poll_fn(|cx| file.poll_read(cx, &mut buf)).await.unwrap();
+            assert_eq!(&buf, b"0123");
+        });
+    }
+}
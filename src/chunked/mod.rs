@@ -0,0 +1,59 @@
+//! Shared machinery for layers that store a file's contents as a sequence of
+//! independently encoded chunks.
+//!
+//! A chunked file starts with a header owned by the layer, followed by one
+//! frame per chunk.  Every frame is an 8-byte header holding the encoded and
+//! decoded lengths as little-endian `u32`s, followed by the encoded bytes.
+//! Every chunk except the last decodes to exactly the chunk size, so any
+//! decoded offset maps directly to a frame, which keeps seeking and resizing
+//! cheap.  How a chunk is encoded is up to the layer's [`FrameCodec`].
+
+mod file;
+mod state;
+
+use std::{fmt::Debug, io};
+
+pub(crate) use file::ChunkedFile;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+pub(crate) use state::{decoded_len, invalid_data, scan, State};
+
+use crate::AsyncFileTrait;
+
+/// Encodes and decodes the chunks of a chunked file.
+///
+/// Every chunk is encoded knowing whether it is the `last` one in the file,
+/// and decoded with the same flag, so that a codec that authenticates its
+/// frames can tell a file that was cut short at a frame boundary from a
+/// whole one.
+pub(crate) trait FrameCodec: Debug + Send + Sync + 'static {
+    /// Encodes chunk number `index`.
+    fn encode(&self,
+              index: u64,
+              last: bool,
+              data: &[u8])
+              -> io::Result<Vec<u8>>;
+
+    /// Decodes chunk number `index`, which is `len` bytes long once decoded.
+    fn decode(&self,
+              index: u64,
+              last: bool,
+              frame: &[u8],
+              len: usize)
+              -> io::Result<Vec<u8>>;
+}
+
+/// The files that chunked files can be stored in.
+pub(crate) trait Inner:
+    AsyncFileTrait + AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+}
+
+impl<T> Inner for T
+    where T: AsyncFileTrait
+              + AsyncRead
+              + AsyncWrite
+              + AsyncSeek
+              + Unpin
+              + 'static
+{
+}
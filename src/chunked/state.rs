@@ -0,0 +1,557 @@
+//! [`State`] keeps track of the frames of a chunked file, and implements
+//! reads, writes, seeks, and resizing on top of them.
+
+use std::{
+    io::{self, SeekFrom},
+    mem
+};
+
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::{FrameCodec, Inner};
+
+/// The size of the header in front of every frame: the encoded length and the
+/// decoded length, both as little-endian `u32`s.
+const FRAME_HEADER_LEN: u64 = 8;
+
+/// Where a frame lives in the underlying file, and how large it is.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Frame {
+    offset: u64,
+    encoded_len: u32,
+    len: u32
+}
+
+impl Frame {
+    /// The offset of the first byte after this frame.
+    fn end(&self) -> u64 {
+        self.offset + FRAME_HEADER_LEN + u64::from(self.encoded_len)
+    }
+}
+
+/// The decoded contents of one chunk.
+#[derive(Debug)]
+struct Chunk {
+    index: usize,
+    data: Vec<u8>,
+    dirty: bool
+}
+
+/// Reads the frame index of a chunked file whose first frame starts at
+/// `data_start`.
+///
+/// Every frame except the last must hold exactly `chunk_size` bytes.
+pub(crate) async fn scan<T>(inner: &mut T,
+                            data_start: u64,
+                            chunk_size: usize)
+                            -> io::Result<Vec<Frame>>
+    where T: Inner
+{
+    let end = inner.seek(SeekFrom::End(0)).await?;
+    let mut frames = Vec::new();
+    let mut offset = data_start;
+    while offset < end {
+        if frames.last()
+                 .map_or(false, |f: &Frame| f.len as usize != chunk_size)
+        {
+            return Err(invalid_data("short frame before the end of the file"));
+        }
+
+        let mut header = [0; FRAME_HEADER_LEN as usize];
+        inner.seek(SeekFrom::Start(offset)).await?;
+        inner.read_exact(&mut header).await?;
+        let frame = Frame { offset,
+                            encoded_len: read_u32(&header[..4]),
+                            len: read_u32(&header[4..]) };
+        if frame.end() > end || frame.len as usize > chunk_size {
+            return Err(invalid_data("corrupt frame header"));
+        }
+        offset = frame.end();
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Returns the decoded length of a file with the given frames.
+pub(crate) fn decoded_len(frames: &[Frame]) -> u64 {
+    frames.iter().map(|f| u64::from(f.len)).sum()
+}
+
+/// An open chunked file.
+///
+/// The decoded contents are split into chunks of `chunk_size` bytes, and each
+/// chunk is stored as one encoded frame.  One chunk at a time is kept in
+/// memory; it is written back when another chunk is needed, or when the file
+/// is flushed.  Writing back a chunk whose encoded size changed moves every
+/// frame after it.
+///
+/// The chunk that holds the end of the file is encoded as the last one.
+/// When the file grows past it, it is encoded again as an ordinary chunk, and
+/// when the file shrinks, the chunk that ends up last is encoded again as the
+/// last one.
+#[derive(Debug)]
+pub(crate) struct State<T, C> {
+    inner: T,
+    codec: C,
+    data_start: u64,
+    chunk_size: usize,
+    frames: Vec<Frame>,
+
+    /// The frame that is stored encoded as the last one, if any.
+    sealed: Option<usize>,
+    cache: Option<Chunk>,
+    pos: u64,
+    len: u64,
+    append: bool
+}
+
+impl<T, C> State<T, C>
+    where T: Inner,
+          C: FrameCodec
+{
+    /// Wraps `inner`, whose frames start at `data_start` and have already
+    /// been read with [`scan()`].
+    pub(crate) fn new(inner: T,
+                      codec: C,
+                      data_start: u64,
+                      chunk_size: usize,
+                      frames: Vec<Frame>,
+                      append: bool)
+                      -> Self {
+        let len = decoded_len(&frames);
+        let sealed = frames.len().checked_sub(1);
+        State { inner,
+                codec,
+                data_start,
+                chunk_size,
+                frames,
+                sealed,
+                cache: None,
+                pos: 0,
+                len,
+                append }
+    }
+
+    pub(crate) fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// The decoded length of the file.
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Moves the cursor back by `len` bytes that were read but never handed
+    /// to the caller.
+    pub(crate) fn unread(&mut self, len: usize) {
+        self.pos -= len as u64;
+    }
+
+    /// Reads up to `len` bytes from the cursor.
+    pub(crate) async fn read(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        if self.pos >= self.len || len == 0 {
+            return Ok(Vec::new());
+        }
+        let (index, offset) = self.locate(self.pos);
+        let chunk = self.load(index).await?;
+        let len = len.min(chunk.data.len().saturating_sub(offset));
+        let data = chunk.data[offset..offset + len].to_vec();
+        self.pos += len as u64;
+        Ok(data)
+    }
+
+    /// Writes all of `buf` at the cursor, or at the end of the file in append
+    /// mode.
+    pub(crate) async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.append {
+            self.pos = self.len;
+        }
+        if self.pos > self.len {
+            self.fill_zeros(self.pos).await?;
+        }
+        self.write_contiguous(buf).await?;
+        Ok(buf.len())
+    }
+
+    /// Moves the cursor.
+    pub(crate) fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => offset(self.len, delta),
+            SeekFrom::Current(delta) => offset(self.pos, delta)
+        };
+        self.pos = target.ok_or_else(|| {
+                             io::Error::new(io::ErrorKind::InvalidInput,
+                                            "invalid seek to a negative or \
+                                              overflowing position")
+                         })?;
+        Ok(self.pos)
+    }
+
+    /// Changes the decoded length of the file, filling any new space with
+    /// zeros.  The cursor doesn't move.
+    pub(crate) async fn set_len(&mut self, len: u64) -> io::Result<()> {
+        if len > self.len {
+            self.fill_zeros(len).await?;
+        } else if len < self.len {
+            let (index, offset) = self.locate(len);
+            let keep = if offset == 0 { index } else { index + 1 };
+            if self.cache.as_ref().map_or(false, |c| c.index >= keep) {
+                self.cache = None;
+            }
+            if keep > 0 {
+                // The chunk that is now last is written back even if it
+                // didn't change, to encode it as the last one.
+                let start = (keep as u64 - 1) * self.chunk_size as u64;
+                let chunk = self.load(keep - 1).await?;
+                chunk.data.truncate((len - start) as usize);
+                chunk.dirty = true;
+            }
+            self.frames.truncate(keep);
+            if self.sealed.map_or(false, |sealed| sealed >= keep) {
+                self.sealed = None;
+            }
+            let end = self.frames_end();
+            self.inner.set_len(end).await?;
+            self.len = len;
+        }
+        self.flush().await
+    }
+
+    /// Stores an empty chunk as the last frame of a file that has no frames,
+    /// so that an empty file still has a last frame to authenticate.
+    #[cfg(any(feature = "aes-gcm", feature = "chacha20poly1305"))]
+    pub(crate) async fn seal_empty(&mut self) -> io::Result<()> {
+        self.store().await?;
+        if self.frames.is_empty() {
+            self.cache = Some(Chunk { index: 0,
+                                      data: Vec::new(),
+                                      dirty: true });
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Writes back the chunk in memory, and flushes the underlying file.
+    pub(crate) async fn flush(&mut self) -> io::Result<()> {
+        self.store().await?;
+        self.inner.flush().await
+    }
+
+    /// Writes back the chunk in memory, and closes the underlying file.
+    pub(crate) async fn close(&mut self) -> io::Result<()> {
+        self.store().await?;
+        self.inner.close().await
+    }
+
+    /// Writes back the chunk in memory, and syncs the underlying file.
+    pub(crate) async fn sync_all(&mut self) -> io::Result<()> {
+        self.flush().await?;
+        self.inner.sync_all().await
+    }
+
+    /// Writes back the chunk in memory, and syncs the underlying file's data.
+    pub(crate) async fn sync_data(&mut self) -> io::Result<()> {
+        self.flush().await?;
+        self.inner.sync_data().await
+    }
+
+    /// Returns the chunk index and the offset within that chunk of the
+    /// decoded position `pos`.
+    fn locate(&self, pos: u64) -> (usize, usize) {
+        let chunk_size = self.chunk_size as u64;
+        ((pos / chunk_size) as usize, (pos % chunk_size) as usize)
+    }
+
+    /// The offset just past the last frame.
+    fn frames_end(&self) -> u64 {
+        self.frames.last().map_or(self.data_start, Frame::end)
+    }
+
+    /// Writes `buf` at the cursor, which must not be past the end of the file.
+    async fn write_contiguous(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let (index, offset) = self.locate(self.pos);
+            let len = buf.len().min(self.chunk_size - offset);
+            // The file grows before the chunk is loaded, so that the chunk
+            // before it is written back as an ordinary one.
+            self.len = self.len.max(self.pos + len as u64);
+            let chunk = self.load(index).await?;
+            if chunk.data.len() < offset + len {
+                chunk.data.resize(offset + len, 0);
+            }
+            chunk.data[offset..offset + len].copy_from_slice(&buf[..len]);
+            chunk.dirty = true;
+
+            buf = &buf[len..];
+            self.pos += len as u64;
+        }
+        Ok(())
+    }
+
+    /// Extends the file with zeros up to `len`, without moving the cursor.
+    async fn fill_zeros(&mut self, len: u64) -> io::Result<()> {
+        let pos = mem::replace(&mut self.pos, self.len);
+        let zeros = vec![0; self.chunk_size];
+        while self.pos < len {
+            let remaining = (len - self.pos).min(zeros.len() as u64) as usize;
+            let (_, offset) = self.locate(self.pos);
+            let step = remaining.min(self.chunk_size - offset);
+            self.write_contiguous(&zeros[..step]).await?;
+        }
+        self.pos = pos;
+        Ok(())
+    }
+
+    /// Makes chunk `index` the one in memory, writing back the previous one
+    /// if needed.  `index` may be one past the last frame, in which case the
+    /// chunk starts out empty.
+    async fn load(&mut self, index: usize) -> io::Result<&mut Chunk> {
+        if self.cache.as_ref().map_or(true, |c| c.index != index) {
+            if index >= self.frames.len() {
+                self.unseal().await?;
+            }
+            self.store().await?;
+            let data = match self.frames.get(index).copied() {
+                Some(frame) => self.decode(index, frame).await?,
+                None => Vec::new()
+            };
+            self.cache = Some(Chunk { index,
+                                      data,
+                                      dirty: false });
+        }
+        Ok(self.cache.as_mut().expect("a chunk was just loaded"))
+    }
+
+    /// Encodes the frame that is stored as the last one again as an
+    /// ordinary one, before the file grows past it.
+    async fn unseal(&mut self) -> io::Result<()> {
+        let index = match self.sealed {
+            Some(index) => index,
+            None => return Ok(())
+        };
+        match self.cache.as_mut() {
+            Some(chunk) if chunk.index == index => chunk.dirty = true,
+            _ => {
+                self.store().await?;
+                let data = self.decode(index, self.frames[index]).await?;
+                self.cache = Some(Chunk { index,
+                                          data,
+                                          dirty: true });
+            }
+        }
+        self.store().await
+    }
+
+    /// Reads and decodes frame `index`.
+    async fn decode(&mut self,
+                    index: usize,
+                    frame: Frame)
+                    -> io::Result<Vec<u8>> {
+        let encoded = self.read_frame(frame).await?;
+        let last = self.sealed == Some(index);
+        let data =
+            self.codec
+                .decode(index as u64, last, &encoded, frame.len as usize)?;
+        if data.len() != frame.len as usize {
+            return Err(invalid_data("frame decoded to the wrong length"));
+        }
+        Ok(data)
+    }
+
+    /// Writes the chunk in memory back to the underlying file, if it changed.
+    async fn store(&mut self) -> io::Result<()> {
+        let chunk = match self.cache.as_mut() {
+            Some(chunk) if chunk.dirty => chunk,
+            _ => return Ok(())
+        };
+        chunk.dirty = false;
+        let index = chunk.index;
+        let chunk_end = (index as u64 + 1) * self.chunk_size as u64;
+        let last = self.len <= chunk_end;
+        let encoded = self.codec.encode(index as u64, last, &chunk.data)?;
+        let len = chunk.data.len() as u32;
+        let encoded_len = u32::try_from(encoded.len()).map_err(|_| {
+                              invalid_data("encoded frame is too large")
+                          })?;
+
+        match self.frames.get(index).copied() {
+            Some(old) if old.encoded_len == encoded_len => {
+                self.write_frame(old.offset, len, &encoded).await?;
+                self.frames[index].len = len;
+            }
+            Some(old) => {
+                // The frame changed size, so every frame after it moves.
+                let moved = self.frames.split_off(index + 1);
+                let mut tail = Vec::with_capacity(moved.len());
+                for frame in moved {
+                    tail.push((frame.len, self.read_frame(frame).await?));
+                }
+                self.frames.truncate(index);
+                self.append_frame(old.offset, len, &encoded).await?;
+                for (len, encoded) in tail {
+                    let offset = self.frames_end();
+                    self.append_frame(offset, len, &encoded).await?;
+                }
+                let end = self.frames_end();
+                self.inner.set_len(end).await?;
+            }
+            None => {
+                debug_assert_eq!(index, self.frames.len());
+                let offset = self.frames_end();
+                self.append_frame(offset, len, &encoded).await?;
+            }
+        }
+        if last {
+            self.sealed = Some(index);
+        } else if self.sealed == Some(index) {
+            self.sealed = None;
+        }
+        Ok(())
+    }
+
+    async fn read_frame(&mut self, frame: Frame) -> io::Result<Vec<u8>> {
+        let mut encoded = vec![0; frame.encoded_len as usize];
+        self.inner
+            .seek(SeekFrom::Start(frame.offset + FRAME_HEADER_LEN))
+            .await?;
+        self.inner.read_exact(&mut encoded).await?;
+        Ok(encoded)
+    }
+
+    async fn write_frame(&mut self,
+                         offset: u64,
+                         len: u32,
+                         encoded: &[u8])
+                         -> io::Result<()> {
+        let mut frame = Vec::with_capacity(encoded.len() + 8);
+        frame.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(encoded);
+        self.inner.seek(SeekFrom::Start(offset)).await?;
+        self.inner.write_all(&frame).await
+    }
+
+    async fn append_frame(&mut self,
+                          offset: u64,
+                          len: u32,
+                          encoded: &[u8])
+                          -> io::Result<()> {
+        self.write_frame(offset, len, encoded).await?;
+        self.frames.push(Frame { offset,
+                                 encoded_len: encoded.len() as u32,
+                                 len });
+        Ok(())
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(bytes);
+    u32::from_le_bytes(buf)
+}
+
+/// Applies a signed `delta` to `base`, returning `None` if the result doesn't
+/// fit in a `u64`.
+fn offset(base: u64, delta: i64) -> Option<u64> {
+    if delta >= 0 {
+        base.checked_add(delta as u64)
+    } else {
+        base.checked_sub(delta.unsigned_abs())
+    }
+}
+
+pub(crate) fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{
+        mem_fs::MemFs, AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait
+    };
+
+    /// Drops trailing zeros, so that a chunk's encoded size depends on its
+    /// contents.
+    #[derive(Debug)]
+    struct TrimZeros;
+
+    impl FrameCodec for TrimZeros {
+        fn encode(&self,
+                  _index: u64,
+                  _last: bool,
+                  data: &[u8])
+                  -> io::Result<Vec<u8>> {
+            let end = data.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            Ok(data[..end].to_vec())
+        }
+
+        fn decode(&self,
+                  _index: u64,
+                  _last: bool,
+                  frame: &[u8],
+                  len: usize)
+                  -> io::Result<Vec<u8>> {
+            let mut data = frame.to_vec();
+            data.resize(len, 0);
+            Ok(data)
+        }
+    }
+
+    #[test]
+    fn growing_a_frame_moves_the_frames_after_it() {
+        block_on(async {
+            let fs = MemFs::new();
+            let open = || async {
+                let mut file = fs.open_options()
+                                 .read(true)
+                                 .write(true)
+                                 .create(true)
+                                 .open("/f")
+                                 .await
+                                 .unwrap();
+                let frames = scan(&mut file, 0, 8).await.unwrap();
+                State::new(file, TrimZeros, 0, 8, frames, false)
+            };
+
+            let mut state = open().await;
+            state.write(&[7; 20]).await.unwrap();
+            state.seek(SeekFrom::Start(2)).unwrap();
+            state.write(&[0; 6]).await.unwrap();
+            state.flush().await.unwrap();
+            let stored = state.inner().metadata().await.unwrap().len();
+
+            state.seek(SeekFrom::Start(3)).unwrap();
+            state.write(&[1; 3]).await.unwrap();
+            state.close().await.unwrap();
+            let grown = state.inner().metadata().await.unwrap().len();
+            assert_eq!(grown, stored + 4);
+
+            let mut state = open().await;
+            assert_eq!(state.len(), 20);
+            let mut expected = vec![7, 7, 0, 1, 1, 1, 0, 0];
+            expected.extend_from_slice(&[7; 12]);
+            let mut contents = Vec::new();
+            loop {
+                let data = state.read(5).await.unwrap();
+                if data.is_empty() {
+                    break;
+                }
+                contents.extend(data);
+            }
+            assert_eq!(contents, expected);
+        });
+    }
+}
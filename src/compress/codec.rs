@@ -0,0 +1,158 @@
+//! [`Codec`] picks the compression algorithm used for each chunk.
+
+use std::io;
+
+use crate::chunked::{invalid_data, FrameCodec};
+
+/// A compression algorithm.
+///
+/// Each variant is only available when the feature of the same name is
+/// enabled.  Files remember which codec they were written with, so a
+/// [`CompressedFs`][1] can read files written with any enabled codec, no
+/// matter which codec it uses for new files.
+///
+/// [1]: super::CompressedFs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Codec {
+    /// Gzip, from the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    Gzip,
+
+    /// Zstandard, from the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd,
+
+    /// LZ4 block compression, from the `lz4` feature.
+    #[cfg(feature = "lz4")]
+    Lz4
+}
+
+impl Codec {
+    /// The identifier stored in the header of files using this codec.
+    pub(super) fn id(self) -> u8 {
+        match self {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => 1,
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => 2,
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => 3
+        }
+    }
+
+    /// Returns the codec with the identifier `id`.
+    ///
+    /// Known codecs whose feature is disabled are reported as
+    /// [`ErrorKind::Unsupported`][1].
+    ///
+    /// [1]: io::ErrorKind::Unsupported
+    pub(super) fn from_id(id: u8) -> io::Result<Self> {
+        match id {
+            #[cfg(feature = "gzip")]
+            1 => Ok(Codec::Gzip),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Codec::Zstd),
+            #[cfg(feature = "lz4")]
+            3 => Ok(Codec::Lz4),
+            #[allow(unreachable_patterns)]
+            1..=3 => Err(io::Error::new(io::ErrorKind::Unsupported,
+                                        "the file was compressed with a \
+                                         codec whose feature is disabled")),
+            _ => Err(invalid_data("unknown compression codec"))
+        }
+    }
+}
+
+impl FrameCodec for Codec {
+    fn encode(&self,
+              _index: u64,
+              _last: bool,
+              data: &[u8])
+              -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => {
+                use std::io::Write;
+
+                use flate2::{write::GzEncoder, Compression};
+
+                let mut encoder =
+                    GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => zstd::bulk::compress(data, 0),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => Ok(lz4_flex::block::compress(data))
+        }
+    }
+
+    fn decode(&self,
+              _index: u64,
+              _last: bool,
+              frame: &[u8],
+              len: usize)
+              -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => {
+                use std::io::Read;
+
+                use flate2::read::GzDecoder;
+
+                let mut data = Vec::with_capacity(len);
+                GzDecoder::new(frame).read_to_end(&mut data)?;
+                Ok(data)
+            }
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => zstd::bulk::decompress(frame, len),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => lz4_flex::block::decompress(frame, len).map_err(|e| {
+                              io::Error::new(io::ErrorKind::InvalidData, e)
+                          })
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Vec<Codec> {
+        vec![#[cfg(feature = "gzip")]
+             Codec::Gzip,
+             #[cfg(feature = "zstd")]
+             Codec::Zstd,
+             #[cfg(feature = "lz4")]
+             Codec::Lz4]
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 7) as u8).collect();
+        for codec in all() {
+            let frame = codec.encode(0, true, &data).unwrap();
+            assert!(frame.len() < data.len(), "{:?} didn't compress", codec);
+            assert_eq!(codec.decode(0, true, &frame, data.len()).unwrap(),
+                       data);
+            assert_eq!(Codec::from_id(codec.id()).unwrap(), codec);
+        }
+    }
+
+    #[test]
+    fn unknown_codecs_are_rejected() {
+        let err = Codec::from_id(0xff).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
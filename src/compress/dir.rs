@@ -0,0 +1,178 @@
+//! [`CompressedReadDir`] lists the entries of a directory in a
+//! [`CompressedFs`][1].
+//!
+//! [1]: super::CompressedFs
+
+use std::{
+    ffi::OsString,
+    io,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::fs::uncompressed;
+use crate::{
+    layer::FileOf, AsyncDirEntryTrait, AsyncFsTrait, AsyncReadDirTrait,
+    FileType, Metadata
+};
+
+/// A stream of the entries in a directory of a [`CompressedFs`][1].
+///
+/// [1]: super::CompressedFs
+pub struct CompressedReadDir<F>
+    where F: AsyncFsTrait
+{
+    inner: F::ReadDir,
+    fs: Arc<F>
+}
+
+impl<F> CompressedReadDir<F> where F: AsyncFsTrait
+{
+    pub(super) fn new(inner: F::ReadDir, fs: Arc<F>) -> Self {
+        CompressedReadDir { inner, fs }
+    }
+}
+
+impl<F> std::fmt::Debug for CompressedReadDir<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedReadDir")
+         .field("inner", &self.inner)
+         .finish_non_exhaustive()
+    }
+}
+
+impl<F> Stream for CompressedReadDir<F> where F: AsyncFsTrait
+{
+    type Item = io::Result<CompressedDirEntry<F>>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let entry = match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(entry) => entry,
+            Poll::Pending => return Poll::Pending
+        };
+        Poll::Ready(entry.map(|entry| {
+                             entry.map(|inner| {
+                                      CompressedDirEntry::new(inner, &this.fs)
+                                  })
+                         }))
+    }
+}
+
+#[async_trait]
+impl<F> AsyncReadDirTrait<CompressedDirEntry<F>> for CompressedReadDir<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+}
+
+/// An entry in a directory of a [`CompressedFs`][1].
+///
+/// [`metadata()`][2] reports the uncompressed length of regular files, which
+/// means opening them.
+///
+/// [1]: super::CompressedFs
+/// [2]: AsyncDirEntryTrait::metadata
+pub struct CompressedDirEntry<F>
+    where F: AsyncFsTrait
+{
+    inner: F::DirEntry,
+    fs: Arc<F>
+}
+
+impl<F> CompressedDirEntry<F> where F: AsyncFsTrait
+{
+    fn new(inner: F::DirEntry, fs: &Arc<F>) -> Self {
+        CompressedDirEntry { inner,
+                             fs: Arc::clone(fs) }
+    }
+}
+
+impl<F> Clone for CompressedDirEntry<F> where F: AsyncFsTrait
+{
+    fn clone(&self) -> Self {
+        CompressedDirEntry::new(self.inner.clone(), &self.fs)
+    }
+}
+
+impl<F> std::fmt::Debug for CompressedDirEntry<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedDirEntry")
+         .field("inner", &self.inner)
+         .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncDirEntryTrait for CompressedDirEntry<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    async fn path(&self) -> PathBuf {
+        self.inner.path().await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        let metadata = self.inner.metadata().await?;
+        let path = self.inner.path().await;
+        uncompressed(&*self.fs, &path, metadata).await
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        self.inner.file_type().await
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.inner.file_name().await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{
+        compress::{Codec, CompressionLayer},
+        layer::FsLayer,
+        mem_fs::MemFs,
+        AsyncDirBuilderTrait
+    };
+
+    #[test]
+    fn directories_pass_through() {
+        block_on(async {
+            let codec = Codec::from_id(1).or_else(|_| Codec::from_id(2))
+                                         .or_else(|_| Codec::from_id(3))
+                                         .unwrap();
+            let fs = CompressionLayer::new(codec).layer(MemFs::new());
+            fs.dir_builder().create("/sub").await.unwrap();
+
+            let mut entries = fs.read_dir("/").await.unwrap();
+            let entry = entries.next().await.unwrap().unwrap();
+            assert_eq!(entry.file_name().await, "sub");
+            assert!(entry.file_type().await.unwrap().is_dir());
+            assert!(entry.metadata().await.unwrap().is_dir());
+            assert!(entries.next().await.is_none());
+        });
+    }
+}
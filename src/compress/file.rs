@@ -0,0 +1,486 @@
+//! [`CompressedOpenOptions`] opens [`CompressedFile`]s in a
+//! [`CompressedFs`][1].
+//!
+//! [1]: super::CompressedFs
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::{fs::Config, Codec};
+use crate::{
+    chunked::{decoded_len, invalid_data, scan, ChunkedFile, Inner, State},
+    layer::FileOf,
+    AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait, Metadata, Permissions
+};
+
+/// The first bytes of every compressed file.
+const MAGIC: &[u8; 4] = b"AFSZ";
+
+/// The version of the file format.
+const VERSION: u8 = 1;
+
+/// The length of the file header: the magic bytes, the version, the codec,
+/// two reserved bytes, and the chunk size as a little-endian `u32`.
+pub(super) const HEADER_LEN: u64 = 12;
+
+/// Builds the header for a new file.
+fn header(config: Config) -> [u8; HEADER_LEN as usize] {
+    let mut header = [0; HEADER_LEN as usize];
+    header[..4].copy_from_slice(MAGIC);
+    header[4] = VERSION;
+    header[5] = config.codec.id();
+    header[8..].copy_from_slice(&(config.chunk_size as u32).to_le_bytes());
+    header
+}
+
+/// Reads the header of `inner`, returning `None` if the file is empty.
+async fn read_header<T>(inner: &mut T) -> io::Result<Option<Config>>
+    where T: Inner
+{
+    let len = inner.seek(SeekFrom::End(0)).await?;
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let mut header = [0; HEADER_LEN as usize];
+    inner.seek(SeekFrom::Start(0)).await?;
+    match inner.read_exact(&mut header).await {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            return Err(invalid_data("not a compressed file"))
+        }
+        result => result?
+    }
+    if &header[..4] != MAGIC {
+        return Err(invalid_data("not a compressed file"));
+    }
+    if header[4] != VERSION {
+        return Err(invalid_data("unsupported compressed file version"));
+    }
+
+    let codec = Codec::from_id(header[5])?;
+    let mut chunk_size = [0; 4];
+    chunk_size.copy_from_slice(&header[8..]);
+    let chunk_size = u32::from_le_bytes(chunk_size) as usize;
+    if chunk_size == 0 {
+        return Err(invalid_data("compressed file has a chunk size of zero"));
+    }
+    Ok(Some(Config { codec, chunk_size }))
+}
+
+/// Returns the uncompressed length of the regular file at `path`.
+pub(super) async fn stored_len<F>(fs: &F, path: &Path) -> io::Result<u64>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    let mut file = fs.open_options().read(true).open(path).await?;
+    match read_header(&mut file).await? {
+        Some(config) => {
+            let frames = scan(&mut file, HEADER_LEN, config.chunk_size).await?;
+            Ok(decoded_len(&frames))
+        }
+        None => Ok(0)
+    }
+}
+
+/// Options for opening a [`CompressedFile`].
+///
+/// The options are passed on to the wrapped filesystem's builder, except that
+/// the underlying file is always opened for reading, and appending is done by
+/// this layer.  As with [`std::fs::OpenOptions`], a file can't be truncated
+/// and appended to at the same time.
+pub struct CompressedOpenOptions<F>
+    where F: AsyncFsTrait
+{
+    inner: F::FileBuilder,
+    config: Config,
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create_new: bool
+}
+
+impl<F> CompressedOpenOptions<F> where F: AsyncFsTrait
+{
+    pub(super) fn new(mut inner: F::FileBuilder, config: Config) -> Self {
+        inner.read(true);
+        CompressedOpenOptions { inner,
+                                config,
+                                read: false,
+                                write: false,
+                                append: false,
+                                truncate: false,
+                                create_new: false }
+    }
+}
+
+impl<F> std::fmt::Debug for CompressedOpenOptions<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedOpenOptions")
+         .field("inner", &self.inner)
+         .field("config", &self.config)
+         .field("read", &self.read)
+         .field("write", &self.write)
+         .field("append", &self.append)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFileBuilderTrait for CompressedOpenOptions<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    type File = CompressedFile<F>;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self.inner.write(self.write || self.append);
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self.inner.write(self.write || self.append);
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self.inner.truncate(truncate);
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.inner.create(create);
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self.inner.create_new(create_new);
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        if self.append && self.truncate && !self.create_new {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "a file can't be truncated and \
+                                       appended to at the same time"));
+        }
+
+        let writable = self.write || self.append;
+        let mut inner = self.inner.open(path).await?;
+        let config = match read_header(&mut inner).await? {
+            Some(config) => config,
+            None if writable => {
+                inner.seek(SeekFrom::Start(0)).await?;
+                inner.write_all(&header(self.config)).await?;
+                self.config
+            }
+            None => self.config
+        };
+        let frames = scan(&mut inner, HEADER_LEN, config.chunk_size).await?;
+        let state = State::new(inner,
+                               config.codec,
+                               HEADER_LEN,
+                               config.chunk_size,
+                               frames,
+                               self.append);
+
+        Ok(CompressedFile { file: ChunkedFile::new(state),
+                            read: self.read,
+                            write: writable })
+    }
+}
+
+/// An open file in a [`CompressedFs`][1].
+///
+/// Reads, writes, and seeks all work in terms of the uncompressed contents,
+/// and [`metadata()`][2] reports the uncompressed length.  The chunk around
+/// the cursor is kept in memory, and is only compressed and written out when
+/// the cursor moves to another chunk or the file is flushed, so **a file that
+/// was written to must be flushed or closed before it is dropped**.
+///
+/// Each file keeps its own copy of the frame index, so a file shouldn't be
+/// written to through more than one handle at a time.
+///
+/// [1]: super::CompressedFs
+/// [2]: AsyncFileTrait::metadata
+pub struct CompressedFile<F>
+    where F: AsyncFsTrait
+{
+    file: ChunkedFile<FileOf<F>, Codec>,
+    read: bool,
+    write: bool
+}
+
+impl<F> CompressedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    /// Returns an error unless this file was opened with write access.
+    fn check_writable(&self) -> io::Result<()> {
+        if self.write {
+            Ok(())
+        } else {
+            Err(not_opened_for("writing"))
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for CompressedFile<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedFile")
+         .field("file", &self.file)
+         .field("read", &self.read)
+         .field("write", &self.write)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFileTrait for CompressedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    async fn sync_all(&self) -> io::Result<()> {
+        self.file.state().await.sync_all().await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.file.state().await.sync_data().await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.check_writable()?;
+        self.file.state().await.set_len(size).await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        let state = self.file.state().await;
+        let metadata = state.inner().metadata().await?;
+        Ok(metadata.with_len(state.len()))
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        self.file.state().await.inner().set_permissions(perm).await
+    }
+}
+
+impl<F> AsyncRead for CompressedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        if !self.read {
+            return Poll::Ready(Err(not_opened_for("reading")));
+        }
+        self.get_mut().file.poll_read(cx, buf)
+    }
+}
+
+impl<F> AsyncWrite for CompressedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    fn poll_write(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        if let Err(e) = self.check_writable() {
+            return Poll::Ready(Err(e));
+        }
+        self.get_mut().file.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        self.get_mut().file.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        self.get_mut().file.poll_close(cx)
+    }
+}
+
+impl<F> AsyncSeek for CompressedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        self.get_mut().file.poll_seek(cx, pos)
+    }
+}
+
+fn not_opened_for(access: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   format!("file was not opened for {}", access))
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{compress::CompressionLayer, layer::FsLayer, mem_fs::MemFs};
+
+    fn codec() -> Codec {
+        Codec::from_id(1).or_else(|_| Codec::from_id(2))
+                         .or_else(|_| Codec::from_id(3))
+                         .unwrap()
+    }
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn seeks_read_and_write_uncompressed_offsets() {
+        block_on(async {
+            let fs = CompressionLayer::new(codec()).chunk_size(100)
+                                                   .layer(MemFs::new());
+            let data = sample(1000);
+            let mut file = fs.open_options()
+                             .read(true)
+                             .write(true)
+                             .create(true)
+                             .open("/f")
+                             .await
+                             .unwrap();
+            file.write_all(&data).await.unwrap();
+
+            file.seek(SeekFrom::Start(250)).await.unwrap();
+            file.write_all(b"hello").await.unwrap();
+            file.seek(SeekFrom::End(-3)).await.unwrap();
+            let mut tail = Vec::new();
+            file.read_to_end(&mut tail).await.unwrap();
+            assert_eq!(tail, data[997..]);
+            file.close().await.unwrap();
+
+            let mut expected = data.clone();
+            expected[250..255].copy_from_slice(b"hello");
+            let mut file =
+                fs.open_options().read(true).open("/f").await.unwrap();
+            file.seek(SeekFrom::Start(245)).await.unwrap();
+            let mut middle = [0; 15];
+            file.read_exact(&mut middle).await.unwrap();
+            assert_eq!(middle, expected[245..260]);
+            assert_eq!(file.metadata().await.unwrap().len(), 1000);
+        });
+    }
+
+    #[test]
+    fn set_len_truncates_and_zero_fills() {
+        block_on(async {
+            let fs = CompressionLayer::new(codec()).chunk_size(64)
+                                                   .layer(MemFs::new());
+            let data = sample(300);
+            let mut file = fs.open_options()
+                             .read(true)
+                             .write(true)
+                             .create(true)
+                             .open("/f")
+                             .await
+                             .unwrap();
+            file.write_all(&data).await.unwrap();
+            file.set_len(100).await.unwrap();
+            file.set_len(150).await.unwrap();
+            file.seek(SeekFrom::Start(0)).await.unwrap();
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).await.unwrap();
+
+            let mut expected = data[..100].to_vec();
+            expected.resize(150, 0);
+            assert_eq!(contents, expected);
+        });
+    }
+
+    #[test]
+    fn appends_go_to_the_end() {
+        block_on(async {
+            let fs = CompressionLayer::new(codec()).layer(MemFs::new());
+            for part in [&b"one "[..], b"two"] {
+                let mut file = fs.open_options()
+                                 .append(true)
+                                 .create(true)
+                                 .open("/f")
+                                 .await
+                                 .unwrap();
+                file.write_all(part).await.unwrap();
+                file.close().await.unwrap();
+            }
+
+            let mut file =
+                fs.open_options().read(true).open("/f").await.unwrap();
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, "one two");
+
+            let err = fs.open_options()
+                        .append(true)
+                        .truncate(true)
+                        .open("/f")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn files_without_a_header_are_rejected() {
+        block_on(async {
+            let mem = MemFs::new();
+            let mut file = mem.open_options()
+                              .write(true)
+                              .create(true)
+                              .open("/plain")
+                              .await
+                              .unwrap();
+            file.write_all(b"not compressed at all").await.unwrap();
+
+            let fs = CompressionLayer::new(codec()).layer(mem);
+            let err = fs.open_options()
+                        .read(true)
+                        .open("/plain")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+    }
+}
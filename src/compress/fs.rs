@@ -0,0 +1,315 @@
+//! [`CompressionLayer`] wraps a filesystem in a [`CompressedFs`].
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{
+    file::stored_len, Codec, CompressedDirEntry, CompressedOpenOptions,
+    CompressedReadDir
+};
+use crate::{
+    layer::{FileOf, FsLayer},
+    AsyncFsTrait, AsyncSymLinkTrait, Metadata, Permissions
+};
+
+/// How new files are compressed.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Config {
+    pub(super) codec: Codec,
+    pub(super) chunk_size: usize
+}
+
+/// A layer that compresses the contents of every file.
+///
+/// New files are compressed with the layer's [`Codec`], in chunks of
+/// [`chunk_size()`][1] uncompressed bytes.  Larger chunks compress better, but
+/// every read or write that lands in a chunk has to decompress all of it.
+///
+/// [1]: CompressionLayer::chunk_size
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionLayer {
+    config: Config
+}
+
+impl CompressionLayer {
+    /// The chunk size used unless another one is set, 64 KiB.
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Creates a layer that compresses new files with `codec`.
+    pub fn new(codec: Codec) -> Self {
+        CompressionLayer { config: Config { codec,
+                                            chunk_size:
+                                                Self::DEFAULT_CHUNK_SIZE } }
+    }
+
+    /// Sets the number of uncompressed bytes in each chunk of a new file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero or doesn't fit in a `u32`.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0 && u32::try_from(chunk_size).is_ok(),
+                "chunk size must be between 1 and u32::MAX");
+        self.config.chunk_size = chunk_size;
+        self
+    }
+}
+
+impl<F> FsLayer<F> for CompressionLayer
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    type Fs = CompressedFs<F>;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        CompressedFs { inner: Arc::new(inner),
+                       config: self.config }
+    }
+}
+
+/// A filesystem whose file contents are compressed.
+///
+/// Directories, links, and permissions pass straight through to the wrapped
+/// filesystem.  Regular files are stored in a chunked format, and every
+/// length this filesystem reports is the uncompressed length.  Regular files
+/// that weren't written through this layer can't be read, and make
+/// [`metadata()`][1] fail with [`ErrorKind::InvalidData`][2], with the
+/// exception of empty files.
+///
+/// This type is only available when at least one of the `gzip`, `zstd`, or
+/// `lz4` features is enabled.
+///
+/// [1]: AsyncFsTrait::metadata
+/// [2]: io::ErrorKind::InvalidData
+pub struct CompressedFs<F> {
+    inner: Arc<F>,
+    config: Config
+}
+
+impl<F> CompressedFs<F> {
+    /// Returns a reference to the wrapped filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F> Clone for CompressedFs<F> {
+    fn clone(&self) -> Self {
+        CompressedFs { inner: Arc::clone(&self.inner),
+                       config: self.config }
+    }
+}
+
+impl<F> std::fmt::Debug for CompressedFs<F> where F: std::fmt::Debug
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedFs")
+         .field("inner", &self.inner)
+         .field("config", &self.config)
+         .finish()
+    }
+}
+
+impl<F> CompressedFs<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    /// Replaces the length in `metadata` with the uncompressed length, if
+    /// `path` is a regular file.
+    async fn uncompressed(&self,
+                          path: &Path,
+                          metadata: Metadata)
+                          -> io::Result<Metadata> {
+        uncompressed(&*self.inner, path, metadata).await
+    }
+}
+
+/// Replaces the length in `metadata` with the uncompressed length, if `path`
+/// is a regular file.
+pub(super) async fn uncompressed<F>(fs: &F,
+                                    path: &Path,
+                                    metadata: Metadata)
+                                    -> io::Result<Metadata>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    if metadata.is_file() {
+        let len = stored_len(fs, path).await?;
+        Ok(metadata.with_len(len))
+    } else {
+        Ok(metadata)
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFsTrait for CompressedFs<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    type DirBuilder = F::DirBuilder;
+    type DirEntry = CompressedDirEntry<F>;
+    type FileBuilder = CompressedOpenOptions<F>;
+    type ReadDir = CompressedReadDir<F>;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        self.inner.dir_builder()
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        CompressedOpenOptions::new(self.inner.open_options(), self.config)
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.canonicalize(path).await
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        self.inner.rename(src, dst).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.set_permissions(path, perm).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        self.inner.hard_link(src, dst).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.read_link(path).await
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let metadata = self.inner.symlink_metadata(path).await?;
+        self.uncompressed(path, metadata).await
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let metadata = self.inner.metadata(path).await?;
+        self.uncompressed(path, metadata).await
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        // The compressed bytes are copied as they are.
+        self.inner.copy(src, dst.as_ref()).await?;
+        stored_len(&*self.inner, dst.as_ref()).await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.remove_file(path).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let inner = self.inner.read_dir(path).await?;
+        Ok(CompressedReadDir::new(inner, Arc::clone(&self.inner)))
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.remove_dir(path).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.remove_dir_all(path).await
+    }
+}
+
+#[async_trait]
+impl<F> AsyncSymLinkTrait for CompressedFs<F>
+    where F: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        self.inner.symlink(src, dst).await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncWriteExt, StreamExt};
+
+    use super::*;
+    use crate::{
+        mem_fs::MemFs, AsyncDirEntryTrait, AsyncFileBuilderTrait,
+        AsyncFileTrait
+    };
+
+    #[test]
+    fn lengths_are_uncompressed() {
+        block_on(async {
+            let codec = Codec::from_id(1).or_else(|_| Codec::from_id(2))
+                                         .or_else(|_| Codec::from_id(3))
+                                         .unwrap();
+            let fs = CompressionLayer::new(codec).layer(MemFs::new());
+            let mut file = fs.open_options()
+                             .write(true)
+                             .create(true)
+                             .open("/zeros")
+                             .await
+                             .unwrap();
+            file.write_all(&[0; 100_000]).await.unwrap();
+            file.close().await.unwrap();
+            fs.copy("/zeros", "/copy").await.unwrap();
+
+            let raw = fs.get_ref().metadata("/zeros").await.unwrap().len();
+            assert!(raw < 10_000, "{} bytes stored", raw);
+            assert_eq!(fs.metadata("/zeros").await.unwrap().len(), 100_000);
+            assert_eq!(file.metadata().await.unwrap().len(), 100_000);
+
+            let mut entries = fs.read_dir("/").await.unwrap();
+            while let Some(entry) = entries.next().await {
+                let metadata = entry.unwrap().metadata().await.unwrap();
+                assert_eq!(metadata.len(), 100_000);
+            }
+        });
+    }
+}
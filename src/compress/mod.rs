@@ -0,0 +1,47 @@
+//! A layer that transparently compresses file contents.
+//!
+//! [`CompressionLayer`] wraps a filesystem in a [`CompressedFs`].  Data
+//! written through [`AsyncWrite`][1] is compressed before it reaches the
+//! wrapped filesystem, and decompressed again by [`AsyncRead`][2].  Lengths
+//! reported by [`metadata()`][3], whether on the filesystem, an open file, or a
+//! directory entry, are always the uncompressed lengths.
+//!
+//! Files are stored as a 12-byte header followed by a sequence of
+//! independently compressed chunks.  Every chunk holds the same number of
+//! uncompressed bytes, except for the last one, so seeking only has to
+//! decompress the chunk that the cursor lands in, and
+//! [`set_len()`][4] only has to rewrite the chunk at the new end.  Writing
+//! into the middle of a file recompresses the chunk that was written to, and
+//! moves every chunk after it if its compressed size changed.
+//!
+//! The wrapped filesystem's files must implement [`AsyncRead`][2],
+//! [`AsyncWrite`][1], and [`AsyncSeek`][5].
+//!
+//! | Codec            | Feature |
+//! |------------------|---------|
+//! | [`Codec::Gzip`]  | `gzip`  |
+//! | [`Codec::Zstd`]  | `zstd`  |
+//! | [`Codec::Lz4`]   | `lz4`   |
+//!
+//! This module is only available when at least one of these features is
+//! enabled.
+//!
+//! [1]: crate::AsyncWrite
+//! [2]: crate::AsyncRead
+//! [3]: crate::AsyncFileTrait::metadata
+//! [4]: crate::AsyncFileTrait::set_len
+//! [5]: crate::AsyncSeek
+
+mod codec;
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use codec::Codec;
+#[doc(inline)]
+pub use dir::{CompressedDirEntry, CompressedReadDir};
+#[doc(inline)]
+pub use file::{CompressedFile, CompressedOpenOptions};
+#[doc(inline)]
+pub use fs::{CompressedFs, CompressionLayer};
@@ -0,0 +1,582 @@
+//! [`check_fs()`] and [`check_symlinks()`], and the checks they run.
+
+use std::{
+    future::Future,
+    io::{self, SeekFrom},
+    path::{Path, PathBuf}
+};
+
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::{
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    StreamExt
+};
+
+use super::{CheckResult, ConformanceReport, Semantic};
+use crate::{
+    layer::FileOf, AsyncDirBuilderTrait, AsyncDirEntryTrait,
+    AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait, AsyncSymLinkTrait,
+    FileType
+};
+
+/// Checks that `fs` follows the documented semantics of [`AsyncFsTrait`],
+/// [`AsyncFileBuilderTrait`], [`AsyncFileTrait`], and
+/// [`AsyncDirBuilderTrait`].
+///
+/// Each check runs in a scratch directory of its own below `dir`, which is
+/// created if it is missing, and the scratch directories are removed
+/// afterwards.  Whatever is already in `dir` is left alone, except for
+/// entries named after a [`Semantic`].
+pub async fn check_fs<F, P>(fs: &F, dir: P) -> ConformanceReport
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin,
+          P: AsRef<Path>
+{
+    let dir = dir.as_ref();
+    let mut report = ConformanceReport::default();
+    report.checks
+          .push(check(fs, dir, Semantic::RenameOverwrites, |at| {
+                    rename_overwrites(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::CopyOverwrites, |at| {
+                    copy_overwrites(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::RemoveDirRejectsNonEmpty, |at| {
+                    remove_dir_rejects_non_empty(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::ReadDirPathsJoin, |at| {
+                    read_dir_paths_join(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::MissingPathsAreNotFound, |at| {
+                    missing_paths_are_not_found(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::CreateNewFailsOnExisting, |at| {
+                    create_new_fails_on_existing(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::WriteKeepsTrailingContents, |at| {
+                    write_keeps_trailing_contents(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::AppendWritesAtEnd, |at| {
+                    append_writes_at_end(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::SetLenZeroFills, |at| {
+                    set_len_zero_fills(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::SetLenKeepsCursor, |at| {
+                    set_len_keeps_cursor(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::CreateDirRejectsExisting, |at| {
+                    create_dir_rejects_existing(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::RecursiveCreatesParents, |at| {
+                    recursive_creates_parents(fs, at)
+                }).await);
+    report
+}
+
+/// Checks that `fs` follows the documented semantics of
+/// [`AsyncSymLinkTrait`], in the same way as [`check_fs()`].
+pub async fn check_symlinks<F, P>(fs: &F, dir: P) -> ConformanceReport
+    where F: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<F>: AsyncWrite + Unpin,
+          P: AsRef<Path>
+{
+    let dir = dir.as_ref();
+    let mut report = ConformanceReport::default();
+    report.checks
+          .push(check(fs, dir, Semantic::SymlinkReadsBack, |at| {
+                    symlink_reads_back(fs, at)
+                }).await);
+    report.checks
+          .push(check(fs, dir, Semantic::SymlinkMetadataNotFollowed, |at| {
+                    symlink_metadata_not_followed(fs, at)
+                }).await);
+    report
+}
+
+/// Runs `body` in a fresh scratch directory for `semantic` below `dir`.
+async fn check<F, B, Fut>(fs: &F,
+                          dir: &Path,
+                          semantic: Semantic,
+                          body: B)
+                          -> CheckResult
+    where F: AsyncFsTrait,
+          B: FnOnce(PathBuf) -> Fut,
+          Fut: Future<Output = io::Result<()>>
+{
+    let at = dir.join(semantic.name());
+    // Whatever a previous, interrupted run left behind is cleared out first.
+    let _ = fs.remove_dir_all(&at).await;
+    let mut result = step("creating the scratch directory",
+                          fs.dir_builder().recursive(true).create(&at)).await;
+    if result.is_ok() {
+        result = body(at.clone()).await;
+    }
+    let _ = fs.remove_dir_all(&at).await;
+    CheckResult { semantic,
+                  deviation: result.err().map(|err| err.to_string()) }
+}
+
+/// Says what was being done when an unexpected error occurs.
+async fn step<T, Fut>(what: &str, fut: Fut) -> io::Result<T>
+    where Fut: Future<Output = io::Result<T>>
+{
+    fut.await.map_err(|err| {
+                 io::Error::new(err.kind(), format!("{} failed: {}", what, err))
+             })
+}
+
+fn deviation(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+fn ensure<M>(ok: bool, message: M) -> io::Result<()>
+    where M: FnOnce() -> String
+{
+    match ok {
+        true => Ok(()),
+        false => Err(deviation(message()))
+    }
+}
+
+fn show(bytes: &[u8]) -> String {
+    format!("\"{}\"", bytes.escape_ascii())
+}
+
+async fn write_file<F>(fs: &F, path: &Path, contents: &[u8]) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncWrite + Unpin
+{
+    let mut file = step("creating a file",
+                        fs.open_options()
+                          .write(true)
+                          .create(true)
+                          .truncate(true)
+                          .open(path)).await?;
+    step("writing a file", file.write_all(contents)).await?;
+    step("closing a file", file.close()).await
+}
+
+async fn read_file<F>(fs: &F, path: &Path) -> io::Result<Vec<u8>>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + Unpin
+{
+    let mut file =
+        step("opening a file", fs.open_options().read(true).open(path)).await?;
+    let mut contents = Vec::new();
+    step("reading a file", file.read_to_end(&mut contents)).await?;
+    Ok(contents)
+}
+
+/// Checks that a file holds `expected`.
+async fn expect_contents<F>(fs: &F,
+                            path: &Path,
+                            expected: &[u8])
+                            -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + Unpin
+{
+    let contents = read_file(fs, path).await?;
+    ensure(contents == expected, || {
+        format!("the file holds {} rather than {}",
+                show(&contents),
+                show(expected))
+    })
+}
+
+/// Checks that `result` is a [`NotFound`][1] error.
+///
+/// [1]: io::ErrorKind::NotFound
+fn expect_not_found<T>(what: &str, result: io::Result<T>) -> io::Result<()> {
+    match result {
+        Ok(_) => {
+            Err(deviation(format!("{} succeeded on a missing path", what)))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(deviation(format!("{} failed with {:?} rather than \
+                                           NotFound: {}",
+                                          what,
+                                          err.kind(),
+                                          err)))
+    }
+}
+
+async fn rename_overwrites<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + Unpin
+{
+    let (from, to) = (at.join("from"), at.join("to"));
+    write_file(fs, &from, b"new").await?;
+    write_file(fs, &to, b"old contents").await?;
+    step("renaming over a file", fs.rename(&from, &to)).await?;
+    expect_contents(fs, &to, b"new").await?;
+    ensure(fs.symlink_metadata(&from).await.is_err(), || {
+        "the renamed file is still at its old path".to_string()
+    })
+}
+
+async fn copy_overwrites<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + Unpin
+{
+    let (from, to) = (at.join("from"), at.join("to"));
+    write_file(fs, &from, b"copied").await?;
+    write_file(fs, &to, b"old, longer contents").await?;
+    let copied = step("copying over a file", fs.copy(&from, &to)).await?;
+    ensure(copied == 6, || {
+        format!("copy() returned {} rather than the 6 bytes copied", copied)
+    })?;
+    expect_contents(fs, &to, b"copied").await?;
+    expect_contents(fs, &from, b"copied").await
+}
+
+async fn remove_dir_rejects_non_empty<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncWrite + Unpin
+{
+    let (dir, file) = (at.join("dir"), at.join("dir/file"));
+    step("creating a directory", fs.dir_builder().create(&dir)).await?;
+    write_file(fs, &file, b"").await?;
+    ensure(fs.remove_dir(&dir).await.is_err(), || {
+        "remove_dir() removed a directory with a file in it".to_string()
+    })?;
+    ensure(fs.metadata(&file).await.is_ok(), || {
+        "remove_dir() failed, but removed the file in the directory".to_string()
+    })?;
+    step("removing a file", fs.remove_file(&file)).await?;
+    step("removing an empty directory", fs.remove_dir(&dir)).await?;
+    ensure(fs.metadata(&dir).await.is_err(), || {
+        "remove_dir() succeeded, but left the directory in place".to_string()
+    })
+}
+
+async fn read_dir_paths_join<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncWrite + Unpin
+{
+    let dir = at.join("dir");
+    step("creating a directory",
+         fs.dir_builder().recursive(true).create(dir.join("c"))).await?;
+    write_file(fs, &dir.join("a"), b"").await?;
+    write_file(fs, &dir.join("b"), b"").await?;
+    let mut entries = step("reading a directory", fs.read_dir(&dir)).await?;
+    let mut names = Vec::new();
+    while let Some(entry) = entries.next().await {
+        let entry = step("reading a directory entry", async { entry }).await?;
+        let (name, path) = (entry.file_name().await, entry.path().await);
+        ensure(path == dir.join(&name), || {
+            format!("the entry {:?} has the path {} rather than {}",
+                    name,
+                    path.display(),
+                    dir.join(&name).display())
+        })?;
+        names.push(name);
+    }
+    names.sort();
+    ensure(names == ["a", "b", "c"], || {
+        format!("read_dir() streamed {:?} rather than [\"a\", \"b\", \"c\"]",
+                names)
+    })
+}
+
+async fn missing_paths_are_not_found<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait
+{
+    let missing = at.join("missing");
+    expect_not_found("metadata()", fs.metadata(&missing).await)?;
+    expect_not_found("symlink_metadata()",
+                     fs.symlink_metadata(&missing).await)?;
+    expect_not_found("read_dir()", fs.read_dir(&missing).await)?;
+    expect_not_found("remove_file()", fs.remove_file(&missing).await)?;
+    expect_not_found("opening a file for reading",
+                     fs.open_options().read(true).open(&missing).await)
+}
+
+async fn create_new_fails_on_existing<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + Unpin
+{
+    let file = at.join("file");
+    step("creating a new file",
+         fs.open_options().write(true).create_new(true).open(&file)).await?;
+    write_file(fs, &file, b"kept").await?;
+    let reopened = fs.open_options()
+                     .write(true)
+                     .create_new(true)
+                     .open(&file)
+                     .await;
+    ensure(reopened.is_err(), || {
+        "create_new opened a file that already exists".to_string()
+    })?;
+    expect_contents(fs, &file, b"kept").await
+}
+
+async fn write_keeps_trailing_contents<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + Unpin
+{
+    let path = at.join("file");
+    write_file(fs, &path, b"hello world").await?;
+    let mut file = step("opening a file for writing",
+                        fs.open_options().write(true).open(&path)).await?;
+    step("writing a file", file.write_all(b"J")).await?;
+    step("closing a file", file.close()).await?;
+    expect_contents(fs, &path, b"Jello world").await
+}
+
+async fn append_writes_at_end<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin
+{
+    let path = at.join("file");
+    write_file(fs, &path, b"abc").await?;
+    let mut file = step("opening a file for appending",
+                        fs.open_options().append(true).open(&path)).await?;
+    step("appending to a file", file.write_all(b"de")).await?;
+    step("flushing a file", file.flush()).await?;
+    // Every write goes to the end, wherever the cursor was moved to.
+    step("seeking in a file", file.seek(SeekFrom::Start(0))).await?;
+    step("appending to a file", file.write_all(b"f")).await?;
+    step("closing a file", file.close()).await?;
+    expect_contents(fs, &path, b"abcdef").await
+}
+
+async fn set_len_zero_fills<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin
+{
+    let path = at.join("file");
+    let mut file = step("creating a file",
+                        fs.open_options()
+                          .read(true)
+                          .write(true)
+                          .create(true)
+                          .open(&path)).await?;
+    step("writing a file", file.write_all(b"abcdef")).await?;
+    step("flushing a file", file.flush()).await?;
+    step("truncating a file", file.set_len(2)).await?;
+    let len = step("reading a file's metadata", file.metadata()).await?
+                                                                .len();
+    ensure(len == 2, || {
+        format!("set_len(2) left the file {} bytes long", len)
+    })?;
+    step("extending a file", file.set_len(5)).await?;
+    step("seeking in a file", file.seek(SeekFrom::Start(0))).await?;
+    let mut contents = Vec::new();
+    step("reading a file", file.read_to_end(&mut contents)).await?;
+    ensure(contents == b"ab\0\0\0", || {
+        format!("truncating to 2 bytes and extending to 5 left {} rather \
+                 than {}",
+                show(&contents),
+                show(b"ab\0\0\0"))
+    })
+}
+
+async fn set_len_keeps_cursor<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin
+{
+    let path = at.join("file");
+    let mut file = step("creating a file",
+                        fs.open_options()
+                          .read(true)
+                          .write(true)
+                          .create(true)
+                          .open(&path)).await?;
+    step("writing a file", file.write_all(b"abcdef")).await?;
+    step("flushing a file", file.flush()).await?;
+    for len in [2, 10] {
+        step("changing a file's length", file.set_len(len)).await?;
+        let cursor =
+            step("seeking in a file", file.seek(SeekFrom::Current(0))).await?;
+        ensure(cursor == 6, || {
+            format!("set_len({}) moved the cursor from 6 to {}", len, cursor)
+        })?;
+    }
+    step("writing a file", file.write_all(b"X")).await?;
+    step("closing a file", file.close()).await?;
+    expect_contents(fs, &path, b"ab\0\0\0\0X\0\0\0").await
+}
+
+async fn create_dir_rejects_existing<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait
+{
+    let dir = at.join("dir");
+    step("creating a directory", fs.dir_builder().create(&dir)).await?;
+    ensure(fs.dir_builder().create(&dir).await.is_err(), || {
+        "create() succeeded on a directory that already exists".to_string()
+    })?;
+    step("creating an existing directory recursively",
+         fs.dir_builder().recursive(true).create(&dir)).await
+}
+
+async fn recursive_creates_parents<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait
+{
+    step("creating directories recursively",
+         fs.dir_builder().recursive(true).create(at.join("a/b/c"))).await?;
+    for path in ["a", "a/b", "a/b/c"] {
+        let metadata = step("reading a directory's metadata",
+                            fs.metadata(at.join(path))).await?;
+        ensure(metadata.is_dir(), || {
+            format!("{} was created as a {:?}", path, metadata.file_type())
+        })?;
+    }
+    Ok(())
+}
+
+async fn symlink_reads_back<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<F>: AsyncWrite + Unpin
+{
+    write_file(fs, &at.join("target"), b"").await?;
+    let targets = [PathBuf::from("target"), at.join("target")];
+    for (link, target) in ["relative", "absolute"].iter().zip(targets) {
+        let link = at.join(link);
+        step("creating a symlink", fs.symlink(&link, &target)).await?;
+        let read = step("reading a symlink", fs.read_link(&link)).await?;
+        ensure(read == target, || {
+            format!("a symlink to {} reads back as {}",
+                    target.display(),
+                    read.display())
+        })?;
+    }
+    Ok(())
+}
+
+async fn symlink_metadata_not_followed<F>(fs: &F, at: PathBuf) -> io::Result<()>
+    where F: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<F>: AsyncWrite + Unpin
+{
+    let link = at.join("link");
+    write_file(fs, &at.join("target"), b"").await?;
+    step("creating a symlink", fs.symlink(&link, "target")).await?;
+    let file_type = step("reading a symlink's metadata",
+                         fs.symlink_metadata(&link)).await?
+                                                    .file_type();
+    ensure(file_type == FileType::Symlink, || {
+        format!("symlink_metadata() describes a symlink as a {:?}",
+                file_type)
+    })?;
+    let file_type = step("reading the metadata of a symlink's target",
+                         fs.metadata(&link)).await?
+                                            .file_type();
+    ensure(file_type == FileType::File, || {
+        format!("metadata() describes a symlink to a file as a {:?}",
+                file_type)
+    })
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test,
+          any(feature = "mem-fs",
+              feature = "std-fs",
+              feature = "tokio",
+              feature = "async-std",
+              feature = "smol")))]
+mod tests {
+    #[cfg(any(feature = "mem-fs",
+              feature = "std-fs",
+              feature = "async-std",
+              feature = "smol"))]
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    #[cfg(feature = "mem-fs")]
+    #[test]
+    fn mem_fs_conforms() {
+        use crate::mem_fs::MemFs;
+
+        block_on(async {
+            let fs = MemFs::new();
+            let mut report = check_fs(&fs, "/scratch").await;
+            report.extend(check_symlinks(&fs, "/scratch").await.checks);
+            report.assert_conformant(&[]);
+            assert_eq!(report.checks.len(), 14);
+            assert!(fs.read_dir("/scratch")
+                      .await
+                      .unwrap()
+                      .next()
+                      .await
+                      .is_none());
+        });
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn std_fs_conforms() {
+        use crate::std_fs::StdFs;
+
+        let dir = tempfile::tempdir().unwrap();
+        block_on(async {
+            let fs = StdFs::new();
+            let mut report = check_fs(&fs, dir.path()).await;
+            #[cfg(unix)]
+            report.extend(check_symlinks(&fs, dir.path()).await.checks);
+            report.assert_conformant(&[]);
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_fs_conforms() {
+        use crate::tokio_fs::TokioFs;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fs = TokioFs::new();
+        let mut report = check_fs(&fs, dir.path()).await;
+        #[cfg(unix)]
+        report.extend(check_symlinks(&fs, dir.path()).await.checks);
+        report.assert_conformant(&[]);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn async_std_fs_conforms() {
+        use crate::async_std_fs::AsyncStdFs;
+
+        let dir = tempfile::tempdir().unwrap();
+        block_on(async {
+            let fs = AsyncStdFs::new();
+            let mut report = check_fs(&fs, dir.path()).await;
+            #[cfg(unix)]
+            report.extend(check_symlinks(&fs, dir.path()).await.checks);
+            report.assert_conformant(&[]);
+        });
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn smol_fs_conforms() {
+        use crate::smol_fs::SmolFs;
+
+        let dir = tempfile::tempdir().unwrap();
+        block_on(async {
+            let fs = SmolFs::new();
+            let mut report = check_fs(&fs, dir.path()).await;
+            #[cfg(unix)]
+            report.extend(check_symlinks(&fs, dir.path()).await.checks);
+            report.assert_conformant(&[]);
+        });
+    }
+}
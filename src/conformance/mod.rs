@@ -0,0 +1,68 @@
+//! A conformance test kit for implementors of the filesystem traits.
+//!
+//! The traits document semantics that generic code, such as the walks,
+//! copies, and syncs in this crate, relies on: that [`rename()`][1]
+//! overwrites, that [`create_new`][2] fails on existing files, that
+//! [`set_len()`][3] zero-fills and keeps the cursor, and so on.  Nothing in
+//! the type system enforces them.  [`check_fs()`] and [`check_symlinks()`]
+//! run a backend through each of them in a scratch directory, and return a
+//! [`ConformanceReport`] saying which [`Semantic`]s it deviates from, and
+//! how:
+//!
+//! ```
+//! # #[cfg(feature = "mem-fs")]
+//! # {
+//! use async_fs_traits::{
+//!     conformance::{check_fs, check_symlinks},
+//!     mem_fs::MemFs
+//! };
+//! use futures_lite::future::block_on;
+//!
+//! let fs = MemFs::new();
+//! block_on(async {
+//!     let mut report = check_fs(&fs, "/scratch").await;
+//!     report.extend(check_symlinks(&fs, "/scratch").await.checks);
+//!     print!("{}", report);
+//!     report.assert_conformant(&[]);
+//! });
+//! # }
+//! ```
+//!
+//! Backends usually run the kit from a test, by enabling the `conformance`
+//! feature in their dev-dependencies.  Semantics that a backend knowingly
+//! doesn't follow can be passed to
+//! [`assert_conformant()`][4], so that the test only catches regressions.
+//!
+//! The checks only cover the cases they were written for.  With the
+//! `proptest` feature, [`check_model()`] also replays random sequences of
+//! [`Op`]s, mixing changes to the tree with reads, writes, and seeks on open
+//! files, against both a backend and an in-memory reference model.  Any
+//! difference in what they return, or in the trees they leave behind, fails
+//! the test with the shortest sequence that still shows it.
+//!
+//! This module is only available when the `conformance` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait::rename
+//! [2]: crate::AsyncFileBuilderTrait::create_new
+//! [3]: crate::AsyncFileTrait::set_len
+//! [4]: ConformanceReport::assert_conformant
+
+mod checks;
+#[cfg(feature = "proptest")]
+mod model;
+#[cfg(feature = "proptest")]
+mod ops;
+#[cfg(feature = "proptest")]
+mod replay;
+mod report;
+
+#[doc(inline)]
+pub use checks::{check_fs, check_symlinks};
+#[cfg(feature = "proptest")]
+#[doc(inline)]
+pub use ops::{op_sequences, Op};
+#[cfg(feature = "proptest")]
+#[doc(inline)]
+pub use replay::{check_model, replay, Divergence};
+#[doc(inline)]
+pub use report::{CheckResult, ConformanceReport, Semantic};
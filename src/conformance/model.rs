@@ -0,0 +1,522 @@
+//! The reference [`Model`] that backends are compared against.
+//!
+//! The model is a deliberately naive filesystem: a sorted map from relative
+//! paths to nodes, with file contents kept apart so that open files keep
+//! theirs after being removed or renamed over.  It follows the traits'
+//! documented semantics, and the POSIX semantics that the standard library
+//! has where the traits leave something open.
+
+use std::{
+    collections::BTreeMap,
+    io::SeekFrom,
+    path::{Path, PathBuf}
+};
+
+use super::Op;
+use crate::{layer::OpenFlags, FileType};
+
+/// How an operation fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Failure {
+    /// Fails with [`ErrorKind::NotFound`][1].
+    ///
+    /// [1]: std::io::ErrorKind::NotFound
+    NotFound,
+
+    /// Fails with any other kind of error.
+    Other,
+
+    /// Fails with any kind of error, because which one depends on the order
+    /// in which a backend checks its arguments.
+    Any
+}
+
+/// What an operation returns when it succeeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Value {
+    Unit,
+    Opened,
+    Copied(u64),
+    Bytes(Vec<u8>),
+    Pos(u64),
+    Metadata(FileType, Option<u64>),
+    Names(Vec<String>)
+}
+
+/// What the model expects of an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Expect {
+    /// The result is left to the backend, so the operation is skipped.
+    Skip,
+
+    /// The operation returns this.
+    Result(Result<Value, Failure>)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Node {
+    Dir,
+
+    /// A file, with the index of its contents.
+    File(usize)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Handle {
+    file: usize,
+    cursor: u64,
+    read: bool,
+    write: bool,
+    append: bool
+}
+
+/// An in-memory filesystem that is simple enough to be obviously right.
+#[derive(Debug, Default)]
+pub(super) struct Model {
+    /// Every entry below the root, which is an implicit directory with an
+    /// empty path.
+    nodes: BTreeMap<PathBuf, Node>,
+
+    /// The contents of every file ever created, including removed ones.
+    files: Vec<Vec<u8>>,
+
+    /// The files that were opened, in order, or `None` once closed.
+    handles: Vec<Option<Handle>>
+}
+
+fn ok(value: Value) -> Expect {
+    Expect::Result(Ok(value))
+}
+
+fn fail(failure: Failure) -> Expect {
+    Expect::Result(Err(failure))
+}
+
+/// Returns early with the failure of a lookup.
+macro_rules! lookup {
+    ($model:expr, $path:expr) => {
+        match $model.lookup($path) {
+            Ok(node) => node,
+            Err(failure) => return fail(failure)
+        }
+    };
+    ($model:expr, $path:expr, $failure:expr) => {
+        match $model.lookup($path) {
+            Ok(node) => node,
+            Err(_) => return fail($failure)
+        }
+    };
+}
+
+impl Model {
+    /// Returns the entries of the tree, with the contents of its files.
+    pub(super) fn tree(&self) -> BTreeMap<PathBuf, Option<&[u8]>> {
+        self.nodes
+            .iter()
+            .map(|(path, node)| {
+                let contents = match *node {
+                    Node::Dir => None,
+                    Node::File(file) => Some(&self.files[file][..])
+                };
+                (path.clone(), contents)
+            })
+            .collect()
+    }
+
+    /// Applies `op`, and returns what a backend should have returned.
+    pub(super) fn apply(&mut self, op: &Op) -> Expect {
+        match op {
+            Op::CreateDir { path, recursive } => {
+                self.create_dir(Path::new(path), *recursive)
+            }
+            Op::RemoveDir { path } => self.remove_dir(Path::new(path), false),
+            Op::RemoveDirAll { path } => self.remove_dir(Path::new(path), true),
+            Op::RemoveFile { path } => self.remove_file(Path::new(path)),
+            Op::Rename { from, to } => {
+                self.rename(Path::new(from), Path::new(to))
+            }
+            Op::Copy { from, to } => self.copy(Path::new(from), Path::new(to)),
+            Op::Metadata { path } => self.metadata(Path::new(path)),
+            Op::ReadDir { path } => self.read_dir(Path::new(path)),
+            Op::Open { path, flags } => self.open(Path::new(path), *flags),
+            Op::Write { handle, data } => self.write(*handle, data),
+            Op::Read { handle, len } => self.read(*handle, *len),
+            Op::Seek { handle, pos } => self.seek(*handle, *pos),
+            Op::SetLen { handle, len } => self.set_len(*handle, *len),
+            Op::Close { handle } => match self.handles.get_mut(*handle) {
+                Some(handle @ Some(_)) => {
+                    *handle = None;
+                    ok(Value::Unit)
+                }
+                _ => Expect::Skip
+            }
+        }
+    }
+
+    /// Returns what is at `path`, if its parent is a directory.
+    fn lookup(&self, path: &Path) -> Result<Option<Node>, Failure> {
+        let parents = path.ancestors().skip(1).collect::<Vec<_>>();
+        for parent in parents.into_iter().rev() {
+            match self.nodes.get(parent) {
+                Some(Node::Dir) => {}
+                Some(Node::File(_)) => return Err(Failure::Other),
+                None if parent.as_os_str().is_empty() => {}
+                None => return Err(Failure::NotFound)
+            }
+        }
+        Ok(self.nodes.get(path).copied())
+    }
+
+    fn children<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = &'a Path> {
+        self.nodes
+            .keys()
+            .filter(move |path| path.parent() == Some(dir))
+            .map(PathBuf::as_path)
+    }
+
+    fn new_file(&mut self) -> usize {
+        self.files.push(Vec::new());
+        self.files.len() - 1
+    }
+
+    fn create_dir(&mut self, path: &Path, recursive: bool) -> Expect {
+        if !recursive {
+            if lookup!(self, path).is_some() {
+                return fail(Failure::Other);
+            }
+            self.nodes.insert(path.to_owned(), Node::Dir);
+            return ok(Value::Unit);
+        }
+        let mut ancestors = path.ancestors().collect::<Vec<_>>();
+        ancestors.pop();
+        for dir in ancestors.into_iter().rev() {
+            match self.nodes.get(dir) {
+                Some(Node::Dir) => {}
+                Some(Node::File(_)) => return fail(Failure::Other),
+                None => {
+                    self.nodes.insert(dir.to_owned(), Node::Dir);
+                }
+            }
+        }
+        ok(Value::Unit)
+    }
+
+    fn remove_dir(&mut self, path: &Path, all: bool) -> Expect {
+        match lookup!(self, path) {
+            None => fail(Failure::NotFound),
+            Some(Node::File(_)) => fail(Failure::Other),
+            Some(Node::Dir) if !all && self.children(path).next().is_some() => {
+                fail(Failure::Other)
+            }
+            Some(Node::Dir) => {
+                self.nodes.retain(|entry, _| !entry.starts_with(path));
+                ok(Value::Unit)
+            }
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path) -> Expect {
+        match lookup!(self, path) {
+            None => fail(Failure::NotFound),
+            Some(Node::Dir) => fail(Failure::Other),
+            Some(Node::File(_)) => {
+                self.nodes.remove(path);
+                ok(Value::Unit)
+            }
+        }
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Expect {
+        let node = match lookup!(self, from, Failure::Any) {
+            Some(node) => node,
+            None => return fail(Failure::Any)
+        };
+        let target = lookup!(self, to, Failure::Any);
+        if from == to {
+            return ok(Value::Unit);
+        }
+        let in_the_way = match (node, target) {
+            (Node::File(_), Some(Node::Dir)) => true,
+            (Node::Dir, Some(Node::File(_))) => true,
+            (Node::Dir, Some(Node::Dir)) => self.children(to).next().is_some(),
+            _ => false
+        };
+        if in_the_way || to.starts_with(from) {
+            return fail(Failure::Any);
+        }
+        self.nodes.remove(to);
+        let moved = self.nodes
+                        .keys()
+                        .filter(|path| path.starts_with(from))
+                        .cloned()
+                        .collect::<Vec<_>>();
+        for path in moved {
+            let node = self.nodes.remove(&path).unwrap();
+            let below = path.strip_prefix(from).unwrap();
+            match below.as_os_str().is_empty() {
+                true => self.nodes.insert(to.to_owned(), node),
+                false => self.nodes.insert(to.join(below), node)
+            };
+        }
+        ok(Value::Unit)
+    }
+
+    fn copy(&mut self, from: &Path, to: &Path) -> Expect {
+        let file = match lookup!(self, from, Failure::Any) {
+            Some(Node::File(file)) => file,
+            _ => return fail(Failure::Any)
+        };
+        let target = match lookup!(self, to, Failure::Any) {
+            // Copying a file onto itself is left to the backend.
+            _ if from == to => return Expect::Skip,
+            Some(Node::Dir) => return fail(Failure::Any),
+            Some(Node::File(target)) => target,
+            None => {
+                let target = self.new_file();
+                self.nodes.insert(to.to_owned(), Node::File(target));
+                target
+            }
+        };
+        // The copy keeps the target's contents, for the files opened on it.
+        self.files[target] = self.files[file].clone();
+        ok(Value::Copied(self.files[target].len() as u64))
+    }
+
+    fn metadata(&self, path: &Path) -> Expect {
+        match lookup!(self, path) {
+            None => fail(Failure::NotFound),
+            Some(Node::Dir) => ok(Value::Metadata(FileType::Dir, None)),
+            Some(Node::File(file)) => {
+                let len = self.files[file].len() as u64;
+                ok(Value::Metadata(FileType::File, Some(len)))
+            }
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> Expect {
+        match lookup!(self, path) {
+            None => fail(Failure::NotFound),
+            Some(Node::File(_)) => fail(Failure::Other),
+            Some(Node::Dir) => {
+                let names = self.children(path)
+                                .map(|child| {
+                                    let name = child.file_name().unwrap();
+                                    name.to_string_lossy().into_owned()
+                                })
+                                .collect();
+                ok(Value::Names(names))
+            }
+        }
+    }
+
+    fn open(&mut self, path: &Path, flags: OpenFlags) -> Expect {
+        let writes = flags.write || flags.append;
+        let creates = flags.truncate || flags.create || flags.create_new;
+        // These are the combinations that the standard library rejects.
+        let invalid = match writes {
+            true => flags.append && flags.truncate && !flags.create_new,
+            false => !flags.read || creates
+        };
+        if invalid {
+            return fail(Failure::Other);
+        }
+        let file = match lookup!(self, path) {
+            Some(_) if flags.create_new => return fail(Failure::Other),
+            Some(Node::Dir) if writes => return fail(Failure::Other),
+            // Whether a directory can be opened for reading is left to the
+            // backend.
+            Some(Node::Dir) => return Expect::Skip,
+            Some(Node::File(file)) => {
+                if flags.truncate {
+                    self.files[file].clear();
+                }
+                file
+            }
+            None if flags.create || flags.create_new => {
+                let file = self.new_file();
+                self.nodes.insert(path.to_owned(), Node::File(file));
+                file
+            }
+            None => return fail(Failure::NotFound)
+        };
+        self.handles.push(Some(Handle { file,
+                                        cursor: 0,
+                                        read: flags.read,
+                                        write: writes,
+                                        append: flags.append }));
+        ok(Value::Opened)
+    }
+
+    fn handle(&mut self, handle: usize) -> Option<&mut Handle> {
+        self.handles.get_mut(handle).and_then(Option::as_mut)
+    }
+
+    fn write(&mut self, handle: usize, data: &[u8]) -> Expect {
+        let Model { files, handles, .. } = self;
+        let handle = match handles.get_mut(handle).and_then(Option::as_mut) {
+            Some(handle) => handle,
+            None => return Expect::Skip
+        };
+        if !handle.write {
+            return fail(Failure::Other);
+        }
+        let contents = &mut files[handle.file];
+        if handle.append {
+            handle.cursor = contents.len() as u64;
+        }
+        // Writing past the end of a file fills the gap with zeros.
+        let start = handle.cursor as usize;
+        let end = start + data.len();
+        if end > contents.len() {
+            contents.resize(end, 0);
+        }
+        contents[start..end].copy_from_slice(data);
+        handle.cursor = end as u64;
+        ok(Value::Unit)
+    }
+
+    fn read(&mut self, handle: usize, len: usize) -> Expect {
+        let Model { files, handles, .. } = self;
+        let handle = match handles.get_mut(handle).and_then(Option::as_mut) {
+            Some(handle) => handle,
+            None => return Expect::Skip
+        };
+        if !handle.read {
+            return fail(Failure::Other);
+        }
+        let contents = &files[handle.file];
+        let start = handle.cursor as usize;
+        // Reading at or past the end of a file reads nothing, and leaves the
+        // cursor where it is.
+        if start >= contents.len() {
+            return ok(Value::Bytes(Vec::new()));
+        }
+        let end = contents.len().min(start + len);
+        handle.cursor = end as u64;
+        ok(Value::Bytes(contents[start..end].to_vec()))
+    }
+
+    fn seek(&mut self, handle: usize, pos: SeekFrom) -> Expect {
+        let len = match self.handle(handle) {
+            Some(handle) => handle.file,
+            None => return Expect::Skip
+        };
+        let len = self.files[len].len() as i64;
+        let handle = self.handle(handle).unwrap();
+        let cursor = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => handle.cursor as i64 + offset,
+            SeekFrom::End(offset) => len + offset
+        };
+        if cursor < 0 {
+            return fail(Failure::Other);
+        }
+        handle.cursor = cursor as u64;
+        ok(Value::Pos(handle.cursor))
+    }
+
+    fn set_len(&mut self, handle: usize, len: u64) -> Expect {
+        let file = match self.handle(handle) {
+            Some(handle) if !handle.write => return fail(Failure::Other),
+            Some(handle) => handle.file,
+            None => return Expect::Skip
+        };
+        self.files[file].resize(len as usize, 0);
+        ok(Value::Unit)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(path: &str) -> String {
+        path.to_string()
+    }
+
+    fn open(path: &str) -> Op {
+        Op::Open { path: path.to_string(),
+                   flags: OpenFlags { read: true,
+                                      write: true,
+                                      create: true,
+                                      ..OpenFlags::default() } }
+    }
+
+    #[test]
+    fn removed_files_stay_open() {
+        let mut model = Model::default();
+        assert_eq!(model.apply(&open("a")), ok(Value::Opened));
+        model.apply(&Op::Write { handle: 0,
+                                 data: b"abc".to_vec() });
+        assert_eq!(model.apply(&Op::RemoveFile { path: path("a") }),
+                   ok(Value::Unit));
+        model.apply(&Op::Seek { handle: 0,
+                                pos: SeekFrom::Start(1) });
+        assert_eq!(model.apply(&Op::Read { handle: 0, len: 8 }),
+                   ok(Value::Bytes(b"bc".to_vec())));
+        assert!(model.tree().is_empty());
+    }
+
+    #[test]
+    fn renames_move_subtrees() {
+        let mut model = Model::default();
+        model.apply(&Op::CreateDir { path: path("a/b"),
+                                     recursive: true });
+        model.apply(&open("a/b/c"));
+        let rename = |from: &str, to: &str| Op::Rename { from: path(from),
+                                                         to: path(to) };
+        assert_eq!(model.apply(&rename("a", "a/b/a")), fail(Failure::Any));
+        assert_eq!(model.apply(&rename("a", "c")), ok(Value::Unit));
+        let tree = model.tree();
+        let paths = tree.keys().map(|path| path.to_str().unwrap());
+        assert_eq!(paths.collect::<Vec<_>>(), ["c", "c/b", "c/b/c"]);
+        assert_eq!(model.apply(&Op::RemoveDir { path: path("c") }),
+                   fail(Failure::Other));
+        assert_eq!(model.apply(&Op::Metadata { path: path("a/b") }),
+                   fail(Failure::NotFound));
+    }
+
+    #[test]
+    fn unspecified_results_are_skipped() {
+        let mut model = Model::default();
+        model.apply(&Op::CreateDir { path: path("d"),
+                                     recursive: false });
+        let read = OpenFlags { read: true,
+                               ..OpenFlags::default() };
+        assert_eq!(model.apply(&Op::Open { path: path("d"),
+                                           flags: read }),
+                   Expect::Skip);
+        model.apply(&open("f"));
+        assert_eq!(model.apply(&Op::Close { handle: 1 }), Expect::Skip);
+        assert_eq!(model.apply(&Op::Close { handle: 0 }), ok(Value::Unit));
+    }
+
+    #[test]
+    fn writes_past_the_end_fill_the_gap_with_zeros() {
+        let mut model = Model::default();
+        model.apply(&open("f"));
+        model.apply(&Op::Seek { handle: 0,
+                                pos: SeekFrom::Start(4) });
+        assert_eq!(model.apply(&Op::Read { handle: 0, len: 1 }),
+                   ok(Value::Bytes(Vec::new())));
+        assert_eq!(model.apply(&Op::Write { handle: 0,
+                                            data: b"ab".to_vec() }),
+                   ok(Value::Unit));
+        assert_eq!(model.apply(&Op::Seek { handle: 0,
+                                           pos: SeekFrom::Current(0) }),
+                   ok(Value::Pos(6)));
+        model.apply(&Op::Seek { handle: 0,
+                                pos: SeekFrom::Start(2) });
+        assert_eq!(model.apply(&Op::Read { handle: 0, len: 8 }),
+                   ok(Value::Bytes(b"\0\0ab".to_vec())));
+    }
+}
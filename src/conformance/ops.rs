@@ -0,0 +1,230 @@
+//! [`Op`], the operations that model-based tests run, and
+//! [`op_sequences()`], which generates them.
+
+use std::io::SeekFrom;
+
+use proptest::{collection::vec, prelude::*, sample::select};
+
+use crate::layer::OpenFlags;
+
+/// An operation that a model-based test applies to both a backend and the
+/// reference model.
+///
+/// Paths are relative to the scratch directory that the test runs in.
+/// Handles are indices into the files that were opened, in the order they
+/// were opened in; operations on handles that were never opened, or that
+/// were closed, are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Op {
+    /// Creates a directory, or a directory and its parents.
+    CreateDir {
+        /// The directory to create.
+        path: String,
+
+        /// Whether its missing parents are created too.
+        recursive: bool
+    },
+
+    /// Removes an empty directory.
+    RemoveDir {
+        /// The directory to remove.
+        path: String
+    },
+
+    /// Removes a directory and everything below it.
+    RemoveDirAll {
+        /// The directory to remove.
+        path: String
+    },
+
+    /// Removes a file.
+    RemoveFile {
+        /// The file to remove.
+        path: String
+    },
+
+    /// Renames a file or directory.
+    Rename {
+        /// What to rename.
+        from: String,
+
+        /// What to rename it to.
+        to: String
+    },
+
+    /// Copies a file.
+    Copy {
+        /// The file to copy.
+        from: String,
+
+        /// Where to copy it to.
+        to: String
+    },
+
+    /// Reads the type of an entry, and the length of a file.
+    Metadata {
+        /// The entry to read the metadata of.
+        path: String
+    },
+
+    /// Lists the names of the entries of a directory.
+    ReadDir {
+        /// The directory to list.
+        path: String
+    },
+
+    /// Opens a file, which becomes the next handle.
+    Open {
+        /// The file to open.
+        path: String,
+
+        /// What to open it with, as passed to the methods of
+        /// [`AsyncFileBuilderTrait`][1] of the same names.
+        ///
+        /// [1]: crate::AsyncFileBuilderTrait
+        flags: OpenFlags
+    },
+
+    /// Writes all of `data` to a file, and flushes it.
+    Write {
+        /// The file to write to.
+        handle: usize,
+
+        /// What to write.
+        data: Vec<u8>
+    },
+
+    /// Reads up to `len` bytes from a file, stopping early only at its end.
+    Read {
+        /// The file to read from.
+        handle: usize,
+
+        /// How much to read.
+        len: usize
+    },
+
+    /// Moves the cursor of a file.
+    Seek {
+        /// The file to seek in.
+        handle: usize,
+
+        /// Where to move the cursor to.
+        pos: SeekFrom
+    },
+
+    /// Truncates or extends a file.
+    SetLen {
+        /// The file to resize.
+        handle: usize,
+
+        /// Its new length.
+        len: u64
+    },
+
+    /// Closes a file.
+    Close {
+        /// The file to close.
+        handle: usize
+    }
+}
+
+/// Paths are drawn from a handful of names, so that operations often meet
+/// the same entries.
+fn path() -> impl Strategy<Value = String> {
+    vec(select(&["a", "b", "c"][..]), 1..=3).prop_map(|names| names.join("/"))
+}
+
+fn handle() -> impl Strategy<Value = usize> {
+    0..4usize
+}
+
+fn flags() -> impl Strategy<Value = OpenFlags> {
+    any::<[bool; 6]>().prop_map(|flags| {
+        let [read, write, append, truncate, create, create_new] = flags;
+        OpenFlags { read,
+                    write,
+                    append,
+                    truncate,
+                    create,
+                    create_new }
+    })
+}
+
+fn path_op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        2 => (path(), any::<bool>()).prop_map(|(path, recursive)| {
+            Op::CreateDir { path, recursive }
+        }),
+        1 => path().prop_map(|path| Op::RemoveDir { path }),
+        1 => path().prop_map(|path| Op::RemoveDirAll { path }),
+        1 => path().prop_map(|path| Op::RemoveFile { path }),
+        1 => (path(), path()).prop_map(|(from, to)| Op::Rename { from, to }),
+        1 => (path(), path()).prop_map(|(from, to)| Op::Copy { from, to }),
+        1 => path().prop_map(|path| Op::Metadata { path }),
+        1 => path().prop_map(|path| Op::ReadDir { path }),
+        3 => (path(), flags()).prop_map(|(path, flags)| {
+            Op::Open { path, flags }
+        })
+    ]
+}
+
+fn handle_op() -> impl Strategy<Value = Op> {
+    let pos = prop_oneof![(0..24u64).prop_map(SeekFrom::Start),
+                          (-8..8i64).prop_map(SeekFrom::Current),
+                          (-8..8i64).prop_map(SeekFrom::End)];
+    prop_oneof![
+        3 => (handle(), vec(b'a'..=b'z', 1..8)).prop_map(|(handle, data)| {
+            Op::Write { handle, data }
+        }),
+        2 => (handle(), 1..24usize).prop_map(|(handle, len)| {
+            Op::Read { handle, len }
+        }),
+        2 => (handle(), pos).prop_map(|(handle, pos)| Op::Seek { handle, pos }),
+        1 => (handle(), 0..24u64).prop_map(|(handle, len)| {
+            Op::SetLen { handle, len }
+        }),
+        1 => handle().prop_map(|handle| Op::Close { handle })
+    ]
+}
+
+/// Returns a strategy for sequences of up to `max_len` operations.
+///
+/// The operations mix changes to the tree with reads, writes, and seeks on
+/// open files, over a small set of paths so that they interact.  Failing
+/// sequences shrink by dropping operations and simplifying the ones that
+/// are left.
+pub fn op_sequences(max_len: usize) -> impl Strategy<Value = Vec<Op>> {
+    vec(prop_oneof![3 => path_op(), 2 => handle_op()],
+        1..=max_len.max(1))
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use proptest::{strategy::ValueTree, test_runner::TestRunner};
+
+    use super::*;
+
+    #[test]
+    fn sequences_stay_within_bounds() {
+        let mut runner = TestRunner::deterministic();
+        for _ in 0..64 {
+            let ops = op_sequences(5).new_tree(&mut runner).unwrap().current();
+            assert!((1..=5).contains(&ops.len()));
+            for op in ops {
+                if let Op::Rename { from, .. } = op {
+                    assert!(from.split('/').all(|name| "abc".contains(name)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! [`replay()`], which runs [`Op`]s against a backend and the reference
+//! model, and [`check_model()`], which generates and shrinks them.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf}
+};
+
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::{
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    StreamExt
+};
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+
+use super::{
+    model::{Expect, Failure, Model, Value},
+    op_sequences, Op
+};
+use crate::{
+    layer::FileOf, AsyncDirBuilderTrait, AsyncDirEntryTrait,
+    AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait
+};
+
+/// How many operations [`check_model()`] runs per case, at most.
+const MAX_OPS: usize = 24;
+
+/// Where a backend and the reference model went different ways.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Divergence {
+    /// The index of the operation whose result differed, or `None` if the
+    /// operations agreed but the trees they left behind don't.
+    pub step: Option<usize>,
+
+    /// What the backend did, and what the model expected of it.
+    pub message: String
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.step {
+            Some(step) => write!(f, "step {}: {}", step, self.message),
+            None => f.write_str(&self.message)
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Applies `ops` to `fs`, in a scratch directory at `dir`, and to the
+/// reference model, and checks that they return the same results and leave
+/// the same tree behind.
+///
+/// The scratch directory is created if it is missing, and is emptied before
+/// the operations run and removed after.  Errors are compared by whether
+/// they are [`NotFound`][1] or not, since the traits promise nothing more.
+/// Operations whose results the traits leave to the backend, such as
+/// opening a directory for reading, are skipped.
+///
+/// [1]: io::ErrorKind::NotFound
+pub async fn replay<F, P>(fs: &F, dir: P, ops: &[Op]) -> Result<(), Divergence>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin,
+          P: AsRef<Path>
+{
+    let dir = dir.as_ref();
+    // Whatever a previous, failed run left behind is cleared out first.
+    let _ = fs.remove_dir_all(dir).await;
+    if let Err(err) = fs.dir_builder().recursive(true).create(dir).await {
+        return Err(Divergence { step: None,
+                                message: format!("creating the scratch \
+                                                  directory failed: {}",
+                                                 err) });
+    }
+    let result = Replay { fs,
+                          dir,
+                          model: Model::default(),
+                          files: Vec::new() }.run(ops)
+                                             .await;
+    let _ = fs.remove_dir_all(dir).await;
+    result
+}
+
+/// Replays random sequences of [`Op`]s for `cases` cases, and panics with
+/// the shortest failing sequence that it shrinks a failure down to.
+///
+/// `replay` is called with every sequence, and usually blocks on
+/// [`replay()`] with the executor that the backend needs:
+///
+/// ```
+/// # #[cfg(feature = "mem-fs")]
+/// # {
+/// use async_fs_traits::{
+///     conformance::{check_model, replay},
+///     mem_fs::MemFs
+/// };
+/// use futures_lite::future::block_on;
+///
+/// let fs = MemFs::new();
+/// check_model(16, |ops| block_on(replay(&fs, "/scratch", ops)));
+/// # }
+/// ```
+pub fn check_model<R>(cases: u32, replay: R)
+    where R: Fn(&[Op]) -> Result<(), Divergence>
+{
+    let mut runner = TestRunner::new(Config { cases,
+                                              failure_persistence: None,
+                                              ..Config::default() });
+    let result = runner.run(&op_sequences(MAX_OPS), |ops| {
+                           replay(&ops).map_err(|divergence| {
+                               TestCaseError::fail(divergence.to_string())
+                           })
+                       });
+    if let Err(err) = result {
+        panic!("backend diverges from the reference model: {}", err);
+    }
+}
+
+/// The state of a replay.
+struct Replay<'a, F>
+    where F: AsyncFsTrait
+{
+    fs: &'a F,
+    dir: &'a Path,
+    model: Model,
+
+    /// The backend's files, indexed like the model's handles.
+    files: Vec<Option<FileOf<F>>>
+}
+
+impl<'a, F> Replay<'a, F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin
+{
+    async fn run(mut self, ops: &[Op]) -> Result<(), Divergence> {
+        for (step, op) in ops.iter().enumerate() {
+            let expected = match self.model.apply(op) {
+                Expect::Skip => continue,
+                Expect::Result(expected) => expected
+            };
+            let actual = self.apply(op).await;
+            if !agree(&expected, &actual) {
+                let message = format!("{:?} returned {} rather than {}",
+                                      op,
+                                      show_actual(&actual),
+                                      show_expected(&expected));
+                return Err(Divergence { step: Some(step),
+                                        message });
+            }
+        }
+        // Files are closed first, for backends that only write on close.
+        for file in self.files.iter_mut().filter_map(Option::take) {
+            let mut file = file;
+            if let Err(err) = file.close().await {
+                return Err(Divergence { step: None,
+                                        message: format!("closing a file \
+                                                          failed: {}",
+                                                         err) });
+            }
+        }
+        self.compare_trees().await
+    }
+
+    /// Applies `op` to the backend.
+    async fn apply(&mut self, op: &Op) -> io::Result<Value> {
+        let (fs, dir) = (self.fs, self.dir);
+        match op {
+            Op::CreateDir { path, recursive } => {
+                fs.dir_builder()
+                  .recursive(*recursive)
+                  .create(dir.join(path))
+                  .await?;
+                Ok(Value::Unit)
+            }
+            Op::RemoveDir { path } => {
+                fs.remove_dir(dir.join(path)).await?;
+                Ok(Value::Unit)
+            }
+            Op::RemoveDirAll { path } => {
+                fs.remove_dir_all(dir.join(path)).await?;
+                Ok(Value::Unit)
+            }
+            Op::RemoveFile { path } => {
+                fs.remove_file(dir.join(path)).await?;
+                Ok(Value::Unit)
+            }
+            Op::Rename { from, to } => {
+                fs.rename(dir.join(from), dir.join(to)).await?;
+                Ok(Value::Unit)
+            }
+            Op::Copy { from, to } => {
+                let copied = fs.copy(dir.join(from), dir.join(to)).await?;
+                Ok(Value::Copied(copied))
+            }
+            Op::Metadata { path } => {
+                let metadata = fs.metadata(dir.join(path)).await?;
+                let len = Some(metadata.len()).filter(|_| metadata.is_file());
+                Ok(Value::Metadata(metadata.file_type(), len))
+            }
+            Op::ReadDir { path } => {
+                let mut entries = fs.read_dir(dir.join(path)).await?;
+                let mut names = Vec::new();
+                while let Some(entry) = entries.next().await {
+                    let name = entry?.file_name().await;
+                    names.push(name.to_string_lossy().into_owned());
+                }
+                names.sort();
+                Ok(Value::Names(names))
+            }
+            Op::Open { path, flags } => {
+                let file = fs.open_options()
+                             .read(flags.read)
+                             .write(flags.write)
+                             .append(flags.append)
+                             .truncate(flags.truncate)
+                             .create(flags.create)
+                             .create_new(flags.create_new)
+                             .open(dir.join(path))
+                             .await?;
+                self.files.push(Some(file));
+                Ok(Value::Opened)
+            }
+            Op::Write { handle, data } => {
+                let file = self.file(*handle)?;
+                file.write_all(data).await?;
+                file.flush().await?;
+                Ok(Value::Unit)
+            }
+            Op::Read { handle, len } => {
+                let mut bytes = Vec::new();
+                let file = self.file(*handle)?;
+                file.take(*len as u64).read_to_end(&mut bytes).await?;
+                Ok(Value::Bytes(bytes))
+            }
+            Op::Seek { handle, pos } => {
+                Ok(Value::Pos(self.file(*handle)?.seek(*pos).await?))
+            }
+            Op::SetLen { handle, len } => {
+                self.file(*handle)?.set_len(*len).await?;
+                Ok(Value::Unit)
+            }
+            Op::Close { handle } => {
+                let file = self.file(*handle)?;
+                let result = file.close().await;
+                self.files[*handle] = None;
+                result.map(|()| Value::Unit)
+            }
+        }
+    }
+
+    fn file(&mut self, handle: usize) -> io::Result<&mut FileOf<F>> {
+        // The model skips operations on handles that aren't open, so this
+        // only fails if the two lost track of each other.
+        self.files
+            .get_mut(handle)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::Other, "the handle isn't open")
+            })
+    }
+
+    /// Checks that the backend's tree matches the model's.
+    async fn compare_trees(&self) -> Result<(), Divergence> {
+        let actual = match self.tree().await {
+            Ok(tree) => tree,
+            Err(err) => {
+                let message = format!("reading the tree failed: {}", err);
+                return Err(Divergence { step: None,
+                                        message });
+            }
+        };
+        let expected = self.model.tree();
+        let paths = actual.keys()
+                          .chain(expected.keys())
+                          .collect::<BTreeSet<_>>();
+        let differences = paths.into_iter()
+                               .filter_map(|path| {
+                                   let actual =
+                                       actual.get(path).map(|c| c.as_deref());
+                                   let expected = expected.get(path).copied();
+                                   (actual != expected).then(|| {
+                                       format!("{} is {} rather than {}",
+                                               path.display(),
+                                               show_entry(actual),
+                                               show_entry(expected))
+                                   })
+                               })
+                               .collect::<Vec<_>>();
+        if differences.is_empty() {
+            return Ok(());
+        }
+        Err(Divergence { step: None,
+                         message: format!("the operations left a different \
+                                           tree behind: {}",
+                                          differences.join(", ")) })
+    }
+
+    /// Reads the backend's tree, with the contents of its files.
+    async fn tree(&self) -> io::Result<BTreeMap<PathBuf, Option<Vec<u8>>>> {
+        let mut tree = BTreeMap::new();
+        let mut dirs = vec![PathBuf::new()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = self.fs.read_dir(self.dir.join(&dir)).await?;
+            while let Some(entry) = entries.next().await {
+                let entry = entry?;
+                let path = dir.join(entry.file_name().await);
+                if entry.file_type().await?.is_dir() {
+                    tree.insert(path.clone(), None);
+                    dirs.push(path);
+                    continue;
+                }
+                let mut file = self.fs
+                                   .open_options()
+                                   .read(true)
+                                   .open(self.dir.join(&path))
+                                   .await?;
+                let mut contents = Vec::new();
+                file.read_to_end(&mut contents).await?;
+                tree.insert(path, Some(contents));
+            }
+        }
+        Ok(tree)
+    }
+}
+
+/// Returns whether a backend's result is the one the model expected.
+fn agree(expected: &Result<Value, Failure>,
+         actual: &io::Result<Value>)
+         -> bool {
+    match (expected, actual) {
+        (Ok(expected), Ok(actual)) => expected == actual,
+        (Err(Failure::NotFound), Err(err)) => {
+            err.kind() == io::ErrorKind::NotFound
+        }
+        (Err(Failure::Other), Err(err)) => {
+            err.kind() != io::ErrorKind::NotFound
+        }
+        (Err(Failure::Any), Err(_)) => true,
+        _ => false
+    }
+}
+
+fn show_value(value: &Value) -> String {
+    let show = |bytes: &[u8]| format!("\"{}\"", bytes.escape_ascii());
+    match value {
+        Value::Unit | Value::Opened => "Ok".to_string(),
+        Value::Bytes(bytes) => show(bytes),
+        value => format!("{:?}", value)
+    }
+}
+
+fn show_actual(actual: &io::Result<Value>) -> String {
+    match actual {
+        Ok(value) => show_value(value),
+        Err(err) => format!("{:?} ({})", err.kind(), err)
+    }
+}
+
+fn show_expected(expected: &Result<Value, Failure>) -> String {
+    match expected {
+        Ok(value) => show_value(value),
+        Err(Failure::NotFound) => "NotFound".to_string(),
+        Err(Failure::Other) => "an error other than NotFound".to_string(),
+        Err(Failure::Any) => "an error".to_string()
+    }
+}
+
+fn show_entry(entry: Option<Option<&[u8]>>) -> String {
+    match entry {
+        None => "missing".to_string(),
+        Some(None) => "a directory".to_string(),
+        Some(Some(contents)) => {
+            format!("a file holding \"{}\"", contents.escape_ascii())
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, any(feature = "mem-fs", feature = "std-fs")))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    #[cfg(feature = "mem-fs")]
+    #[test]
+    fn mem_fs_matches_the_model() {
+        let fs = crate::mem_fs::MemFs::new();
+        check_model(64, |ops| block_on(replay(&fs, "/scratch", ops)));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn std_fs_matches_the_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let fs = crate::std_fs::StdFs::new();
+        check_model(64, |ops| {
+            block_on(replay(&fs, dir.path().join("scratch"), ops))
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "step 0: it diverged")]
+    fn divergences_panic() {
+        check_model(1, |_| {
+            Err(Divergence { step: Some(0),
+                             message: "it diverged".to_string() })
+        });
+    }
+}
@@ -0,0 +1,240 @@
+//! [`ConformanceReport`] lists which documented semantics a backend follows.
+
+use std::fmt;
+
+/// A documented behavior of the filesystem traits that the kit checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Semantic {
+    /// [`rename()`][1] replaces a file that is already at the target.
+    ///
+    /// [1]: crate::AsyncFsTrait::rename
+    RenameOverwrites,
+
+    /// [`copy()`][1] replaces the contents of a file that is already at the
+    /// target, and returns the number of bytes copied.
+    ///
+    /// [1]: crate::AsyncFsTrait::copy
+    CopyOverwrites,
+
+    /// [`remove_dir()`][1] refuses to remove a directory that isn't empty,
+    /// and removes one that is.
+    ///
+    /// [1]: crate::AsyncFsTrait::remove_dir
+    RemoveDirRejectsNonEmpty,
+
+    /// The entries streamed by [`read_dir()`][1] have the path that was
+    /// passed to it joined with their names as their [`path()`][2].
+    ///
+    /// [1]: crate::AsyncFsTrait::read_dir
+    /// [2]: crate::AsyncDirEntryTrait::path
+    ReadDirPathsJoin,
+
+    /// Paths that don't exist fail with [`ErrorKind::NotFound`][1], which
+    /// generic code relies on to tell them apart from other errors.
+    ///
+    /// [1]: std::io::ErrorKind::NotFound
+    MissingPathsAreNotFound,
+
+    /// [`create_new`][1] makes opening a file that already exists fail.
+    ///
+    /// [1]: crate::AsyncFileBuilderTrait::create_new
+    CreateNewFailsOnExisting,
+
+    /// Opening an existing file for [`write`][1] overwrites its contents
+    /// from the start, without truncating it.
+    ///
+    /// [1]: crate::AsyncFileBuilderTrait::write
+    WriteKeepsTrailingContents,
+
+    /// Files opened for [`append`][1] are written at their end.
+    ///
+    /// [1]: crate::AsyncFileBuilderTrait::append
+    AppendWritesAtEnd,
+
+    /// [`set_len()`][1] truncates files, and extends them with zeros, even
+    /// over contents that an earlier truncation cut off.
+    ///
+    /// [1]: crate::AsyncFileTrait::set_len
+    SetLenZeroFills,
+
+    /// [`set_len()`][1] leaves the cursor where it was.
+    ///
+    /// [1]: crate::AsyncFileTrait::set_len
+    SetLenKeepsCursor,
+
+    /// [`create()`][1] fails on a directory that already exists, unless
+    /// [`recursive`][2] is set.
+    ///
+    /// [1]: crate::AsyncDirBuilderTrait::create
+    /// [2]: crate::AsyncDirBuilderTrait::recursive
+    CreateDirRejectsExisting,
+
+    /// [`recursive`][1] creates every missing parent directory.
+    ///
+    /// [1]: crate::AsyncDirBuilderTrait::recursive
+    RecursiveCreatesParents,
+
+    /// [`read_link()`][1] returns the target that a [`symlink()`][2] was
+    /// created with.
+    ///
+    /// [1]: crate::AsyncFsTrait::read_link
+    /// [2]: crate::AsyncSymLinkTrait::symlink
+    SymlinkReadsBack,
+
+    /// [`symlink_metadata()`][1] describes a symlink itself, while
+    /// [`metadata()`][2] follows it.
+    ///
+    /// [1]: crate::AsyncFsTrait::symlink_metadata
+    /// [2]: crate::AsyncFsTrait::metadata
+    SymlinkMetadataNotFollowed
+}
+
+impl Semantic {
+    /// Returns a short name for the semantic, which is also the name of the
+    /// scratch directory it is checked in.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Semantic::RenameOverwrites => "rename_overwrites",
+            Semantic::CopyOverwrites => "copy_overwrites",
+            Semantic::RemoveDirRejectsNonEmpty => {
+                "remove_dir_rejects_non_empty"
+            }
+            Semantic::ReadDirPathsJoin => "read_dir_paths_join",
+            Semantic::MissingPathsAreNotFound => "missing_paths_are_not_found",
+            Semantic::CreateNewFailsOnExisting => {
+                "create_new_fails_on_existing"
+            }
+            Semantic::WriteKeepsTrailingContents => {
+                "write_keeps_trailing_contents"
+            }
+            Semantic::AppendWritesAtEnd => "append_writes_at_end",
+            Semantic::SetLenZeroFills => "set_len_zero_fills",
+            Semantic::SetLenKeepsCursor => "set_len_keeps_cursor",
+            Semantic::CreateDirRejectsExisting => "create_dir_rejects_existing",
+            Semantic::RecursiveCreatesParents => "recursive_creates_parents",
+            Semantic::SymlinkReadsBack => "symlink_reads_back",
+            Semantic::SymlinkMetadataNotFollowed => {
+                "symlink_metadata_not_followed"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Semantic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The outcome of checking one [`Semantic`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CheckResult {
+    /// The semantic that was checked.
+    pub semantic: Semantic,
+
+    /// How the backend deviated from the semantic, or `None` if it didn't.
+    pub deviation: Option<String>
+}
+
+/// Which documented semantics a backend follows, and how it deviates from
+/// the others.
+///
+/// A report prints one line per check, so a failing test shows everything
+/// at once:
+///
+/// ```text
+/// ok   rename_overwrites
+/// FAIL set_len_keeps_cursor: the cursor moved from 6 to 2
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ConformanceReport {
+    /// Every check that was run, in the order it was run in.
+    pub checks: Vec<CheckResult>
+}
+
+impl ConformanceReport {
+    /// Returns the checks that the backend deviated from.
+    pub fn deviations(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks.iter().filter(|check| check.deviation.is_some())
+    }
+
+    /// Returns `true` if the backend deviated from none of the checks.
+    pub fn is_conformant(&self) -> bool {
+        self.deviations().next().is_none()
+    }
+
+    /// Panics with the whole report if the backend deviated from any of the
+    /// checks.
+    ///
+    /// Semantics that a backend knowingly doesn't follow can be listed in
+    /// `allowed`, and aren't counted as deviations.
+    pub fn assert_conformant(&self, allowed: &[Semantic]) {
+        let unexpected = self.deviations()
+                             .any(|check| !allowed.contains(&check.semantic));
+        if unexpected {
+            panic!("backend deviates from documented semantics:\n{}", self);
+        }
+    }
+}
+
+impl Extend<CheckResult> for ConformanceReport {
+    fn extend<I>(&mut self, checks: I)
+        where I: IntoIterator<Item = CheckResult>
+    {
+        self.checks.extend(checks);
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.deviation {
+                None => writeln!(f, "ok   {}", check.semantic)?,
+                Some(deviation) => {
+                    writeln!(f, "FAIL {}: {}", check.semantic, deviation)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+        report.extend([CheckResult { semantic: Semantic::RenameOverwrites,
+                                     deviation: None },
+                       CheckResult { semantic: Semantic::SetLenKeepsCursor,
+                                     deviation: Some("it moved".into()) }]);
+        report
+    }
+
+    #[test]
+    fn deviations_are_reported() {
+        let report = report();
+        assert!(!report.is_conformant());
+        assert_eq!(report.to_string(),
+                   "ok   rename_overwrites\nFAIL set_len_keeps_cursor: it \
+                    moved\n");
+        report.assert_conformant(&[Semantic::SetLenKeepsCursor]);
+    }
+
+    #[test]
+    #[should_panic(expected = "FAIL set_len_keeps_cursor")]
+    fn unexpected_deviations_panic() {
+        report().assert_conformant(&[]);
+    }
+}
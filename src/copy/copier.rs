@@ -0,0 +1,532 @@
+//! [`copy_tree()`] and the [`Job`] that copies each entry of a tree.
+
+use std::{
+    collections::HashSet,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::Poll
+};
+
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_lite::{
+    future::poll_fn,
+    io::{AsyncReadExt, AsyncWriteExt},
+    Stream
+};
+
+use super::{CopyOptions, CopyStats, Overwrite};
+use crate::{
+    layer::FileOf,
+    tree::CHUNK,
+    walk::{WalkEntry, WalkOptions},
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFileBuilderTrait,
+    AsyncFsTrait, AsyncSymLinkTrait, FileType, Metadata, Permissions
+};
+
+pub(super) type Progress = Arc<dyn Fn(&Path, &CopyStats) + Send + Sync>;
+
+type Task<'a> = Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>>;
+
+/// Copies `src` in `src_fs` to `dst` in `dst_fs`, along with everything
+/// below it, with the default [`CopyOptions`].
+///
+/// Symlinks are recreated rather than followed, except when `src` itself is
+/// one, and permissions are kept.  Fails with
+/// [`ErrorKind::AlreadyExists`][1] if anything is in the way, other than
+/// directories, which are merged into.
+///
+/// [1]: std::io::ErrorKind::AlreadyExists
+pub async fn copy_tree<S, D, P, Q>(src_fs: &S,
+                                   src: P,
+                                   dst_fs: &D,
+                                   dst: Q)
+                                   -> io::Result<CopyStats>
+    where S: AsyncFsTrait,
+          D: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<S>: AsyncRead + Unpin,
+          FileOf<D>: AsyncWrite + Unpin,
+          P: AsRef<Path>,
+          Q: AsRef<Path>
+{
+    CopyOptions::new().copy_tree(src_fs, src, dst_fs, dst).await
+}
+
+fn in_the_way(target: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists,
+                   format!("{} already exists", target.display()))
+}
+
+/// The copy of one tree, shared by the tasks that copy its entries.
+struct Job<'a, S, D> {
+    src_fs: &'a S,
+    src: PathBuf,
+    dst_fs: &'a D,
+    dst: PathBuf,
+    options: CopyOptions,
+    stats: Mutex<CopyStats>,
+
+    /// Directories that were skipped, whose entries the walk leaves out.
+    skipped: Arc<Mutex<HashSet<PathBuf>>>,
+
+    /// Directories that were created, with the permissions they get once
+    /// everything has been copied into them.
+    dirs: Mutex<Vec<(PathBuf, Permissions)>>
+}
+
+impl<S, D> Job<'_, S, D>
+    where S: AsyncFsTrait,
+          D: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<S>: AsyncRead + Unpin,
+          FileOf<D>: AsyncWrite + Unpin
+{
+    /// Returns where `entry` is copied to.
+    fn target(&self, entry: &WalkEntry<S::DirEntry>) -> PathBuf {
+        match entry.path().strip_prefix(&self.src) {
+            Ok(below) if below.as_os_str().is_empty() => self.dst.clone(),
+            Ok(below) => self.dst.join(below),
+            Err(_) => self.dst.join(entry.file_name())
+        }
+    }
+
+    /// Adds to the totals, and reports them.
+    fn record<F>(&self, path: &Path, update: F)
+        where F: FnOnce(&mut CopyStats)
+    {
+        let stats = {
+            let mut stats = self.stats.lock().unwrap();
+            update(&mut stats);
+            *stats
+        };
+        if let Some(progress) = &self.options.progress {
+            progress(path, &stats);
+        }
+    }
+
+    /// Decides what to do about whatever is at `target`.  Returns `false` if
+    /// the entry should be skipped, and removes what is there if it should
+    /// be replaced.
+    async fn make_room(&self,
+                       target: &Path,
+                       existing: &Metadata,
+                       metadata: &Metadata)
+                       -> io::Result<bool> {
+        let replace = match self.options.overwrite {
+            Overwrite::Fail => return Err(in_the_way(target)),
+            Overwrite::Skip => false,
+            Overwrite::Replace => true,
+            Overwrite::IfNewer => {
+                match (metadata.modified(), existing.modified()) {
+                    (Ok(ours), Ok(theirs)) => ours > theirs,
+                    _ => true
+                }
+            }
+        };
+        if replace && existing.is_dir() {
+            self.dst_fs.remove_dir_all(target).await?;
+        } else if replace {
+            self.dst_fs.remove_file(target).await?;
+        }
+        Ok(replace)
+    }
+
+    /// Returns what is at `target`, without following symlinks.
+    async fn existing(&self, target: &Path) -> io::Result<Option<Metadata>> {
+        match self.dst_fs.symlink_metadata(target).await {
+            Ok(existing) => Ok(Some(existing)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)
+        }
+    }
+
+    async fn copy(&self, entry: WalkEntry<S::DirEntry>) -> io::Result<()> {
+        let target = self.target(&entry);
+        if entry.is_dir() {
+            let metadata = entry.metadata().await?;
+            return self.copy_dir(&entry, &target, metadata).await;
+        }
+        let follow = self.options.follow_links || entry.depth() == 0;
+        match entry.file_type() {
+            FileType::File => {
+                let metadata = entry.metadata().await?;
+                self.copy_file(&entry, &target, metadata).await
+            }
+            FileType::Symlink if follow => match entry.metadata().await {
+                Ok(metadata) if metadata.is_file() => {
+                    self.copy_file(&entry, &target, metadata).await
+                }
+                Ok(_) => self.skip(&entry),
+                Err(_) => self.copy_link(&entry, &target).await
+            },
+            FileType::Symlink => self.copy_link(&entry, &target).await,
+            _ => self.skip(&entry)
+        }
+    }
+
+    fn skip(&self, entry: &WalkEntry<S::DirEntry>) -> io::Result<()> {
+        self.record(entry.path(), |stats| stats.skipped += 1);
+        Ok(())
+    }
+
+    async fn copy_dir(&self,
+                      entry: &WalkEntry<S::DirEntry>,
+                      target: &Path,
+                      metadata: Metadata)
+                      -> io::Result<()> {
+        match self.existing(target).await? {
+            Some(existing) if existing.is_dir() => return Ok(()),
+            Some(existing)
+                if !self.make_room(target, &existing, &metadata).await? =>
+            {
+                self.skipped.lock().unwrap().insert(entry.path().to_owned());
+                return self.skip(entry);
+            }
+            _ => {}
+        }
+        self.dst_fs.dir_builder().create(target).await?;
+        if self.options.permissions {
+            self.dirs
+                .lock()
+                .unwrap()
+                .push((target.to_owned(), metadata.permissions()));
+        }
+        self.record(entry.path(), |stats| stats.dirs += 1);
+        Ok(())
+    }
+
+    async fn copy_file(&self,
+                       entry: &WalkEntry<S::DirEntry>,
+                       target: &Path,
+                       metadata: Metadata)
+                       -> io::Result<()> {
+        if let Some(existing) = self.existing(target).await? {
+            if !self.make_room(target, &existing, &metadata).await? {
+                return self.skip(entry);
+            }
+        }
+        let mut src = self.src_fs
+                          .open_options()
+                          .read(true)
+                          .open(entry.path())
+                          .await?;
+        let mut dst = self.dst_fs
+                          .open_options()
+                          .write(true)
+                          .create_new(true)
+                          .open(target)
+                          .await?;
+        let mut chunk = vec![0; CHUNK];
+        loop {
+            let len = src.read(&mut chunk).await?;
+            if len == 0 {
+                break;
+            }
+            dst.write_all(&chunk[..len]).await?;
+            self.record(entry.path(), |stats| stats.bytes += len as u64);
+        }
+        dst.close().await?;
+        if self.options.permissions {
+            self.dst_fs
+                .set_permissions(target, metadata.permissions())
+                .await?;
+        }
+        self.record(entry.path(), |stats| stats.files += 1);
+        Ok(())
+    }
+
+    async fn copy_link(&self,
+                       entry: &WalkEntry<S::DirEntry>,
+                       target: &Path)
+                       -> io::Result<()> {
+        let link = self.src_fs.symlink_metadata(entry.path()).await?;
+        if let Some(existing) = self.existing(target).await? {
+            if !self.make_room(target, &existing, &link).await? {
+                return self.skip(entry);
+            }
+        }
+        let points_to = self.src_fs.read_link(entry.path()).await?;
+        self.dst_fs.symlink(target, points_to).await?;
+        self.record(entry.path(), |stats| stats.symlinks += 1);
+        Ok(())
+    }
+
+    /// Gives the directories that were created their permissions, deepest
+    /// first, so that read-only ones don't get in the way of the others.
+    async fn finish(&self) -> io::Result<()> {
+        let dirs = std::mem::take(&mut *self.dirs.lock().unwrap());
+        for (dir, permissions) in dirs.into_iter().rev() {
+            self.dst_fs.set_permissions(dir, permissions).await?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) async fn run<S, D>(options: &CopyOptions,
+                              src_fs: &S,
+                              src: &Path,
+                              dst_fs: &D,
+                              dst: &Path)
+                              -> io::Result<CopyStats>
+    where S: AsyncFsTrait,
+          D: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<S>: AsyncRead + Unpin,
+          FileOf<D>: AsyncWrite + Unpin
+{
+    let skipped = Arc::new(Mutex::new(HashSet::<PathBuf>::new()));
+    let job = Arc::new(Job { src_fs,
+                             src: src.to_owned(),
+                             dst_fs,
+                             dst: dst.to_owned(),
+                             options: options.clone(),
+                             stats: Mutex::new(CopyStats::default()),
+                             skipped: skipped.clone(),
+                             dirs: Mutex::new(Vec::new()) });
+
+    let mut walk = WalkOptions::new();
+    walk.follow_links(options.follow_links)
+        .max_concurrency(options.max_concurrency)
+        .filter_entry(move |entry| match entry.path().parent() {
+            Some(parent) => !skipped.lock().unwrap().contains(parent),
+            None => true
+        });
+    let mut walk = Some(walk.walk(src_fs, src));
+
+    // A directory is created before anything is copied into it, so the walk
+    // waits for it, while files and symlinks are copied side by side.
+    let mut dir: Option<Task<'_>> = None;
+    let mut copying: Vec<Task<'_>> = Vec::new();
+    poll_fn(|cx| loop {
+        let mut progress = false;
+        if let Some(task) = &mut dir {
+            if let Poll::Ready(result) = Future::poll(task.as_mut(), cx) {
+                result?;
+                dir = None;
+                progress = true;
+            }
+        }
+        let mut i = 0;
+        while i < copying.len() {
+            match Future::poll(copying[i].as_mut(), cx) {
+                Poll::Ready(result) => {
+                    result?;
+                    drop(copying.swap_remove(i));
+                    progress = true;
+                }
+                Poll::Pending => i += 1
+            }
+        }
+        let room = dir.is_none() && copying.len() < options.max_concurrency;
+        if let (true, Some(entries)) = (room, &mut walk) {
+            if let Poll::Ready(entry) = Pin::new(entries).poll_next(cx) {
+                match entry.transpose()? {
+                    Some(entry) => {
+                        let is_dir = entry.is_dir();
+                        let job = job.clone();
+                        let task: Task<'_> =
+                            Box::pin(async move { job.copy(entry).await });
+                        match is_dir {
+                            true => dir = Some(task),
+                            false => copying.push(task)
+                        }
+                    }
+                    None => walk = None
+                }
+                progress = true;
+            }
+        }
+        if walk.is_none() && dir.is_none() && copying.is_empty() {
+            return Poll::Ready(io::Result::Ok(()));
+        }
+        if !progress {
+            return Poll::Pending;
+        }
+    }).await?;
+
+    job.finish().await?;
+    let stats = *job.stats.lock().unwrap();
+    Ok(stats)
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::mem_fs::MemFs;
+
+    async fn write(fs: &MemFs, path: &str, contents: &[u8]) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create(true)
+                         .truncate(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(contents).await.unwrap();
+        file.close().await.unwrap();
+    }
+
+    async fn read(fs: &MemFs, path: &str) -> Vec<u8> {
+        let mut file = fs.open_options().read(true).open(path).await.unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).await.unwrap();
+        contents
+    }
+
+    async fn source() -> MemFs {
+        let fs = MemFs::new();
+        fs.dir_builder()
+          .recursive(true)
+          .create("/src/sub/deeper")
+          .await
+          .unwrap();
+        write(&fs, "/src/a", b"alpha").await;
+        write(&fs, "/src/sub/b", b"beta").await;
+        write(&fs, "/src/sub/deeper/c", &[7; 200_000]).await;
+        fs.symlink("/src/link", "sub/b").await.unwrap();
+        fs
+    }
+
+    fn mode(metadata: Metadata) -> Option<u32> {
+        metadata.permissions().mode().map(|mode| mode & 0o777)
+    }
+
+    #[test]
+    fn trees_are_copied_across_filesystems() {
+        block_on(async {
+            let src = source().await;
+            src.set_permissions("/src/a", Permissions::from_mode(0o600))
+               .await
+               .unwrap();
+            src.set_permissions("/src/sub", Permissions::from_mode(0o555))
+               .await
+               .unwrap();
+            let dst = MemFs::new();
+            let stats = copy_tree(&src, "/src", &dst, "/copy").await.unwrap();
+            assert_eq!(stats,
+                       CopyStats { dirs: 3,
+                                   files: 3,
+                                   symlinks: 1,
+                                   skipped: 0,
+                                   bytes: 200_009 });
+
+            assert_eq!(read(&dst, "/copy/a").await, b"alpha");
+            assert_eq!(read(&dst, "/copy/sub/deeper/c").await, [7; 200_000]);
+            assert_eq!(dst.read_link("/copy/link").await.unwrap(),
+                       Path::new("sub/b"));
+            let a = dst.metadata("/copy/a").await.unwrap();
+            assert_eq!(mode(a), Some(0o600));
+            let sub = dst.metadata("/copy/sub").await.unwrap();
+            assert_eq!(mode(sub), Some(0o555));
+
+            let err = copy_tree(&src, "/src", &dst, "/copy").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+            let err = copy_tree(&src, "/src", &dst, "/no/parent").await;
+            assert_eq!(err.unwrap_err().kind(), io::ErrorKind::NotFound);
+        });
+    }
+
+    #[test]
+    fn overwrite_policies_are_followed() {
+        block_on(async {
+            let src = source().await;
+            let dst = MemFs::new();
+            dst.dir_builder().create("/copy").await.unwrap();
+            write(&dst, "/copy/a", b"old").await;
+            write(&dst, "/copy/sub", b"not a directory").await;
+
+            let mut options = CopyOptions::new();
+            let stats = options.overwrite(Overwrite::Skip)
+                               .copy_tree(&src, "/src", &dst, "/copy")
+                               .await
+                               .unwrap();
+            assert_eq!((stats.files, stats.skipped), (0, 2));
+            assert_eq!(read(&dst, "/copy/a").await, b"old");
+            assert_eq!(read(&dst, "/copy/sub").await, b"not a directory");
+
+            // Only the files written since are newer than their copies.
+            let stats = options.overwrite(Overwrite::IfNewer)
+                               .copy_tree(&src, "/src", &dst, "/copy")
+                               .await
+                               .unwrap();
+            assert_eq!((stats.files, stats.skipped), (0, 3));
+            write(&src, "/src/a", b"newer").await;
+            let stats = options.copy_tree(&src, "/src", &dst, "/copy")
+                               .await
+                               .unwrap();
+            assert_eq!((stats.files, stats.skipped), (1, 2));
+            assert_eq!(read(&dst, "/copy/a").await, b"newer");
+
+            let stats = options.overwrite(Overwrite::Replace)
+                               .copy_tree(&src, "/src", &dst, "/copy")
+                               .await
+                               .unwrap();
+            assert_eq!((stats.files, stats.symlinks, stats.skipped), (3, 1, 0));
+            assert_eq!(read(&dst, "/copy/sub/b").await, b"beta");
+        });
+    }
+
+    #[test]
+    fn symlinks_can_be_followed() {
+        block_on(async {
+            let src = source().await;
+            src.symlink("/src/dangling", "/nowhere").await.unwrap();
+            let dst = MemFs::new();
+            let stats =
+                CopyOptions::new().follow_links(true)
+                                  .copy_tree(&src, "/src", &dst, "/copy")
+                                  .await
+                                  .unwrap();
+            assert_eq!((stats.files, stats.symlinks), (4, 1));
+            assert_eq!(read(&dst, "/copy/link").await, b"beta");
+            assert!(dst.symlink_metadata("/copy/dangling")
+                       .await
+                       .unwrap()
+                       .is_symlink());
+        });
+    }
+
+    #[test]
+    fn copies_run_side_by_side() {
+        block_on(async {
+            let src = MemFs::new();
+            for n in 0..50 {
+                let dir = format!("/src/{}", n % 5);
+                src.dir_builder()
+                   .recursive(true)
+                   .create(&dir)
+                   .await
+                   .unwrap();
+                write(&src, &format!("{}/{}", dir, n), &[n; 1000]).await;
+            }
+            let dst = MemFs::new();
+            let reports = Arc::new(Mutex::new(Vec::new()));
+            let seen = reports.clone();
+            let stats =
+                CopyOptions::new().max_concurrency(8)
+                                  .on_progress(move |_, stats| {
+                                      seen.lock().unwrap().push(*stats)
+                                  })
+                                  .copy_tree(&src, "/src", &dst, "/copy")
+                                  .await
+                                  .unwrap();
+            assert_eq!((stats.dirs, stats.files, stats.bytes), (6, 50, 50_000));
+            assert_eq!(reports.lock().unwrap().last(), Some(&stats));
+            for n in 0..50 {
+                let path = format!("/copy/{}/{}", n % 5, n);
+                assert_eq!(read(&dst, &path).await, [n; 1000]);
+            }
+        });
+    }
+}
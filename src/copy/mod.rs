@@ -0,0 +1,48 @@
+//! Copies of whole directory trees, from one filesystem to another.
+//!
+//! [`AsyncFsTrait::copy()`][1] copies a single file within a single
+//! filesystem.  [`copy_tree()`] and [`CopyOptions::copy_tree()`] copy a
+//! directory and everything below it between any two filesystems, such as
+//! from an archive to the disk.  They [walk][2] the source tree, recreate
+//! directories with [`AsyncDirBuilderTrait`][3] and symlinks with
+//! [`AsyncSymLinkTrait`][4], stream file contents through
+//! [`AsyncRead`][5] and [`AsyncWrite`][6], and carry permissions over with
+//! [`set_permissions()`][7]:
+//!
+//! ```
+//! # #[cfg(feature = "mem-fs")]
+//! # {
+//! use async_fs_traits::{copy::copy_tree, mem_fs::MemFs, AsyncDirBuilderTrait,
+//!                       AsyncFsTrait};
+//! use futures_lite::future::block_on;
+//!
+//! let (src, dst) = (MemFs::new(), MemFs::new());
+//! block_on(async {
+//!     src.dir_builder().recursive(true).create("/a/b/c").await?;
+//!     let stats = copy_tree(&src, "/a", &dst, "/a").await?;
+//!     assert_eq!(stats.dirs, 3);
+//!     std::io::Result::Ok(())
+//! });
+//! # }
+//! ```
+//!
+//! [`CopyOptions`] decides what happens to entries that are in the way,
+//! follows symlinks, reports progress, and copies several files at once.
+//!
+//! This module is only available when the `copy` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait::copy
+//! [2]: crate::walk
+//! [3]: crate::AsyncDirBuilderTrait
+//! [4]: crate::AsyncSymLinkTrait
+//! [5]: futures_io::AsyncRead
+//! [6]: futures_io::AsyncWrite
+//! [7]: crate::AsyncFsTrait::set_permissions
+
+mod copier;
+mod options;
+
+#[doc(inline)]
+pub use copier::copy_tree;
+#[doc(inline)]
+pub use options::{CopyOptions, CopyStats, Overwrite};
@@ -0,0 +1,229 @@
+//! [`CopyOptions`] configures how a directory tree is copied.
+
+use std::{fmt, io, path::Path, sync::Arc};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use super::copier::{self, Progress};
+use crate::{layer::FileOf, AsyncFsTrait, AsyncSymLinkTrait};
+
+/// What to do when something is already in the way of a copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overwrite {
+    /// Fails with [`ErrorKind::AlreadyExists`][1].
+    ///
+    /// [1]: std::io::ErrorKind::AlreadyExists
+    Fail,
+
+    /// Leaves what is there alone, along with everything below the entry
+    /// being copied.
+    Skip,
+
+    /// Removes what is there and copies the entry in its place.
+    Replace,
+
+    /// Replaces what is there if the entry being copied was modified more
+    /// recently, and skips the entry otherwise.  An entry is replaced if
+    /// either modification time is unknown.
+    IfNewer
+}
+
+impl Default for Overwrite {
+    fn default() -> Self {
+        Overwrite::Fail
+    }
+}
+
+/// What a copy has done so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CopyStats {
+    /// The number of directories created.  Directories that already existed
+    /// are merged into, and not counted.
+    pub dirs: u64,
+
+    /// The number of files copied.
+    pub files: u64,
+
+    /// The number of symlinks recreated.
+    pub symlinks: u64,
+
+    /// The number of entries that weren't copied, either because of the
+    /// [overwrite policy][1], or because they are special files, such as
+    /// devices or named pipes.
+    ///
+    /// [1]: Overwrite
+    pub skipped: u64,
+
+    /// The number of bytes of file contents copied.
+    pub bytes: u64
+}
+
+/// Options for copying a directory tree.
+///
+/// The options start out failing on anything that is in the way, recreating
+/// symlinks rather than following them, keeping permissions, and copying one
+/// entry at a time.
+///
+/// ```
+/// # #[cfg(feature = "mem-fs")]
+/// # {
+/// use async_fs_traits::{
+///     copy::{CopyOptions, Overwrite},
+///     mem_fs::MemFs,
+///     AsyncDirBuilderTrait, AsyncFsTrait
+/// };
+/// use futures_lite::future::block_on;
+///
+/// let (archive, disk) = (MemFs::new(), MemFs::new());
+/// block_on(async {
+///     archive.dir_builder().create("/release").await?;
+///     disk.dir_builder().create("/opt").await?;
+///     let stats = CopyOptions::new()
+///         .overwrite(Overwrite::IfNewer)
+///         .max_concurrency(8)
+///         .on_progress(|path, stats| {
+///             println!("{} ({} bytes so far)", path.display(), stats.bytes)
+///         })
+///         .copy_tree(&archive, "/release", &disk, "/opt/release")
+///         .await?;
+///     assert_eq!(stats.dirs, 1);
+///     std::io::Result::Ok(())
+/// });
+/// # }
+/// ```
+pub struct CopyOptions {
+    pub(super) overwrite: Overwrite,
+    pub(super) follow_links: bool,
+    pub(super) permissions: bool,
+    pub(super) max_concurrency: usize,
+    pub(super) progress: Option<Progress>
+}
+
+impl CopyOptions {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        CopyOptions { overwrite: Overwrite::Fail,
+                      follow_links: false,
+                      permissions: true,
+                      max_concurrency: 1,
+                      progress: None }
+    }
+
+    /// Sets what to do when something is already in the way of a copy.
+    ///
+    /// Directories that are already in the way of a directory are never
+    /// in the way: the copy is merged into them.
+    pub fn overwrite(&mut self, overwrite: Overwrite) -> &mut Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Copies what symlinks point to, rather than recreating the symlinks.
+    /// Symlinks that don't point to anything are still recreated.
+    pub fn follow_links(&mut self, follow_links: bool) -> &mut Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Gives files and directories the same permissions as the ones they
+    /// were copied from.
+    ///
+    /// Directories get their permissions last, so that read-only ones can
+    /// still be filled.
+    pub fn permissions(&mut self, permissions: bool) -> &mut Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Copies up to `max_concurrency` files and symlinks at once, and reads
+    /// as many directories ahead.  A value of 0 is treated as 1.
+    pub fn max_concurrency(&mut self, max_concurrency: usize) -> &mut Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Calls `progress` with the path of what is being copied and the totals
+    /// so far, after each entry is copied or skipped, and as file contents
+    /// are copied.
+    pub fn on_progress<F>(&mut self, progress: F) -> &mut Self
+        where F: Fn(&Path, &CopyStats) + Send + Sync + 'static
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Copies `src` in `src_fs` to `dst` in `dst_fs`, along with everything
+    /// below it.
+    ///
+    /// `dst` is the path of the copy, not a directory to copy into, and its
+    /// parent must exist.  File contents are streamed from one filesystem to
+    /// the other.  The first error stops the copy, and is returned; whatever
+    /// was copied before it is left in place.
+    pub async fn copy_tree<S, D, P, Q>(&self,
+                                       src_fs: &S,
+                                       src: P,
+                                       dst_fs: &D,
+                                       dst: Q)
+                                       -> io::Result<CopyStats>
+        where S: AsyncFsTrait,
+              D: AsyncFsTrait + AsyncSymLinkTrait,
+              FileOf<S>: AsyncRead + Unpin,
+              FileOf<D>: AsyncWrite + Unpin,
+              P: AsRef<Path>,
+              Q: AsRef<Path>
+    {
+        copier::run(self, src_fs, src.as_ref(), dst_fs, dst.as_ref()).await
+    }
+}
+
+impl Clone for CopyOptions {
+    fn clone(&self) -> Self {
+        CopyOptions { overwrite: self.overwrite,
+                      follow_links: self.follow_links,
+                      permissions: self.permissions,
+                      max_concurrency: self.max_concurrency,
+                      progress: self.progress.clone() }
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions::new()
+    }
+}
+
+impl fmt::Debug for CopyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CopyOptions")
+         .field("overwrite", &self.overwrite)
+         .field("follow_links", &self.follow_links)
+         .field("permissions", &self.permissions)
+         .field("max_concurrency", &self.max_concurrency)
+         .field("progress", &self.progress.is_some())
+         .finish()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_start_out_careful() {
+        let mut options = CopyOptions::default();
+        assert_eq!(options.overwrite, Overwrite::Fail);
+        assert!(options.permissions && !options.follow_links);
+        assert_eq!(options.max_concurrency(0).max_concurrency, 1);
+        let debug = format!("{:?}", options.on_progress(|_, _| {}));
+        assert!(debug.contains("progress: true"));
+    }
+}
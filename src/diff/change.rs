@@ -0,0 +1,217 @@
+//! [`Change`] describes one difference between two directory trees.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf}
+};
+
+use crate::{FileType, Permissions};
+
+/// A difference between two directory trees.
+///
+/// Paths are relative to the roots of both trees, and the roots themselves
+/// have an empty path.  The tree that is diffed from is called the old tree,
+/// and the one diffed to is the new tree.
+///
+/// A change prints as a single line, with a letter for its kind followed by
+/// its path, or `.` for the roots, which suits snapshots:
+///
+/// ```text
+/// A bin/tool
+/// D lib/old.so
+/// M etc/config.toml
+/// T share (file -> dir)
+/// P bin/run (0o644 -> 0o755)
+/// ```
+///
+/// When the `serde` feature is enabled, changes can be serialized and
+/// deserialized, tagged with a `change` field naming their kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde",
+           derive(serde::Serialize, serde::Deserialize),
+           serde(tag = "change", rename_all = "snake_case"))]
+pub enum Change {
+    /// An entry that only the new tree has.
+    Added {
+        /// The path of the entry.
+        path: PathBuf,
+
+        /// The type of the entry.
+        file_type: FileType
+    },
+
+    /// An entry that only the old tree has.
+    Removed {
+        /// The path of the entry.
+        path: PathBuf,
+
+        /// The type of the entry.
+        file_type: FileType
+    },
+
+    /// A file whose contents changed, or a symlink that points somewhere
+    /// else.
+    Modified {
+        /// The path of the entry.
+        path: PathBuf,
+
+        /// The type of the entry.
+        file_type: FileType
+    },
+
+    /// An entry that has another type in the new tree.
+    TypeChanged {
+        /// The path of the entry.
+        path: PathBuf,
+
+        /// The type of the entry in the old tree.
+        from: FileType,
+
+        /// The type of the entry in the new tree.
+        to: FileType
+    },
+
+    /// An entry that has other permissions in the new tree.
+    PermissionsChanged {
+        /// The path of the entry.
+        path: PathBuf,
+
+        /// The permissions of the entry in the old tree.
+        from: Permissions,
+
+        /// The permissions of the entry in the new tree.
+        to: Permissions
+    }
+}
+
+impl Change {
+    /// Returns the path of the entry that changed.
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Modified { path, .. }
+            | Change::TypeChanged { path, .. }
+            | Change::PermissionsChanged { path, .. } => path
+        }
+    }
+}
+
+/// Prints a file type the way [`Change`]'s [`Display`][1] does.
+///
+/// [1]: fmt::Display
+struct Kind(FileType);
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.0 {
+                        FileType::File => "file",
+                        FileType::Dir => "dir",
+                        FileType::Symlink => "symlink",
+                        FileType::BlockDevice => "block device",
+                        FileType::CharDevice => "char device",
+                        FileType::Fifo => "fifo",
+                        FileType::Socket => "socket",
+                        _ => "other"
+                    })
+    }
+}
+
+/// Prints permissions the way [`Change`]'s [`Display`][1] does.
+///
+/// [1]: fmt::Display
+struct Mode(Permissions);
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.0.mode(), self.0.readonly()) {
+            (Some(mode), _) => write!(f, "{:#o}", mode & 0o7777),
+            (None, true) => f.write_str("read-only"),
+            (None, false) => f.write_str("writable")
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The roots print as `.`, so that every line has a path.
+        let path = match self.path() {
+            path if path.as_os_str().is_empty() => Path::new("."),
+            path => path
+        };
+        let path = path.display();
+        match *self {
+            Change::Added { .. } => write!(f, "A {}", path),
+            Change::Removed { .. } => write!(f, "D {}", path),
+            Change::Modified { .. } => write!(f, "M {}", path),
+            Change::TypeChanged { from, to, .. } => {
+                write!(f, "T {} ({} -> {})", path, Kind(from), Kind(to))
+            }
+            Change::PermissionsChanged { from, to, .. } => {
+                write!(f, "P {} ({} -> {})", path, Mode(from), Mode(to))
+            }
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes() -> Vec<Change> {
+        vec![Change::Added { path: PathBuf::from("bin/tool"),
+                             file_type: FileType::File },
+             Change::Removed { path: PathBuf::from("lib"),
+                               file_type: FileType::Dir },
+             Change::Modified { path: PathBuf::from("current"),
+                                file_type: FileType::Symlink },
+             Change::TypeChanged { path: PathBuf::from("share"),
+                                   from: FileType::File,
+                                   to: FileType::Dir },
+             Change::PermissionsChanged { path: PathBuf::new(),
+                                          from:
+                                              Permissions::from_mode(0o40755),
+                                          to: Permissions::new(true) }]
+    }
+
+    #[test]
+    fn changes_print_one_per_line() {
+        let lines = changes().iter()
+                             .map(|change| change.to_string())
+                             .collect::<Vec<_>>();
+        assert_eq!(lines,
+                   ["A bin/tool",
+                    "D lib",
+                    "M current",
+                    "T share (file -> dir)",
+                    "P . (0o755 -> read-only)"]);
+        assert_eq!(changes()[3].path(), Path::new("share"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn changes_are_tagged_when_serialized() {
+        use serde_test::{assert_tokens, Token};
+
+        assert_tokens(&changes()[0],
+                      &[Token::Struct { name: "Change",
+                                        len: 3 },
+                        Token::Str("change"),
+                        Token::Str("added"),
+                        Token::Str("path"),
+                        Token::Str("bin/tool"),
+                        Token::Str("file_type"),
+                        Token::UnitVariant { name: "FileType",
+                                             variant: "file" },
+                        Token::StructEnd]);
+    }
+}
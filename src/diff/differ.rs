@@ -0,0 +1,424 @@
+//! [`diff_trees()`], the [`Diff`] stream it returns, and what compares the
+//! trees behind it.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt, io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use futures_core::Stream;
+use futures_io::AsyncRead;
+use futures_lite::stream;
+
+use super::{Change, DiffOptions};
+use crate::{
+    layer::FileOf,
+    tree::{list, same_contents, same_permissions},
+    AsyncFileBuilderTrait, AsyncFsTrait, FileType, Metadata
+};
+
+/// Diffs the old tree at `old` in `old_fs` against the new tree at `new` in
+/// `new_fs` with the default [`DiffOptions`], and streams the changes between
+/// them.
+///
+/// Files are compared by their sizes and modification times, and permission
+/// changes are reported.
+pub fn diff_trees<'a, A, B, P, Q>(old_fs: &'a A,
+                                  old: P,
+                                  new_fs: &'a B,
+                                  new: Q)
+                                  -> Diff<'a>
+    where A: AsyncFsTrait,
+          B: AsyncFsTrait,
+          FileOf<A>: AsyncRead + Unpin,
+          FileOf<B>: AsyncRead + Unpin,
+          P: AsRef<Path>,
+          Q: AsRef<Path>
+{
+    DiffOptions::new().diff_trees(old_fs, old, new_fs, new)
+}
+
+/// Something left to compare.
+enum Task {
+    /// The roots, which are followed if they are symlinks.
+    Roots,
+
+    /// An entry, with its type in each tree that has it.
+    Entry {
+        path: PathBuf,
+        old: Option<FileType>,
+        new: Option<FileType>
+    },
+
+    /// A directory whose entries are compared next, with whether each tree
+    /// has it.
+    Dir { path: PathBuf, old: bool, new: bool }
+}
+
+/// Compares two trees one entry at a time, queueing up the changes found.
+pub(super) struct Differ<'a, A, B> {
+    options: DiffOptions,
+    old_fs: &'a A,
+    old_root: PathBuf,
+    new_fs: &'a B,
+    new_root: PathBuf,
+    tasks: Vec<Task>,
+    changes: VecDeque<io::Result<Change>>
+}
+
+impl<'a, A, B> Differ<'a, A, B>
+    where A: AsyncFsTrait,
+          B: AsyncFsTrait,
+          FileOf<A>: AsyncRead + Unpin,
+          FileOf<B>: AsyncRead + Unpin
+{
+    pub(super) fn new(options: DiffOptions,
+                      old_fs: &'a A,
+                      old_root: &Path,
+                      new_fs: &'a B,
+                      new_root: &Path)
+                      -> Self {
+        Differ { options,
+                 old_fs,
+                 old_root: old_root.to_owned(),
+                 new_fs,
+                 new_root: new_root.to_owned(),
+                 tasks: vec![Task::Roots],
+                 changes: VecDeque::new() }
+    }
+
+    /// Turns the differ into a stream of the changes it finds.
+    pub(super) fn diff(self) -> Diff<'a> {
+        let stream = stream::unfold(self, |mut differ| async move {
+            let item = differ.next().await?;
+            Some((item, differ))
+        });
+        Diff { stream: Box::pin(stream) }
+    }
+
+    async fn next(&mut self) -> Option<io::Result<Change>> {
+        loop {
+            if let Some(change) = self.changes.pop_front() {
+                return Some(change);
+            }
+            let task = self.tasks.pop()?;
+            if let Err(err) = self.run(task).await {
+                self.changes.push_back(Err(err));
+            }
+        }
+    }
+
+    async fn run(&mut self, task: Task) -> io::Result<()> {
+        match task {
+            Task::Roots => {
+                let old = self.old_fs.metadata(&self.old_root).await?;
+                let new = self.new_fs.metadata(&self.new_root).await?;
+                self.compare(PathBuf::new(), &old, &new).await
+            }
+            Task::Entry { path,
+                          old: Some(_),
+                          new: Some(_) } => {
+                let old = self.old_fs
+                              .symlink_metadata(self.old_root.join(&path))
+                              .await?;
+                let new = self.new_fs
+                              .symlink_metadata(self.new_root.join(&path))
+                              .await?;
+                self.compare(path, &old, &new).await
+            }
+            Task::Entry { path,
+                          old: Some(file_type),
+                          new: None } => {
+                self.push_dir(&path, file_type.is_dir(), false);
+                self.push(Change::Removed { path, file_type });
+                Ok(())
+            }
+            Task::Entry { path,
+                          new: Some(file_type),
+                          .. } => {
+                self.push_dir(&path, false, file_type.is_dir());
+                self.push(Change::Added { path, file_type });
+                Ok(())
+            }
+            Task::Entry { .. } => Ok(()),
+            Task::Dir { path, old, new } => self.list(path, old, new).await
+        }
+    }
+
+    fn push(&mut self, change: Change) {
+        self.changes.push_back(Ok(change));
+    }
+
+    /// Queues up the entries of the directory `path` if either tree has it.
+    fn push_dir(&mut self, path: &Path, old: bool, new: bool) {
+        if old || new {
+            self.tasks.push(Task::Dir { path: path.to_owned(),
+                                        old,
+                                        new });
+        }
+    }
+
+    /// Compares an entry that both trees have.
+    async fn compare(&mut self,
+                     path: PathBuf,
+                     old: &Metadata,
+                     new: &Metadata)
+                     -> io::Result<()> {
+        let (from, to) = (old.file_type(), new.file_type());
+        if from != to {
+            self.push_dir(&path, from.is_dir(), to.is_dir());
+            self.push(Change::TypeChanged { path, from, to });
+            return Ok(());
+        }
+        let modified = match from {
+            FileType::File => self.modified(&path, old, new).await?,
+            FileType::Symlink => {
+                let old = self.old_fs.read_link(self.old_root.join(&path));
+                let new = self.new_fs.read_link(self.new_root.join(&path));
+                old.await? != new.await?
+            }
+            _ => false
+        };
+        if modified {
+            self.push(Change::Modified { path: path.clone(),
+                                         file_type: from });
+        }
+        let (old, new) = (old.permissions(), new.permissions());
+        if self.options.permissions
+           && !from.is_symlink()
+           && !same_permissions(old, new)
+        {
+            self.push(Change::PermissionsChanged { path: path.clone(),
+                                                   from: old,
+                                                   to: new });
+        }
+        self.push_dir(&path, from.is_dir(), to.is_dir());
+        Ok(())
+    }
+
+    /// Returns `true` if the file at `path` differs between the trees.
+    async fn modified(&self,
+                      path: &Path,
+                      old: &Metadata,
+                      new: &Metadata)
+                      -> io::Result<bool> {
+        if old.len() != new.len() {
+            return Ok(true);
+        }
+        if !self.options.contents {
+            return Ok(match (old.modified(), new.modified()) {
+                (Ok(old), Ok(new)) => old != new,
+                _ => false
+            });
+        }
+        let mut old = self.old_fs
+                          .open_options()
+                          .read(true)
+                          .open(self.old_root.join(path))
+                          .await?;
+        let mut new = self.new_fs
+                          .open_options()
+                          .read(true)
+                          .open(self.new_root.join(path))
+                          .await?;
+        Ok(!same_contents(&mut old, &mut new).await?)
+    }
+
+    /// Lists the directory `path` in the trees that have it, and queues up
+    /// its entries in order.
+    async fn list(&mut self,
+                  path: PathBuf,
+                  old: bool,
+                  new: bool)
+                  -> io::Result<()> {
+        let mut old_entries = BTreeMap::new();
+        if old {
+            old_entries = list(self.old_fs, &self.old_root.join(&path)).await?;
+        }
+        let mut new_entries = BTreeMap::new();
+        if new {
+            new_entries = list(self.new_fs, &self.new_root.join(&path)).await?;
+        }
+        let names = old_entries.keys()
+                               .chain(new_entries.keys())
+                               .collect::<BTreeSet<_>>();
+        for name in names.into_iter().rev() {
+            self.tasks.push(Task::Entry { path: path.join(name),
+                                          old: old_entries.get(name)
+                                                          .copied(),
+                                          new: new_entries.get(name)
+                                                          .copied() });
+        }
+        Ok(())
+    }
+}
+
+/// A stream of the changes between two directory trees, returned by
+/// [`diff_trees()`] and [`DiffOptions::diff_trees()`].
+///
+/// Collecting it gives the whole changeset:
+///
+/// ```
+/// # #[cfg(feature = "mem-fs")]
+/// # {
+/// use async_fs_traits::{diff::{diff_trees, Change}, mem_fs::MemFs};
+/// use futures_lite::{future::block_on, StreamExt};
+///
+/// let (old, new) = (MemFs::new(), MemFs::new());
+/// block_on(async {
+///     let diff = diff_trees(&old, "/", &new, "/");
+///     let changes: Vec<Change> = diff.try_collect().await?;
+///     assert!(changes.is_empty());
+///     std::io::Result::Ok(())
+/// });
+/// # }
+/// ```
+pub struct Diff<'a> {
+    stream: Pin<Box<dyn Stream<Item = io::Result<Change>> + Send + 'a>>
+}
+
+impl Stream for Diff<'_> {
+    type Item = io::Result<Change>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        self.get_mut().stream.as_mut().poll_next(cx)
+    }
+}
+
+impl fmt::Debug for Diff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Diff").finish_non_exhaustive()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, io::AsyncWriteExt, StreamExt};
+
+    use super::*;
+    use crate::{
+        mem_fs::MemFs, AsyncDirBuilderTrait, AsyncSymLinkTrait, Permissions
+    };
+
+    async fn write(fs: &MemFs, path: &str, contents: &[u8]) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create(true)
+                         .truncate(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(contents).await.unwrap();
+        file.close().await.unwrap();
+    }
+
+    async fn tree(fs: &MemFs) {
+        fs.dir_builder()
+          .recursive(true)
+          .create("/app/lib")
+          .await
+          .unwrap();
+        write(fs, "/app/config", b"debug = false").await;
+        write(fs, "/app/lib/core.so", b"core").await;
+        fs.symlink("/app/current", "lib/core.so").await.unwrap();
+    }
+
+    async fn lines(diff: Diff<'_>) -> Vec<String> {
+        diff.map(|change| change.unwrap().to_string())
+            .collect()
+            .await
+    }
+
+    #[test]
+    fn identical_trees_have_no_changes() {
+        block_on(async {
+            let fs = MemFs::new();
+            tree(&fs).await;
+            assert!(lines(diff_trees(&fs, "/app", &fs, "/app")).await
+                                                               .is_empty());
+        });
+    }
+
+    #[test]
+    fn changes_come_in_path_order() {
+        block_on(async {
+            let (old, new) = (MemFs::new(), MemFs::new());
+            tree(&old).await;
+            tree(&new).await;
+            write(&new, "/app/config", b"debug = true!").await;
+            new.remove_file("/app/current").await.unwrap();
+            new.symlink("/app/current", "lib").await.unwrap();
+            new.remove_dir_all("/app/lib").await.unwrap();
+            write(&new, "/app/lib", b"not a directory").await;
+            new.dir_builder().create("/app/bin").await.unwrap();
+            write(&new, "/app/bin/tool", b"tool").await;
+            new.set_permissions("/app/bin/tool", Permissions::from_mode(0o755))
+               .await
+               .unwrap();
+
+            let diff =
+                DiffOptions::new().contents(true)
+                                  .diff_trees(&old, "/app", &new, "/app");
+            assert_eq!(lines(diff).await,
+                       ["A bin",
+                        "A bin/tool",
+                        "M config",
+                        "M current",
+                        "T lib (dir -> file)",
+                        "D lib/core.so"]);
+        });
+    }
+
+    #[test]
+    fn contents_and_permissions_are_optional() {
+        block_on(async {
+            let (old, new) = (MemFs::new(), MemFs::new());
+            tree(&old).await;
+            tree(&new).await;
+            new.set_permissions("/app/config", Permissions::from_mode(0o600))
+               .await
+               .unwrap();
+            let mut options = DiffOptions::new();
+            options.contents(true);
+            let diff = options.diff_trees(&old, "/app", &new, "/app");
+            assert_eq!(lines(diff).await.len(), 1);
+            let diff = options.permissions(false)
+                              .diff_trees(&old, "/app", &new, "/app");
+            assert!(lines(diff).await.is_empty());
+
+            // The copies were made later, so their times differ.
+            let diff = diff_trees(&old, "/app", &new, "/app");
+            let changes: Vec<Change> = diff.try_collect().await.unwrap();
+            assert!(changes.contains(&Change::Modified { path:
+                                                             "config".into(),
+                                                         file_type:
+                                                             FileType::File }));
+        });
+    }
+
+    #[test]
+    fn errors_are_yielded_in_place() {
+        block_on(async {
+            let fs = MemFs::new();
+            tree(&fs).await;
+            let mut diff = diff_trees(&fs, "/app", &fs, "/missing");
+            let err = diff.next().await.unwrap().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(diff.next().await.is_none());
+        });
+    }
+}
@@ -0,0 +1,45 @@
+//! Structured diffs between the directory trees of any two filesystems.
+//!
+//! [`diff_trees()`] and [`DiffOptions::diff_trees()`] return a [`Diff`],
+//! which streams a [`Change`] for every entry that was added, removed,
+//! modified, changed type, or changed permissions between an old tree and a
+//! new one.  Diffs are built on [`read_dir()`][1], [`symlink_metadata()`][2],
+//! and, when contents are compared, on reading both files, so the trees can
+//! live on different backends:
+//!
+//! ```
+//! # #[cfg(feature = "mem-fs")]
+//! # {
+//! use async_fs_traits::{diff::diff_trees, mem_fs::MemFs, AsyncDirBuilderTrait,
+//!                       AsyncFsTrait};
+//! use futures_lite::{future::block_on, StreamExt};
+//!
+//! let (release, deployed) = (MemFs::new(), MemFs::new());
+//! block_on(async {
+//!     release.dir_builder().recursive(true).create("/srv/bin").await?;
+//!     deployed.dir_builder().create("/srv").await?;
+//!     let mut diff = diff_trees(&deployed, "/srv", &release, "/srv");
+//!     assert_eq!(diff.next().await.unwrap()?.to_string(), "A bin");
+//!     std::io::Result::Ok(())
+//! });
+//! # }
+//! ```
+//!
+//! Each change prints as one line, for snapshots, and the `serde` feature
+//! makes changes serializable, for tooling.
+//!
+//! This module is only available when the `diff` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait::read_dir
+//! [2]: crate::AsyncFsTrait::symlink_metadata
+
+mod change;
+mod differ;
+mod options;
+
+#[doc(inline)]
+pub use change::Change;
+#[doc(inline)]
+pub use differ::{diff_trees, Diff};
+#[doc(inline)]
+pub use options::DiffOptions;
@@ -0,0 +1,125 @@
+//! [`DiffOptions`] configures how two directory trees are compared.
+
+use std::path::Path;
+
+use futures_io::AsyncRead;
+
+use super::differ::{Diff, Differ};
+use crate::{layer::FileOf, AsyncFsTrait};
+
+/// Options for diffing two directory trees.
+///
+/// The options start out comparing files by their metadata, and reporting
+/// permission changes.
+///
+/// ```
+/// # #[cfg(feature = "mem-fs")]
+/// # {
+/// use async_fs_traits::{
+///     diff::DiffOptions, mem_fs::MemFs, AsyncDirBuilderTrait, AsyncFsTrait
+/// };
+/// use futures_lite::{future::block_on, StreamExt};
+///
+/// let (expected, actual) = (MemFs::new(), MemFs::new());
+/// block_on(async {
+///     expected.dir_builder().create("/out").await?;
+///     actual.dir_builder().create("/out").await?;
+///     let mut diff = DiffOptions::new()
+///         .contents(true)
+///         .permissions(false)
+///         .diff_trees(&expected, "/out", &actual, "/out");
+///     while let Some(change) = diff.next().await {
+///         println!("{}", change?);
+///     }
+///     std::io::Result::Ok(())
+/// });
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    pub(super) contents: bool,
+    pub(super) permissions: bool
+}
+
+impl DiffOptions {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        DiffOptions { contents: false,
+                      permissions: true }
+    }
+
+    /// Compares files by their contents, rather than by their sizes and
+    /// modification times.
+    ///
+    /// Comparing metadata is cheap, but counts a file as modified if it was
+    /// merely touched, or copied without keeping its modification time.
+    /// Comparing contents finds exactly the files that changed, but reads
+    /// every file of the same size on both sides.
+    pub fn contents(&mut self, contents: bool) -> &mut Self {
+        self.contents = contents;
+        self
+    }
+
+    /// Reports entries whose permissions changed.
+    pub fn permissions(&mut self, permissions: bool) -> &mut Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Diffs the old tree at `old` in `old_fs` against the new tree at `new`
+    /// in `new_fs`, and streams the changes between them.
+    ///
+    /// Both roots are followed if they are symlinks, but nothing below them
+    /// is.  Directories are compared entry by entry, using the file types of
+    /// their entries, and the changes come in the order of their paths, with
+    /// each directory before its contents.  An entry can have more than one
+    /// change, such as new contents and new permissions.  The entries below a
+    /// directory that was added, removed, or changed type are reported one
+    /// by one.
+    ///
+    /// An error reading a directory or comparing an entry is yielded in
+    /// place, and the diff carries on with the next entry.
+    pub fn diff_trees<'a, A, B, P, Q>(&self,
+                                      old_fs: &'a A,
+                                      old: P,
+                                      new_fs: &'a B,
+                                      new: Q)
+                                      -> Diff<'a>
+        where A: AsyncFsTrait,
+              B: AsyncFsTrait,
+              FileOf<A>: AsyncRead + Unpin,
+              FileOf<B>: AsyncRead + Unpin,
+              P: AsRef<Path>,
+              Q: AsRef<Path>
+    {
+        Differ::new(*self, old_fs, old.as_ref(), new_fs, new.as_ref()).diff()
+    }
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions::new()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_start_out_cheap() {
+        let mut options = DiffOptions::default();
+        assert!(!options.contents && options.permissions);
+        options.contents(true).permissions(false);
+        assert!(options.contents && !options.permissions);
+    }
+}
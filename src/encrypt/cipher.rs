@@ -0,0 +1,326 @@
+//! [`Cipher`] picks the AEAD algorithm that protects file contents and names.
+
+use std::{
+    ffi::{OsStr, OsString},
+    fmt, io
+};
+
+#[cfg(feature = "aes-gcm")]
+use aes_gcm::aead;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+#[cfg(all(feature = "chacha20poly1305", not(feature = "aes-gcm")))]
+use chacha20poly1305::aead;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use self::aead::{rand_core::RngCore, Aead as _, KeyInit, OsRng, Payload};
+use super::Key;
+use crate::chunked::{invalid_data, FrameCodec};
+
+/// The length of the nonce stored in front of every encrypted frame and name.
+const NONCE_LEN: usize = 12;
+
+/// The length of the authentication tag that follows every ciphertext.
+const TAG_LEN: usize = 16;
+
+/// An authenticated encryption algorithm.
+///
+/// Each variant is only available when the feature of the same name is
+/// enabled.  Files remember which cipher they were written with, so an
+/// [`EncryptedFs`][1] can read files written with any enabled cipher.
+///
+/// [1]: super::EncryptedFs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Cipher {
+    /// AES-256 in Galois/Counter Mode, from the `aes-gcm` feature.
+    #[cfg(feature = "aes-gcm")]
+    Aes256Gcm,
+
+    /// ChaCha20-Poly1305, from the `chacha20poly1305` feature.
+    #[cfg(feature = "chacha20poly1305")]
+    ChaCha20Poly1305
+}
+
+impl Cipher {
+    /// The identifier stored in the header of files using this cipher.
+    pub(super) fn id(self) -> u8 {
+        match self {
+            #[cfg(feature = "aes-gcm")]
+            Cipher::Aes256Gcm => 1,
+            #[cfg(feature = "chacha20poly1305")]
+            Cipher::ChaCha20Poly1305 => 2
+        }
+    }
+
+    /// Returns the cipher with the identifier `id`.
+    ///
+    /// Known ciphers whose feature is disabled are reported as
+    /// [`ErrorKind::Unsupported`][1].
+    ///
+    /// [1]: io::ErrorKind::Unsupported
+    pub(super) fn from_id(id: u8) -> io::Result<Self> {
+        match id {
+            #[cfg(feature = "aes-gcm")]
+            1 => Ok(Cipher::Aes256Gcm),
+            #[cfg(feature = "chacha20poly1305")]
+            2 => Ok(Cipher::ChaCha20Poly1305),
+            #[allow(unreachable_patterns)]
+            1..=2 => Err(io::Error::new(io::ErrorKind::Unsupported,
+                                        "the file was encrypted with a \
+                                         cipher whose feature is disabled")),
+            _ => Err(invalid_data("unknown cipher"))
+        }
+    }
+
+    /// Sets up this cipher with the key derived from `key` for `purpose`.
+    fn with_key(self, key: &Key, purpose: &str) -> Sealer {
+        let key = derive(key, purpose);
+        match self {
+            #[cfg(feature = "aes-gcm")]
+            Cipher::Aes256Gcm => {
+                Sealer::Aes(Box::new(aes_gcm::Aes256Gcm::new(&key.into())))
+            }
+            #[cfg(feature = "chacha20poly1305")]
+            Cipher::ChaCha20Poly1305 => {
+                use chacha20poly1305::ChaCha20Poly1305;
+
+                Sealer::ChaCha(ChaCha20Poly1305::new(&key.into()))
+            }
+        }
+    }
+}
+
+/// Derives the key for `purpose` from the master key `key`, so that no two
+/// purposes ever share a key.
+fn derive(key: &Key, purpose: &str) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(b"async-fs-traits ");
+    mac.update(purpose.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Fills `buf` with random bytes from the operating system.
+pub(super) fn random_bytes(buf: &mut [u8]) {
+    OsRng.fill_bytes(buf);
+}
+
+/// A [`Cipher`] that has been set up with a key.
+enum Sealer {
+    #[cfg(feature = "aes-gcm")]
+    Aes(Box<aes_gcm::Aes256Gcm>),
+    #[cfg(feature = "chacha20poly1305")]
+    ChaCha(chacha20poly1305::ChaCha20Poly1305)
+}
+
+impl Sealer {
+    /// Encrypts `msg`, returning the nonce followed by the ciphertext.
+    fn seal(&self,
+            nonce: [u8; NONCE_LEN],
+            aad: &[u8],
+            msg: &[u8])
+            -> io::Result<Vec<u8>> {
+        let payload = Payload { msg, aad };
+        let sealed = match self {
+            #[cfg(feature = "aes-gcm")]
+            Sealer::Aes(cipher) => cipher.encrypt(&nonce.into(), payload),
+            #[cfg(feature = "chacha20poly1305")]
+            Sealer::ChaCha(cipher) => cipher.encrypt(&nonce.into(), payload)
+        };
+        let sealed = sealed.map_err(|_| {
+                               io::Error::new(io::ErrorKind::InvalidInput,
+                                              "data is too large to encrypt")
+                           })?;
+
+        let mut out = Vec::with_capacity(NONCE_LEN + sealed.len());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    /// Decrypts the output of [`seal()`][1].
+    ///
+    /// [1]: Sealer::seal
+    fn open(&self, aad: &[u8], sealed: &[u8]) -> io::Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return Err(invalid_data("encrypted data is truncated"));
+        }
+        let (nonce, msg) = sealed.split_at(NONCE_LEN);
+        let payload = Payload { msg, aad };
+        let opened = match self {
+            #[cfg(feature = "aes-gcm")]
+            Sealer::Aes(cipher) => cipher.decrypt(nonce.into(), payload),
+            #[cfg(feature = "chacha20poly1305")]
+            Sealer::ChaCha(cipher) => cipher.decrypt(nonce.into(), payload)
+        };
+        opened.map_err(|_| {
+                  invalid_data("encrypted data failed authentication; the \
+                                key is wrong or the data was modified")
+              })
+    }
+}
+
+impl fmt::Debug for Sealer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sealer(..)")
+    }
+}
+
+/// Encrypts the frames of one file.
+///
+/// Every frame gets a fresh random nonce, so rewriting a chunk never reuses
+/// one.  The file's header, which holds the file's own random nonce, the
+/// frame's index, and whether it is the last frame are authenticated along
+/// with each frame, as in the STREAM construction.  Frames therefore can't be
+/// reordered or moved between files, and a file can't be cut short at a frame
+/// boundary, without being detected.
+#[derive(Debug)]
+pub(super) struct FrameCipher {
+    sealer: Sealer,
+    header: Vec<u8>
+}
+
+impl FrameCipher {
+    pub(super) fn new(cipher: Cipher, key: &Key, header: &[u8]) -> Self {
+        FrameCipher { sealer: cipher.with_key(key, "contents"),
+                      header: header.to_vec() }
+    }
+
+    fn aad(&self, index: u64, last: bool) -> Vec<u8> {
+        let mut aad = self.header.clone();
+        aad.extend_from_slice(&index.to_le_bytes());
+        aad.push(last as u8);
+        aad
+    }
+}
+
+impl FrameCodec for FrameCipher {
+    fn encode(&self,
+              index: u64,
+              last: bool,
+              data: &[u8])
+              -> io::Result<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        random_bytes(&mut nonce);
+        self.sealer.seal(nonce, &self.aad(index, last), data)
+    }
+
+    fn decode(&self,
+              index: u64,
+              last: bool,
+              frame: &[u8],
+              _len: usize)
+              -> io::Result<Vec<u8>> {
+        self.sealer.open(&self.aad(index, last), frame)
+    }
+}
+
+/// Encrypts file names.
+///
+/// Names have to be encrypted deterministically, so that a path can be looked
+/// up without listing its directory.  The nonce of each name is therefore
+/// derived from the name itself with a keyed hash, in the style of SIV
+/// constructions: equal names encrypt to equal ciphertexts, but nothing else
+/// about a name is revealed beyond its length.  The result is encoded with
+/// URL-safe base64, so it is always a valid file name.
+#[derive(Debug)]
+pub(super) struct NameCipher {
+    sealer: Sealer,
+    nonce_key: [u8; 32]
+}
+
+impl NameCipher {
+    pub(super) fn new(cipher: Cipher, key: &Key) -> Self {
+        NameCipher { sealer: cipher.with_key(key, "names"),
+                     nonce_key: derive(key, "name nonces") }
+    }
+
+    fn nonce(&self, name: &[u8]) -> [u8; NONCE_LEN] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.nonce_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(name);
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&mac.finalize().into_bytes()[..NONCE_LEN]);
+        nonce
+    }
+
+    pub(super) fn encrypt(&self, name: &OsStr) -> io::Result<OsString> {
+        let name = name.to_str().ok_or_else(|| {
+                                     io::Error::new(io::ErrorKind::InvalidInput,
+                                                    "file names must be \
+                                                     valid UTF-8 to be \
+                                                     encrypted")
+                                 })?;
+        let sealed =
+            self.sealer
+                .seal(self.nonce(name.as_bytes()), &[], name.as_bytes())?;
+        Ok(URL_SAFE_NO_PAD.encode(sealed).into())
+    }
+
+    pub(super) fn decrypt(&self, name: &OsStr) -> io::Result<OsString> {
+        let undecryptable = || invalid_data("file name can't be decrypted");
+        let sealed = name.to_str()
+                         .and_then(|name| URL_SAFE_NO_PAD.decode(name).ok())
+                         .ok_or_else(undecryptable)?;
+        let name = self.sealer.open(&[], &sealed)?;
+        if sealed[..NONCE_LEN] != self.nonce(&name) {
+            return Err(undecryptable());
+        }
+        String::from_utf8(name).map(OsString::from)
+                               .map_err(|_| undecryptable())
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Vec<Cipher> {
+        vec![#[cfg(feature = "aes-gcm")]
+             Cipher::Aes256Gcm,
+             #[cfg(feature = "chacha20poly1305")]
+             Cipher::ChaCha20Poly1305]
+    }
+
+    #[test]
+    fn frames_are_bound_to_their_index_and_header() {
+        let key = Key::from_bytes([1; 32]);
+        for cipher in all() {
+            let frames = FrameCipher::new(cipher, &key, b"header");
+            let frame = frames.encode(3, false, b"secret").unwrap();
+            assert_ne!(frame, frames.encode(3, false, b"secret").unwrap());
+            assert_eq!(frames.decode(3, false, &frame, 6).unwrap(), b"secret");
+            assert!(frames.decode(4, false, &frame, 6).is_err());
+            assert!(frames.decode(3, true, &frame, 6).is_err());
+
+            let other = FrameCipher::new(cipher, &key, b"other");
+            assert!(other.decode(3, false, &frame, 6).is_err());
+            let wrong = FrameCipher::new(cipher, &[2; 32].into(), b"header");
+            assert!(wrong.decode(3, false, &frame, 6).is_err());
+        }
+    }
+
+    #[test]
+    fn names_are_deterministic() {
+        let key = Key::from_bytes([1; 32]);
+        for cipher in all() {
+            let names = NameCipher::new(cipher, &key);
+            let name = names.encrypt("report.txt".as_ref()).unwrap();
+            assert_eq!(name, names.encrypt("report.txt".as_ref()).unwrap());
+            assert_ne!(name, names.encrypt("report.txu".as_ref()).unwrap());
+            assert!(!name.to_str().unwrap().contains('/'));
+            assert_eq!(names.decrypt(&name).unwrap(), "report.txt");
+            assert!(names.decrypt("report.txt".as_ref()).is_err());
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! [`EncryptedDirBuilder`] creates directories in an [`EncryptedFs`], and
+//! [`EncryptedReadDir`] lists them.
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{cipher::NameCipher, fs::plaintext, EncryptedFs, KeyProvider};
+use crate::{
+    layer::FileOf, AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFsTrait,
+    AsyncReadDirTrait, FileType, Metadata
+};
+
+/// Creates directories in an [`EncryptedFs`], encrypting their names if the
+/// filesystem encrypts names.
+pub struct EncryptedDirBuilder<F, K>
+    where F: AsyncFsTrait
+{
+    inner: F::DirBuilder,
+    fs: EncryptedFs<F, K>
+}
+
+impl<F, K> EncryptedDirBuilder<F, K> where F: AsyncFsTrait
+{
+    pub(super) fn new(inner: F::DirBuilder, fs: EncryptedFs<F, K>) -> Self {
+        EncryptedDirBuilder { inner, fs }
+    }
+}
+
+impl<F, K> std::fmt::Debug for EncryptedDirBuilder<F, K> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedDirBuilder")
+         .field("inner", &self.inner)
+         .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<F, K> AsyncDirBuilderTrait for EncryptedDirBuilder<F, K>
+    where F: AsyncFsTrait,
+          K: KeyProvider
+{
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.inner.recursive(recursive);
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.fs.encrypt_path(path.as_ref()).await?;
+        self.inner.create(path).await
+    }
+}
+
+/// A stream of the entries in a directory of an [`EncryptedFs`].
+pub struct EncryptedReadDir<F>
+    where F: AsyncFsTrait
+{
+    inner: F::ReadDir,
+    fs: Arc<F>,
+    names: Option<Arc<NameCipher>>,
+    dir: PathBuf
+}
+
+impl<F> EncryptedReadDir<F> where F: AsyncFsTrait
+{
+    pub(super) fn new(inner: F::ReadDir,
+                      fs: Arc<F>,
+                      names: Option<Arc<NameCipher>>,
+                      dir: &Path)
+                      -> Self {
+        EncryptedReadDir { inner,
+                           fs,
+                           names,
+                           dir: dir.to_owned() }
+    }
+}
+
+impl<F> std::fmt::Debug for EncryptedReadDir<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedReadDir")
+         .field("inner", &self.inner)
+         .field("dir", &self.dir)
+         .finish_non_exhaustive()
+    }
+}
+
+impl<F> Stream for EncryptedReadDir<F> where F: AsyncFsTrait
+{
+    type Item = io::Result<EncryptedDirEntry<F>>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let entry = match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(entry) => entry,
+            Poll::Pending => return Poll::Pending
+        };
+        Poll::Ready(entry.map(|entry| {
+                             entry.map(|inner| {
+                                      EncryptedDirEntry { inner,
+                                                     fs: Arc::clone(&this.fs),
+                                                     names: this.names
+                                                                .clone(),
+                                                     dir: this.dir.clone() }
+                                  })
+                         }))
+    }
+}
+
+#[async_trait]
+impl<F> AsyncReadDirTrait<EncryptedDirEntry<F>> for EncryptedReadDir<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+}
+
+/// An entry in a directory of an [`EncryptedFs`].
+///
+/// If the filesystem encrypts names, [`file_name()`][1] and [`path()`][2]
+/// return the decrypted name.  Names that can't be decrypted, such as those of
+/// files that weren't created through the layer, are returned as they are.
+/// [`metadata()`][3] reports the plaintext length of regular files, which
+/// means opening them.
+///
+/// [1]: AsyncDirEntryTrait::file_name
+/// [2]: AsyncDirEntryTrait::path
+/// [3]: AsyncDirEntryTrait::metadata
+pub struct EncryptedDirEntry<F>
+    where F: AsyncFsTrait
+{
+    inner: F::DirEntry,
+    fs: Arc<F>,
+    names: Option<Arc<NameCipher>>,
+
+    /// The path of the directory, as it was passed to `read_dir()`.
+    dir: PathBuf
+}
+
+impl<F> Clone for EncryptedDirEntry<F> where F: AsyncFsTrait
+{
+    fn clone(&self) -> Self {
+        EncryptedDirEntry { inner: self.inner.clone(),
+                            fs: Arc::clone(&self.fs),
+                            names: self.names.clone(),
+                            dir: self.dir.clone() }
+    }
+}
+
+impl<F> std::fmt::Debug for EncryptedDirEntry<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedDirEntry")
+         .field("inner", &self.inner)
+         .field("dir", &self.dir)
+         .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncDirEntryTrait for EncryptedDirEntry<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    async fn path(&self) -> PathBuf {
+        match self.names {
+            Some(_) => self.dir.join(self.file_name().await),
+            None => self.inner.path().await
+        }
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        let metadata = self.inner.metadata().await?;
+        let path = self.inner.path().await;
+        plaintext(&*self.fs, &path, metadata).await
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        self.inner.file_type().await
+    }
+
+    async fn file_name(&self) -> OsString {
+        let name = self.inner.file_name().await;
+        match &self.names {
+            Some(names) => names.decrypt(&name).unwrap_or(name),
+            None => name
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{
+        encrypt::{Cipher, EncryptionLayer, Key, StaticKey},
+        layer::FsLayer,
+        mem_fs::MemFs
+    };
+
+    #[test]
+    fn entries_have_decrypted_names() {
+        block_on(async {
+            let cipher =
+                Cipher::from_id(1).or_else(|_| Cipher::from_id(2)).unwrap();
+            let key = StaticKey::new(Key::from_bytes([3; 32]));
+            let mem = MemFs::new();
+            let fs = EncryptionLayer::new(cipher, key).encrypt_names(0)
+                                                      .layer(mem.clone());
+            fs.dir_builder().create("/secret").await.unwrap();
+            mem.dir_builder().create("/plain").await.unwrap();
+
+            let mut names = Vec::new();
+            let mut entries = fs.read_dir("/").await.unwrap();
+            while let Some(entry) = entries.next().await {
+                let entry = entry.unwrap();
+                assert!(entry.metadata().await.unwrap().is_dir());
+                names.push(entry.path().await);
+            }
+            names.sort();
+            assert_eq!(names, [Path::new("/plain"), Path::new("/secret")]);
+        });
+    }
+}
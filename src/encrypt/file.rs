@@ -0,0 +1,648 @@
+//! [`EncryptedOpenOptions`] opens [`EncryptedFile`]s in an
+//! [`EncryptedFs`][1].
+//!
+//! [1]: super::EncryptedFs
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::{
+    cipher::{random_bytes, FrameCipher},
+    fs::Config,
+    Cipher, EncryptedFs, KeyId, KeyProvider
+};
+use crate::{
+    chunked::{decoded_len, invalid_data, scan, ChunkedFile, Inner, State},
+    layer::FileOf,
+    AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait, Metadata, Permissions
+};
+
+/// The first bytes of every encrypted file.
+const MAGIC: &[u8; 4] = b"AFSE";
+
+/// The version of the file format.
+const VERSION: u8 = 1;
+
+/// The length of the file header: the magic bytes, the version, the cipher,
+/// two reserved bytes, the chunk size and the key identifier as
+/// little-endian `u32`s, and the file's random 16-byte nonce.
+const HEADER_LEN: usize = 32;
+
+/// The header of an encrypted file.  The whole header is authenticated along
+/// with every frame.
+struct Header {
+    cipher: Cipher,
+    chunk_size: usize,
+    key_id: KeyId,
+    bytes: [u8; HEADER_LEN]
+}
+
+impl Header {
+    /// Creates the header for a new file, with a fresh random nonce.
+    fn new(config: Config, key_id: KeyId) -> Self {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[..4].copy_from_slice(MAGIC);
+        bytes[4] = VERSION;
+        bytes[5] = config.cipher.id();
+        bytes[8..12].copy_from_slice(&(config.chunk_size as u32).to_le_bytes());
+        bytes[12..16].copy_from_slice(&key_id.to_le_bytes());
+        random_bytes(&mut bytes[16..]);
+        Header { cipher: config.cipher,
+                 chunk_size: config.chunk_size,
+                 key_id,
+                 bytes }
+    }
+
+    /// Reads the header of `inner`, returning `None` if the file is empty.
+    async fn read<T>(inner: &mut T) -> io::Result<Option<Self>>
+        where T: Inner
+    {
+        let len = inner.seek(SeekFrom::End(0)).await?;
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut bytes = [0; HEADER_LEN];
+        inner.seek(SeekFrom::Start(0)).await?;
+        match inner.read_exact(&mut bytes).await {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(invalid_data("not an encrypted file"))
+            }
+            result => result?
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(invalid_data("not an encrypted file"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid_data("unsupported encrypted file version"));
+        }
+
+        let cipher = Cipher::from_id(bytes[5])?;
+        let chunk_size = read_u32(&bytes[8..12]) as usize;
+        if chunk_size == 0 {
+            return Err(invalid_data("encrypted file has a chunk size of zero"));
+        }
+        Ok(Some(Header { cipher,
+                         chunk_size,
+                         key_id: read_u32(&bytes[12..16]),
+                         bytes }))
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(bytes);
+    u32::from_le_bytes(buf)
+}
+
+/// The error for a file that lost its header or its last frame.  Every file
+/// is written with both, even an empty one.
+fn cut_short() -> io::Error {
+    invalid_data("encrypted file was cut short")
+}
+
+/// Returns the plaintext length of the regular file at `path`, which is a
+/// path in the wrapped filesystem.  No key is needed for this, so the length
+/// isn't authenticated: a file that was cut short at a chunk boundary reports
+/// its shorter length here, and only fails once its last chunk is read.
+pub(super) async fn stored_len<F>(fs: &F, path: &Path) -> io::Result<u64>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    let mut file = fs.open_options().read(true).open(path).await?;
+    let header = Header::read(&mut file).await?.ok_or_else(cut_short)?;
+    let frames = scan(&mut file, HEADER_LEN as u64, header.chunk_size).await?;
+    if frames.is_empty() {
+        return Err(cut_short());
+    }
+    Ok(decoded_len(&frames))
+}
+
+/// Options for opening an [`EncryptedFile`].
+///
+/// The options are passed on to the wrapped filesystem's builder, except that
+/// the underlying file is always opened for reading, and appending is done by
+/// this layer.  As with [`std::fs::OpenOptions`], a file can't be truncated
+/// and appended to at the same time.
+pub struct EncryptedOpenOptions<F, K>
+    where F: AsyncFsTrait
+{
+    inner: F::FileBuilder,
+    fs: EncryptedFs<F, K>,
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool
+}
+
+impl<F, K> EncryptedOpenOptions<F, K> where F: AsyncFsTrait
+{
+    pub(super) fn new(mut inner: F::FileBuilder,
+                      fs: EncryptedFs<F, K>)
+                      -> Self {
+        inner.read(true);
+        EncryptedOpenOptions { inner,
+                               fs,
+                               read: false,
+                               write: false,
+                               append: false,
+                               truncate: false,
+                               create: false,
+                               create_new: false }
+    }
+}
+
+impl<F, K> std::fmt::Debug for EncryptedOpenOptions<F, K> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedOpenOptions")
+         .field("inner", &self.inner)
+         .field("read", &self.read)
+         .field("write", &self.write)
+         .field("append", &self.append)
+         .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<F, K> AsyncFileBuilderTrait for EncryptedOpenOptions<F, K>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static,
+          K: KeyProvider
+{
+    type File = EncryptedFile<F>;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self.inner.write(self.write || self.append);
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self.inner.write(self.write || self.append);
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self.inner.truncate(truncate);
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self.inner.create(create);
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self.inner.create_new(create_new);
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        if self.append && self.truncate && !self.create_new {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "a file can't be truncated and \
+                                       appended to at the same time"));
+        }
+
+        let writable = self.write || self.append;
+        let path = self.fs.encrypt_path(path.as_ref()).await?;
+        // An empty file is only a new one if this is what created or emptied
+        // it.  Any other empty file was cut short.
+        let mut fresh = self.truncate || self.create_new;
+        if self.create && !fresh {
+            fresh = match self.fs.get_ref().metadata(&*path).await {
+                Ok(_) => false,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+                Err(e) => return Err(e)
+            };
+        }
+        let mut inner = self.inner.open(path).await?;
+        let shared = self.fs.shared();
+        let (header, new) = match Header::read(&mut inner).await? {
+            Some(header) => (header, false),
+            None if fresh && writable => {
+                let key_id = shared.provider.current_key_id();
+                (Header::new(shared.config, key_id), true)
+            }
+            None => return Err(cut_short())
+        };
+        let key = shared.provider.key(header.key_id).await?;
+        if new {
+            inner.seek(SeekFrom::Start(0)).await?;
+            inner.write_all(&header.bytes).await?;
+        }
+        let codec = FrameCipher::new(header.cipher, &key, &header.bytes);
+
+        let data_start = HEADER_LEN as u64;
+        let frames = scan(&mut inner, data_start, header.chunk_size).await?;
+        if frames.is_empty() && !new {
+            return Err(cut_short());
+        }
+        let mut state = State::new(inner,
+                                   codec,
+                                   data_start,
+                                   header.chunk_size,
+                                   frames,
+                                   self.append);
+        if new {
+            state.seal_empty().await?;
+        }
+
+        Ok(EncryptedFile { file: ChunkedFile::new(state),
+                           read: self.read,
+                           write: writable })
+    }
+}
+
+/// An open file in an [`EncryptedFs`].
+///
+/// Reads, writes, and seeks all work in terms of the plaintext, and
+/// [`metadata()`][1] reports the plaintext length.  Every chunk is
+/// authenticated when it is read, and reads fail with
+/// [`ErrorKind::InvalidData`][2] if it was modified.
+///
+/// The chunk around the cursor is kept in memory, and is only encrypted and
+/// written out when the cursor moves to another chunk or the file is flushed,
+/// so **a file that was written to must be flushed or closed before it is
+/// dropped**.  Each file keeps its own copy of the frame index, so a file
+/// shouldn't be written to through more than one handle at a time.
+///
+/// Every file ends with a frame that is authenticated as the last one, even
+/// an empty file, so a file that was cut short fails with `InvalidData` too,
+/// once its end is reached.
+///
+/// [1]: AsyncFileTrait::metadata
+/// [2]: io::ErrorKind::InvalidData
+pub struct EncryptedFile<F>
+    where F: AsyncFsTrait
+{
+    file: ChunkedFile<FileOf<F>, FrameCipher>,
+    read: bool,
+    write: bool
+}
+
+impl<F> EncryptedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    /// Returns an error unless this file was opened with write access.
+    fn check_writable(&self) -> io::Result<()> {
+        if self.write {
+            Ok(())
+        } else {
+            Err(not_opened_for("writing"))
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for EncryptedFile<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedFile")
+         .field("file", &self.file)
+         .field("read", &self.read)
+         .field("write", &self.write)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFileTrait for EncryptedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    async fn sync_all(&self) -> io::Result<()> {
+        self.file.state().await.sync_all().await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.file.state().await.sync_data().await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.check_writable()?;
+        let mut state = self.file.state().await;
+        state.set_len(size).await?;
+        state.seal_empty().await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        let state = self.file.state().await;
+        let metadata = state.inner().metadata().await?;
+        Ok(metadata.with_len(state.len()))
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        self.file.state().await.inner().set_permissions(perm).await
+    }
+}
+
+impl<F> AsyncRead for EncryptedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        if !self.read {
+            return Poll::Ready(Err(not_opened_for("reading")));
+        }
+        self.get_mut().file.poll_read(cx, buf)
+    }
+}
+
+impl<F> AsyncWrite for EncryptedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    fn poll_write(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        if let Err(e) = self.check_writable() {
+            return Poll::Ready(Err(e));
+        }
+        self.get_mut().file.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        self.get_mut().file.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        self.get_mut().file.poll_close(cx)
+    }
+}
+
+impl<F> AsyncSeek for EncryptedFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        self.get_mut().file.poll_seek(cx, pos)
+    }
+}
+
+fn not_opened_for(access: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   format!("file was not opened for {}", access))
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{
+        encrypt::{EncryptionLayer, Key},
+        layer::FsLayer,
+        mem_fs::MemFs
+    };
+
+    /// Two keys, with the current one chosen when the provider is created.
+    #[derive(Debug)]
+    struct Rotating(KeyId);
+
+    #[async_trait]
+    impl KeyProvider for Rotating {
+        fn current_key_id(&self) -> KeyId {
+            self.0
+        }
+
+        async fn key(&self, id: KeyId) -> io::Result<Key> {
+            match id {
+                1 | 2 => Ok(Key::from_bytes([id as u8; 32])),
+                _ => Err(io::ErrorKind::NotFound.into())
+            }
+        }
+    }
+
+    fn cipher() -> Cipher {
+        Cipher::from_id(1).or_else(|_| Cipher::from_id(2)).unwrap()
+    }
+
+    async fn write(fs: &EncryptedFs<MemFs, Rotating>, path: &str, data: &[u8]) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(data).await.unwrap();
+        file.close().await.unwrap();
+    }
+
+    async fn read(fs: &EncryptedFs<MemFs, Rotating>,
+                  path: &str)
+                  -> io::Result<Vec<u8>> {
+        let mut file = fs.open_options().read(true).open(path).await?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).await?;
+        Ok(data)
+    }
+
+    #[test]
+    fn seeking_and_resizing_work_on_plaintext_offsets() {
+        block_on(async {
+            let mem = MemFs::new();
+            let fs =
+                EncryptionLayer::new(cipher(), Rotating(1)).chunk_size(16)
+                                                           .layer(mem.clone());
+            let data: Vec<u8> = (0..100).collect();
+            write(&fs, "/f", &data).await;
+
+            let mut file = fs.open_options()
+                             .read(true)
+                             .write(true)
+                             .open("/f")
+                             .await
+                             .unwrap();
+            file.seek(SeekFrom::Start(40)).await.unwrap();
+            file.write_all(b"xyz").await.unwrap();
+            file.set_len(50).await.unwrap();
+            file.seek(SeekFrom::Start(38)).await.unwrap();
+            let mut tail = Vec::new();
+            file.read_to_end(&mut tail).await.unwrap();
+            file.close().await.unwrap();
+
+            let mut expected = data[38..50].to_vec();
+            expected[2..5].copy_from_slice(b"xyz");
+            assert_eq!(tail, expected);
+            assert_eq!(file.metadata().await.unwrap().len(), 50);
+        });
+    }
+
+    #[test]
+    fn old_keys_keep_working_after_rotation() {
+        block_on(async {
+            let mem = MemFs::new();
+            let old =
+                EncryptionLayer::new(cipher(), Rotating(1)).layer(mem.clone());
+            write(&old, "/old", b"first").await;
+
+            let new =
+                EncryptionLayer::new(cipher(), Rotating(2)).layer(mem.clone());
+            write(&new, "/new", b"second").await;
+            assert_eq!(read(&new, "/old").await.unwrap(), b"first");
+            assert_eq!(read(&old, "/new").await.unwrap(), b"second");
+
+            let unknown =
+                EncryptionLayer::new(cipher(), Rotating(3)).layer(mem.clone());
+            let err = unknown.open_options()
+                             .write(true)
+                             .create(true)
+                             .open("/other")
+                             .await
+                             .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        });
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        block_on(async {
+            let mem = MemFs::new();
+            let fs =
+                EncryptionLayer::new(cipher(), Rotating(1)).layer(mem.clone());
+            write(&fs, "/f", b"the secret plans").await;
+
+            let mut raw = mem.open_options()
+                             .read(true)
+                             .write(true)
+                             .open("/f")
+                             .await
+                             .unwrap();
+            raw.seek(SeekFrom::End(-1)).await.unwrap();
+            let mut last = [0];
+            raw.read_exact(&mut last).await.unwrap();
+            raw.seek(SeekFrom::End(-1)).await.unwrap();
+            raw.write_all(&[last[0] ^ 1]).await.unwrap();
+
+            let err = read(&fs, "/f").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+    }
+
+    #[test]
+    fn truncation_at_a_frame_boundary_is_detected() {
+        block_on(async {
+            let mem = MemFs::new();
+            let fs =
+                EncryptionLayer::new(cipher(), Rotating(1)).chunk_size(16)
+                                                           .layer(mem.clone());
+            let data: Vec<u8> = (0..40).collect();
+            write(&fs, "/f", &data).await;
+
+            // Each frame is its header, a nonce, 16 bytes, and a tag.
+            let frame_len = 8 + 12 + 16 + 16;
+            let raw = mem.open_options().write(true).open("/f").await.unwrap();
+            raw.set_len((HEADER_LEN + frame_len) as u64).await.unwrap();
+            let err = read(&fs, "/f").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+    }
+
+    #[test]
+    fn truncation_to_the_header_is_detected() {
+        block_on(async {
+            let mem = MemFs::new();
+            let fs =
+                EncryptionLayer::new(cipher(), Rotating(1)).layer(mem.clone());
+            write(&fs, "/f", b"hello").await;
+            write(&fs, "/empty", b"").await;
+            assert_eq!(read(&fs, "/empty").await.unwrap(), b"");
+            let emptied =
+                fs.open_options().write(true).open("/f").await.unwrap();
+            emptied.set_len(0).await.unwrap();
+            assert_eq!(read(&fs, "/f").await.unwrap(), b"");
+            write(&fs, "/f", b"hello").await;
+
+            let raw = mem.open_options().write(true).open("/f").await.unwrap();
+            raw.set_len(HEADER_LEN as u64).await.unwrap();
+            let err = read(&fs, "/f").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            let err = fs.metadata("/f").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            // Cut back to nothing, it isn't taken for a new file either.
+            raw.set_len(0).await.unwrap();
+            let err = read(&fs, "/f").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            let err = fs.open_options()
+                        .write(true)
+                        .create(true)
+                        .open("/f")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+    }
+
+    #[test]
+    fn the_last_frame_moves_with_the_end_of_the_file() {
+        block_on(async {
+            let fs =
+                EncryptionLayer::new(cipher(), Rotating(1)).chunk_size(16)
+                                                           .layer(MemFs::new());
+            let data: Vec<u8> = (0..40).collect();
+            write(&fs, "/f", &data).await;
+
+            // Shrinking to a frame boundary makes an untouched frame the last
+            // one, and growing again makes it an ordinary one.
+            let mut file = fs.open_options()
+                             .read(true)
+                             .write(true)
+                             .open("/f")
+                             .await
+                             .unwrap();
+            file.set_len(32).await.unwrap();
+            file.close().await.unwrap();
+            assert_eq!(read(&fs, "/f").await.unwrap(), &data[..32]);
+
+            let mut file =
+                fs.open_options().append(true).open("/f").await.unwrap();
+            file.write_all(&data[32..]).await.unwrap();
+            file.close().await.unwrap();
+            assert_eq!(read(&fs, "/f").await.unwrap(), data);
+        });
+    }
+}
@@ -0,0 +1,460 @@
+//! [`EncryptionLayer`] wraps a filesystem in an [`EncryptedFs`].
+
+use std::{
+    borrow::Cow,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc
+};
+
+use async_lock::OnceCell;
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{
+    cipher::NameCipher, file::stored_len, Cipher, EncryptedDirBuilder,
+    EncryptedDirEntry, EncryptedOpenOptions, EncryptedReadDir, KeyId,
+    KeyProvider
+};
+use crate::{
+    layer::{FileOf, FsLayer},
+    AsyncFsTrait, AsyncSymLinkTrait, Metadata, Permissions
+};
+
+/// How new files are encrypted.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Config {
+    pub(super) cipher: Cipher,
+    pub(super) chunk_size: usize
+}
+
+/// A layer that encrypts the contents, and optionally the names, of every
+/// file.
+///
+/// New files are encrypted with the layer's [`Cipher`], using the key that
+/// the [`KeyProvider`] reports as current, in chunks of
+/// [`chunk_size()`][1] bytes.  Each chunk is stored with a 28-byte nonce and
+/// authentication tag, so smaller chunks cost more space but make random
+/// access cheaper.
+///
+/// [1]: EncryptionLayer::chunk_size
+#[derive(Debug)]
+pub struct EncryptionLayer<K> {
+    provider: Arc<K>,
+    config: Config,
+    names: Option<KeyId>
+}
+
+impl<K> EncryptionLayer<K> {
+    /// The chunk size used unless another one is set, 64 KiB.
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Creates a layer that encrypts new files with `cipher`, using keys
+    /// from `provider`.
+    pub fn new(cipher: Cipher, provider: K) -> Self {
+        EncryptionLayer { provider: Arc::new(provider),
+                          config: Config { cipher,
+                                           chunk_size:
+                                               Self::DEFAULT_CHUNK_SIZE },
+                          names: None }
+    }
+
+    /// Sets the number of plaintext bytes in each chunk of a new file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero or doesn't fit in a `u32`.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0 && u32::try_from(chunk_size).is_ok(),
+                "chunk size must be between 1 and u32::MAX");
+        self.config.chunk_size = chunk_size;
+        self
+    }
+
+    /// Encrypts the names of files, directories, and symlink targets too,
+    /// using the key with the identifier `key`.
+    ///
+    /// Every component of every path is encrypted, so the wrapped filesystem
+    /// should be rooted at the directory that holds the encrypted data.
+    /// Names have to be looked up without knowing which key they were
+    /// written with, so `key` and the layer's cipher can't be changed later
+    /// without renaming every file.
+    pub fn encrypt_names(mut self, key: KeyId) -> Self {
+        self.names = Some(key);
+        self
+    }
+}
+
+impl<K> Clone for EncryptionLayer<K> {
+    fn clone(&self) -> Self {
+        EncryptionLayer { provider: Arc::clone(&self.provider),
+                          config: self.config,
+                          names: self.names }
+    }
+}
+
+impl<F, K> FsLayer<F> for EncryptionLayer<K>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static,
+          K: KeyProvider
+{
+    type Fs = EncryptedFs<F, K>;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        let shared = Shared { provider: Arc::clone(&self.provider),
+                              config: self.config,
+                              names: self.names,
+                              name_cipher: OnceCell::new() };
+        EncryptedFs { inner: Arc::new(inner),
+                      shared: Arc::new(shared) }
+    }
+}
+
+/// The parts of an [`EncryptedFs`] that are shared with its builders and
+/// directory streams.
+#[derive(Debug)]
+pub(super) struct Shared<K> {
+    pub(super) provider: Arc<K>,
+    pub(super) config: Config,
+    names: Option<KeyId>,
+    name_cipher: OnceCell<Arc<NameCipher>>
+}
+
+/// A filesystem whose file contents, and optionally names, are encrypted.
+///
+/// Regular files are stored in an authenticated, chunked format, and every
+/// length this filesystem reports is the plaintext length.  Regular files
+/// that weren't written through this layer can't be read, and make
+/// [`metadata()`][1] fail with [`ErrorKind::InvalidData`][2], with the
+/// exception of empty files.  Reading data that was modified, or that was
+/// encrypted with a different key, fails with the same error.
+///
+/// Permissions, timestamps, the directory structure, and the approximate
+/// size of every file are not hidden.
+///
+/// This type is only available when at least one of the `aes-gcm` or
+/// `chacha20poly1305` features is enabled.
+///
+/// [1]: AsyncFsTrait::metadata
+/// [2]: io::ErrorKind::InvalidData
+pub struct EncryptedFs<F, K> {
+    inner: Arc<F>,
+    shared: Arc<Shared<K>>
+}
+
+impl<F, K> EncryptedFs<F, K> {
+    /// Returns a reference to the wrapped filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    pub(super) fn shared(&self) -> &Arc<Shared<K>> {
+        &self.shared
+    }
+}
+
+impl<F, K> EncryptedFs<F, K> where K: KeyProvider
+{
+    /// Returns the cipher for file names, or `None` if names aren't
+    /// encrypted.
+    pub(super) async fn names(&self) -> io::Result<Option<Arc<NameCipher>>> {
+        let id = match self.shared.names {
+            Some(id) => id,
+            None => return Ok(None)
+        };
+        let init = || async move {
+            let key = self.shared.provider.key(id).await?;
+            let names = NameCipher::new(self.shared.config.cipher, &key);
+            Ok::<_, io::Error>(Arc::new(names))
+        };
+        let names = self.shared.name_cipher.get_or_try_init(init).await?;
+        Ok(Some(Arc::clone(names)))
+    }
+
+    /// Returns the path in the wrapped filesystem that `path` refers to.
+    pub(super) async fn encrypt_path<'a>(&self,
+                                         path: &'a Path)
+                                         -> io::Result<Cow<'a, Path>> {
+        match self.names().await? {
+            Some(names) => encrypt_path(&names, path).map(Cow::Owned),
+            None => Ok(Cow::Borrowed(path))
+        }
+    }
+}
+
+impl<F, K> Clone for EncryptedFs<F, K> {
+    fn clone(&self) -> Self {
+        EncryptedFs { inner: Arc::clone(&self.inner),
+                      shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<F, K> std::fmt::Debug for EncryptedFs<F, K>
+    where F: std::fmt::Debug,
+          K: std::fmt::Debug
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedFs")
+         .field("inner", &self.inner)
+         .field("shared", &self.shared)
+         .finish()
+    }
+}
+
+/// Encrypts every normal component of `path`.
+fn encrypt_path(names: &NameCipher, path: &Path) -> io::Result<PathBuf> {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => names.encrypt(name).map(PathBuf::from),
+            other => Ok(PathBuf::from(other.as_os_str()))
+        })
+        .collect()
+}
+
+/// Decrypts every normal component of `path`.  Components that can't be
+/// decrypted, such as the parts of an absolute path that lie outside the
+/// encrypted tree, are left as they are.
+pub(super) fn decrypt_path(names: &NameCipher, path: &Path) -> PathBuf {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => {
+                names.decrypt(name).unwrap_or_else(|_| name.to_owned())
+            }
+            other => other.as_os_str().to_owned()
+        })
+        .collect()
+}
+
+/// Replaces the length in `metadata` with the plaintext length, if `path` is
+/// a regular file.  `path` is a path in the wrapped filesystem.
+pub(super) async fn plaintext<F>(fs: &F,
+                                 path: &Path,
+                                 metadata: Metadata)
+                                 -> io::Result<Metadata>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    if metadata.is_file() {
+        let len = stored_len(fs, path).await?;
+        Ok(metadata.with_len(len))
+    } else {
+        Ok(metadata)
+    }
+}
+
+#[async_trait]
+impl<F, K> AsyncFsTrait for EncryptedFs<F, K>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static,
+          K: KeyProvider
+{
+    type DirBuilder = EncryptedDirBuilder<F, K>;
+    type DirEntry = EncryptedDirEntry<F>;
+    type FileBuilder = EncryptedOpenOptions<F, K>;
+    type ReadDir = EncryptedReadDir<F>;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        EncryptedDirBuilder::new(self.inner.dir_builder(), self.clone())
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        EncryptedOpenOptions::new(self.inner.open_options(), self.clone())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        let canonical = self.inner.canonicalize(path).await?;
+        Ok(match self.names().await? {
+            Some(names) => decrypt_path(&names, &canonical),
+            None => canonical
+        })
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = self.encrypt_path(src.as_ref()).await?;
+        let dst = self.encrypt_path(dst.as_ref()).await?;
+        self.inner.rename(src, dst).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        self.inner.set_permissions(path, perm).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = self.encrypt_path(src.as_ref()).await?;
+        let dst = self.encrypt_path(dst.as_ref()).await?;
+        self.inner.hard_link(src, dst).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        let target = self.inner.read_link(path).await?;
+        Ok(match self.names().await? {
+            Some(names) => decrypt_path(&names, &target),
+            None => target
+        })
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        let metadata = self.inner.symlink_metadata(&path).await?;
+        plaintext(&*self.inner, &path, metadata).await
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        let metadata = self.inner.metadata(&path).await?;
+        plaintext(&*self.inner, &path, metadata).await
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        // Frames are only bound to their file's header, which is copied
+        // along with them, so the ciphertext can be copied as it is.
+        let src = self.encrypt_path(src.as_ref()).await?;
+        let dst = self.encrypt_path(dst.as_ref()).await?;
+        self.inner.copy(src, &dst).await?;
+        stored_len(&*self.inner, &dst).await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        self.inner.remove_file(path).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let encrypted = self.encrypt_path(path.as_ref()).await?;
+        let inner = self.inner.read_dir(encrypted).await?;
+        Ok(EncryptedReadDir::new(inner,
+                                 Arc::clone(&self.inner),
+                                 self.names().await?,
+                                 path.as_ref()))
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        self.inner.remove_dir(path).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = self.encrypt_path(path.as_ref()).await?;
+        self.inner.remove_dir_all(path).await
+    }
+}
+
+#[async_trait]
+impl<F, K> AsyncSymLinkTrait for EncryptedFs<F, K>
+    where F: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static,
+          K: KeyProvider
+{
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = self.encrypt_path(src.as_ref()).await?;
+        let dst = self.encrypt_path(dst.as_ref()).await?;
+        self.inner.symlink(src, dst).await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{
+        encrypt::{Key, StaticKey},
+        mem_fs::MemFs,
+        AsyncDirBuilderTrait, AsyncFileBuilderTrait
+    };
+
+    fn layer() -> EncryptionLayer<StaticKey> {
+        let cipher =
+            Cipher::from_id(1).or_else(|_| Cipher::from_id(2)).unwrap();
+        EncryptionLayer::new(cipher, StaticKey::new(Key::from_bytes([9; 32])))
+    }
+
+    #[test]
+    fn names_are_encrypted_in_the_wrapped_filesystem() {
+        block_on(async {
+            let fs = layer().encrypt_names(0).layer(MemFs::new());
+            fs.dir_builder().create("/docs").await.unwrap();
+            let mut file = fs.open_options()
+                             .write(true)
+                             .create(true)
+                             .open("/docs/plan.txt")
+                             .await
+                             .unwrap();
+            file.write_all(b"attack at dawn").await.unwrap();
+            file.close().await.unwrap();
+            fs.symlink("/link", "docs/plan.txt").await.unwrap();
+
+            let raw = fs.get_ref();
+            assert!(raw.metadata("/docs").await.is_err());
+            let names = fs.names().await.unwrap().unwrap();
+            let docs = names.encrypt("docs".as_ref()).unwrap();
+            assert!(raw.metadata(Path::new("/").join(docs)).await.is_ok());
+
+            assert_eq!(fs.read_link("/link").await.unwrap(),
+                       Path::new("docs/plan.txt"));
+            assert_eq!(fs.canonicalize("/link").await.unwrap(),
+                       Path::new("/docs/plan.txt"));
+            assert_eq!(fs.metadata("/link").await.unwrap().len(), 14);
+
+            let mut file =
+                fs.open_options().read(true).open("/link").await.unwrap();
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, "attack at dawn");
+        });
+    }
+
+    #[test]
+    fn names_are_untouched_by_default() {
+        block_on(async {
+            let fs = layer().layer(MemFs::new());
+            fs.dir_builder().create("/docs").await.unwrap();
+            assert!(fs.get_ref().metadata("/docs").await.unwrap().is_dir());
+        });
+    }
+}
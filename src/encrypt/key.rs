@@ -0,0 +1,131 @@
+//! [`KeyProvider`] hands out the keys used by an [`EncryptedFs`][1].
+//!
+//! [1]: super::EncryptedFs
+
+use std::{fmt, io};
+
+use async_trait::async_trait;
+
+/// Identifies a key.  The identifier of the key that a file was encrypted
+/// with is stored in the file's header, so that keys can be rotated.
+pub type KeyId = u32;
+
+/// A 256-bit master key.
+///
+/// The key is never used directly: the keys for file contents and for file
+/// names are derived from it.  Its [`Debug`][1] output never includes the
+/// key material.
+///
+/// [1]: fmt::Debug
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    /// The length of a key, in bytes.
+    pub const LEN: usize = 32;
+
+    /// Wraps the raw key material `bytes`.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Key(bytes)
+    }
+
+    /// Returns the raw key material.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for Key {
+    fn from(bytes: [u8; 32]) -> Self {
+        Key(bytes)
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// A source of keys.
+///
+/// New files are encrypted with the key returned by
+/// [`current_key_id()`][1], while existing files are decrypted with whichever
+/// key they were written with.  Keeping old keys available through
+/// [`key()`][2] therefore lets the current key be rotated without having to
+/// re-encrypt every file at once.
+///
+/// Keys are requested every time a file is opened, so implementations that
+/// fetch keys from somewhere slow should cache them.
+///
+/// [1]: KeyProvider::current_key_id
+/// [2]: KeyProvider::key
+#[async_trait]
+pub trait KeyProvider: fmt::Debug + Send + Sync {
+    /// Returns the identifier of the key that new files are encrypted with.
+    fn current_key_id(&self) -> KeyId;
+
+    /// Returns the key with the identifier `id`.
+    ///
+    /// Unknown identifiers should be reported as
+    /// [`ErrorKind::NotFound`][1].
+    ///
+    /// [1]: io::ErrorKind::NotFound
+    async fn key(&self, id: KeyId) -> io::Result<Key>;
+}
+
+/// A [`KeyProvider`] with a single key, whose identifier is 0.
+#[derive(Debug, Clone)]
+pub struct StaticKey {
+    key: Key
+}
+
+impl StaticKey {
+    /// Creates a provider that only knows about `key`.
+    pub fn new(key: Key) -> Self {
+        StaticKey { key }
+    }
+}
+
+#[async_trait]
+impl KeyProvider for StaticKey {
+    fn current_key_id(&self) -> KeyId {
+        0
+    }
+
+    async fn key(&self, id: KeyId) -> io::Result<Key> {
+        if id == 0 {
+            Ok(self.key.clone())
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound,
+                               format!("no key with identifier {}", id)))
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    #[test]
+    fn keys_are_redacted_and_looked_up_by_id() {
+        let key = Key::from_bytes([7; 32]);
+        assert_eq!(format!("{:?}", key), "Key(..)");
+
+        let provider = StaticKey::new(key.clone());
+        assert_eq!(block_on(provider.key(0)).unwrap(), key);
+        let err = block_on(provider.key(1)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
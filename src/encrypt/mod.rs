@@ -0,0 +1,59 @@
+//! A layer that encrypts file contents, and optionally file names, at rest.
+//!
+//! [`EncryptionLayer`] wraps a filesystem in an [`EncryptedFs`].  Data
+//! written through [`AsyncWrite`][1] is encrypted with an authenticated
+//! cipher before it reaches the wrapped filesystem, and decrypted and
+//! verified again by [`AsyncRead`][2].  Lengths reported by
+//! [`metadata()`][3] are always plaintext lengths.
+//!
+//! Files are stored as a 32-byte header followed by a sequence of
+//! independently encrypted chunks, in the same chunked format as the
+//! [`compress`][4] module uses.  The header holds a random nonce that is
+//! unique to the file, and is authenticated along with every chunk, as are
+//! the chunk's position and whether it is the last one.  Every file ends with
+//! a last chunk, even an empty one, so that chunks can't be reordered or
+//! dropped from the end without reads failing with
+//! [`ErrorKind::InvalidData`][7].  Every chunk also gets a fresh random nonce
+//! each time it is written.  Since any offset maps directly to a chunk,
+//! [`AsyncSeek`][5] and [`set_len()`][6] keep working.
+//!
+//! Keys come from a [`KeyProvider`].  The identifier of the key that a file
+//! was encrypted with is stored in its header, so keys can be rotated.  File
+//! names can optionally be encrypted as well; see
+//! [`EncryptionLayer::encrypt_names()`].
+//!
+//! The wrapped filesystem's files must implement [`AsyncRead`][2],
+//! [`AsyncWrite`][1], and [`AsyncSeek`][5].
+//!
+//! | Cipher                        | Feature            |
+//! |-------------------------------|--------------------|
+//! | [`Cipher::Aes256Gcm`]         | `aes-gcm`          |
+//! | [`Cipher::ChaCha20Poly1305`]  | `chacha20poly1305` |
+//!
+//! This module is only available when at least one of these features is
+//! enabled.
+//!
+//! [1]: crate::AsyncWrite
+//! [2]: crate::AsyncRead
+//! [3]: crate::AsyncFileTrait::metadata
+//! [4]: https://docs.rs/async-fs-traits/latest/async_fs_traits/compress/
+//! [5]: crate::AsyncSeek
+//! [6]: crate::AsyncFileTrait::set_len
+//! [7]: std::io::ErrorKind::InvalidData
+
+mod cipher;
+mod dir;
+mod file;
+mod fs;
+mod key;
+
+#[doc(inline)]
+pub use cipher::Cipher;
+#[doc(inline)]
+pub use dir::{EncryptedDirBuilder, EncryptedDirEntry, EncryptedReadDir};
+#[doc(inline)]
+pub use file::{EncryptedFile, EncryptedOpenOptions};
+#[doc(inline)]
+pub use fs::{EncryptedFs, EncryptionLayer};
+#[doc(inline)]
+pub use key::{Key, KeyId, KeyProvider, StaticKey};
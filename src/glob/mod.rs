@@ -0,0 +1,43 @@
+//! Glob patterns matched against the directory trees of any filesystem.
+//!
+//! [`glob()`] and [`GlobOptions::glob()`] return a [`Glob`], which streams a
+//! [`WalkEntry`][1] for each entry that the patterns select.  The search is
+//! a [walk][2] that only descends into directories where something could
+//! still match, so it works on every backend in this crate, and starts
+//! yielding entries as soon as the first directory has been read:
+//!
+//! ```
+//! # #[cfg(feature = "mem-fs")]
+//! # {
+//! use async_fs_traits::{glob::glob, mem_fs::MemFs};
+//! use futures_lite::{future::block_on, StreamExt};
+//!
+//! let fs = MemFs::new();
+//! block_on(async {
+//!     let mut sources = glob(&fs, "src/**/*.rs")?;
+//!     while let Some(entry) = sources.next().await {
+//!         println!("{}", entry?.path().display());
+//!     }
+//!     std::io::Result::Ok(())
+//! });
+//! # }
+//! ```
+//!
+//! [`GlobOptions`] matches sets of patterns with `.gitignore`-style
+//! negations, ignores case, follows symlinks, and reads several directories
+//! at once.
+//!
+//! This module is only available when the `glob` feature is enabled.
+//!
+//! [1]: crate::walk::WalkEntry
+//! [2]: crate::walk
+
+mod options;
+mod pattern;
+mod set;
+mod stream;
+
+#[doc(inline)]
+pub use options::GlobOptions;
+#[doc(inline)]
+pub use stream::{glob, Glob};
@@ -0,0 +1,180 @@
+//! [`GlobOptions`] configures how glob patterns are matched.
+
+use std::{io, path::PathBuf};
+
+use super::{
+    pattern::{Pattern, Segment},
+    set::PatternSet,
+    Glob
+};
+use crate::{walk::WalkOptions, AsyncFsTrait};
+
+/// Returns the directories that `pattern` starts with, before its first
+/// wildcard.
+fn literal_prefix(pattern: &Pattern) -> Vec<String> {
+    let dirs = &pattern.segments[..pattern.segments.len() - 1];
+    dirs.iter().map_while(Segment::literal).collect()
+}
+
+/// Options for matching glob patterns.
+///
+/// The options start out matching case-sensitively, without following
+/// symlinks, and reading one directory at a time.
+///
+/// ```
+/// # #[cfg(feature = "mem-fs")]
+/// # {
+/// use async_fs_traits::{glob::GlobOptions, mem_fs::MemFs};
+/// use futures_lite::{future::block_on, StreamExt};
+///
+/// let fs = MemFs::new();
+/// block_on(async {
+///     let mut glob =
+///         GlobOptions::new().case_insensitive(true)
+///                           .glob(&fs, ["/src/**/*.rs", "!/src/gen/**"])?;
+///     while let Some(entry) = glob.next().await {
+///         println!("{}", entry?.path().display());
+///     }
+///     std::io::Result::Ok(())
+/// });
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GlobOptions {
+    case_insensitive: bool,
+    follow_links: bool,
+    max_concurrency: usize
+}
+
+impl GlobOptions {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        GlobOptions { case_insensitive: false,
+                      follow_links: false,
+                      max_concurrency: 1 }
+    }
+
+    /// Matches names without regard to case.
+    ///
+    /// Every directory is then read from the root of the patterns down,
+    /// since the filesystem can't be asked for a name in any case.
+    pub fn case_insensitive(&mut self, case_insensitive: bool) -> &mut Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Descends into symlinks that point to directories, as
+    /// [`WalkOptions::follow_links()`] does.
+    pub fn follow_links(&mut self, follow_links: bool) -> &mut Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Reads up to `max_concurrency` directories at once, as
+    /// [`WalkOptions::max_concurrency()`] does.
+    pub fn max_concurrency(&mut self, max_concurrency: usize) -> &mut Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    /// Finds the entries in `fs` that `patterns` select.
+    ///
+    /// As in a `.gitignore` file, patterns starting with `!` exclude what
+    /// they match, and the last pattern that matches a path decides whether
+    /// it is selected.  Nothing below an excluded directory is selected,
+    /// and directories that no pattern could select anything below are
+    /// never read.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`][1] if a pattern is malformed,
+    /// or if absolute and relative patterns are mixed.
+    ///
+    /// [1]: std::io::ErrorKind::InvalidInput
+    pub fn glob<'a, F, I>(&self,
+                          fs: &'a F,
+                          patterns: I)
+                          -> io::Result<Glob<'a, F>>
+        where F: AsyncFsTrait,
+              I: IntoIterator,
+              I::Item: AsRef<str>
+    {
+        let patterns = patterns.into_iter()
+                               .map(|pattern| Pattern::parse(pattern.as_ref()))
+                               .collect::<io::Result<Vec<_>>>()?;
+        let mut selecting = patterns.iter().filter(|pattern| !pattern.negated);
+        let first = match selecting.next() {
+            Some(first) => first,
+            None => return Ok(Glob::empty())
+        };
+        if patterns.iter()
+                   .any(|pattern| pattern.absolute != first.absolute)
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "glob patterns mix absolute and \
+                                       relative paths"));
+        }
+
+        // The walk starts from the directories that every selecting pattern
+        // starts with.
+        let mut base = match self.case_insensitive {
+            true => Vec::new(),
+            false => literal_prefix(first)
+        };
+        for pattern in selecting {
+            let prefix = literal_prefix(pattern);
+            let common =
+                base.iter().zip(&prefix).take_while(|(a, b)| a == b).count();
+            base.truncate(common);
+        }
+        let mut root = match (first.absolute, base.is_empty()) {
+            (true, _) => PathBuf::from("/"),
+            (false, true) => PathBuf::from("."),
+            (false, false) => PathBuf::new()
+        };
+        root.extend(&base);
+
+        let set = PatternSet::new(patterns, &base, self.case_insensitive);
+        let mut options = WalkOptions::new();
+        options.follow_links(self.follow_links)
+               .max_concurrency(self.max_concurrency);
+        Ok(Glob::new(fs, root, set, options))
+    }
+}
+
+impl Default for GlobOptions {
+    fn default() -> Self {
+        GlobOptions::new()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_stop_at_wildcards() {
+        let prefix =
+            |pattern| literal_prefix(&Pattern::parse(pattern).unwrap());
+        assert_eq!(prefix("/src/walk/*.rs"), ["src", "walk"]);
+        assert_eq!(prefix("src/w?lk/mod.rs"), ["src"]);
+        assert_eq!(prefix("src/**/lib.rs"), ["src"]);
+        assert!(prefix("lib.rs").is_empty());
+    }
+
+    #[cfg(feature = "mem-fs")]
+    #[test]
+    fn absolute_and_relative_patterns_dont_mix() {
+        let fs = crate::mem_fs::MemFs::new();
+        let err = GlobOptions::new().glob(&fs, ["/src/*", "!gen"])
+                                    .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,345 @@
+//! [`Pattern`] is a single glob pattern, compiled into the path components
+//! that it matches.
+
+use std::{io, str::Chars};
+
+fn invalid(pattern: &str, problem: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput,
+                   format!("{} in glob pattern `{}`", problem, pattern))
+}
+
+/// Returns `c` in lower case, for matching without regard to case.
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Part of the pattern for a single path component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Token {
+    /// A character that matches itself.
+    Char(char),
+
+    /// `?`, which matches any one character.
+    Any,
+
+    /// `*`, which matches any run of characters.
+    Star,
+
+    /// `[...]` or `[!...]`, which matches one character in or out of the
+    /// given ranges.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>
+    }
+}
+
+impl Token {
+    fn matches(&self, c: char, case_insensitive: bool) -> bool {
+        match self {
+            Token::Char(p) if case_insensitive => fold(*p) == fold(c),
+            Token::Char(p) => *p == c,
+            Token::Any => true,
+            Token::Star => false,
+            Token::Class { negated, ranges } => {
+                let contains = |c: char| {
+                    ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c))
+                };
+                let found = contains(c)
+                            || case_insensitive
+                               && (c.to_lowercase().any(contains)
+                                   || c.to_uppercase().any(contains));
+                found != *negated
+            }
+        }
+    }
+}
+
+/// The pattern for a single path component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Segment {
+    /// Matches one component.
+    Name(Vec<Token>),
+
+    /// `**`, which matches any number of components.
+    Recursive
+}
+
+impl Segment {
+    /// Returns the name that this segment matches, if it only matches one.
+    pub(super) fn literal(&self) -> Option<String> {
+        match self {
+            Segment::Name(tokens) => tokens.iter()
+                                           .map(|token| match token {
+                                               Token::Char(c) => Some(*c),
+                                               _ => None
+                                           })
+                                           .collect(),
+            Segment::Recursive => None
+        }
+    }
+
+    fn parse(segment: &str, pattern: &str) -> io::Result<Self> {
+        if segment == "**" {
+            return Ok(Segment::Recursive);
+        }
+        let mut tokens = Vec::new();
+        let mut chars = segment.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if tokens.last() == Some(&Token::Star) => {}
+                '*' => tokens.push(Token::Star),
+                '?' => tokens.push(Token::Any),
+                '[' => {
+                    let class = parse_class(&mut chars).ok_or_else(|| {
+                                    invalid(pattern, "unclosed character class")
+                                })?;
+                    tokens.push(class);
+                }
+                c => tokens.push(Token::Char(c))
+            }
+        }
+        Ok(Segment::Name(tokens))
+    }
+
+    fn matches(&self, name: &str, case_insensitive: bool) -> bool {
+        let tokens = match self {
+            Segment::Name(tokens) => tokens,
+            Segment::Recursive => return true
+        };
+        let name = name.chars().collect::<Vec<_>>();
+        let (mut t, mut n) = (0, 0);
+        // Where the last star was, and how much of the name it has taken.
+        let mut star = None;
+        while n < name.len() {
+            match tokens.get(t) {
+                Some(Token::Star) => {
+                    star = Some((t, n));
+                    t += 1;
+                }
+                Some(token) if token.matches(name[n], case_insensitive) => {
+                    t += 1;
+                    n += 1;
+                }
+                _ => match star {
+                    Some((star_t, star_n)) => {
+                        star = Some((star_t, star_n + 1));
+                        t = star_t + 1;
+                        n = star_n + 1;
+                    }
+                    None => return false
+                }
+            }
+        }
+        tokens[t..].iter().all(|token| *token == Token::Star)
+    }
+}
+
+/// Parses a character class, after its opening `[`.  Returns `None` if it
+/// isn't closed.
+fn parse_class(chars: &mut Chars<'_>) -> Option<Token> {
+    let mut rest = chars.clone();
+    let negated = matches!(rest.clone().next(), Some('!') | Some('^'));
+    if negated {
+        rest.next();
+    }
+    let mut ranges = Vec::new();
+    loop {
+        let c = rest.next()?;
+        // A `]` right after the opening bracket stands for itself.
+        if c == ']' && !ranges.is_empty() {
+            break;
+        }
+        let mut ahead = rest.clone();
+        if ahead.next() == Some('-') {
+            if let Some(hi) = ahead.next().filter(|hi| *hi != ']') {
+                ranges.push((c, hi));
+                rest = ahead;
+                continue;
+            }
+        }
+        ranges.push((c, c));
+    }
+    *chars = rest;
+    Some(Token::Class { negated, ranges })
+}
+
+/// A glob pattern.
+///
+/// Patterns are matched a path component at a time, by tracking which of
+/// their segments could come next, so that a partial path also tells
+/// whether anything below it can still match.
+#[derive(Debug, Clone)]
+pub(super) struct Pattern {
+    /// Whether the pattern starts with `!`, and so excludes what it matches.
+    pub(super) negated: bool,
+    pub(super) absolute: bool,
+    pub(super) segments: Vec<Segment>
+}
+
+impl Pattern {
+    pub(super) fn parse(pattern: &str) -> io::Result<Self> {
+        let (negated, rest) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, pattern)
+        };
+        let segments =
+            rest.split('/')
+                .filter(|segment| !segment.is_empty() && *segment != ".")
+                .map(|segment| Segment::parse(segment, pattern))
+                .collect::<io::Result<Vec<_>>>()?;
+        if segments.is_empty() {
+            return Err(invalid(pattern, "no path components"));
+        }
+        Ok(Pattern { negated,
+                     absolute: rest.starts_with('/'),
+                     segments })
+    }
+
+    /// Returns the states before anything has been matched.  State `i` means
+    /// that the next component is matched against segment `i`, and the last
+    /// state that the whole pattern has matched.
+    pub(super) fn start(&self) -> Vec<bool> {
+        let mut states = vec![false; self.segments.len() + 1];
+        states[0] = true;
+        self.close(&mut states);
+        states
+    }
+
+    /// Returns the states after matching the next component, `name`.
+    pub(super) fn advance(&self,
+                          states: &[bool],
+                          name: &str,
+                          case_insensitive: bool)
+                          -> Vec<bool> {
+        let last = self.segments.len() - 1;
+        let mut next = vec![false; states.len()];
+        for (i, segment) in self.segments.iter().enumerate() {
+            if !states[i] || !segment.matches(name, case_insensitive) {
+                continue;
+            }
+            next[i + 1] = true;
+            if *segment == Segment::Recursive {
+                next[i] = true;
+                // A trailing `**` matches what is inside a directory, but
+                // not the directory itself.
+                next[i + 1] = i == last;
+            }
+        }
+        self.close(&mut next);
+        next
+    }
+
+    /// Lets each `**` match no components at all, unless it is trailing.
+    fn close(&self, states: &mut [bool]) {
+        let last = self.segments.len() - 1;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if states[i] && *segment == Segment::Recursive && i != last {
+                states[i + 1] = true;
+            }
+        }
+    }
+
+    /// Returns `true` if the path that led to `states` matches.
+    pub(super) fn matched(&self, states: &[bool]) -> bool {
+        states[self.segments.len()]
+    }
+
+    /// Returns `true` if something below the path that led to `states` could
+    /// match.
+    pub(super) fn may_match_below(&self, states: &[bool]) -> bool {
+        states[..self.segments.len()].iter().any(|state| *state)
+    }
+
+    /// Returns `true` if everything below the path that led to `states`
+    /// matches.
+    pub(super) fn matches_all_below(&self, states: &[bool]) -> bool {
+        (0..self.segments.len()).any(|i| {
+                                    states[i]
+                                    && self.segments[i..].iter().all(|s| {
+                                           *s == Segment::Recursive
+                                       })
+                                })
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_matches(pattern: &str, name: &str) -> bool {
+        Segment::parse(pattern, pattern).unwrap()
+                                        .matches(name, false)
+    }
+
+    fn path_matches(pattern: &str, path: &str) -> bool {
+        let pattern = Pattern::parse(pattern).unwrap();
+        let mut states = pattern.start();
+        for name in path.split('/') {
+            states = pattern.advance(&states, name, false);
+        }
+        pattern.matched(&states)
+    }
+
+    #[test]
+    fn names_are_matched() {
+        assert!(name_matches("*.rs", "lib.rs"));
+        assert!(name_matches("*.rs", ".rs"));
+        assert!(!name_matches("*.rs", "lib.rs.bak"));
+        assert!(name_matches("a*b*c", "aXbYbZc"));
+        assert!(!name_matches("a*b*c", "aXbYbZ"));
+        assert!(name_matches("?.md", "a.md"));
+        assert!(!name_matches("?.md", "ab.md"));
+        assert!(name_matches("[a-c]x", "bx"));
+        assert!(!name_matches("[!a-c]x", "bx"));
+        assert!(name_matches("[]]", "]"));
+        assert!(name_matches("[a-]", "-"));
+        assert!(name_matches("lib.rs", "lib.rs"));
+        assert!(!name_matches("lib.rs", "LIB.RS"));
+        let segment = Segment::parse("[a-c]*.RS", "").unwrap();
+        assert!(segment.matches("B.rs", true));
+        assert!(!segment.matches("B.rs", false));
+    }
+
+    #[test]
+    fn paths_are_matched() {
+        assert!(path_matches("src/*.rs", "src/lib.rs"));
+        assert!(!path_matches("src/*.rs", "src/walk/mod.rs"));
+        assert!(path_matches("src/**/*.rs", "src/lib.rs"));
+        assert!(path_matches("src/**/*.rs", "src/walk/entry/mod.rs"));
+        assert!(path_matches("**/mod.rs", "mod.rs"));
+        assert!(path_matches("src/**", "src/walk/mod.rs"));
+        assert!(!path_matches("src/**", "src"));
+        assert!(path_matches("./src//*", "src/lib.rs"));
+    }
+
+    #[test]
+    fn partial_paths_tell_what_is_below() {
+        let pattern = Pattern::parse("src/**/gen/**").unwrap();
+        let src = pattern.advance(&pattern.start(), "src", false);
+        assert!(pattern.may_match_below(&src));
+        assert!(!pattern.matches_all_below(&src));
+        let gen = pattern.advance(&src, "gen", false);
+        assert!(pattern.matches_all_below(&gen));
+        let tests = pattern.advance(&pattern.start(), "tests", false);
+        assert!(!pattern.may_match_below(&tests));
+    }
+
+    #[test]
+    fn bad_patterns_are_refused() {
+        for pattern in ["", "/", "!.", "src/[a-"] {
+            let err = Pattern::parse(pattern).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        let pattern = Pattern::parse("!/src").unwrap();
+        assert!(pattern.negated && pattern.absolute);
+    }
+}
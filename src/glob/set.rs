@@ -0,0 +1,163 @@
+//! [`PatternSet`] decides which paths a list of glob patterns selects.
+
+use std::path::{Component, Path};
+
+use super::pattern::Pattern;
+
+/// What a [`PatternSet`] makes of a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Verdict {
+    /// Whether the path is selected.
+    pub(super) matched: bool,
+
+    /// Whether anything below the path could be selected.
+    pub(super) descend: bool
+}
+
+/// A list of patterns, where the last one that matches a path decides
+/// whether it is selected, as in a `.gitignore` file.
+#[derive(Debug)]
+pub(super) struct PatternSet {
+    /// Each pattern, with its states after matching the base of the set.
+    patterns: Vec<(Pattern, Vec<bool>)>,
+    case_insensitive: bool
+}
+
+impl PatternSet {
+    /// Returns the set of `patterns`, which are matched against paths below
+    /// `base`.
+    pub(super) fn new(patterns: Vec<Pattern>,
+                      base: &[String],
+                      case_insensitive: bool)
+                      -> Self {
+        let patterns = patterns.into_iter()
+                               .map(|pattern| {
+                                   let states =
+                            base.iter().fold(pattern.start(), |states, name| {
+                                           pattern.advance(&states,
+                                                           name,
+                                                           case_insensitive)
+                                       });
+                                   (pattern, states)
+                               })
+                               .collect();
+        PatternSet { patterns,
+                     case_insensitive }
+    }
+
+    /// Judges `path`, relative to the base of the set.
+    pub(super) fn judge(&self, path: &Path) -> Verdict {
+        let names =
+            path.components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy()),
+                    _ => None
+                })
+                .collect::<Vec<_>>();
+        let states = self.patterns
+                         .iter()
+                         .map(|(pattern, states)| {
+                             names.iter().fold(states.clone(),
+                                               |states, name| {
+                                                   pattern.advance(&states,
+                                                    name,
+                                                    self.case_insensitive)
+                                               })
+                         })
+                         .collect::<Vec<_>>();
+        let mut judged = self.patterns
+                             .iter()
+                             .map(|(pattern, _)| pattern)
+                             .zip(&states)
+                             .rev();
+        let decider = judged.clone()
+                            .find(|(pattern, states)| pattern.matched(states))
+                            .map(|(pattern, _)| pattern);
+        // Nothing below an excluded directory can be selected again.
+        if decider.map_or(false, |pattern| pattern.negated) {
+            return Verdict { matched: false,
+                             descend: false };
+        }
+        let matched = decider.is_some();
+        // Below the path, a later pattern that could select something wins
+        // over an earlier one that excludes everything.
+        let descend =
+            judged.find_map(|(pattern, states)| {
+                      if !pattern.negated && pattern.may_match_below(states) {
+                          Some(true)
+                      } else if pattern.negated
+                                && pattern.matches_all_below(states)
+                      {
+                          Some(false)
+                      } else {
+                          None
+                      }
+                  })
+                  .unwrap_or(false);
+        Verdict { matched, descend }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern_set(patterns: &[&str], base: &[&str]) -> PatternSet {
+        let patterns = patterns.iter()
+                               .map(|pattern| Pattern::parse(pattern).unwrap())
+                               .collect();
+        let base = base.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        PatternSet::new(patterns, &base, false)
+    }
+
+    fn verdict(matched: bool, descend: bool) -> Verdict {
+        Verdict { matched, descend }
+    }
+
+    #[test]
+    fn later_patterns_win() {
+        let set =
+            pattern_set(&["src/**/*.rs", "!src/gen/**", "src/gen/keep.rs"],
+                        &["src"]);
+        assert_eq!(set.judge(Path::new("lib.rs")), verdict(true, true));
+        assert_eq!(set.judge(Path::new("walk/mod.rs")), verdict(true, true));
+        assert_eq!(set.judge(Path::new("gen")), verdict(false, true));
+        assert_eq!(set.judge(Path::new("gen/x.rs")), verdict(false, false));
+        assert_eq!(set.judge(Path::new("gen/keep.rs")), verdict(true, false));
+        assert_eq!(set.judge(Path::new("lib.md")), verdict(false, true));
+
+        let set = pattern_set(&["**", "!tests", "tests/it.rs"], &[]);
+        assert_eq!(set.judge(Path::new("tests")), verdict(false, false));
+        assert_eq!(set.judge(Path::new("tests/it.rs")), verdict(true, true));
+    }
+
+    #[test]
+    fn excluded_trees_are_pruned() {
+        let set = pattern_set(&["**/*.rs", "!**/target/**"], &[]);
+        assert_eq!(set.judge(Path::new("target")), verdict(false, false));
+        assert_eq!(set.judge(Path::new("a/target")), verdict(false, false));
+        assert_eq!(set.judge(Path::new("a/target.rs")), verdict(true, true));
+
+        let set = pattern_set(&["src/*/*.rs"], &[]);
+        assert_eq!(set.judge(Path::new("src/a")), verdict(false, true));
+        assert_eq!(set.judge(Path::new("src/a/b")), verdict(false, false));
+        assert_eq!(set.judge(Path::new("tests")), verdict(false, false));
+    }
+
+    #[test]
+    fn case_can_be_ignored() {
+        let pattern = Pattern::parse("SRC/*.RS").unwrap();
+        let set = PatternSet::new(vec![pattern], &[], true);
+        assert!(set.judge(Path::new("src/lib.rs")).matched);
+        assert!(set.judge(Path::new("Src")).descend);
+    }
+}
@@ -0,0 +1,263 @@
+//! [`Glob`] streams the entries that glob patterns select.
+
+use std::{
+    fmt, io, mem,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use futures_core::{ready, Stream};
+
+use super::{
+    set::{PatternSet, Verdict},
+    GlobOptions
+};
+use crate::{
+    walk::{Walk, WalkEntry, WalkOptions},
+    AsyncFsTrait
+};
+
+/// Finds the entries in `fs` that `pattern` selects, with the default
+/// [`GlobOptions`].
+///
+/// Patterns are made of path components separated by `/`.  Within a
+/// component, `*` matches any run of characters, `?` matches any one
+/// character, and `[a-z]` or `[!a-z]` matches one character in or out of the
+/// given ranges.  A component that is just `**` matches any number of
+/// directories; at the end of a pattern, it matches everything inside a
+/// directory, but not the directory itself.
+///
+/// The entries are yielded as the directories holding them are read, with
+/// the paths that [`read_dir()`][1] gives them.  Relative patterns without
+/// a leading directory are looked for in `.`, so their paths start with
+/// `./`.  Nothing is yielded if the leading directories don't exist.
+///
+/// Fails with [`ErrorKind::InvalidInput`][2] if the pattern is malformed.
+///
+/// [1]: AsyncFsTrait::read_dir
+/// [2]: std::io::ErrorKind::InvalidInput
+pub fn glob<'a, F>(fs: &'a F, pattern: &str) -> io::Result<Glob<'a, F>>
+    where F: AsyncFsTrait
+{
+    GlobOptions::new().glob(fs, [pattern])
+}
+
+/// Judges the entries of a walk by their paths below its root.
+#[derive(Debug)]
+struct Matcher {
+    root: PathBuf,
+    set: PatternSet
+}
+
+impl Matcher {
+    fn judge<E>(&self, entry: &WalkEntry<E>) -> Verdict {
+        let path = entry.path();
+        self.set
+            .judge(path.strip_prefix(&self.root).unwrap_or(path))
+    }
+}
+
+/// A stream of the entries that glob patterns select, returned by
+/// [`glob()`] and [`GlobOptions::glob()`].
+///
+/// An error reading a directory is yielded in place of its contents, and the
+/// search carries on with the next entry.
+pub struct Glob<'a, F>
+    where F: AsyncFsTrait
+{
+    /// The walk over the directories that could hold matches, until it ends.
+    walk: Option<Walk<'a, F>>,
+    matcher: Option<Arc<Matcher>>,
+    started: bool
+}
+
+impl<'a, F> Glob<'a, F> where F: AsyncFsTrait
+{
+    pub(super) fn new(fs: &'a F,
+                      root: PathBuf,
+                      set: PatternSet,
+                      mut options: WalkOptions<F::DirEntry>)
+                      -> Self {
+        let matcher = Arc::new(Matcher { root, set });
+        let filter = matcher.clone();
+        let walk = options.filter_entry(move |entry| {
+                              let verdict = filter.judge(entry);
+                              verdict.matched
+                              || entry.is_dir() && verdict.descend
+                          })
+                          .walk(fs, &matcher.root);
+        Glob { walk: Some(walk),
+               matcher: Some(matcher),
+               started: false }
+    }
+
+    /// Returns a stream that yields nothing, for patterns that can't select
+    /// anything.
+    pub(super) fn empty() -> Self {
+        Glob { walk: None,
+               matcher: None,
+               started: false }
+    }
+}
+
+impl<F> Stream for Glob<'_, F> where F: AsyncFsTrait
+{
+    type Item = io::Result<WalkEntry<F::DirEntry>>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        while let (Some(walk), Some(matcher)) = (&mut this.walk, &this.matcher)
+        {
+            let item = ready!(Pin::new(walk).poll_next(cx));
+            let first = !mem::replace(&mut this.started, true);
+            match item {
+                // The leading directories don't exist.
+                Some(Err(err))
+                    if first && err.kind() == io::ErrorKind::NotFound => {}
+                None => {}
+                Some(Ok(entry)) if !matcher.judge(&entry).matched => continue,
+                item => return Poll::Ready(item)
+            }
+            this.walk = None;
+        }
+        Poll::Ready(None)
+    }
+}
+
+impl<F> fmt::Debug for Glob<'_, F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Glob").finish_non_exhaustive()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{
+        mem_fs::MemFs, AsyncDirBuilderTrait, AsyncFileBuilderTrait,
+        AsyncSymLinkTrait
+    };
+
+    async fn tree() -> MemFs {
+        let fs = MemFs::new();
+        for dir in ["/src/walk", "/src/gen/deep", "/tests"] {
+            fs.dir_builder().recursive(true).create(dir).await.unwrap();
+        }
+        for file in ["/src/lib.rs",
+                     "/src/README.md",
+                     "/src/walk/mod.rs",
+                     "/src/gen/a.rs",
+                     "/src/gen/keep.rs",
+                     "/src/gen/deep/b.rs",
+                     "/tests/it.rs",
+                     "/Cargo.toml"]
+        {
+            fs.open_options()
+              .write(true)
+              .create_new(true)
+              .open(file)
+              .await
+              .unwrap();
+        }
+        fs
+    }
+
+    async fn paths(glob: Glob<'_, MemFs>) -> Vec<String> {
+        let mut paths =
+            glob.map(|entry| {
+                    entry.unwrap().path().to_string_lossy().into_owned()
+                })
+                .collect::<Vec<_>>()
+                .await;
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn patterns_select_entries() {
+        block_on(async {
+            let fs = tree().await;
+            assert_eq!(paths(glob(&fs, "/src/*.rs").unwrap()).await,
+                       ["/src/lib.rs"]);
+            assert_eq!(paths(glob(&fs, "/src/**/*.rs").unwrap()).await,
+                       ["/src/gen/a.rs",
+                        "/src/gen/deep/b.rs",
+                        "/src/gen/keep.rs",
+                        "/src/lib.rs",
+                        "/src/walk/mod.rs"]);
+            assert_eq!(paths(glob(&fs, "/*/[!g]*").unwrap()).await,
+                       ["/src/README.md",
+                        "/src/lib.rs",
+                        "/src/walk",
+                        "/tests/it.rs"]);
+            assert_eq!(paths(glob(&fs, "/src/gen/**").unwrap()).await,
+                       ["/src/gen/a.rs",
+                        "/src/gen/deep",
+                        "/src/gen/deep/b.rs",
+                        "/src/gen/keep.rs"]);
+            assert_eq!(paths(glob(&fs, "*.toml").unwrap()).await,
+                       ["./Cargo.toml"]);
+            assert_eq!(paths(glob(&fs, "tests/*").unwrap()).await,
+                       ["tests/it.rs"]);
+            assert!(paths(glob(&fs, "/missing/**").unwrap()).await.is_empty());
+        });
+    }
+
+    #[test]
+    fn negations_exclude_entries() {
+        block_on(async {
+            let fs = tree().await;
+            let patterns =
+                ["/**/*.rs", "!/src/gen/**", "/src/gen/keep.rs", "!/tests"];
+            let glob = GlobOptions::new().glob(&fs, patterns).unwrap();
+            assert_eq!(paths(glob).await,
+                       ["/src/gen/keep.rs", "/src/lib.rs", "/src/walk/mod.rs"]);
+
+            let glob = GlobOptions::new().glob(&fs, ["!/src/**"]).unwrap();
+            assert!(paths(glob).await.is_empty());
+        });
+    }
+
+    #[test]
+    fn case_can_be_ignored() {
+        block_on(async {
+            let fs = tree().await;
+            assert!(paths(glob(&fs, "/SRC/*.RS").unwrap()).await.is_empty());
+            let glob = GlobOptions::new().case_insensitive(true)
+                                         .glob(&fs, ["/SRC/*.RS", "/readme*"])
+                                         .unwrap();
+            assert_eq!(paths(glob).await, ["/src/lib.rs"]);
+        });
+    }
+
+    #[test]
+    fn symlinks_can_be_followed() {
+        block_on(async {
+            let fs = tree().await;
+            fs.symlink("/tests/src", "/src").await.unwrap();
+            let glob = glob(&fs, "/tests/**/*.rs").unwrap();
+            assert_eq!(paths(glob).await, ["/tests/it.rs"]);
+            let glob = GlobOptions::new().follow_links(true)
+                                         .max_concurrency(4)
+                                         .glob(&fs, ["/tests/*/walk/*"])
+                                         .unwrap();
+            assert_eq!(paths(glob).await, ["/tests/src/walk/mod.rs"]);
+        });
+    }
+}
@@ -0,0 +1,658 @@
+//! [`Layered`] routes every operation on a filesystem, and on everything it
+//! hands out, through an [`FsMiddleware`].
+
+use std::{
+    ffi::OsString,
+    fmt,
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::{ready, Stream};
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{FileOf, FsMiddleware, OpenFlags};
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFileBuilderTrait,
+    AsyncFileTrait, AsyncFsTrait, AsyncReadDirTrait, AsyncSymLinkTrait,
+    FileType, Metadata, Permissions
+};
+
+/// A filesystem whose operations are routed through the middleware `M`.
+///
+/// The builders, files, directory streams, and directory entries handed out
+/// by a `Layered` are wrapped in the other `Layered*` types in this module,
+/// which share the same middleware.  Files implement [`AsyncRead`],
+/// [`AsyncWrite`], and [`AsyncSeek`] whenever the wrapped files do.
+pub struct Layered<M, F> {
+    inner: F,
+    middleware: Arc<M>
+}
+
+impl<M, F> Layered<M, F> {
+    /// Wraps `inner` with `middleware`.
+    pub fn new(inner: F, middleware: M) -> Self {
+        Self::from_arc(inner, Arc::new(middleware))
+    }
+
+    /// Wraps `inner` with `middleware`, which may be shared with other
+    /// filesystems.
+    pub fn from_arc(inner: F, middleware: Arc<M>) -> Self {
+        Layered { inner, middleware }
+    }
+
+    /// Returns a reference to the wrapped filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Returns a reference to the middleware.
+    pub fn middleware(&self) -> &M {
+        &self.middleware
+    }
+
+    /// Unwraps this filesystem, returning the wrapped filesystem.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<M, F> Clone for Layered<M, F> where F: Clone
+{
+    fn clone(&self) -> Self {
+        Layered { inner: self.inner.clone(),
+                  middleware: self.middleware.clone() }
+    }
+}
+
+impl<M, F> fmt::Debug for Layered<M, F>
+    where M: fmt::Debug,
+          F: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Layered")
+         .field("inner", &self.inner)
+         .field("middleware", &self.middleware)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<M, F> AsyncFsTrait for Layered<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait
+{
+    type DirBuilder = LayeredDirBuilder<M, F>;
+    type DirEntry = LayeredDirEntry<M, F>;
+    type FileBuilder = LayeredOpenOptions<M, F>;
+    type ReadDir = LayeredReadDir<M, F>;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        LayeredDirBuilder { inner: self.inner.dir_builder(),
+                            middleware: self.middleware.clone(),
+                            recursive: false }
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        LayeredOpenOptions { inner: self.inner.open_options(),
+                             middleware: self.middleware.clone(),
+                             flags: OpenFlags::default() }
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware
+            .canonicalize(&self.inner, path.as_ref())
+            .await
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        self.middleware
+            .rename(&self.inner, src.as_ref(), dst.as_ref())
+            .await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware
+            .set_permissions(&self.inner, path.as_ref(), perm)
+            .await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        self.middleware
+            .hard_link(&self.inner, src.as_ref(), dst.as_ref())
+            .await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware.read_link(&self.inner, path.as_ref()).await
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware
+            .symlink_metadata(&self.inner, path.as_ref())
+            .await
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware.metadata(&self.inner, path.as_ref()).await
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        self.middleware
+            .copy(&self.inner, src.as_ref(), dst.as_ref())
+            .await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware
+            .remove_file(&self.inner, path.as_ref())
+            .await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let inner =
+            self.middleware.read_dir(&self.inner, path.as_ref()).await?;
+        Ok(LayeredReadDir { inner,
+                            middleware: self.middleware.clone() })
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware.remove_dir(&self.inner, path.as_ref()).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware
+            .remove_dir_all(&self.inner, path.as_ref())
+            .await
+    }
+}
+
+#[async_trait]
+impl<M, F> AsyncSymLinkTrait for Layered<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait + AsyncSymLinkTrait
+{
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        self.middleware
+            .symlink(&self.inner, src.as_ref(), dst.as_ref())
+            .await
+    }
+}
+
+/// A builder for creating directories in a [`Layered`] filesystem.
+pub struct LayeredDirBuilder<M, F>
+    where F: AsyncFsTrait
+{
+    inner: F::DirBuilder,
+    middleware: Arc<M>,
+    recursive: bool
+}
+
+impl<M, F> fmt::Debug for LayeredDirBuilder<M, F>
+    where M: fmt::Debug,
+          F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredDirBuilder")
+         .field("inner", &self.inner)
+         .field("middleware", &self.middleware)
+         .field("recursive", &self.recursive)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<M, F> AsyncDirBuilderTrait for LayeredDirBuilder<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait
+{
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.inner.recursive(recursive);
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.middleware
+            .create_dir(&self.inner, path.as_ref(), self.recursive)
+            .await
+    }
+}
+
+/// Options for opening a [`LayeredFile`].
+pub struct LayeredOpenOptions<M, F>
+    where F: AsyncFsTrait
+{
+    inner: F::FileBuilder,
+    middleware: Arc<M>,
+    flags: OpenFlags
+}
+
+impl<M, F> fmt::Debug for LayeredOpenOptions<M, F>
+    where M: fmt::Debug,
+          F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredOpenOptions")
+         .field("inner", &self.inner)
+         .field("middleware", &self.middleware)
+         .field("flags", &self.flags)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<M, F> AsyncFileBuilderTrait for LayeredOpenOptions<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait
+{
+    type File = LayeredFile<M, F>;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.inner.read(read);
+        self.flags.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.inner.write(write);
+        self.flags.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.inner.append(append);
+        self.flags.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.inner.truncate(truncate);
+        self.flags.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.inner.create(create);
+        self.flags.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.inner.create_new(create_new);
+        self.flags.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let inner = self.middleware.open(&self.inner, path, self.flags).await?;
+        Ok(LayeredFile { inner,
+                         middleware: self.middleware.clone(),
+                         path: path.to_owned() })
+    }
+}
+
+/// An open file in a [`Layered`] filesystem.
+pub struct LayeredFile<M, F>
+    where F: AsyncFsTrait
+{
+    inner: FileOf<F>,
+    middleware: Arc<M>,
+    path: PathBuf
+}
+
+impl<M, F> LayeredFile<M, F> where F: AsyncFsTrait
+{
+    /// Returns the path that this file was opened with.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns a reference to the wrapped file.
+    pub fn get_ref(&self) -> &FileOf<F> {
+        &self.inner
+    }
+
+    /// Unwraps this file, returning the wrapped file.
+    pub fn into_inner(self) -> FileOf<F> {
+        self.inner
+    }
+}
+
+impl<M, F> fmt::Debug for LayeredFile<M, F>
+    where M: fmt::Debug,
+          F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredFile")
+         .field("inner", &self.inner)
+         .field("middleware", &self.middleware)
+         .field("path", &self.path)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<M, F> AsyncFileTrait for LayeredFile<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait
+{
+    async fn sync_all(&self) -> io::Result<()> {
+        self.middleware.sync_all(&self.inner, &self.path).await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.middleware.sync_data(&self.inner, &self.path).await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.middleware.set_len(&self.inner, &self.path, size).await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.middleware.file_metadata(&self.inner, &self.path).await
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        self.middleware
+            .file_set_permissions(&self.inner, &self.path, perm)
+            .await
+    }
+}
+
+impl<M, F> AsyncRead for LayeredFile<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + Unpin
+{
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.middleware
+            .poll_read(Pin::new(&mut this.inner), &this.path, cx, buf)
+    }
+}
+
+impl<M, F> AsyncWrite for LayeredFile<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait,
+          FileOf<F>: AsyncWrite + Unpin
+{
+    fn poll_write(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.middleware
+            .poll_write(Pin::new(&mut this.inner), &this.path, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.middleware
+            .poll_flush(Pin::new(&mut this.inner), &this.path, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.middleware
+            .poll_close(Pin::new(&mut this.inner), &this.path, cx)
+    }
+}
+
+impl<M, F> AsyncSeek for LayeredFile<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait,
+          FileOf<F>: AsyncSeek + Unpin
+{
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        this.middleware
+            .poll_seek(Pin::new(&mut this.inner), &this.path, cx, pos)
+    }
+}
+
+/// A stream of entries in a directory of a [`Layered`] filesystem.
+pub struct LayeredReadDir<M, F>
+    where F: AsyncFsTrait
+{
+    inner: F::ReadDir,
+    middleware: Arc<M>
+}
+
+impl<M, F> fmt::Debug for LayeredReadDir<M, F>
+    where M: fmt::Debug,
+          F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredReadDir")
+         .field("inner", &self.inner)
+         .field("middleware", &self.middleware)
+         .finish()
+    }
+}
+
+impl<M, F> Stream for LayeredReadDir<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait
+{
+    type Item = io::Result<LayeredDirEntry<M, F>>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let entry = ready!(this.middleware
+                               .poll_next_entry(Pin::new(&mut this.inner), cx));
+        Poll::Ready(entry.map(|entry| {
+                             entry.map(|inner| {
+                                      LayeredDirEntry { inner,
+                                                        middleware:
+                                                            this.middleware
+                                                                .clone() }
+                                  })
+                         }))
+    }
+}
+
+impl<M, F> AsyncReadDirTrait<LayeredDirEntry<M, F>> for LayeredReadDir<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait
+{
+}
+
+/// An entry in a directory of a [`Layered`] filesystem.
+pub struct LayeredDirEntry<M, F>
+    where F: AsyncFsTrait
+{
+    inner: F::DirEntry,
+    middleware: Arc<M>
+}
+
+impl<M, F> LayeredDirEntry<M, F> where F: AsyncFsTrait
+{
+    /// Returns a reference to the wrapped entry.
+    pub fn get_ref(&self) -> &F::DirEntry {
+        &self.inner
+    }
+}
+
+impl<M, F> Clone for LayeredDirEntry<M, F> where F: AsyncFsTrait
+{
+    fn clone(&self) -> Self {
+        LayeredDirEntry { inner: self.inner.clone(),
+                          middleware: self.middleware.clone() }
+    }
+}
+
+impl<M, F> fmt::Debug for LayeredDirEntry<M, F>
+    where M: fmt::Debug,
+          F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredDirEntry")
+         .field("inner", &self.inner)
+         .field("middleware", &self.middleware)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<M, F> AsyncDirEntryTrait for LayeredDirEntry<M, F>
+    where M: FsMiddleware<F>,
+          F: AsyncFsTrait
+{
+    async fn path(&self) -> PathBuf {
+        self.middleware.entry_path(&self.inner).await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.middleware.entry_metadata(&self.inner).await
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        self.middleware.entry_file_type(&self.inner).await
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.middleware.entry_file_name(&self.inner).await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{
+        future::block_on, AsyncReadExt, AsyncWriteExt, StreamExt
+    };
+
+    use super::*;
+    use crate::mem_fs::{MemDirEntry, MemFs};
+
+    /// Upper-cases everything written through it, and the names of directory
+    /// entries.
+    #[derive(Debug)]
+    struct Shouty;
+
+    #[async_trait]
+    impl FsMiddleware<MemFs> for Shouty {
+        fn poll_write(&self,
+                      file: Pin<&mut FileOf<MemFs>>,
+                      _path: &Path,
+                      cx: &mut Context<'_>,
+                      buf: &[u8])
+                      -> Poll<io::Result<usize>> {
+            file.poll_write(cx, &buf.to_ascii_uppercase())
+        }
+
+        async fn entry_file_name(&self, entry: &MemDirEntry) -> OsString {
+            entry.file_name().await.to_ascii_uppercase()
+        }
+    }
+
+    #[test]
+    fn unchanged_operations_are_forwarded() {
+        let fs = Layered::new(MemFs::new(), Shouty);
+        block_on(async {
+            fs.dir_builder()
+              .recursive(true)
+              .create("/a/b")
+              .await
+              .unwrap();
+            let mut file = fs.open_options()
+                             .write(true)
+                             .create(true)
+                             .open("/a/quiet")
+                             .await
+                             .unwrap();
+            file.write_all(b"quiet").await.unwrap();
+            assert_eq!(file.path(), Path::new("/a/quiet"));
+            assert_eq!(file.metadata().await.unwrap().len(), 5);
+            drop(file);
+
+            let mut contents = String::new();
+            fs.get_ref()
+              .open_options()
+              .read(true)
+              .open("/a/quiet")
+              .await
+              .unwrap()
+              .read_to_string(&mut contents)
+              .await
+              .unwrap();
+            assert_eq!(contents, "QUIET");
+
+            let names: Vec<_> =
+                fs.read_dir("/a")
+                  .await
+                  .unwrap()
+                  .then(|entry| async move { entry.unwrap().file_name().await })
+                  .collect()
+                  .await;
+            assert_eq!(names,
+                       vec![OsString::from("B"), OsString::from("QUIET")]);
+
+            fs.rename("/a/quiet", "/a/loud").await.unwrap();
+            assert!(fs.metadata("/a/loud").await.unwrap().is_file());
+        });
+    }
+}
@@ -0,0 +1,349 @@
+//! [`FsMiddleware`] lets a type intercept individual operations on a wrapped
+//! filesystem, without having to implement every trait in the family itself.
+
+use std::{
+    ffi::OsString,
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{FsLayer, Layered};
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFileBuilderTrait,
+    AsyncFileTrait, AsyncFsTrait, AsyncSymLinkTrait, FileType, Metadata,
+    Permissions
+};
+
+/// The type of file opened by the filesystem `F`.
+pub type FileOf<F> =
+    <<F as AsyncFsTrait>::FileBuilder as AsyncFileBuilderTrait>::File;
+
+/// The options that a file was opened with.
+///
+/// [`AsyncFileBuilderTrait`] has setters but no getters, so the
+/// [`LayeredOpenOptions`][1] record each option here as it is set, and hand
+/// them to [`FsMiddleware::open()`].
+///
+/// [1]: super::LayeredOpenOptions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct OpenFlags {
+    /// The value passed to [`AsyncFileBuilderTrait::read()`].
+    pub read: bool,
+
+    /// The value passed to [`AsyncFileBuilderTrait::write()`].
+    pub write: bool,
+
+    /// The value passed to [`AsyncFileBuilderTrait::append()`].
+    pub append: bool,
+
+    /// The value passed to [`AsyncFileBuilderTrait::truncate()`].
+    pub truncate: bool,
+
+    /// The value passed to [`AsyncFileBuilderTrait::create()`].
+    pub create: bool,
+
+    /// The value passed to [`AsyncFileBuilderTrait::create_new()`].
+    pub create_new: bool
+}
+
+/// Hooks that are called in place of the operations of the filesystem `F`.
+///
+/// Wrapping `F` in a [`Layered`] routes every operation on it, and on the
+/// files, builders, directory streams, and directory entries that it hands
+/// out, through one of these hooks.  Each hook is given the wrapped object and
+/// the original arguments, and by default simply forwards to the wrapped
+/// object.  An implementor only has to override the hooks for the operations
+/// it cares about; everything else passes through untouched.
+///
+/// Hooks that act on an open file are also given the path that the file was
+/// opened with, since files don't otherwise know where they came from.
+///
+/// Middleware that needs to change the *types* involved (for instance, to
+/// hand out files that can't be written to) should implement [`FsLayer`]
+/// directly instead.
+#[async_trait]
+pub trait FsMiddleware<F>: std::fmt::Debug + Send + Sync
+    where F: AsyncFsTrait
+{
+    /// Called in place of [`AsyncFsTrait::canonicalize()`].
+    async fn canonicalize(&self, fs: &F, path: &Path) -> io::Result<PathBuf> {
+        fs.canonicalize(path).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::rename()`].
+    async fn rename(&self, fs: &F, src: &Path, dst: &Path) -> io::Result<()> {
+        fs.rename(src, dst).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::set_permissions()`].
+    async fn set_permissions(&self,
+                             fs: &F,
+                             path: &Path,
+                             perm: Permissions)
+                             -> io::Result<()> {
+        fs.set_permissions(path, perm).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::hard_link()`].
+    async fn hard_link(&self,
+                       fs: &F,
+                       src: &Path,
+                       dst: &Path)
+                       -> io::Result<()> {
+        fs.hard_link(src, dst).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::read_link()`].
+    async fn read_link(&self, fs: &F, path: &Path) -> io::Result<PathBuf> {
+        fs.read_link(path).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::symlink_metadata()`].
+    async fn symlink_metadata(&self,
+                              fs: &F,
+                              path: &Path)
+                              -> io::Result<Metadata> {
+        fs.symlink_metadata(path).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::metadata()`].
+    async fn metadata(&self, fs: &F, path: &Path) -> io::Result<Metadata> {
+        fs.metadata(path).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::copy()`].
+    async fn copy(&self, fs: &F, src: &Path, dst: &Path) -> io::Result<u64> {
+        fs.copy(src, dst).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::remove_file()`].
+    async fn remove_file(&self, fs: &F, path: &Path) -> io::Result<()> {
+        fs.remove_file(path).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::read_dir()`].
+    async fn read_dir(&self, fs: &F, path: &Path) -> io::Result<F::ReadDir> {
+        fs.read_dir(path).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::remove_dir()`].
+    async fn remove_dir(&self, fs: &F, path: &Path) -> io::Result<()> {
+        fs.remove_dir(path).await
+    }
+
+    /// Called in place of [`AsyncFsTrait::remove_dir_all()`].
+    async fn remove_dir_all(&self, fs: &F, path: &Path) -> io::Result<()> {
+        fs.remove_dir_all(path).await
+    }
+
+    /// Called in place of [`AsyncSymLinkTrait::symlink()`].
+    async fn symlink(&self, fs: &F, src: &Path, dst: &Path) -> io::Result<()>
+        where F: AsyncSymLinkTrait
+    {
+        fs.symlink(src, dst).await
+    }
+
+    /// Called in place of [`AsyncDirBuilderTrait::create()`].
+    ///
+    /// `recursive` is the last value passed to
+    /// [`AsyncDirBuilderTrait::recursive()`], which has already been applied
+    /// to `builder`.
+    async fn create_dir(&self,
+                        builder: &F::DirBuilder,
+                        path: &Path,
+                        recursive: bool)
+                        -> io::Result<()> {
+        let _ = recursive;
+        builder.create(path).await
+    }
+
+    /// Called in place of [`AsyncFileBuilderTrait::open()`].
+    ///
+    /// `flags` have already been applied to `builder`.
+    async fn open(&self,
+                  builder: &F::FileBuilder,
+                  path: &Path,
+                  flags: OpenFlags)
+                  -> io::Result<FileOf<F>> {
+        let _ = flags;
+        builder.open(path).await
+    }
+
+    /// Called in place of [`AsyncFileTrait::sync_all()`].
+    async fn sync_all(&self, file: &FileOf<F>, path: &Path) -> io::Result<()> {
+        let _ = path;
+        file.sync_all().await
+    }
+
+    /// Called in place of [`AsyncFileTrait::sync_data()`].
+    async fn sync_data(&self, file: &FileOf<F>, path: &Path) -> io::Result<()> {
+        let _ = path;
+        file.sync_data().await
+    }
+
+    /// Called in place of [`AsyncFileTrait::set_len()`].
+    async fn set_len(&self,
+                     file: &FileOf<F>,
+                     path: &Path,
+                     size: u64)
+                     -> io::Result<()> {
+        let _ = path;
+        file.set_len(size).await
+    }
+
+    /// Called in place of [`AsyncFileTrait::metadata()`].
+    async fn file_metadata(&self,
+                           file: &FileOf<F>,
+                           path: &Path)
+                           -> io::Result<Metadata> {
+        let _ = path;
+        file.metadata().await
+    }
+
+    /// Called in place of [`AsyncFileTrait::set_permissions()`].
+    async fn file_set_permissions(&self,
+                                  file: &FileOf<F>,
+                                  path: &Path,
+                                  perm: Permissions)
+                                  -> io::Result<()> {
+        let _ = path;
+        file.set_permissions(perm).await
+    }
+
+    /// Called in place of [`AsyncRead::poll_read()`] on a file.
+    fn poll_read(&self,
+                 file: Pin<&mut FileOf<F>>,
+                 path: &Path,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>>
+        where FileOf<F>: AsyncRead
+    {
+        let _ = path;
+        file.poll_read(cx, buf)
+    }
+
+    /// Called in place of [`AsyncWrite::poll_write()`] on a file.
+    fn poll_write(&self,
+                  file: Pin<&mut FileOf<F>>,
+                  path: &Path,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>>
+        where FileOf<F>: AsyncWrite
+    {
+        let _ = path;
+        file.poll_write(cx, buf)
+    }
+
+    /// Called in place of [`AsyncWrite::poll_flush()`] on a file.
+    fn poll_flush(&self,
+                  file: Pin<&mut FileOf<F>>,
+                  path: &Path,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>>
+        where FileOf<F>: AsyncWrite
+    {
+        let _ = path;
+        file.poll_flush(cx)
+    }
+
+    /// Called in place of [`AsyncWrite::poll_close()`] on a file.
+    fn poll_close(&self,
+                  file: Pin<&mut FileOf<F>>,
+                  path: &Path,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>>
+        where FileOf<F>: AsyncWrite
+    {
+        let _ = path;
+        file.poll_close(cx)
+    }
+
+    /// Called in place of [`AsyncSeek::poll_seek()`] on a file.
+    fn poll_seek(&self,
+                 file: Pin<&mut FileOf<F>>,
+                 path: &Path,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>>
+        where FileOf<F>: AsyncSeek
+    {
+        let _ = path;
+        file.poll_seek(cx, pos)
+    }
+
+    /// Called in place of [`Stream::poll_next()`] on a directory stream.
+    fn poll_next_entry(&self,
+                       read_dir: Pin<&mut F::ReadDir>,
+                       cx: &mut Context<'_>)
+                       -> Poll<Option<io::Result<F::DirEntry>>> {
+        read_dir.poll_next(cx)
+    }
+
+    /// Called in place of [`AsyncDirEntryTrait::path()`].
+    async fn entry_path(&self, entry: &F::DirEntry) -> PathBuf {
+        entry.path().await
+    }
+
+    /// Called in place of [`AsyncDirEntryTrait::metadata()`].
+    async fn entry_metadata(&self,
+                            entry: &F::DirEntry)
+                            -> io::Result<Metadata> {
+        entry.metadata().await
+    }
+
+    /// Called in place of [`AsyncDirEntryTrait::file_type()`].
+    async fn entry_file_type(&self,
+                             entry: &F::DirEntry)
+                             -> io::Result<FileType> {
+        entry.file_type().await
+    }
+
+    /// Called in place of [`AsyncDirEntryTrait::file_name()`].
+    async fn entry_file_name(&self, entry: &F::DirEntry) -> OsString {
+        entry.file_name().await
+    }
+}
+
+/// An [`FsLayer`] that wraps filesystems in a [`Layered`] with a shared
+/// [`FsMiddleware`].
+///
+/// This is usually created through [`FsStackBuilder::middleware()`][1].
+///
+/// [1]: super::FsStackBuilder::middleware
+#[derive(Debug)]
+pub struct MiddlewareLayer<M> {
+    middleware: Arc<M>
+}
+
+impl<M> MiddlewareLayer<M> {
+    /// Creates a layer that wraps filesystems with `middleware`.
+    pub fn new(middleware: M) -> Self {
+        MiddlewareLayer { middleware: Arc::new(middleware) }
+    }
+}
+
+impl<M> Clone for MiddlewareLayer<M> {
+    fn clone(&self) -> Self {
+        MiddlewareLayer { middleware: self.middleware.clone() }
+    }
+}
+
+impl<F, M> FsLayer<F> for MiddlewareLayer<M>
+    where F: AsyncFsTrait,
+          M: FsMiddleware<F>
+{
+    type Fs = Layered<M, F>;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        Layered::from_arc(inner, self.middleware.clone())
+    }
+}
@@ -0,0 +1,56 @@
+//! Tower-style layers for stacking filesystem wrappers.
+//!
+//! A wrapper around a filesystem has to wrap everything that the filesystem
+//! hands out as well: its builders, its files, its directory streams, and their
+//! entries.  This module provides two ways of writing one:
+//!
+//! - [`FsLayer`] is the equivalent of Tower's `Layer` trait.  It turns one
+//!   filesystem into another, and places no restrictions on the types
+//!   involved.  Layers are combined into a stack with [`FsStackBuilder`].
+//! - [`FsMiddleware`] is a set of hooks, one per operation in the trait
+//!   family, that forward to the wrapped object by default.  Wrapping a
+//!   filesystem in a [`Layered`] routes everything through those hooks, so a
+//!   middleware only overrides the operations it cares about.
+//!   [`MiddlewareLayer`] turns a middleware into a layer.
+//!
+//! ```
+//! # #[cfg(feature = "mem-fs")]
+//! # {
+//! use std::{io, path::Path};
+//!
+//! use async_fs_traits::{
+//!     layer::{FsMiddleware, FsStackBuilder},
+//!     mem_fs::MemFs,
+//!     AsyncFsTrait
+//! };
+//! use async_trait::async_trait;
+//!
+//! /// Refuses to remove anything.
+//! #[derive(Debug)]
+//! struct NoRemove;
+//!
+//! #[async_trait]
+//! impl<F: AsyncFsTrait> FsMiddleware<F> for NoRemove {
+//!     async fn remove_file(&self, _fs: &F, _path: &Path) -> io::Result<()> {
+//!         Err(io::ErrorKind::PermissionDenied.into())
+//!     }
+//! }
+//!
+//! let fs = FsStackBuilder::new().middleware(NoRemove).build(MemFs::new());
+//! # let _ = fs;
+//! # }
+//! ```
+
+mod layered;
+mod middleware;
+mod stack;
+
+#[doc(inline)]
+pub use layered::{
+    Layered, LayeredDirBuilder, LayeredDirEntry, LayeredFile,
+    LayeredOpenOptions, LayeredReadDir
+};
+#[doc(inline)]
+pub use middleware::{FileOf, FsMiddleware, MiddlewareLayer, OpenFlags};
+#[doc(inline)]
+pub use stack::{FsLayer, FsStackBuilder, Identity, Stack};
@@ -0,0 +1,217 @@
+//! [`FsLayer`] and the types used to stack layers on top of each other.
+
+use super::MiddlewareLayer;
+use crate::AsyncFsTrait;
+
+/// Wraps a filesystem in another filesystem.
+///
+/// This is the equivalent of Tower's [`Layer`][1] trait.  A layer is a
+/// reusable description of some wrapper (a configuration, a key, a logger,
+/// ...), and [`layer()`][2] applies that wrapper to a particular filesystem.
+/// Layers are combined with an [`FsStackBuilder`].
+///
+/// A layer is free to choose any type for the filesystem that it returns.
+/// Most layers that only need to intercept some operations can implement
+/// [`FsMiddleware`][3] instead and be turned into a layer with
+/// [`MiddlewareLayer`].
+///
+/// [1]: https://docs.rs/tower/latest/tower/trait.Layer.html
+/// [2]: FsLayer::layer
+/// [3]: super::FsMiddleware
+pub trait FsLayer<F> {
+    /// The type of the wrapped filesystem.
+    type Fs: AsyncFsTrait;
+
+    /// Wraps `inner`.
+    fn layer(&self, inner: F) -> Self::Fs;
+}
+
+/// A layer that returns the filesystem unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Identity;
+
+impl Identity {
+    /// Creates the identity layer.
+    pub fn new() -> Self {
+        Identity
+    }
+}
+
+impl<F> FsLayer<F> for Identity where F: AsyncFsTrait
+{
+    type Fs = F;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        inner
+    }
+}
+
+/// Two layers applied one after the other.
+///
+/// `inner` is applied to the filesystem first, and then `outer` is applied to
+/// the result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer
+}
+
+impl<Inner, Outer> Stack<Inner, Outer> {
+    /// Creates a stack that applies `inner` and then `outer`.
+    pub fn new(inner: Inner, outer: Outer) -> Self {
+        Stack { inner, outer }
+    }
+}
+
+impl<F, Inner, Outer> FsLayer<F> for Stack<Inner, Outer>
+    where Inner: FsLayer<F>,
+          Outer: FsLayer<Inner::Fs>
+{
+    type Fs = Outer::Fs;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        self.outer.layer(self.inner.layer(inner))
+    }
+}
+
+/// Builds a stack of layers, in the style of Tower's [`ServiceBuilder`][1].
+///
+/// Layers are listed from the outside in: the first layer added is the first
+/// one to see every operation, and the filesystem passed to [`build()`][2] is
+/// the last.
+///
+/// ```
+/// use async_fs_traits::layer::{FsStackBuilder, Identity};
+///
+/// let stack = FsStackBuilder::new().layer(Identity::new())
+///                                  .layer(Identity::new());
+/// # let _ = stack;
+/// ```
+///
+/// [1]: https://docs.rs/tower/latest/tower/struct.ServiceBuilder.html
+/// [2]: FsStackBuilder::build
+#[derive(Debug, Clone)]
+pub struct FsStackBuilder<L> {
+    layer: L
+}
+
+impl FsStackBuilder<Identity> {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        FsStackBuilder { layer: Identity }
+    }
+}
+
+impl Default for FsStackBuilder<Identity> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> FsStackBuilder<L> {
+    /// Adds `layer` below every layer that has been added so far.
+    pub fn layer<T>(self, layer: T) -> FsStackBuilder<Stack<T, L>> {
+        FsStackBuilder { layer: Stack::new(layer, self.layer) }
+    }
+
+    /// Adds `middleware` below every layer that has been added so far.
+    ///
+    /// This is shorthand for adding a [`MiddlewareLayer`].
+    pub fn middleware<M>(self,
+                         middleware: M)
+                         -> FsStackBuilder<Stack<MiddlewareLayer<M>, L>> {
+        self.layer(MiddlewareLayer::new(middleware))
+    }
+
+    /// Returns the combined layer.
+    pub fn into_inner(self) -> L {
+        self.layer
+    }
+
+    /// Wraps `fs` in every layer of the stack.
+    pub fn build<F>(&self, fs: F) -> L::Fs
+        where L: FsLayer<F>
+    {
+        self.layer.layer(fs)
+    }
+}
+
+impl<F, L> FsLayer<F> for FsStackBuilder<L> where L: FsLayer<F>
+{
+    type Fs = L::Fs;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        self.layer.layer(inner)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use std::{
+        io,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex}
+    };
+
+    use async_trait::async_trait;
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{layer::FsMiddleware, mem_fs::MemFs, AsyncDirBuilderTrait};
+
+    /// Appends its name to a shared log whenever a directory is created.
+    #[derive(Debug)]
+    struct Recorder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<&'static str>>>
+    }
+
+    #[async_trait]
+    impl<F> FsMiddleware<F> for Recorder where F: AsyncFsTrait
+    {
+        async fn create_dir(&self,
+                            builder: &F::DirBuilder,
+                            path: &Path,
+                            _recursive: bool)
+                            -> io::Result<()> {
+            self.log.lock().unwrap().push(self.name);
+            builder.create(path).await
+        }
+
+        async fn canonicalize(&self,
+                              _fs: &F,
+                              _path: &Path)
+                              -> io::Result<PathBuf> {
+            Ok(PathBuf::from(self.name))
+        }
+    }
+
+    #[test]
+    fn first_layer_is_outermost() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let fs = FsStackBuilder::new().middleware(Recorder { name: "outer",
+                                                             log:
+                                                                 log.clone() })
+                                      .middleware(Recorder { name: "inner",
+                                                             log: log.clone() })
+                                      .layer(Identity::new())
+                                      .build(MemFs::new());
+        block_on(async {
+            fs.dir_builder().create("/dir").await.unwrap();
+            assert_eq!(*log.lock().unwrap(), vec!["outer", "inner"]);
+
+            assert_eq!(fs.canonicalize("/dir").await.unwrap(),
+                       PathBuf::from("outer"));
+            assert!(fs.metadata("/dir").await.unwrap().is_dir());
+        });
+    }
+}
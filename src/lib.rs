@@ -25,10 +25,59 @@
         rust_2018_idioms,
         rustdoc::missing_crate_level_docs)]
 
-#[doc(no_inline)]
-pub use std::fs::{FileType, Metadata, Permissions};
-
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod archive;
+#[cfg(feature = "async-std")]
+pub mod async_std_fs;
+#[cfg(feature = "chroot")]
+pub mod chroot;
+#[cfg(any(feature = "aes-gcm",
+          feature = "chacha20poly1305",
+          feature = "gzip",
+          feature = "lz4",
+          feature = "zstd"))]
+mod chunked;
+#[cfg(any(feature = "gzip", feature = "lz4", feature = "zstd"))]
+pub mod compress;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(feature = "copy")]
+pub mod copy;
+#[cfg(feature = "diff")]
+pub mod diff;
+#[cfg(any(feature = "aes-gcm", feature = "chacha20poly1305"))]
+pub mod encrypt;
+#[cfg(feature = "glob")]
+pub mod glob;
+pub mod layer;
+#[cfg(feature = "mem-fs")]
+pub mod mem_fs;
+pub mod metadata;
+#[cfg(feature = "mount")]
+pub mod mount;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+#[cfg(feature = "read-only")]
+pub mod read_only;
+#[cfg(feature = "smol")]
+pub mod smol_fs;
+#[cfg(feature = "std-fs")]
+pub mod std_fs;
+#[cfg(feature = "sync")]
+pub mod sync;
+#[cfg(feature = "tar")]
+pub mod tar_fs;
+#[cfg(feature = "tokio")]
+pub mod tokio_fs;
+#[cfg(feature = "tracing")]
+pub mod trace;
 pub mod traits;
+#[cfg(any(feature = "copy", feature = "diff", feature = "sync"))]
+mod tree;
+#[cfg(feature = "walk")]
+pub mod walk;
+#[cfg(feature = "zip")]
+pub mod zip_fs;
 #[doc(no_inline)]
 pub use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, Result, SeekFrom};
 
@@ -50,6 +99,8 @@ pub use async_sym_link_trait::AsyncSymLinkTrait;
 pub use futures_core::stream::Stream;
 #[doc(no_inline)]
 pub use futures_io::{AsyncBufRead, AsyncRead, AsyncSeek, AsyncWrite};
+#[doc(inline)]
+pub use metadata::{Extensions, FileType, Metadata, Permissions};
 pub use traits::*;
 
 //  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
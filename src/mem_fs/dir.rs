@@ -0,0 +1,266 @@
+//! Directory types for [`MemFs`].
+//!
+//! [`MemDirBuilder`] creates directories, and [`MemReadDir`] streams the
+//! [`MemDirEntry`]s of an existing directory.
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    vec
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+
+use super::{
+    tree::{already_exists, NodeKind, Tree},
+    MemFs
+};
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFsTrait, AsyncReadDirTrait,
+    FileType, Metadata
+};
+
+/// A builder for creating directories in a [`MemFs`].
+///
+/// These are obtained from [`MemFs::dir_builder()`][1].  The
+/// [`recursive()`][2] option is initially set to `false`.
+///
+/// [1]: crate::AsyncFsTrait::dir_builder
+/// [2]: AsyncDirBuilderTrait::recursive
+#[derive(Debug, Clone)]
+pub struct MemDirBuilder {
+    fs: MemFs,
+    recursive: bool
+}
+
+impl MemDirBuilder {
+    pub(super) fn new(fs: MemFs) -> Self {
+        MemDirBuilder { fs,
+                        recursive: false }
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for MemDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let mut tree = self.fs.lock();
+        if self.recursive {
+            create_dir_all(&mut tree, path.as_ref())
+        } else {
+            create_dir(&mut tree, path.as_ref())
+        }
+    }
+}
+
+fn empty_dir() -> NodeKind {
+    NodeKind::Dir { entries: Default::default(),
+                    parent: 0 }
+}
+
+/// Creates a single directory, which must not exist yet.
+fn create_dir(tree: &mut Tree, path: &Path) -> io::Result<()> {
+    let lookup = tree.resolve(path, false)?;
+    let (parent, name) = lookup.vacant()?;
+    tree.insert(parent, name, empty_dir())?;
+    Ok(())
+}
+
+/// Creates a directory and all of its missing ancestors.  It isn't an error
+/// for the directory to exist already.
+fn create_dir_all(tree: &mut Tree, path: &Path) -> io::Result<()> {
+    match tree.resolve(path, false) {
+        Ok(lookup) if lookup.ino.is_none() => {
+            let (parent, name) = lookup.named()?;
+            tree.insert(parent, name, empty_dir())?;
+            Ok(())
+        }
+        Ok(_) => {
+            let ino = tree.resolve(path, true)?.existing()?;
+            if tree.node(ino)?.is_dir() {
+                Ok(())
+            } else {
+                Err(already_exists())
+            }
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => {
+                    create_dir_all(tree, parent)?;
+                    create_dir_all(tree, path)
+                }
+                _ => Err(error)
+            }
+        }
+        Err(error) => Err(error)
+    }
+}
+
+/// A stream of entries in a directory of a [`MemFs`].
+///
+/// The entries are taken from a snapshot of the directory at the time it was
+/// read, sorted by name, so changes made while streaming aren't reflected.
+#[derive(Debug)]
+pub struct MemReadDir {
+    entries: vec::IntoIter<MemDirEntry>
+}
+
+impl From<Vec<MemDirEntry>> for MemReadDir {
+    fn from(entries: Vec<MemDirEntry>) -> Self {
+        MemReadDir { entries: entries.into_iter() }
+    }
+}
+
+impl Stream for MemReadDir {
+    type Item = io::Result<MemDirEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 _cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.entries.next().map(Ok))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl AsyncReadDirTrait<MemDirEntry> for MemReadDir {}
+
+/// An entry in a directory of a [`MemFs`].
+///
+/// [`path()`][1], [`file_name()`][2], and [`file_type()`][3] are answered
+/// from what was recorded when the directory was read.
+///
+/// [1]: AsyncDirEntryTrait::path
+/// [2]: AsyncDirEntryTrait::file_name
+/// [3]: AsyncDirEntryTrait::file_type
+#[derive(Debug, Clone)]
+pub struct MemDirEntry {
+    fs: MemFs,
+    path: PathBuf,
+    name: OsString,
+    file_type: FileType
+}
+
+impl MemDirEntry {
+    pub(super) fn new(fs: MemFs,
+                      path: PathBuf,
+                      name: OsString,
+                      file_type: FileType)
+                      -> Self {
+        MemDirEntry { fs,
+                      path,
+                      name,
+                      file_type }
+    }
+}
+
+#[async_trait]
+impl AsyncDirEntryTrait for MemDirEntry {
+    async fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.fs.metadata(&self.path).await
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.file_type)
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{AsyncFileBuilderTrait, AsyncSymLinkTrait};
+
+    #[test]
+    fn recursive_create_and_read_dir() {
+        let fs = MemFs::new();
+        block_on(async {
+            assert!(fs.dir_builder().create("/a/b").await.is_err());
+            fs.dir_builder()
+              .recursive(true)
+              .create("/a/b")
+              .await
+              .unwrap();
+            fs.dir_builder()
+              .recursive(true)
+              .create("/a/b")
+              .await
+              .unwrap();
+            assert_eq!(fs.dir_builder()
+                         .create("/a/b")
+                         .await
+                         .unwrap_err()
+                         .kind(),
+                       io::ErrorKind::AlreadyExists);
+
+            fs.open_options()
+              .write(true)
+              .create(true)
+              .open("/a/file")
+              .await
+              .unwrap();
+            fs.symlink("/a/link", "b").await.unwrap();
+
+            let entries: Vec<_> = fs.read_dir("/a")
+                                    .await
+                                    .unwrap()
+                                    .map(Result::unwrap)
+                                    .collect()
+                                    .await;
+            let mut seen = Vec::new();
+            for entry in &entries {
+                seen.push((entry.file_name().await,
+                           entry.file_type().await.unwrap(),
+                           entry.metadata().await.unwrap().file_type()));
+            }
+            assert_eq!(seen,
+                       vec![(OsString::from("b"),
+                             FileType::Dir,
+                             FileType::Dir),
+                            (OsString::from("file"),
+                             FileType::File,
+                             FileType::File),
+                            (OsString::from("link"),
+                             FileType::Symlink,
+                             FileType::Dir)]);
+            assert_eq!(entries[0].path().await, PathBuf::from("/a/b"));
+
+            let error = fs.dir_builder()
+                          .recursive(true)
+                          .create("/a/file/c")
+                          .await
+                          .unwrap_err();
+            assert_eq!(error.to_string(), "not a directory");
+        });
+    }
+}
@@ -0,0 +1,454 @@
+//! [`MemOpenOptions`] opens [`MemFile`]s in a [`MemFs`].
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+    time::SystemTime
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{
+    tree::{
+        already_exists, invalid_input, is_a_directory, not_found, NodeKind
+    },
+    MemFs
+};
+use crate::{AsyncFileBuilderTrait, AsyncFileTrait, Metadata, Permissions};
+
+/// Options for opening a [`MemFile`].
+///
+/// These are obtained from [`MemFs::open_options()`][1], and follow the same
+/// rules as [`std::fs::OpenOptions`]: every option starts out set to `false`,
+/// and [`truncate()`][2], [`create()`][3], and [`create_new()`][4] all require
+/// write access.
+///
+/// [1]: crate::AsyncFsTrait::open_options
+/// [2]: AsyncFileBuilderTrait::truncate
+/// [3]: AsyncFileBuilderTrait::create
+/// [4]: AsyncFileBuilderTrait::create_new
+#[derive(Debug, Clone)]
+pub struct MemOpenOptions {
+    fs: MemFs,
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool
+}
+
+impl MemOpenOptions {
+    pub(super) fn new(fs: MemFs) -> Self {
+        MemOpenOptions { fs,
+                         read: false,
+                         write: false,
+                         append: false,
+                         truncate: false,
+                         create: false,
+                         create_new: false }
+    }
+
+    /// Checks that the combination of options makes sense, using the same
+    /// rules as the standard library.
+    fn validate(&self) -> io::Result<()> {
+        match (self.write, self.append) {
+            (true, false) => {}
+            (false, false) => {
+                if self.truncate || self.create || self.create_new {
+                    return Err(invalid_input("creating or truncating a file \
+                                              requires write or append \
+                                              access"));
+                }
+                if !self.read {
+                    return Err(invalid_input("at least one of read, write, \
+                                              or append access is \
+                                              required"));
+                }
+            }
+            (_, true) => {
+                if self.truncate && !self.create_new {
+                    return Err(invalid_input("a file can't be truncated \
+                                              and appended to at the same \
+                                              time"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AsyncFileBuilderTrait for MemOpenOptions {
+    type File = MemFile;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        self.validate()?;
+
+        let mut tree = self.fs.lock();
+        // Like `O_EXCL`, `create_new` refuses to follow a final symlink.
+        let lookup = tree.resolve(path.as_ref(), !self.create_new)?;
+        let ino = match lookup.ino {
+            Some(_) if self.create_new => return Err(already_exists()),
+            Some(ino) => ino,
+            None if self.create || self.create_new => {
+                let (parent, name) = lookup.named()?;
+                tree.insert(parent, name, NodeKind::File(Vec::new()))?
+            }
+            None => return Err(not_found())
+        };
+
+        let node = tree.node_mut(ino)?;
+        match &mut node.kind {
+            NodeKind::File(data) => {
+                if self.truncate && !data.is_empty() {
+                    data.clear();
+                    node.modified = SystemTime::now();
+                }
+            }
+            NodeKind::Dir { .. } => return Err(is_a_directory()),
+            NodeKind::Symlink(_) => {
+                unreachable!("final symlinks are followed when opening files")
+            }
+        }
+        tree.open(ino)?;
+
+        Ok(MemFile { fs: self.fs.clone(),
+                     ino,
+                     pos: 0,
+                     read: self.read,
+                     write: self.write || self.append,
+                     append: self.append })
+    }
+}
+
+/// An open file in a [`MemFs`].
+///
+/// Each `MemFile` has its own cursor, but the contents are shared with every
+/// other file that has the same node open, so writes are visible to all of
+/// them immediately.  Flushing and syncing are no-ops.
+#[derive(Debug)]
+pub struct MemFile {
+    fs: MemFs,
+    ino: u64,
+    pos: u64,
+    read: bool,
+    write: bool,
+    append: bool
+}
+
+impl MemFile {
+    /// Returns an error unless this file was opened with write access.
+    fn check_writable(&self) -> io::Result<()> {
+        if self.write {
+            Ok(())
+        } else {
+            Err(not_opened_for("writing"))
+        }
+    }
+
+    /// Runs `f` on the contents of this file, updating its modification time
+    /// afterwards if `modify` is `true` and `f` succeeded.
+    fn with_data<F, T>(&self, modify: bool, f: F) -> io::Result<T>
+        where F: FnOnce(&mut Vec<u8>) -> io::Result<T>
+    {
+        let mut tree = self.fs.lock();
+        let node = tree.node_mut(self.ino)?;
+        let result = match &mut node.kind {
+            NodeKind::File(data) => f(data)?,
+            _ => return Err(is_a_directory())
+        };
+        if modify {
+            node.modified = SystemTime::now();
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for MemFile {
+    fn drop(&mut self) {
+        self.fs.lock().close(self.ino);
+    }
+}
+
+#[async_trait]
+impl AsyncFileTrait for MemFile {
+    async fn sync_all(&self) -> io::Result<()> {
+        self.fs.lock().node(self.ino).map(|_| ())
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.fs.lock().node(self.ino).map(|_| ())
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.check_writable()?;
+        let size = usize::try_from(size).map_err(|_| too_large())?;
+        self.with_data(true, |data| {
+                data.resize(size, 0);
+                Ok(())
+            })
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.fs.lock().metadata(self.ino)
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        self.fs.lock().node_mut(self.ino)?.permissions = perm;
+        Ok(())
+    }
+}
+
+impl AsyncRead for MemFile {
+    fn poll_read(mut self: Pin<&mut Self>,
+                 _cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        if !self.read {
+            return Poll::Ready(Err(not_opened_for("reading")));
+        }
+
+        let pos = self.pos;
+        let read =
+            self.with_data(false, |data| {
+                    let start = usize::try_from(pos).unwrap_or(usize::MAX)
+                                                    .min(data.len());
+                    let len = buf.len().min(data.len() - start);
+                    buf[..len].copy_from_slice(&data[start..start + len]);
+                    Ok(len)
+                });
+        if let Ok(len) = read {
+            self.pos += len as u64;
+        }
+        Poll::Ready(read)
+    }
+}
+
+impl AsyncWrite for MemFile {
+    fn poll_write(mut self: Pin<&mut Self>,
+                  _cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        if let Err(error) = self.check_writable() {
+            return Poll::Ready(Err(error));
+        }
+
+        let (pos, append) = (self.pos, self.append);
+        let written = self.with_data(true, |data| {
+                              let start = if append {
+                                  data.len()
+                              } else {
+                                  usize::try_from(pos).map_err(|_| too_large())?
+                              };
+                              let end = start.checked_add(buf.len())
+                                             .ok_or_else(too_large)?;
+                              if data.len() < end {
+                                  data.resize(end, 0);
+                              }
+                              data[start..end].copy_from_slice(buf);
+                              Ok(end)
+                          });
+        Poll::Ready(written.map(|end| {
+                               self.pos = end as u64;
+                               buf.len()
+                           }))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  _cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  _cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for MemFile {
+    fn poll_seek(mut self: Pin<&mut Self>,
+                 _cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Ok(Some(offset)),
+            SeekFrom::End(delta) => self.with_data(false, |data| {
+                                            Ok(offset(data.len() as u64, delta))
+                                        }),
+            SeekFrom::Current(delta) => Ok(offset(self.pos, delta))
+        };
+        Poll::Ready(match target {
+                        Ok(Some(target)) => {
+                            self.pos = target;
+                            Ok(target)
+                        }
+                        Ok(None) => {
+                            Err(invalid_input("invalid seek to a negative or \
+                                               overflowing position"))
+                        }
+                        Err(error) => Err(error)
+                    })
+    }
+}
+
+/// Applies a signed `delta` to `base`, returning `None` if the result doesn't
+/// fit in a `u64`.
+fn offset(base: u64, delta: i64) -> Option<u64> {
+    if delta >= 0 {
+        base.checked_add(delta as u64)
+    } else {
+        base.checked_sub(delta.unsigned_abs())
+    }
+}
+
+fn not_opened_for(access: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   format!("file not opened for {}", access))
+}
+
+fn too_large() -> io::Error {
+    invalid_input("file too large")
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{
+        future::block_on, AsyncReadExt, AsyncSeekExt, AsyncWriteExt
+    };
+
+    use super::*;
+    use crate::{AsyncFsTrait, AsyncSymLinkTrait};
+
+    #[test]
+    fn create_new_conflicts() {
+        let fs = MemFs::new();
+        block_on(async {
+            let mut options = fs.open_options();
+            options.read(true).write(true).create_new(true);
+
+            let mut file = options.open("/file").await.unwrap();
+            file.write_all(b"hello world").await.unwrap();
+            file.seek(SeekFrom::Start(6)).await.unwrap();
+            let mut buf = String::new();
+            file.read_to_string(&mut buf).await.unwrap();
+            assert_eq!(buf, "world");
+
+            let error = options.open("/file").await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+            fs.symlink("/dangling", "/missing").await.unwrap();
+            let error = options.open("/dangling").await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+            assert!(fs.metadata("/missing").await.is_err());
+
+            let error = fs.open_options()
+                          .create(true)
+                          .open("/other")
+                          .await
+                          .unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn set_len_zero_fills_and_keeps_cursor() {
+        let fs = MemFs::new();
+        block_on(async {
+            let mut file = fs.open_options()
+                             .read(true)
+                             .write(true)
+                             .create(true)
+                             .open("/file")
+                             .await
+                             .unwrap();
+            file.write_all(b"abc").await.unwrap();
+
+            file.set_len(6).await.unwrap();
+            assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 3);
+            assert_eq!(file.metadata().await.unwrap().len(), 6);
+
+            file.seek(SeekFrom::Start(0)).await.unwrap();
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).await.unwrap();
+            assert_eq!(contents, b"abc\0\0\0");
+
+            file.set_len(1).await.unwrap();
+            assert!(file.seek(SeekFrom::End(-2)).await.is_err());
+            file.seek(SeekFrom::Start(4)).await.unwrap();
+            file.write_all(b"z").await.unwrap();
+            assert_eq!(file.metadata().await.unwrap().len(), 5);
+        });
+    }
+
+    #[test]
+    fn append_and_access_modes() {
+        let fs = MemFs::new();
+        block_on(async {
+            let mut file = fs.open_options()
+                             .append(true)
+                             .create(true)
+                             .open("/log")
+                             .await
+                             .unwrap();
+            file.write_all(b"one ").await.unwrap();
+            file.seek(SeekFrom::Start(0)).await.unwrap();
+            file.write_all(b"two").await.unwrap();
+            assert!(file.read(&mut [0; 4]).await.is_err());
+
+            let mut reader =
+                fs.open_options().read(true).open("/log").await.unwrap();
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, "one two");
+            assert!(reader.write(b"x").await.is_err());
+            assert!(reader.set_len(0).await.is_err());
+        });
+    }
+}
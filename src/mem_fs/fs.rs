@@ -0,0 +1,439 @@
+//! [`MemFs`] implements [`AsyncFsTrait`] on top of an in-memory inode tree.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard
+    }
+};
+
+use async_trait::async_trait;
+
+use super::{
+    tree::{
+        self, directory_not_empty, invalid_input, is_a_directory,
+        not_a_directory, NodeKind, Tree
+    },
+    MemDirBuilder, MemDirEntry, MemOpenOptions, MemReadDir
+};
+use crate::{AsyncFsTrait, AsyncSymLinkTrait, Metadata, Permissions};
+
+/// The device number handed to the next [`MemFs`], so that every instance
+/// reports a distinct [`Metadata::dev()`].
+static NEXT_DEV: AtomicU64 = AtomicU64::new(1);
+
+/// A filesystem that lives entirely in memory.
+///
+/// A new `MemFs` contains nothing but an empty root directory.  Paths are
+/// resolved against that root whether or not they are absolute, and `..` at
+/// the root stays at the root.  Regular files, directories, symlinks, and hard
+/// links are all supported, and behave the way they do on a POSIX system:
+///
+/// - [`rename()`][1] replaces an existing destination, as long as it isn't a
+///   non-empty directory.
+/// - Opening a file with [`create_new()`][2] fails if anything, even a
+///   dangling symlink, already exists at that path.
+/// - [`remove_dir()`][3] fails on directories that aren't empty.
+/// - [`set_len()`][4] fills any new space with zeros.
+/// - A removed file stays readable and writable through the [`MemFile`][5]s
+///   that already have it open.
+///
+/// Permissions are recorded and reported, but never enforced.
+///
+/// Cloning a `MemFs` is cheap, and the clone refers to the same tree.  Two
+/// filesystems created with [`MemFs::new()`] are completely independent.
+///
+/// [1]: AsyncFsTrait::rename
+/// [2]: crate::AsyncFileBuilderTrait::create_new
+/// [3]: AsyncFsTrait::remove_dir
+/// [4]: crate::AsyncFileTrait::set_len
+/// [5]: super::MemFile
+#[derive(Clone)]
+pub struct MemFs {
+    tree: Arc<Mutex<Tree>>
+}
+
+impl MemFs {
+    /// Creates a new, empty filesystem.
+    pub fn new() -> Self {
+        let dev = NEXT_DEV.fetch_add(1, Ordering::Relaxed);
+        MemFs { tree: Arc::new(Mutex::new(Tree::new(dev))) }
+    }
+
+    pub(super) fn lock(&self) -> MutexGuard<'_, Tree> {
+        tree::lock(&self.tree)
+    }
+}
+
+impl Default for MemFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MemFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemFs").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for MemFs {
+    type DirBuilder = MemDirBuilder;
+    type DirEntry = MemDirEntry;
+    type FileBuilder = MemOpenOptions;
+    type ReadDir = MemReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        MemDirBuilder::new(self.clone())
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        MemOpenOptions::new(self.clone())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let tree = self.lock();
+        let lookup = tree.resolve(path.as_ref(), true)?;
+        tree.canonical_path(&lookup)
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let from = tree.resolve(src.as_ref(), false)?;
+        let ino = from.existing()?;
+        let (src_parent, src_name) = from.named()?;
+        let to = tree.resolve(dst.as_ref(), false)?;
+        let (dst_parent, dst_name) = to.named()?;
+        let is_dir = tree.node(ino)?.is_dir();
+
+        if let Some(existing) = to.ino {
+            if existing == ino {
+                return Ok(());
+            }
+            match (is_dir, tree.node(existing)?.is_dir()) {
+                (true, false) => return Err(not_a_directory()),
+                (false, true) => return Err(is_a_directory()),
+                (true, true) if !tree.entries(existing)?.is_empty() => {
+                    return Err(directory_not_empty());
+                }
+                _ => {}
+            }
+        }
+        if is_dir && tree.is_within(dst_parent, ino) {
+            return Err(invalid_input("cannot move a directory into itself"));
+        }
+
+        tree.relink(src_parent, src_name, dst_parent, dst_name)
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let ino = tree.resolve(path.as_ref(), true)?.existing()?;
+        tree.node_mut(ino)?.permissions = perm;
+        Ok(())
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let ino = tree.resolve(src.as_ref(), false)?.existing()?;
+        if tree.node(ino)?.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                      "cannot hard link a directory"));
+        }
+        let to = tree.resolve(dst.as_ref(), false)?;
+        let (parent, name) = to.vacant()?;
+        tree.link(parent, name, ino)
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let tree = self.lock();
+        let ino = tree.resolve(path.as_ref(), false)?.existing()?;
+        match &tree.node(ino)?.kind {
+            NodeKind::Symlink(target) => Ok(target.clone()),
+            _ => Err(invalid_input("not a symbolic link"))
+        }
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let tree = self.lock();
+        let ino = tree.resolve(path.as_ref(), false)?.existing()?;
+        tree.metadata(ino)
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let tree = self.lock();
+        let ino = tree.resolve(path.as_ref(), true)?.existing()?;
+        tree.metadata(ino)
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let ino = tree.resolve(src.as_ref(), true)?.existing()?;
+        let node = tree.node(ino)?;
+        let (data, permissions) = match &node.kind {
+            NodeKind::File(data) => (data.clone(), node.permissions),
+            _ => {
+                return Err(invalid_input("the source path is not an existing \
+                                          regular file"));
+            }
+        };
+        let len = data.len() as u64;
+
+        let to = tree.resolve(dst.as_ref(), true)?;
+        let target = match to.ino {
+            Some(target) => target,
+            None => {
+                let (parent, name) = to.named()?;
+                tree.insert(parent, name, NodeKind::File(Vec::new()))?
+            }
+        };
+        let node = tree.node_mut(target)?;
+        match &mut node.kind {
+            NodeKind::File(contents) => *contents = data,
+            _ => return Err(is_a_directory())
+        }
+        node.permissions = permissions;
+        tree.touch(target)?;
+        Ok(len)
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let lookup = tree.resolve(path.as_ref(), false)?;
+        if tree.node(lookup.existing()?)?.is_dir() {
+            return Err(is_a_directory());
+        }
+        let (parent, name) = lookup.named()?;
+        tree.unlink(parent, name)
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let tree = self.lock();
+        let ino = tree.resolve(path, true)?.existing()?;
+        let mut entries = Vec::new();
+        for (name, child) in tree.entries(ino)? {
+            entries.push(MemDirEntry::new(self.clone(),
+                                          path.join(name),
+                                          name.clone(),
+                                          tree.node(*child)?.file_type()));
+        }
+        Ok(entries.into())
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let lookup = tree.resolve(path.as_ref(), false)?;
+        if !tree.entries(lookup.existing()?)?.is_empty() {
+            return Err(directory_not_empty());
+        }
+        let (parent, name) = lookup.named()?;
+        tree.unlink(parent, name)
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let lookup = tree.resolve(path.as_ref(), false)?;
+        if let NodeKind::File(_) = tree.node(lookup.existing()?)?.kind {
+            return Err(not_a_directory());
+        }
+        let (parent, name) = lookup.named()?;
+        tree.unlink(parent, name)
+    }
+}
+
+#[async_trait]
+impl AsyncSymLinkTrait for MemFs {
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let mut tree = self.lock();
+        let lookup = tree.resolve(src.as_ref(), false)?;
+        let (parent, name) = lookup.vacant()?;
+        tree.insert(parent, name, NodeKind::Symlink(dst.as_ref().to_owned()))?;
+        Ok(())
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{AsyncDirBuilderTrait, AsyncFileBuilderTrait, FileType};
+
+    async fn write(fs: &MemFs, path: &str, contents: &[u8]) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create(true)
+                         .truncate(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(contents).await.unwrap();
+    }
+
+    async fn read(fs: &MemFs, path: &str) -> Vec<u8> {
+        let mut file = fs.open_options().read(true).open(path).await.unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).await.unwrap();
+        contents
+    }
+
+    #[test]
+    fn rename_overwrites_destination() {
+        let fs = MemFs::new();
+        block_on(async {
+            write(&fs, "/src", b"new").await;
+            write(&fs, "/dst", b"old").await;
+
+            fs.rename("/src", "/dst").await.unwrap();
+
+            assert!(fs.metadata("/src").await.is_err());
+            assert_eq!(read(&fs, "/dst").await, b"new");
+        });
+    }
+
+    #[test]
+    fn rename_directories() {
+        let fs = MemFs::new();
+        block_on(async {
+            fs.dir_builder()
+              .recursive(true)
+              .create("/a/b")
+              .await
+              .unwrap();
+            fs.dir_builder().create("/c").await.unwrap();
+            write(&fs, "/c/file", b"").await;
+
+            let error = fs.rename("/a", "/c").await.unwrap_err();
+            assert_eq!(error.to_string(), "directory not empty");
+            let error = fs.rename("/a", "/a/b/d").await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+            fs.remove_file("/c/file").await.unwrap();
+            fs.rename("/a", "/c").await.unwrap();
+            assert!(fs.metadata("/c/b").await.unwrap().is_dir());
+            assert_eq!(fs.canonicalize("/c/b/..").await.unwrap(),
+                       PathBuf::from("/c"));
+        });
+    }
+
+    #[test]
+    fn remove_dir_requires_an_empty_directory() {
+        let fs = MemFs::new();
+        block_on(async {
+            fs.dir_builder().create("/dir").await.unwrap();
+            write(&fs, "/dir/file", b"").await;
+
+            assert!(fs.remove_dir("/dir").await.is_err());
+            assert!(fs.remove_file("/dir").await.is_err());
+            assert!(fs.remove_dir("/").await.is_err());
+
+            fs.remove_dir_all("/dir").await.unwrap();
+            assert_eq!(fs.metadata("/dir").await.unwrap_err().kind(),
+                       io::ErrorKind::NotFound);
+        });
+    }
+
+    #[test]
+    fn symlinks_and_hard_links() {
+        let fs = MemFs::new();
+        block_on(async {
+            write(&fs, "/target", b"12345").await;
+            fs.symlink("/link", "target").await.unwrap();
+            fs.hard_link("/target", "/other").await.unwrap();
+
+            assert_eq!(fs.read_link("/link").await.unwrap(),
+                       PathBuf::from("target"));
+            assert_eq!(fs.metadata("/link").await.unwrap().len(), 5);
+            assert_eq!(fs.symlink_metadata("/link").await.unwrap().file_type(),
+                       FileType::Symlink);
+            assert_eq!(fs.canonicalize("/link").await.unwrap(),
+                       PathBuf::from("/target"));
+
+            let metadata = fs.metadata("/other").await.unwrap();
+            assert_eq!(metadata.nlink(), Some(2));
+            assert_eq!(metadata.ino(),
+                       fs.metadata("/target").await.unwrap().ino());
+
+            fs.remove_file("/target").await.unwrap();
+            assert_eq!(read(&fs, "/other").await, b"12345");
+            assert!(fs.metadata("/link").await.is_err());
+            assert!(fs.symlink("/link", "other").await.is_err());
+        });
+    }
+
+    #[test]
+    fn copy_and_set_permissions() {
+        let fs = MemFs::new();
+        block_on(async {
+            write(&fs, "/src", b"hello").await;
+            fs.set_permissions("/src", Permissions::new(true))
+              .await
+              .unwrap();
+
+            assert_eq!(fs.copy("/src", "/dst").await.unwrap(), 5);
+            assert_eq!(read(&fs, "/dst").await, b"hello");
+            let metadata = fs.metadata("/dst").await.unwrap();
+            assert!(metadata.permissions().readonly());
+        });
+    }
+
+    #[test]
+    fn instances_are_independent() {
+        let first = MemFs::new();
+        let second = MemFs::new();
+        let clone = first.clone();
+        block_on(async {
+            write(&first, "/file", b"").await;
+            assert!(clone.metadata("/file").await.is_ok());
+            assert!(second.metadata("/file").await.is_err());
+            assert_ne!(first.metadata("/").await.unwrap().dev(),
+                       second.metadata("/").await.unwrap().dev());
+        });
+    }
+}
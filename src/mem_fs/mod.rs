@@ -0,0 +1,40 @@
+//! A backend that keeps the whole filesystem in memory.
+//!
+//! This is mostly useful for tests, and as a scratch space that never touches
+//! the disk.  It supports regular files, directories, symlinks, and hard links,
+//! and follows POSIX semantics closely enough that code tested against it
+//! should behave the same on a real filesystem.
+//!
+//! The entry point is [`MemFs`], which implements [`AsyncFsTrait`][1] and
+//! [`AsyncSymLinkTrait`][2].  Everything else in this module is reached
+//! through it:
+//!
+//! - [`MemOpenOptions`] is returned by [`MemFs::open_options()`][3], and opens
+//!   [`MemFile`]s.
+//! - [`MemDirBuilder`] is returned by [`MemFs::dir_builder()`][4].
+//! - [`MemReadDir`] is returned by [`MemFs::read_dir()`][5], and yields
+//!   [`MemDirEntry`]s.
+//!
+//! Every operation completes without ever returning [`Poll::Pending`][6], so
+//! this backend works with any executor.
+//!
+//! This module is only available when the `mem-fs` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait
+//! [2]: crate::AsyncSymLinkTrait
+//! [3]: crate::AsyncFsTrait::open_options
+//! [4]: crate::AsyncFsTrait::dir_builder
+//! [5]: crate::AsyncFsTrait::read_dir
+//! [6]: std::task::Poll::Pending
+
+mod dir;
+mod file;
+mod fs;
+mod tree;
+
+#[doc(inline)]
+pub use dir::{MemDirBuilder, MemDirEntry, MemReadDir};
+#[doc(inline)]
+pub use file::{MemFile, MemOpenOptions};
+#[doc(inline)]
+pub use fs::MemFs;
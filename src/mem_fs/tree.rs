@@ -0,0 +1,547 @@
+//! The inode tree that backs a [`MemFs`][1].
+//!
+//! Every object in the filesystem is a [`Node`] that is identified by its
+//! inode number.  Directories map names to inode numbers, which is what allows
+//! several names to refer to the same file (hard links).  A node is only
+//! dropped once nothing links to it and no [`MemFile`][2] has it open, which
+//! gives the usual POSIX behavior of being able to keep using a file after it
+//! has been removed.
+//!
+//! [1]: super::MemFs
+//! [2]: super::MemFile
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    ffi::{OsStr, OsString},
+    io,
+    path::{Component, Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::SystemTime
+};
+
+use crate::{FileType, Metadata, Permissions};
+
+/// The inode number of the root directory.
+pub(super) const ROOT: u64 = 1;
+
+/// The maximum number of symlinks that are followed while resolving a single
+/// path, matching Linux's limit.
+const MAX_SYMLINKS: usize = 40;
+
+/// The contents of a node.
+#[derive(Debug)]
+pub(super) enum NodeKind {
+    /// A regular file and its contents.
+    File(Vec<u8>),
+
+    /// A directory, its entries, and the inode of its parent.  The root
+    /// directory is its own parent.
+    Dir {
+        entries: BTreeMap<OsString, u64>,
+        parent: u64
+    },
+
+    /// A symbolic link and its target.
+    Symlink(PathBuf)
+}
+
+/// A single object in the filesystem.
+#[derive(Debug)]
+pub(super) struct Node {
+    pub(super) kind: NodeKind,
+    pub(super) permissions: Permissions,
+    pub(super) created: SystemTime,
+    pub(super) modified: SystemTime,
+    pub(super) accessed: SystemTime,
+
+    /// The number of directory entries that refer to this node.
+    nlink: u64,
+
+    /// The number of open files that refer to this node.
+    open: u64
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Self {
+        let now = SystemTime::now();
+        let mode = match kind {
+            NodeKind::File(_) => 0o644,
+            NodeKind::Dir { .. } => 0o755,
+            NodeKind::Symlink(_) => 0o777
+        };
+        Node { kind,
+               permissions: Permissions::from_mode(mode),
+               created: now,
+               modified: now,
+               accessed: now,
+               nlink: 1,
+               open: 0 }
+    }
+
+    pub(super) fn file_type(&self) -> FileType {
+        match self.kind {
+            NodeKind::File(_) => FileType::File,
+            NodeKind::Dir { .. } => FileType::Dir,
+            NodeKind::Symlink(_) => FileType::Symlink
+        }
+    }
+
+    pub(super) fn is_dir(&self) -> bool {
+        matches!(self.kind, NodeKind::Dir { .. })
+    }
+}
+
+/// The result of resolving a path.
+///
+/// `parent` and `name` describe where the final component lives (after any
+/// symlinks were followed), and `ino` is the node found there, if any.  `name`
+/// is `None` when the path ends at the root or at a `..` component, in which
+/// case `ino` is always `Some`.
+#[derive(Debug)]
+pub(super) struct Lookup {
+    pub(super) parent: u64,
+    pub(super) name: Option<OsString>,
+    pub(super) ino: Option<u64>
+}
+
+impl Lookup {
+    /// Returns the node that was found, or a "not found" error.
+    pub(super) fn existing(&self) -> io::Result<u64> {
+        self.ino.ok_or_else(not_found)
+    }
+
+    /// Returns the parent and name of a final component that must not exist
+    /// yet.
+    pub(super) fn vacant(&self) -> io::Result<(u64, &OsStr)> {
+        match (&self.name, self.ino) {
+            (Some(name), None) => Ok((self.parent, name)),
+            _ => Err(already_exists())
+        }
+    }
+
+    /// Returns the parent and name of the final component.
+    pub(super) fn named(&self) -> io::Result<(u64, &OsStr)> {
+        match &self.name {
+            Some(name) => Ok((self.parent, name)),
+            None => Err(invalid_input("path must end in a file name"))
+        }
+    }
+}
+
+/// The inode tree.
+#[derive(Debug)]
+pub(super) struct Tree {
+    nodes: HashMap<u64, Node>,
+    next_ino: u64,
+    dev: u64
+}
+
+impl Tree {
+    pub(super) fn new(dev: u64) -> Self {
+        let mut nodes = HashMap::new();
+        nodes.insert(ROOT,
+                     Node::new(NodeKind::Dir { entries: BTreeMap::new(),
+                                               parent: ROOT }));
+        Tree { nodes,
+               next_ino: ROOT + 1,
+               dev }
+    }
+
+    pub(super) fn node(&self, ino: u64) -> io::Result<&Node> {
+        self.nodes.get(&ino).ok_or_else(not_found)
+    }
+
+    pub(super) fn node_mut(&mut self, ino: u64) -> io::Result<&mut Node> {
+        self.nodes.get_mut(&ino).ok_or_else(not_found)
+    }
+
+    pub(super) fn entries(&self,
+                          ino: u64)
+                          -> io::Result<&BTreeMap<OsString, u64>> {
+        match &self.node(ino)?.kind {
+            NodeKind::Dir { entries, .. } => Ok(entries),
+            _ => Err(not_a_directory())
+        }
+    }
+
+    fn entries_mut(&mut self,
+                   ino: u64)
+                   -> io::Result<&mut BTreeMap<OsString, u64>> {
+        match &mut self.node_mut(ino)?.kind {
+            NodeKind::Dir { entries, .. } => Ok(entries),
+            _ => Err(not_a_directory())
+        }
+    }
+
+    fn parent_of(&self, ino: u64) -> io::Result<u64> {
+        match self.node(ino)?.kind {
+            NodeKind::Dir { parent, .. } => Ok(parent),
+            _ => Err(not_a_directory())
+        }
+    }
+
+    /// Resolves `path` relative to the root.
+    ///
+    /// Symlinks in intermediate components are always followed.  A symlink in
+    /// the final component is only followed if `follow` is `true`.
+    pub(super) fn resolve(&self,
+                          path: &Path,
+                          follow: bool)
+                          -> io::Result<Lookup> {
+        if path.as_os_str().is_empty() {
+            return Err(not_found());
+        }
+
+        let mut queue: VecDeque<PathBuf> =
+            path.components()
+                .map(|c| PathBuf::from(c.as_os_str()))
+                .collect();
+        let mut current = ROOT;
+        let mut links = 0;
+
+        while let Some(component) = queue.pop_front() {
+            match component.components().next() {
+                Some(Component::Prefix(_)) | Some(Component::RootDir) => {
+                    current = ROOT;
+                }
+                Some(Component::CurDir) | None => {
+                    self.entries(current)?;
+                }
+                Some(Component::ParentDir) => {
+                    current = self.parent_of(current)?;
+                }
+                Some(Component::Normal(name)) => {
+                    let is_last = queue.is_empty();
+                    let child = match self.entries(current)?.get(name) {
+                        Some(child) => *child,
+                        None if is_last => {
+                            return Ok(Lookup { parent: current,
+                                               name: Some(name.to_owned()),
+                                               ino: None });
+                        }
+                        None => return Err(not_found())
+                    };
+
+                    if let NodeKind::Symlink(target) = &self.node(child)?.kind {
+                        if !is_last || follow {
+                            links += 1;
+                            if links > MAX_SYMLINKS {
+                                return Err(too_many_links());
+                            }
+                            let target =
+                                target.components()
+                                      .map(|c| PathBuf::from(c.as_os_str()))
+                                      .collect::<Vec<_>>();
+                            for component in target.into_iter().rev() {
+                                queue.push_front(component);
+                            }
+                            continue;
+                        }
+                    }
+
+                    if is_last {
+                        return Ok(Lookup { parent: current,
+                                           name: Some(name.to_owned()),
+                                           ino: Some(child) });
+                    }
+                    current = child;
+                }
+            }
+        }
+
+        // The path ended at the root, at `.`, or at `..`.
+        self.entries(current)?;
+        Ok(Lookup { parent: self.parent_of(current)?,
+                    name: None,
+                    ino: Some(current) })
+    }
+
+    /// Returns the absolute path of the directory `ino`.
+    pub(super) fn dir_path(&self, mut ino: u64) -> io::Result<PathBuf> {
+        let mut names = Vec::new();
+        while ino != ROOT {
+            let parent = self.parent_of(ino)?;
+            let name = self.entries(parent)?
+                           .iter()
+                           .find(|(_, child)| **child == ino)
+                           .map(|(name, _)| name.clone())
+                           .ok_or_else(not_found)?;
+            names.push(name);
+            ino = parent;
+        }
+
+        let mut path = PathBuf::from("/");
+        path.extend(names.iter().rev());
+        Ok(path)
+    }
+
+    /// Returns the canonical path of whatever `lookup` found.
+    pub(super) fn canonical_path(&self,
+                                 lookup: &Lookup)
+                                 -> io::Result<PathBuf> {
+        let ino = lookup.existing()?;
+        match &lookup.name {
+            Some(name) if !self.node(ino)?.is_dir() => {
+                Ok(self.dir_path(lookup.parent)?.join(name))
+            }
+            _ => self.dir_path(ino)
+        }
+    }
+
+    /// Creates a new node and links it into `parent` under `name`.
+    pub(super) fn insert(&mut self,
+                         parent: u64,
+                         name: &OsStr,
+                         kind: NodeKind)
+                         -> io::Result<u64> {
+        if self.entries(parent)?.contains_key(name) {
+            return Err(already_exists());
+        }
+
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        let kind = match kind {
+            NodeKind::Dir { entries, .. } => NodeKind::Dir { entries, parent },
+            kind => kind
+        };
+        self.nodes.insert(ino, Node::new(kind));
+        self.entries_mut(parent)?.insert(name.to_owned(), ino);
+        self.touch(parent)?;
+        Ok(ino)
+    }
+
+    /// Adds another name for the existing node `ino`.
+    pub(super) fn link(&mut self,
+                       parent: u64,
+                       name: &OsStr,
+                       ino: u64)
+                       -> io::Result<()> {
+        if self.entries(parent)?.contains_key(name) {
+            return Err(already_exists());
+        }
+        self.node_mut(ino)?.nlink += 1;
+        self.entries_mut(parent)?.insert(name.to_owned(), ino);
+        self.touch(parent)
+    }
+
+    /// Removes the entry `name` from `parent`, dropping the node it refers to
+    /// if nothing else is using it.  Directories are dropped along with their
+    /// contents.
+    pub(super) fn unlink(&mut self,
+                         parent: u64,
+                         name: &OsStr)
+                         -> io::Result<()> {
+        let ino = self.entries_mut(parent)?
+                      .remove(name)
+                      .ok_or_else(not_found)?;
+        self.touch(parent)?;
+        let node = self.node_mut(ino)?;
+        node.nlink -= 1;
+        if node.nlink == 0 {
+            if let NodeKind::Dir { entries, .. } = &node.kind {
+                let names: Vec<OsString> = entries.keys().cloned().collect();
+                for name in names {
+                    self.unlink(ino, &name)?;
+                }
+            }
+        }
+        self.collect(ino);
+        Ok(())
+    }
+
+    /// Moves the entry `name` in `parent` to `new_name` in `new_parent`,
+    /// replacing whatever was there.  The caller is responsible for checking
+    /// that the replacement is allowed.
+    pub(super) fn relink(&mut self,
+                         parent: u64,
+                         name: &OsStr,
+                         new_parent: u64,
+                         new_name: &OsStr)
+                         -> io::Result<()> {
+        if self.entries(new_parent)?.contains_key(new_name) {
+            self.unlink(new_parent, new_name)?;
+        }
+        let ino = self.entries_mut(parent)?
+                      .remove(name)
+                      .ok_or_else(not_found)?;
+        self.entries_mut(new_parent)?
+            .insert(new_name.to_owned(), ino);
+        if let NodeKind::Dir { parent, .. } = &mut self.node_mut(ino)?.kind {
+            *parent = new_parent;
+        }
+        self.touch(parent)?;
+        self.touch(new_parent)
+    }
+
+    /// Returns `true` if `ino` is `ancestor` or lives somewhere below it.
+    pub(super) fn is_within(&self, mut ino: u64, ancestor: u64) -> bool {
+        loop {
+            if ino == ancestor {
+                return true;
+            }
+            match self.parent_of(ino) {
+                Ok(parent) if parent != ino => ino = parent,
+                _ => return false
+            }
+        }
+    }
+
+    /// Records that a file has `ino` open.
+    pub(super) fn open(&mut self, ino: u64) -> io::Result<()> {
+        self.node_mut(ino)?.open += 1;
+        Ok(())
+    }
+
+    /// Records that a file that had `ino` open has been dropped.
+    pub(super) fn close(&mut self, ino: u64) {
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            node.open -= 1;
+        }
+        self.collect(ino);
+    }
+
+    /// Drops `ino` if nothing links to it and no file has it open.
+    fn collect(&mut self, ino: u64) {
+        if let Some(node) = self.nodes.get(&ino) {
+            if node.nlink == 0 && node.open == 0 {
+                self.nodes.remove(&ino);
+            }
+        }
+    }
+
+    /// Updates the modification time of `ino`.
+    pub(super) fn touch(&mut self, ino: u64) -> io::Result<()> {
+        self.node_mut(ino)?.modified = SystemTime::now();
+        Ok(())
+    }
+
+    pub(super) fn metadata(&self, ino: u64) -> io::Result<Metadata> {
+        let node = self.node(ino)?;
+        let len = match &node.kind {
+            NodeKind::File(data) => data.len() as u64,
+            NodeKind::Dir { .. } => 0,
+            NodeKind::Symlink(target) => target.as_os_str().len() as u64
+        };
+        Ok(Metadata::new(node.file_type(), len)
+            .with_permissions(node.permissions)
+            .with_created(node.created)
+            .with_modified(node.modified)
+            .with_accessed(node.accessed)
+            .with_ino(ino)
+            .with_dev(self.dev)
+            .with_nlink(node.nlink))
+    }
+}
+
+/// Locks `tree`, ignoring poisoning.
+///
+/// No method panics while it holds the lock in a way that could leave the tree
+/// inconsistent, so a poisoned lock is still safe to use.
+pub(super) fn lock(tree: &Mutex<Tree>) -> MutexGuard<'_, Tree> {
+    tree.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(super) fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such file or directory")
+}
+
+pub(super) fn already_exists() -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, "file exists")
+}
+
+pub(super) fn not_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "not a directory")
+}
+
+pub(super) fn is_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "is a directory")
+}
+
+pub(super) fn directory_not_empty() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "directory not empty")
+}
+
+pub(super) fn too_many_links() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "too many levels of symbolic links")
+}
+
+pub(super) fn invalid_input(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> Tree {
+        let mut tree = Tree::new(0);
+        let a = tree.insert(ROOT,
+                            OsStr::new("a"),
+                            NodeKind::Dir { entries: BTreeMap::new(),
+                                            parent: ROOT })
+                    .unwrap();
+        tree.insert(a, OsStr::new("file"), NodeKind::File(Vec::new()))
+            .unwrap();
+        tree.insert(ROOT,
+                    OsStr::new("link"),
+                    NodeKind::Symlink(PathBuf::from("a/file")))
+            .unwrap();
+        tree.insert(ROOT,
+                    OsStr::new("loop"),
+                    NodeKind::Symlink(PathBuf::from("loop")))
+            .unwrap();
+        tree
+    }
+
+    #[test]
+    fn resolves_dot_dot_and_symlinks() {
+        let tree = tree();
+
+        let lookup = tree.resolve(Path::new("/a/../a/./file"), true).unwrap();
+        assert_eq!(tree.canonical_path(&lookup).unwrap(),
+                   PathBuf::from("/a/file"));
+
+        let lookup = tree.resolve(Path::new("link"), true).unwrap();
+        assert_eq!(tree.canonical_path(&lookup).unwrap(),
+                   PathBuf::from("/a/file"));
+
+        let lookup = tree.resolve(Path::new("link"), false).unwrap();
+        assert_eq!(tree.node(lookup.ino.unwrap()).unwrap().file_type(),
+                   FileType::Symlink);
+
+        let lookup = tree.resolve(Path::new("/a/missing"), true).unwrap();
+        assert!(lookup.ino.is_none());
+        assert!(tree.resolve(Path::new("/missing/file"), true).is_err());
+    }
+
+    #[test]
+    fn symlink_loops_are_detected() {
+        let tree = tree();
+        let error = tree.resolve(Path::new("/loop"), true).unwrap_err();
+        assert_eq!(error.to_string(), "too many levels of symbolic links");
+        assert!(tree.resolve(Path::new("/loop"), false).is_ok());
+    }
+
+    #[test]
+    fn open_nodes_outlive_their_last_link() {
+        let mut tree = tree();
+        let lookup = tree.resolve(Path::new("/a/file"), false).unwrap();
+        let ino = lookup.ino.unwrap();
+
+        tree.open(ino).unwrap();
+        tree.unlink(lookup.parent, OsStr::new("file")).unwrap();
+        assert!(tree.node(ino).is_ok());
+
+        tree.close(ino);
+        assert!(tree.node(ino).is_err());
+    }
+}
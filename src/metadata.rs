@@ -0,0 +1,602 @@
+//! Crate-owned [`Metadata`], [`FileType`], and [`Permissions`] types.
+//!
+//! The standard library's [`std::fs::Metadata`], [`std::fs::FileType`], and
+//! [`std::fs::Permissions`] types can only be created by the operating system,
+//! which means that a filesystem that isn't backed by the OS (an archive, an
+//! in-memory tree, a remote store, etc.) has no way of returning them.  The
+//! types in this module fill the same role, but can be constructed by anyone.
+//!
+//! Every type in this module can be converted losslessly from its standard
+//! library counterpart via [`From`].  Fields that the standard library only
+//! exposes on some platforms (mode bits, user and group IDs, inode and device
+//! numbers, link counts) are [`Option`]s here, and are `None` when the backend
+//! doesn't know them.  Backends that need to expose more than this can attach
+//! arbitrary data to a [`Metadata`] through its [`Extensions`].
+//!
+//! When the `serde` feature is enabled, [`FileType`] and [`Permissions`] can
+//! be serialized and deserialized.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt, io,
+    sync::Arc,
+    time::SystemTime
+};
+
+/// The type of an object in a filesystem.
+///
+/// This is the crate's equivalent of [`std::fs::FileType`].  Unlike the
+/// standard library's type, it is a plain enum that any backend can create.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde",
+           derive(serde::Serialize, serde::Deserialize),
+           serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum FileType {
+    /// A regular file.
+    File,
+
+    /// A directory.
+    Dir,
+
+    /// A symbolic link.
+    Symlink,
+
+    /// A block device.
+    BlockDevice,
+
+    /// A character device.
+    CharDevice,
+
+    /// A named pipe.
+    Fifo,
+
+    /// A socket.
+    Socket,
+
+    /// Some other type of object that the backend is able to represent, but
+    /// that doesn't fit into any of the other categories.
+    Other
+}
+
+impl FileType {
+    /// Returns `true` if this file type is a directory.
+    pub fn is_dir(&self) -> bool {
+        *self == FileType::Dir
+    }
+
+    /// Returns `true` if this file type is a regular file.
+    pub fn is_file(&self) -> bool {
+        *self == FileType::File
+    }
+
+    /// Returns `true` if this file type is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        *self == FileType::Symlink
+    }
+}
+
+impl From<std::fs::FileType> for FileType {
+    fn from(file_type: std::fs::FileType) -> Self {
+        if file_type.is_dir() {
+            return FileType::Dir;
+        }
+        if file_type.is_file() {
+            return FileType::File;
+        }
+        if file_type.is_symlink() {
+            return FileType::Symlink;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+
+            if file_type.is_block_device() {
+                return FileType::BlockDevice;
+            }
+            if file_type.is_char_device() {
+                return FileType::CharDevice;
+            }
+            if file_type.is_fifo() {
+                return FileType::Fifo;
+            }
+            if file_type.is_socket() {
+                return FileType::Socket;
+            }
+        }
+
+        FileType::Other
+    }
+}
+
+/// Representation of the various permissions on a file.
+///
+/// This is the crate's equivalent of [`std::fs::Permissions`].  Every backend
+/// is expected to support the read-only flag.  Backends that understand POSIX
+/// mode bits can also set them via [`from_mode()`][1], in which case the
+/// read-only flag is derived from the mode bits in the same way that the
+/// standard library does on Unix.
+///
+/// [1]: Permissions::from_mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Permissions {
+    readonly: bool,
+    mode: Option<u32>
+}
+
+impl Permissions {
+    /// Creates a new set of permissions that only knows about the read-only
+    /// flag.
+    pub fn new(readonly: bool) -> Self {
+        Permissions { readonly,
+                      mode: None }
+    }
+
+    /// Creates a new set of permissions from POSIX mode bits.
+    pub fn from_mode(mode: u32) -> Self {
+        Permissions { readonly: mode & 0o222 == 0,
+                      mode: Some(mode) }
+    }
+
+    /// Returns `true` if these permissions describe a read-only file.
+    pub fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Modifies the read-only flag.
+    ///
+    /// If mode bits are present, then setting the flag to `true` clears all of
+    /// the write bits, and setting it to `false` sets all of them, which
+    /// matches the standard library's behavior on Unix.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+        if let Some(mode) = self.mode.as_mut() {
+            if readonly {
+                *mode &= !0o222;
+            } else {
+                *mode |= 0o222;
+            }
+        }
+    }
+
+    /// Returns the POSIX mode bits, if the backend knows them.
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// Sets the POSIX mode bits, updating the read-only flag to match.
+    pub fn set_mode(&mut self, mode: u32) {
+        *self = Permissions::from_mode(mode);
+    }
+}
+
+impl From<std::fs::Permissions> for Permissions {
+    fn from(permissions: std::fs::Permissions) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            Permissions::from_mode(permissions.mode())
+        }
+
+        #[cfg(not(unix))]
+        {
+            Permissions::new(permissions.readonly())
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<Permissions> for std::fs::Permissions {
+    fn from(permissions: Permissions) -> Self {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = permissions.mode.unwrap_or(if permissions.readonly {
+                                                  0o444
+                                              } else {
+                                                  0o644
+                                              });
+        std::fs::Permissions::from_mode(mode)
+    }
+}
+
+/// Converts the crate's [`Permissions`] into [`std::fs::Permissions`].
+///
+/// On Unix the conversion is direct.  Elsewhere [`std::fs::Permissions`] can't
+/// be created from scratch, so `current` is called to fetch the object's
+/// current permissions, and only the read-only flag is changed.
+#[cfg(any(feature = "async-std",
+          feature = "smol",
+          feature = "std-fs",
+          feature = "tokio"))]
+pub(crate) fn to_std_permissions<F>(perm: Permissions,
+                                    current: F)
+                                    -> io::Result<std::fs::Permissions>
+    where F: FnOnce() -> io::Result<std::fs::Permissions>
+{
+    #[cfg(unix)]
+    {
+        let _ = current;
+        Ok(perm.into())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let mut std_perm = current()?;
+        std_perm.set_readonly(perm.readonly());
+        Ok(std_perm)
+    }
+}
+
+/// A type map of backend-specific extensions to [`Metadata`].
+///
+/// Backends may insert at most one value of any given type.  The values are
+/// reference counted so that cloning a [`Metadata`] stays cheap.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>
+}
+
+impl Extensions {
+    /// Creates an empty set of extensions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, replacing any previous value of the same type.
+    pub fn insert<T>(&mut self, value: T)
+        where T: Any + Send + Sync
+    {
+        self.map.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Returns a reference to the value of type `T`, if there is one.
+    pub fn get<T>(&self) -> Option<&T>
+        where T: Any + Send + Sync
+    {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// Removes the value of type `T`, returning `true` if there was one.
+    pub fn remove<T>(&mut self) -> bool
+        where T: Any + Send + Sync
+    {
+        self.map.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Returns `true` if there are no extensions.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of extensions.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+         .field("len", &self.map.len())
+         .finish()
+    }
+}
+
+/// Metadata information about a file.
+///
+/// This is the crate's equivalent of [`std::fs::Metadata`].  It is created with
+/// [`Metadata::new()`] and then filled in with the `with_*()` methods, or
+/// converted from a [`std::fs::Metadata`] via [`From`].  When converted from
+/// the standard library's type, the original [`std::fs::Metadata`] is also
+/// stored in the [`extensions()`][1] so that nothing is lost.
+///
+/// [1]: Metadata::extensions
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    file_type: FileType,
+    len: u64,
+    permissions: Permissions,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    created: Option<SystemTime>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    ino: Option<u64>,
+    dev: Option<u64>,
+    nlink: Option<u64>,
+    extensions: Extensions
+}
+
+impl Metadata {
+    /// Creates metadata for an object of the given type and length.
+    ///
+    /// The permissions are initially writable and carry no mode bits, and all
+    /// of the optional fields are `None`.
+    pub fn new(file_type: FileType, len: u64) -> Self {
+        Metadata { file_type,
+                   len,
+                   permissions: Permissions::new(false),
+                   modified: None,
+                   accessed: None,
+                   created: None,
+                   uid: None,
+                   gid: None,
+                   ino: None,
+                   dev: None,
+                   nlink: None,
+                   extensions: Extensions::new() }
+    }
+
+    /// Sets the size of the file, in bytes.
+    ///
+    /// This is mostly useful for wrappers that change how a file's contents
+    /// are stored, and so have to report a different length than the
+    /// underlying file.
+    pub fn with_len(mut self, len: u64) -> Self {
+        self.len = len;
+        self
+    }
+
+    /// Sets the permissions.
+    pub fn with_permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Sets the last modification time.
+    pub fn with_modified(mut self, modified: SystemTime) -> Self {
+        self.modified = Some(modified);
+        self
+    }
+
+    /// Sets the last access time.
+    pub fn with_accessed(mut self, accessed: SystemTime) -> Self {
+        self.accessed = Some(accessed);
+        self
+    }
+
+    /// Sets the creation time.
+    pub fn with_created(mut self, created: SystemTime) -> Self {
+        self.created = Some(created);
+        self
+    }
+
+    /// Sets the owning user's ID.
+    pub fn with_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Sets the owning group's ID.
+    pub fn with_gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Sets the inode number.
+    pub fn with_ino(mut self, ino: u64) -> Self {
+        self.ino = Some(ino);
+        self
+    }
+
+    /// Sets the ID of the device containing the object.
+    pub fn with_dev(mut self, dev: u64) -> Self {
+        self.dev = Some(dev);
+        self
+    }
+
+    /// Sets the number of hard links pointing at the object.
+    pub fn with_nlink(mut self, nlink: u64) -> Self {
+        self.nlink = Some(nlink);
+        self
+    }
+
+    /// Inserts a backend-specific extension.
+    pub fn with_extension<T>(mut self, value: T) -> Self
+        where T: Any + Send + Sync
+    {
+        self.extensions.insert(value);
+        self
+    }
+
+    /// Returns the file type for this metadata.
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns `true` if this metadata is for a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type.is_dir()
+    }
+
+    /// Returns `true` if this metadata is for a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type.is_file()
+    }
+
+    /// Returns `true` if this metadata is for a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.file_type.is_symlink()
+    }
+
+    /// Returns the size of the file, in bytes.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the permissions of the file.
+    pub fn permissions(&self) -> Permissions {
+        self.permissions
+    }
+
+    /// Returns the last modification time.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind [`io::ErrorKind::Unsupported`] is returned if the
+    /// backend doesn't record this time.
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        time_or_unsupported(self.modified, "modification time")
+    }
+
+    /// Returns the last access time.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind [`io::ErrorKind::Unsupported`] is returned if the
+    /// backend doesn't record this time.
+    pub fn accessed(&self) -> io::Result<SystemTime> {
+        time_or_unsupported(self.accessed, "access time")
+    }
+
+    /// Returns the creation time.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind [`io::ErrorKind::Unsupported`] is returned if the
+    /// backend doesn't record this time.
+    pub fn created(&self) -> io::Result<SystemTime> {
+        time_or_unsupported(self.created, "creation time")
+    }
+
+    /// Returns the owning user's ID, if known.
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    /// Returns the owning group's ID, if known.
+    pub fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    /// Returns the inode number, if known.
+    pub fn ino(&self) -> Option<u64> {
+        self.ino
+    }
+
+    /// Returns the ID of the device containing the object, if known.
+    pub fn dev(&self) -> Option<u64> {
+        self.dev
+    }
+
+    /// Returns the number of hard links pointing at the object, if known.
+    pub fn nlink(&self) -> Option<u64> {
+        self.nlink
+    }
+
+    /// Returns the backend-specific extensions.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns the backend-specific extensions mutably.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+}
+
+impl From<std::fs::Metadata> for Metadata {
+    fn from(metadata: std::fs::Metadata) -> Self {
+        let mut result = Metadata::new(metadata.file_type().into(),
+                                       metadata.len())
+            .with_permissions(metadata.permissions().into());
+        result.modified = metadata.modified().ok();
+        result.accessed = metadata.accessed().ok();
+        result.created = metadata.created().ok();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            result.uid = Some(metadata.uid());
+            result.gid = Some(metadata.gid());
+            result.ino = Some(metadata.ino());
+            result.dev = Some(metadata.dev());
+            result.nlink = Some(metadata.nlink());
+        }
+
+        result.extensions.insert(metadata);
+        result
+    }
+}
+
+fn time_or_unsupported(time: Option<SystemTime>,
+                       what: &str)
+                       -> io::Result<SystemTime> {
+    time.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported,
+                           format!("{what} is not available on this backend"))
+        })
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_from_std_metadata() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let std_metadata = std::fs::metadata(path).unwrap();
+        let metadata = Metadata::from(std_metadata.clone());
+
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), std_metadata.len());
+        assert_eq!(metadata.modified().unwrap(),
+                   std_metadata.modified().unwrap());
+        assert_eq!(metadata.permissions().readonly(),
+                   std_metadata.permissions().readonly());
+        assert!(metadata.extensions().get::<std::fs::Metadata>().is_some());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            assert_eq!(metadata.permissions().mode(),
+                       Some(std_metadata.mode()));
+            assert_eq!(metadata.ino(), Some(std_metadata.ino()));
+            assert_eq!(metadata.nlink(), Some(std_metadata.nlink()));
+        }
+    }
+
+    #[test]
+    fn readonly_tracks_mode_bits() {
+        let mut permissions = Permissions::from_mode(0o644);
+        assert!(!permissions.readonly());
+
+        permissions.set_readonly(true);
+        assert!(permissions.readonly());
+        assert_eq!(permissions.mode(), Some(0o444));
+
+        permissions.set_readonly(false);
+        assert_eq!(permissions.mode(), Some(0o666));
+    }
+
+    #[test]
+    fn missing_times_are_unsupported() {
+        #[derive(Debug, PartialEq)]
+        struct Marker(u8);
+
+        let metadata =
+            Metadata::new(FileType::Dir, 0).with_extension(Marker(7));
+        assert_eq!(metadata.modified().unwrap_err().kind(),
+                   io::ErrorKind::Unsupported);
+        assert_eq!(metadata.extensions().get::<Marker>(), Some(&Marker(7)));
+        assert_eq!(metadata.uid(), None);
+    }
+}
@@ -0,0 +1,377 @@
+//! [`Backend`] erases the types of a mounted filesystem, so that filesystems
+//! of different types can be mounted side by side.
+
+use std::{
+    ffi::OsString,
+    fmt,
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::StreamExt;
+
+use crate::{
+    layer::{FileOf, OpenFlags},
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFileBuilderTrait,
+    AsyncFileTrait, AsyncFsTrait, FileType, Metadata, Permissions
+};
+
+/// The error returned for changes to a filesystem that is mounted read-only.
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   "filesystem is mounted read-only")
+}
+
+/// A file of any mounted filesystem.
+pub(super) trait DynFile:
+    AsyncFileTrait + AsyncRead + AsyncWrite + AsyncSeek + Unpin
+{
+}
+
+impl<T> DynFile for T
+    where T: AsyncFileTrait + AsyncRead + AsyncWrite + AsyncSeek + Unpin
+{
+}
+
+/// A directory entry of any mounted filesystem.
+#[async_trait]
+pub(super) trait DynEntry: fmt::Debug + Send + Sync {
+    async fn metadata(&self) -> io::Result<Metadata>;
+
+    async fn file_type(&self) -> io::Result<FileType>;
+}
+
+#[async_trait]
+impl<T> DynEntry for T where T: AsyncDirEntryTrait
+{
+    async fn metadata(&self) -> io::Result<Metadata> {
+        AsyncDirEntryTrait::metadata(self).await
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        AsyncDirEntryTrait::file_type(self).await
+    }
+}
+
+/// The entries of a directory of a mounted filesystem, with their names.
+pub(super) type EntryStream =
+    Pin<Box<dyn Stream<Item = io::Result<(OsString, Arc<dyn DynEntry>)>>
+                + Send>>;
+
+/// The operations of [`AsyncFsTrait`], on a mounted filesystem of any type.
+#[async_trait]
+pub(super) trait Backend: fmt::Debug + Send + Sync {
+    async fn create_dir(&self, path: &Path, recursive: bool) -> io::Result<()>;
+
+    async fn open(&self,
+                  path: &Path,
+                  flags: OpenFlags)
+                  -> io::Result<Box<dyn DynFile>>;
+
+    async fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    async fn rename(&self, src: &Path, dst: &Path) -> io::Result<()>;
+
+    async fn set_permissions(&self,
+                             path: &Path,
+                             perm: Permissions)
+                             -> io::Result<()>;
+
+    async fn hard_link(&self, src: &Path, dst: &Path) -> io::Result<()>;
+
+    async fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+    async fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    async fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    async fn copy(&self, src: &Path, dst: &Path) -> io::Result<u64>;
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    async fn read_dir(&self, path: &Path) -> io::Result<EntryStream>;
+
+    async fn remove_dir(&self, path: &Path) -> io::Result<()>;
+
+    async fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+}
+
+/// A mounted filesystem.
+pub(super) struct Mounted<F>
+    where F: AsyncFsTrait
+{
+    fs: F,
+    read_only: bool,
+
+    /// Turns the filesystem's files into [`DynFile`]s.
+    wrap: fn(FileOf<F>) -> Box<dyn DynFile>
+}
+
+impl<F> Mounted<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncSeek + Unpin + 'static
+{
+    /// Mounts `fs` read-only.
+    pub(super) fn read_only(fs: F) -> Self {
+        Mounted { fs,
+                  read_only: true,
+                  wrap: |file| Box::new(NoWrite(file)) }
+    }
+
+    /// Fails if the filesystem is mounted read-only.
+    fn writable(&self) -> io::Result<()> {
+        match self.read_only {
+            true => Err(read_only()),
+            false => Ok(())
+        }
+    }
+}
+
+impl<F> Mounted<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+{
+    /// Mounts `fs` for reading and writing.
+    pub(super) fn read_write(fs: F) -> Self {
+        Mounted { fs,
+                  read_only: false,
+                  wrap: |file| Box::new(file) }
+    }
+}
+
+impl<F> fmt::Debug for Mounted<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mounted")
+         .field("fs", &self.fs)
+         .field("read_only", &self.read_only)
+         .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<F> Backend for Mounted<F>
+    where F: AsyncFsTrait + 'static,
+          F::DirEntry: 'static,
+          F::ReadDir: 'static,
+          FileOf<F>: AsyncRead + AsyncSeek + Unpin + 'static
+{
+    async fn create_dir(&self, path: &Path, recursive: bool) -> io::Result<()> {
+        self.writable()?;
+        self.fs
+            .dir_builder()
+            .recursive(recursive)
+            .create(path)
+            .await
+    }
+
+    async fn open(&self,
+                  path: &Path,
+                  flags: OpenFlags)
+                  -> io::Result<Box<dyn DynFile>> {
+        let OpenFlags { read,
+                        write,
+                        append,
+                        truncate,
+                        create,
+                        create_new } = flags;
+        if write || append || truncate || create || create_new {
+            self.writable()?;
+        }
+        let file = self.fs
+                       .open_options()
+                       .read(read)
+                       .write(write)
+                       .append(append)
+                       .truncate(truncate)
+                       .create(create)
+                       .create_new(create_new)
+                       .open(path)
+                       .await?;
+        Ok((self.wrap)(file))
+    }
+
+    async fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.fs.canonicalize(path).await
+    }
+
+    async fn rename(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.writable()?;
+        self.fs.rename(src, dst).await
+    }
+
+    async fn set_permissions(&self,
+                             path: &Path,
+                             perm: Permissions)
+                             -> io::Result<()> {
+        self.writable()?;
+        self.fs.set_permissions(path, perm).await
+    }
+
+    async fn hard_link(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.writable()?;
+        self.fs.hard_link(src, dst).await
+    }
+
+    async fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.fs.read_link(path).await
+    }
+
+    async fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.fs.symlink_metadata(path).await
+    }
+
+    async fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.fs.metadata(path).await
+    }
+
+    async fn copy(&self, src: &Path, dst: &Path) -> io::Result<u64> {
+        self.writable()?;
+        self.fs.copy(src, dst).await
+    }
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.writable()?;
+        self.fs.remove_file(path).await
+    }
+
+    async fn read_dir(&self, path: &Path) -> io::Result<EntryStream> {
+        let entries = self.fs.read_dir(path).await?;
+        Ok(Box::pin(entries.then(|entry| async move {
+                               let entry = entry?;
+                               let name = entry.file_name().await;
+                               let entry: Arc<dyn DynEntry> = Arc::new(entry);
+                               Ok((name, entry))
+                           })))
+    }
+
+    async fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.writable()?;
+        self.fs.remove_dir(path).await
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.writable()?;
+        self.fs.remove_dir_all(path).await
+    }
+}
+
+/// A file of a filesystem that is mounted read-only.
+#[derive(Debug)]
+struct NoWrite<T>(T);
+
+#[async_trait]
+impl<T> AsyncFileTrait for NoWrite<T> where T: AsyncFileTrait
+{
+    async fn sync_all(&self) -> io::Result<()> {
+        self.0.sync_all().await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.0.sync_data().await
+    }
+
+    async fn set_len(&self, _size: u64) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.0.metadata().await
+    }
+
+    async fn set_permissions(&self, _perm: Permissions) -> io::Result<()> {
+        Err(read_only())
+    }
+}
+
+impl<T> AsyncRead for NoWrite<T> where T: AsyncRead + Unpin
+{
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+impl<T> AsyncWrite for NoWrite<T> {
+    fn poll_write(self: Pin<&mut Self>,
+                  _cx: &mut Context<'_>,
+                  _buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(read_only()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  _cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  _cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> AsyncSeek for NoWrite<T> where T: AsyncSeek + Unpin
+{
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.get_mut().0).poll_seek(cx, pos)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncWriteExt};
+
+    use super::*;
+    use crate::mem_fs::MemFs;
+
+    #[test]
+    fn read_only_mounts_refuse_changes() {
+        block_on(async {
+            let mem = MemFs::new();
+            mem.open_options()
+               .write(true)
+               .create(true)
+               .open("/f")
+               .await
+               .unwrap();
+            let backend = Mounted::read_only(mem);
+
+            let flags = OpenFlags { read: true,
+                                    ..OpenFlags::default() };
+            let mut file = backend.open(Path::new("/f"), flags).await.unwrap();
+            let err = file.write_all(b"x").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+            let flags = OpenFlags { write: true,
+                                    ..OpenFlags::default() };
+            let err = backend.open(Path::new("/f"), flags).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            let err = backend.remove_file(Path::new("/f")).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            assert!(backend.metadata(Path::new("/f")).await.is_ok());
+        });
+    }
+}
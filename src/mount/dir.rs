@@ -0,0 +1,268 @@
+//! [`MountDirBuilder`] creates directories in a [`MountFs`], and
+//! [`MountReadDir`] lists them.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    vec
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+
+use super::{
+    backend::{self, Backend, EntryStream},
+    fs::{already_exists, virtual_dir, Child, Target},
+    MountFs
+};
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncReadDirTrait, FileType,
+    Metadata
+};
+
+/// Creates directories in a [`MountFs`].
+#[derive(Debug)]
+pub struct MountDirBuilder {
+    fs: MountFs,
+    recursive: bool
+}
+
+impl MountDirBuilder {
+    pub(super) fn new(fs: MountFs) -> Self {
+        MountDirBuilder { fs,
+                          recursive: false }
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for MountDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        if self.fs.is_virtual_or_mount_point(path) {
+            return match self.recursive {
+                true => Ok(()),
+                false => Err(already_exists())
+            };
+        }
+        match self.fs.resolve(path)? {
+            Target::Backend(backend, path) => {
+                backend.create_dir(&path, self.recursive).await
+            }
+            Target::Virtual => unreachable!("virtual directories are checked")
+        }
+    }
+}
+
+/// A stream of the entries in a directory of a [`MountFs`].
+///
+/// Entries of the filesystem that holds the directory come first, followed by
+/// the mount points in the directory, in order of their names.
+pub struct MountReadDir {
+    dir: PathBuf,
+    entries: Option<EntryStream>,
+
+    /// The names of the mount points, which hide entries of the same name.
+    hidden: HashSet<OsString>,
+    mount_points: vec::IntoIter<MountDirEntry>
+}
+
+impl MountReadDir {
+    pub(super) fn new(dir: PathBuf,
+                      entries: Option<EntryStream>,
+                      children: BTreeMap<OsString, Child>)
+                      -> Self {
+        let hidden = children.keys().cloned().collect();
+        let mount_points =
+            children.into_iter()
+                    .map(|(name, child)| {
+                        let kind = match child {
+                            Child::Mount(backend) => Kind::Mount(backend),
+                            Child::Virtual => Kind::Virtual
+                        };
+                        MountDirEntry { path: dir.join(&name),
+                                        name,
+                                        kind }
+                    })
+                    .collect::<Vec<_>>();
+        MountReadDir { dir,
+                       entries,
+                       hidden,
+                       mount_points: mount_points.into_iter() }
+    }
+}
+
+impl fmt::Debug for MountReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MountReadDir")
+         .field("dir", &self.dir)
+         .field("mount_points", &self.mount_points)
+         .finish_non_exhaustive()
+    }
+}
+
+impl Stream for MountReadDir {
+    type Item = io::Result<MountDirEntry>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        while let Some(entries) = &mut this.entries {
+            match entries.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok((name, entry)))) => {
+                    if this.hidden.contains(&name) {
+                        continue;
+                    }
+                    let entry = MountDirEntry { path: this.dir.join(&name),
+                                                name,
+                                                kind: Kind::Entry(entry) };
+                    return Poll::Ready(Some(Ok(entry)));
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    return Poll::Ready(Some(Err(error)))
+                }
+                Poll::Ready(None) => this.entries = None,
+                Poll::Pending => return Poll::Pending
+            }
+        }
+        Poll::Ready(this.mount_points.next().map(Ok))
+    }
+}
+
+impl AsyncReadDirTrait<MountDirEntry> for MountReadDir {}
+
+/// What a [`MountDirEntry`] is.
+#[derive(Clone)]
+enum Kind {
+    /// An entry of a mounted filesystem.
+    Entry(Arc<dyn backend::DynEntry>),
+
+    /// A mount point.
+    Mount(Arc<dyn Backend>),
+
+    /// A directory with a mount point somewhere below it.
+    Virtual
+}
+
+/// An entry in a directory of a [`MountFs`].
+#[derive(Clone)]
+pub struct MountDirEntry {
+    path: PathBuf,
+    name: OsString,
+    kind: Kind
+}
+
+impl fmt::Debug for MountDirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("MountDirEntry");
+        f.field("path", &self.path);
+        match &self.kind {
+            Kind::Entry(entry) => f.field("entry", entry),
+            Kind::Mount(backend) => f.field("mount", backend),
+            Kind::Virtual => f.field("virtual", &true)
+        };
+        f.finish()
+    }
+}
+
+#[async_trait]
+impl AsyncDirEntryTrait for MountDirEntry {
+    async fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        match &self.kind {
+            Kind::Entry(entry) => entry.metadata().await,
+            Kind::Mount(backend) => backend.metadata(Path::new("/")).await,
+            Kind::Virtual => Ok(virtual_dir())
+        }
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        match &self.kind {
+            Kind::Entry(entry) => entry.file_type().await,
+            Kind::Mount(backend) => {
+                let metadata = backend.symlink_metadata(Path::new("/")).await?;
+                Ok(metadata.file_type())
+            }
+            Kind::Virtual => Ok(FileType::Dir)
+        }
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{mem_fs::MemFs, AsyncFileBuilderTrait, AsyncFsTrait};
+
+    async fn list(fs: &MountFs, path: &str) -> Vec<(PathBuf, bool)> {
+        let mut entries = Vec::new();
+        let mut stream = fs.read_dir(path).await.unwrap();
+        while let Some(entry) = stream.next().await {
+            let entry = entry.unwrap();
+            let dir = entry.metadata().await.unwrap().is_dir();
+            entries.push((entry.path().await, dir));
+        }
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn mount_points_are_listed() {
+        block_on(async {
+            let root = MemFs::new();
+            root.dir_builder().create("/mem").await.unwrap();
+            root.open_options()
+                .write(true)
+                .create(true)
+                .open("/f")
+                .await
+                .unwrap();
+            let fs = MountFs::new().mount("/", root)
+                                   .mount("/mem", MemFs::new())
+                                   .mount("/deep/down/here", MemFs::new());
+            fs.dir_builder()
+              .recursive(true)
+              .create("/deep/down/here/dir")
+              .await
+              .unwrap();
+
+            assert_eq!(list(&fs, "/").await,
+                       [(PathBuf::from("/deep"), true),
+                        (PathBuf::from("/f"), false),
+                        (PathBuf::from("/mem"), true)]);
+            assert_eq!(list(&fs, "/deep").await,
+                       [(PathBuf::from("/deep/down"), true)]);
+            assert_eq!(list(&fs, "/deep/down/here").await,
+                       [(PathBuf::from("/deep/down/here/dir"), true)]);
+        });
+    }
+}
@@ -0,0 +1,233 @@
+//! [`MountOpenOptions`] opens [`MountFile`]s in a [`MountFs`][1].
+//!
+//! [1]: super::MountFs
+
+use std::{
+    fmt,
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{
+    backend::DynFile,
+    fs::{not_found, Target},
+    MountFs
+};
+use crate::{
+    layer::OpenFlags, AsyncFileBuilderTrait, AsyncFileTrait, Metadata,
+    Permissions
+};
+
+/// Options for opening a [`MountFile`].
+#[derive(Debug)]
+pub struct MountOpenOptions {
+    fs: MountFs,
+    flags: OpenFlags
+}
+
+impl MountOpenOptions {
+    pub(super) fn new(fs: MountFs) -> Self {
+        MountOpenOptions { fs,
+                           flags: OpenFlags::default() }
+    }
+}
+
+#[async_trait]
+impl AsyncFileBuilderTrait for MountOpenOptions {
+    type File = MountFile;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.flags.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.flags.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.flags.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.flags.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.flags.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.flags.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        match self.fs.resolve(path.as_ref())? {
+            Target::Backend(backend, path) => {
+                let inner = backend.open(&path, self.flags).await?;
+                Ok(MountFile { inner })
+            }
+            Target::Virtual => Err(not_found())
+        }
+    }
+}
+
+/// An open file in a [`MountFs`][1], from whichever filesystem holds it.
+///
+/// Files of filesystems mounted with [`mount_read_only()`][2] can't be
+/// written to.
+///
+/// [1]: super::MountFs
+/// [2]: super::MountFs::mount_read_only
+pub struct MountFile {
+    inner: Box<dyn DynFile>
+}
+
+impl fmt::Debug for MountFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MountFile")
+         .field("inner", &self.inner)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl AsyncFileTrait for MountFile {
+    async fn sync_all(&self) -> io::Result<()> {
+        self.inner.sync_all().await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.inner.sync_data().await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.inner.set_len(size).await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.inner.metadata().await
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        self.inner.set_permissions(perm).await
+    }
+}
+
+impl AsyncRead for MountFile {
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for MountFile {
+    fn poll_write(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.get_mut().inner).poll_close(cx)
+    }
+}
+
+impl AsyncSeek for MountFile {
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        Pin::new(&mut *self.get_mut().inner).poll_seek(cx, pos)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{
+        future::block_on, AsyncReadExt, AsyncSeekExt, AsyncWriteExt
+    };
+
+    use super::*;
+    use crate::{mem_fs::MemFs, AsyncFsTrait};
+
+    #[test]
+    fn files_of_any_mount_are_read_written_and_seeked() {
+        block_on(async {
+            let archive = MemFs::new();
+            archive.open_options()
+                   .write(true)
+                   .create(true)
+                   .open("/f")
+                   .await
+                   .unwrap()
+                   .write_all(b"archived")
+                   .await
+                   .unwrap();
+            let fs = MountFs::new().mount("/mem", MemFs::new())
+                                   .mount_read_only("/archive", archive);
+
+            let mut file = fs.open_options()
+                             .read(true)
+                             .write(true)
+                             .create(true)
+                             .open("/mem/f")
+                             .await
+                             .unwrap();
+            file.write_all(b"contents").await.unwrap();
+            file.seek(SeekFrom::Start(3)).await.unwrap();
+            let mut rest = String::new();
+            file.read_to_string(&mut rest).await.unwrap();
+            assert_eq!(rest, "tents");
+
+            let mut file = fs.open_options()
+                             .read(true)
+                             .open("/archive/f")
+                             .await
+                             .unwrap();
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, "archived");
+            let err = file.write_all(b"x").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            let err = fs.open_options()
+                        .read(true)
+                        .open("/elsewhere")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        });
+    }
+}
@@ -0,0 +1,507 @@
+//! [`MountFs`] combines several filesystems under one namespace.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::AsyncWriteExt;
+
+use super::{
+    backend::{Backend, Mounted},
+    MountDirBuilder, MountDirEntry, MountOpenOptions, MountReadDir
+};
+use crate::{
+    layer::FileOf, AsyncFileBuilderTrait, AsyncFsTrait, FileType, Metadata,
+    Permissions
+};
+
+pub(super) fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such file or directory")
+}
+
+pub(super) fn already_exists() -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, "file exists")
+}
+
+fn cross_device() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "cross-device link")
+}
+
+fn busy() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "mount point is busy")
+}
+
+/// Turns `path` into an absolute path without `.` or `..` components.
+pub(super) fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            Component::Normal(name) => normal.push(name)
+        }
+    }
+    normal
+}
+
+/// The metadata reported for directories that only exist because something
+/// is mounted below them.
+pub(super) fn virtual_dir() -> Metadata {
+    Metadata::new(FileType::Dir, 0).with_permissions(Permissions::new(true))
+}
+
+/// A filesystem mounted at some path.
+#[derive(Debug, Clone)]
+struct Mount {
+    point: PathBuf,
+    backend: Arc<dyn Backend>
+}
+
+/// What a path of a [`MountFs`] leads to.
+pub(super) enum Target<'a> {
+    /// A path inside a mounted filesystem, relative to its root.
+    Backend(&'a Arc<dyn Backend>, PathBuf),
+
+    /// A directory that isn't inside any mounted filesystem, but has one
+    /// mounted somewhere below it.
+    Virtual
+}
+
+/// A child of a directory of a [`MountFs`] that is, or leads to, a mount
+/// point.
+pub(super) enum Child {
+    /// A mount point.
+    Mount(Arc<dyn Backend>),
+
+    /// A directory with a mount point somewhere below it.
+    Virtual
+}
+
+/// Several filesystems combined under one namespace.
+///
+/// Each filesystem is mounted at a path, and every path passed to the
+/// `MountFs` is handled by the filesystem with the longest mount point that
+/// the path starts with, after `.` and `..` components have been removed
+/// lexically.  That filesystem sees the rest of the path as an absolute path,
+/// so a file opened as `/mem/data/f` in a filesystem mounted at `/mem` is
+/// opened as `/data/f`.  Paths returned by [`canonicalize()`][1] and absolute
+/// paths returned by [`read_link()`][2] are turned back into paths of the
+/// `MountFs` the same way.
+///
+/// [`read_dir()`][3] lists mount points among the entries of their parent
+/// directory, hiding any entry of the same name.  Parents of mount points
+/// that aren't inside any mounted filesystem are reported as read-only, empty
+/// directories apart from the mount points below them.  Mount points can't be
+/// removed or renamed.
+///
+/// [`rename()`][4] and [`hard_link()`][5] between different filesystems fail
+/// with a cross-device error, of kind [`ErrorKind::Other`][6].
+/// [`copy()`][7] between different filesystems streams the contents from one
+/// to the other.
+///
+/// Symlinks can't be created, since the mounted filesystems' types are erased.
+///
+/// [1]: AsyncFsTrait::canonicalize
+/// [2]: AsyncFsTrait::read_link
+/// [3]: AsyncFsTrait::read_dir
+/// [4]: AsyncFsTrait::rename
+/// [5]: AsyncFsTrait::hard_link
+/// [6]: io::ErrorKind::Other
+/// [7]: AsyncFsTrait::copy
+#[derive(Debug, Clone, Default)]
+pub struct MountFs {
+    /// The mounted filesystems, with the longest mount points first.
+    mounts: Arc<Vec<Mount>>
+}
+
+impl MountFs {
+    /// Creates a `MountFs` with nothing mounted.
+    pub fn new() -> Self {
+        MountFs::default()
+    }
+
+    /// Mounts `fs` at `path`, replacing any filesystem mounted there before.
+    pub fn mount<P, F>(self, path: P, fs: F) -> Self
+        where P: AsRef<Path>,
+              F: AsyncFsTrait + 'static,
+              FileOf<F>: AsyncRead + AsyncWrite + AsyncSeek + Unpin + 'static
+    {
+        self.insert(path.as_ref(), Arc::new(Mounted::read_write(fs)))
+    }
+
+    /// Mounts `fs` at `path` for reading only, replacing any filesystem
+    /// mounted there before.
+    ///
+    /// Unlike [`mount()`][1], this doesn't need `fs`'s files to implement
+    /// [`AsyncWrite`].  Every operation that would change `fs` fails with
+    /// [`ErrorKind::PermissionDenied`][2].
+    ///
+    /// [1]: MountFs::mount
+    /// [2]: io::ErrorKind::PermissionDenied
+    pub fn mount_read_only<P, F>(self, path: P, fs: F) -> Self
+        where P: AsRef<Path>,
+              F: AsyncFsTrait + 'static,
+              FileOf<F>: AsyncRead + AsyncSeek + Unpin + 'static
+    {
+        self.insert(path.as_ref(), Arc::new(Mounted::read_only(fs)))
+    }
+
+    fn insert(mut self, path: &Path, backend: Arc<dyn Backend>) -> Self {
+        let point = normalize(path);
+        let mounts = Arc::make_mut(&mut self.mounts);
+        mounts.retain(|mount| mount.point != point);
+        let depth = point.components().count();
+        let index =
+            mounts.iter()
+                  .position(|mount| mount.point.components().count() < depth)
+                  .unwrap_or(mounts.len());
+        mounts.insert(index, Mount { point, backend });
+        self
+    }
+
+    /// Returns the mount points, with the longest first.
+    pub fn mount_points(&self) -> impl Iterator<Item = &Path> {
+        self.mounts.iter().map(|mount| mount.point.as_path())
+    }
+
+    /// Returns the mount that handles `path`, which must be normalized.
+    fn route(&self, path: &Path) -> Option<&Mount> {
+        self.mounts
+            .iter()
+            .find(|mount| path.starts_with(&mount.point))
+    }
+
+    /// Returns true if something is mounted strictly below `path`, which must
+    /// be normalized.
+    fn has_mounts_below(&self, path: &Path) -> bool {
+        self.mounts
+            .iter()
+            .any(|mount| mount.point != path && mount.point.starts_with(path))
+    }
+
+    /// Returns true if something is mounted at `path`, which must be
+    /// normalized.
+    fn is_mount_point(&self, path: &Path) -> bool {
+        self.mounts.iter().any(|mount| mount.point == path)
+    }
+
+    /// Finds what `path` leads to.
+    pub(super) fn resolve(&self, path: &Path) -> io::Result<Target<'_>> {
+        let path = normalize(path);
+        match self.route(&path) {
+            Some(mount) => {
+                let rest = path.strip_prefix(&mount.point).unwrap_or(&path);
+                Ok(Target::Backend(&mount.backend, Path::new("/").join(rest)))
+            }
+            None if self.has_mounts_below(&path) => Ok(Target::Virtual),
+            None => Err(not_found())
+        }
+    }
+
+    /// Like [`resolve()`][1], but fails unless `path` is inside a mounted
+    /// filesystem.
+    ///
+    /// [1]: MountFs::resolve
+    fn backend(&self, path: &Path) -> io::Result<(&Arc<dyn Backend>, PathBuf)> {
+        match self.resolve(path)? {
+            Target::Backend(backend, path) => Ok((backend, path)),
+            Target::Virtual => Err(busy())
+        }
+    }
+
+    /// Like [`backend()`][1], but also fails if `path` is a mount point, or
+    /// has one below it.
+    ///
+    /// [1]: MountFs::backend
+    fn removable(&self,
+                 path: &Path)
+                 -> io::Result<(&Arc<dyn Backend>, PathBuf)> {
+        let normal = normalize(path);
+        if self.is_mount_point(&normal) || self.has_mounts_below(&normal) {
+            return Err(busy());
+        }
+        self.backend(path)
+    }
+
+    /// Returns true if `path`, which may not be normalized, leads to a mount
+    /// point or a virtual directory.
+    pub(super) fn is_virtual_or_mount_point(&self, path: &Path) -> bool {
+        let path = normalize(path);
+        self.is_mount_point(&path) || self.has_mounts_below(&path)
+    }
+
+    /// Returns the children of `path`, which must be normalized, that are or
+    /// lead to mount points.
+    fn children(&self, path: &Path) -> BTreeMap<OsString, Child> {
+        let mut children = BTreeMap::new();
+        for mount in self.mounts.iter() {
+            let name = match mount.point.strip_prefix(path) {
+                Ok(rest) => match rest.components().next() {
+                    Some(Component::Normal(name)) => name.to_owned(),
+                    _ => continue
+                },
+                Err(_) => continue
+            };
+            if mount.point.parent() == Some(path) {
+                children.insert(name, Child::Mount(Arc::clone(&mount.backend)));
+            } else {
+                children.entry(name).or_insert(Child::Virtual);
+            }
+        }
+        children
+    }
+
+    /// Turns `path`, a path returned by the filesystem mounted at `point`,
+    /// into a path of this filesystem.
+    fn rebase(point: &Path, path: PathBuf) -> PathBuf {
+        match path.strip_prefix("/") {
+            Ok(rest) => point.join(rest),
+            Err(_) => path
+        }
+    }
+
+    /// Returns the mount point of the filesystem that handles `path`.
+    fn mount_point(&self, path: &Path) -> &Path {
+        self.route(&normalize(path))
+            .map(|mount| mount.point.as_path())
+            .unwrap_or_else(|| Path::new("/"))
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for MountFs {
+    type DirBuilder = MountDirBuilder;
+    type DirEntry = MountDirEntry;
+    type FileBuilder = MountOpenOptions;
+    type ReadDir = MountReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        MountDirBuilder::new(self.clone())
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        MountOpenOptions::new(self.clone())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        match self.resolve(path)? {
+            Target::Backend(backend, rest) => {
+                let canonical = backend.canonicalize(&rest).await?;
+                Ok(Self::rebase(self.mount_point(path), canonical))
+            }
+            Target::Virtual => Ok(normalize(path))
+        }
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let (src_backend, src) = self.removable(src.as_ref())?;
+        let (dst_backend, dst) = self.removable(dst.as_ref())?;
+        if !Arc::ptr_eq(src_backend, dst_backend) {
+            return Err(cross_device());
+        }
+        src_backend.rename(&src, &dst).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let (backend, path) = self.backend(path.as_ref())?;
+        backend.set_permissions(&path, perm).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let (src_backend, src) = self.backend(src.as_ref())?;
+        let (dst_backend, dst) = self.removable(dst.as_ref())?;
+        if !Arc::ptr_eq(src_backend, dst_backend) {
+            return Err(cross_device());
+        }
+        src_backend.hard_link(&src, &dst).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let (backend, rest) = self.backend(path)?;
+        let target = backend.read_link(&rest).await?;
+        Ok(Self::rebase(self.mount_point(path), target))
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        match self.resolve(path.as_ref())? {
+            Target::Backend(backend, path) => {
+                backend.symlink_metadata(&path).await
+            }
+            Target::Virtual => Ok(virtual_dir())
+        }
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        match self.resolve(path.as_ref())? {
+            Target::Backend(backend, path) => backend.metadata(&path).await,
+            Target::Virtual => Ok(virtual_dir())
+        }
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let (src_backend, src_path) = self.backend(src.as_ref())?;
+        let (dst_backend, dst_path) = self.backend(dst.as_ref())?;
+        if Arc::ptr_eq(src_backend, dst_backend) {
+            return src_backend.copy(&src_path, &dst_path).await;
+        }
+
+        let mut reader = self.open_options().read(true).open(src).await?;
+        let mut writer = self.open_options()
+                             .write(true)
+                             .create(true)
+                             .truncate(true)
+                             .open(dst)
+                             .await?;
+        let copied = futures_lite::io::copy(&mut reader, &mut writer).await?;
+        writer.close().await?;
+        Ok(copied)
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let (backend, path) = self.removable(path.as_ref())?;
+        backend.remove_file(&path).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let dir = normalize(path.as_ref());
+        let children = self.children(&dir);
+        let entries = match self.resolve(&dir)? {
+            Target::Backend(backend, path) => {
+                match backend.read_dir(&path).await {
+                    Ok(entries) => Some(entries),
+                    Err(_) if !children.is_empty() => None,
+                    Err(error) => return Err(error)
+                }
+            }
+            Target::Virtual => None
+        };
+        Ok(MountReadDir::new(dir, entries, children))
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let (backend, path) = self.removable(path.as_ref())?;
+        backend.remove_dir(&path).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let (backend, path) = self.removable(path.as_ref())?;
+        backend.remove_dir_all(&path).await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt};
+
+    use super::*;
+    use crate::{mem_fs::MemFs, AsyncDirBuilderTrait};
+
+    async fn write(fs: &MountFs, path: &str, contents: &str) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(contents.as_bytes()).await.unwrap();
+    }
+
+    #[test]
+    fn paths_go_to_the_longest_mount_point() {
+        block_on(async {
+            let root = MemFs::new();
+            let data = MemFs::new();
+            let nested = MemFs::new();
+            let fs = MountFs::new().mount("/data/nested", nested.clone())
+                                   .mount("/", root.clone())
+                                   .mount("/data/", data.clone());
+            assert_eq!(fs.mount_points().collect::<Vec<_>>(),
+                       ["/data/nested", "/data", "/"]);
+
+            write(&fs, "/f", "root").await;
+            write(&fs, "/data/f", "data").await;
+            write(&fs, "/data/./nested/../nested/f", "nested").await;
+            for (mem, contents) in
+                [(&root, "root"), (&data, "data"), (&nested, "nested")]
+            {
+                let mut file =
+                    mem.open_options().read(true).open("/f").await.unwrap();
+                let mut read = String::new();
+                file.read_to_string(&mut read).await.unwrap();
+                assert_eq!(read, contents);
+            }
+            assert_eq!(fs.canonicalize("/data/nested/f").await.unwrap(),
+                       Path::new("/data/nested/f"));
+        });
+    }
+
+    #[test]
+    fn renames_stay_on_one_filesystem_and_copies_stream() {
+        block_on(async {
+            let fs = MountFs::new().mount("/a", MemFs::new())
+                                   .mount("/b", MemFs::new());
+            write(&fs, "/a/f", "contents").await;
+            fs.rename("/a/f", "/a/g").await.unwrap();
+            let err = fs.rename("/a/g", "/b/g").await.unwrap_err();
+            assert_eq!(err.to_string(), "cross-device link");
+
+            assert_eq!(fs.copy("/a/g", "/b/g").await.unwrap(), 8);
+            assert_eq!(fs.metadata("/b/g").await.unwrap().len(), 8);
+
+            assert!(fs.metadata("/").await.unwrap().is_dir());
+            assert!(fs.metadata("/c").await.is_err());
+            assert!(fs.remove_dir("/a").await.is_err());
+            let err = fs.dir_builder().create("/a").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        });
+    }
+}
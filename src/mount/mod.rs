@@ -0,0 +1,36 @@
+//! A filesystem that mounts other filesystems at different paths.
+//!
+//! [`MountFs`] combines any number of filesystems, of any types, under one
+//! namespace, much like a Unix mount table.  For example, the operating
+//! system's filesystem could be mounted at `/`, an in-memory filesystem at
+//! `/mem`, and an archive at `/archive`:
+//!
+//! ```
+//! # #[cfg(all(feature = "mem-fs", feature = "std-fs"))]
+//! # {
+//! use async_fs_traits::{mem_fs::MemFs, mount::MountFs, std_fs::StdFs};
+//!
+//! let fs = MountFs::new().mount("/", StdFs::new())
+//!                        .mount("/mem", MemFs::new());
+//! # let _ = fs;
+//! # }
+//! ```
+//!
+//! Every operation is passed on to the filesystem with the longest mount
+//! point that its path starts with; see [`MountFs`] for the details.  Files
+//! and directory entries are those of the mounted filesystems, with their
+//! types erased.
+//!
+//! This module is only available when the `mount` feature is enabled.
+
+mod backend;
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{MountDirBuilder, MountDirEntry, MountReadDir};
+#[doc(inline)]
+pub use file::{MountFile, MountOpenOptions};
+#[doc(inline)]
+pub use fs::MountFs;
@@ -0,0 +1,278 @@
+//! [`OverlayDirBuilder`] creates directories in an [`OverlayFs`], and
+//! [`OverlayReadDir`] lists them.
+
+use std::{
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    vec
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use super::{fs::normalize, OverlayFs};
+use crate::{
+    layer::FileOf, AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFsTrait,
+    AsyncReadDirTrait, FileType, Metadata
+};
+
+/// Creates directories in an [`OverlayFs`].
+///
+/// Directories are always created in the upper filesystem, along with any of
+/// their parents that only exist in the lower one.
+pub struct OverlayDirBuilder<L, U> {
+    fs: OverlayFs<L, U>,
+    recursive: bool
+}
+
+impl<L, U> OverlayDirBuilder<L, U> {
+    pub(super) fn new(fs: OverlayFs<L, U>) -> Self {
+        OverlayDirBuilder { fs,
+                            recursive: false }
+    }
+}
+
+impl<L, U> fmt::Debug for OverlayDirBuilder<L, U>
+    where L: fmt::Debug,
+          U: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverlayDirBuilder")
+         .field("fs", &self.fs)
+         .field("recursive", &self.recursive)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<L, U> AsyncDirBuilderTrait for OverlayDirBuilder<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: AsyncRead + Unpin,
+          FileOf<U>: AsyncRead + AsyncWrite + Unpin
+{
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        if !self.recursive {
+            return self.fs.create_dir(&path, false).await;
+        }
+        let mut prefix = PathBuf::from("/");
+        for name in path.components().skip(1) {
+            prefix.push(name);
+            self.fs.create_dir(&prefix, true).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A stream of the entries in a directory of an [`OverlayFs`].
+///
+/// The entries of both filesystems are read when the directory is opened, so
+/// changes made while streaming aren't reflected.
+pub struct OverlayReadDir<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    entries: vec::IntoIter<OverlayDirEntry<L, U>>
+}
+
+impl<L, U> OverlayReadDir<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    pub(super) fn new(entries: Vec<OverlayDirEntry<L, U>>) -> Self {
+        OverlayReadDir { entries: entries.into_iter() }
+    }
+}
+
+// The entries are never pinned.
+impl<L, U> Unpin for OverlayReadDir<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+}
+
+impl<L, U> fmt::Debug for OverlayReadDir<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverlayReadDir")
+         .field("entries", &self.entries)
+         .finish()
+    }
+}
+
+impl<L, U> Stream for OverlayReadDir<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    type Item = io::Result<OverlayDirEntry<L, U>>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 _cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().entries.next().map(Ok))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+#[async_trait]
+impl<L, U> AsyncReadDirTrait<OverlayDirEntry<L, U>> for OverlayReadDir<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+}
+
+/// An entry in a directory of an [`OverlayFs`], from whichever filesystem
+/// holds it.
+pub enum OverlayDirEntry<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    /// An entry of the lower filesystem.
+    Lower(L::DirEntry),
+
+    /// An entry of the upper filesystem.
+    Upper(U::DirEntry)
+}
+
+impl<L, U> Clone for OverlayDirEntry<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    fn clone(&self) -> Self {
+        match self {
+            OverlayDirEntry::Lower(entry) => {
+                OverlayDirEntry::Lower(entry.clone())
+            }
+            OverlayDirEntry::Upper(entry) => {
+                OverlayDirEntry::Upper(entry.clone())
+            }
+        }
+    }
+}
+
+impl<L, U> fmt::Debug for OverlayDirEntry<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverlayDirEntry::Lower(entry) => {
+                f.debug_tuple("Lower").field(entry).finish()
+            }
+            OverlayDirEntry::Upper(entry) => {
+                f.debug_tuple("Upper").field(entry).finish()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<L, U> AsyncDirEntryTrait for OverlayDirEntry<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    async fn path(&self) -> PathBuf {
+        match self {
+            OverlayDirEntry::Lower(entry) => entry.path().await,
+            OverlayDirEntry::Upper(entry) => entry.path().await
+        }
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        match self {
+            OverlayDirEntry::Lower(entry) => entry.metadata().await,
+            OverlayDirEntry::Upper(entry) => entry.metadata().await
+        }
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        match self {
+            OverlayDirEntry::Lower(entry) => entry.file_type().await,
+            OverlayDirEntry::Upper(entry) => entry.file_type().await
+        }
+    }
+
+    async fn file_name(&self) -> OsString {
+        match self {
+            OverlayDirEntry::Lower(entry) => entry.file_name().await,
+            OverlayDirEntry::Upper(entry) => entry.file_name().await
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{mem_fs::MemFs, AsyncFileBuilderTrait};
+
+    #[test]
+    fn entries_are_merged() {
+        block_on(async {
+            let lower = MemFs::new();
+            let upper = MemFs::new();
+            for (fs, names) in [(&lower, ["/both", "/gone", "/lower"]),
+                                (&upper, ["/both", "/upper", "/.wh.gone"])]
+            {
+                for name in names {
+                    fs.open_options()
+                      .write(true)
+                      .create(true)
+                      .open(name)
+                      .await
+                      .unwrap();
+                }
+            }
+            let fs = OverlayFs::new(lower, upper);
+            fs.dir_builder()
+              .recursive(true)
+              .create("/new/dir")
+              .await
+              .unwrap();
+
+            let mut names = Vec::new();
+            let mut entries = fs.read_dir("/").await.unwrap();
+            while let Some(entry) = entries.next().await {
+                let entry = entry.unwrap();
+                let layer = match entry {
+                    OverlayDirEntry::Lower(_) => "lower",
+                    OverlayDirEntry::Upper(_) => "upper"
+                };
+                names.push((entry.path().await, layer));
+            }
+            names.sort();
+            assert_eq!(names,
+                       [(PathBuf::from("/both"), "upper"),
+                        (PathBuf::from("/lower"), "lower"),
+                        (PathBuf::from("/new"), "upper"),
+                        (PathBuf::from("/upper"), "upper")]);
+        });
+    }
+}
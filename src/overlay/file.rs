@@ -0,0 +1,390 @@
+//! [`OverlayOpenOptions`] opens [`OverlayFile`]s in an [`OverlayFs`].
+
+use std::{
+    fmt,
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncBufRead, AsyncRead, AsyncSeek, AsyncWrite};
+
+use super::{
+    fs::{already_exists, normalize, not_found, Layer},
+    OverlayFs
+};
+use crate::{
+    layer::{FileOf, OpenFlags},
+    AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait, Metadata, Permissions
+};
+
+/// The error returned for changes to a file of the lower filesystem.
+fn lower_read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   "file of the lower filesystem is read-only")
+}
+
+/// Options for opening an [`OverlayFile`].
+///
+/// Files that are only opened for reading are opened in whichever filesystem
+/// holds them.  Any of the other options opens the file in the upper
+/// filesystem, copying it up first if it is only found in the lower one.
+pub struct OverlayOpenOptions<L, U> {
+    fs: OverlayFs<L, U>,
+    flags: OpenFlags
+}
+
+impl<L, U> OverlayOpenOptions<L, U> {
+    pub(super) fn new(fs: OverlayFs<L, U>) -> Self {
+        OverlayOpenOptions { fs,
+                             flags: OpenFlags::default() }
+    }
+}
+
+impl<L, U> fmt::Debug for OverlayOpenOptions<L, U>
+    where L: fmt::Debug,
+          U: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverlayOpenOptions")
+         .field("fs", &self.fs)
+         .field("flags", &self.flags)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<L, U> AsyncFileBuilderTrait for OverlayOpenOptions<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: AsyncRead + Unpin,
+          FileOf<U>: AsyncRead + AsyncWrite + Unpin
+{
+    type File = OverlayFile<L, U>;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.flags.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.flags.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.flags.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.flags.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.flags.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.flags.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        let OpenFlags { read,
+                        write,
+                        append,
+                        truncate,
+                        create,
+                        create_new } = self.flags;
+        let path = normalize(path.as_ref())?;
+        let layer = self.fs.find(&path).await;
+        if !(write || append || truncate || create || create_new) {
+            return match layer {
+                Some(Layer::Lower) => {
+                    let mut options = self.fs.lower().open_options();
+                    let file = options.read(read).open(path).await?;
+                    Ok(OverlayFile::Lower(file))
+                }
+                Some(Layer::Upper) => {
+                    let mut options = self.fs.upper().open_options();
+                    let file = options.read(read).open(path).await?;
+                    Ok(OverlayFile::Upper(file))
+                }
+                None => Err(not_found())
+            };
+        }
+
+        let created = match layer {
+            Some(_) if create_new => return Err(already_exists()),
+            Some(Layer::Lower) => {
+                self.fs.copy_up(&path).await?;
+                false
+            }
+            Some(Layer::Upper) => false,
+            None if create => {
+                self.fs.copy_up_parents(&path).await?;
+                true
+            }
+            None => return Err(not_found())
+        };
+        let file = self.fs
+                       .upper()
+                       .open_options()
+                       .read(read)
+                       .write(write)
+                       .append(append)
+                       .truncate(truncate)
+                       .create(create)
+                       .create_new(create_new)
+                       .open(&path)
+                       .await?;
+        if created {
+            self.fs.settle(&path, false).await?;
+        }
+        Ok(OverlayFile::Upper(file))
+    }
+}
+
+/// An open file in an [`OverlayFs`].
+///
+/// Files of the lower filesystem are only ever opened for reading, so writing
+/// to them, or changing their length or permissions, fails with
+/// [`ErrorKind::PermissionDenied`][1].
+///
+/// This implements [`AsyncRead`], [`AsyncBufRead`], and [`AsyncSeek`]
+/// whenever the files of both filesystems do, and [`AsyncWrite`] whenever the
+/// files of the upper filesystem do.
+///
+/// [1]: io::ErrorKind::PermissionDenied
+pub enum OverlayFile<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    /// A file of the lower filesystem.
+    Lower(FileOf<L>),
+
+    /// A file of the upper filesystem.
+    Upper(FileOf<U>)
+}
+
+impl<L, U> fmt::Debug for OverlayFile<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverlayFile::Lower(file) => {
+                f.debug_tuple("Lower").field(file).finish()
+            }
+            OverlayFile::Upper(file) => {
+                f.debug_tuple("Upper").field(file).finish()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<L, U> AsyncFileTrait for OverlayFile<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait
+{
+    async fn sync_all(&self) -> io::Result<()> {
+        match self {
+            OverlayFile::Lower(file) => file.sync_all().await,
+            OverlayFile::Upper(file) => file.sync_all().await
+        }
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        match self {
+            OverlayFile::Lower(file) => file.sync_data().await,
+            OverlayFile::Upper(file) => file.sync_data().await
+        }
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        match self {
+            OverlayFile::Lower(_) => Err(lower_read_only()),
+            OverlayFile::Upper(file) => file.set_len(size).await
+        }
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        match self {
+            OverlayFile::Lower(file) => file.metadata().await,
+            OverlayFile::Upper(file) => file.metadata().await
+        }
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        match self {
+            OverlayFile::Lower(_) => Err(lower_read_only()),
+            OverlayFile::Upper(file) => file.set_permissions(perm).await
+        }
+    }
+}
+
+impl<L, U> AsyncRead for OverlayFile<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: AsyncRead + Unpin,
+          FileOf<U>: AsyncRead + Unpin
+{
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            OverlayFile::Lower(file) => Pin::new(file).poll_read(cx, buf),
+            OverlayFile::Upper(file) => Pin::new(file).poll_read(cx, buf)
+        }
+    }
+}
+
+impl<L, U> AsyncBufRead for OverlayFile<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: AsyncBufRead + Unpin,
+          FileOf<U>: AsyncBufRead + Unpin
+{
+    fn poll_fill_buf(self: Pin<&mut Self>,
+                     cx: &mut Context<'_>)
+                     -> Poll<io::Result<&[u8]>> {
+        match self.get_mut() {
+            OverlayFile::Lower(file) => Pin::new(file).poll_fill_buf(cx),
+            OverlayFile::Upper(file) => Pin::new(file).poll_fill_buf(cx)
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        match self.get_mut() {
+            OverlayFile::Lower(file) => Pin::new(file).consume(amt),
+            OverlayFile::Upper(file) => Pin::new(file).consume(amt)
+        }
+    }
+}
+
+impl<L, U> AsyncWrite for OverlayFile<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: Unpin,
+          FileOf<U>: AsyncWrite + Unpin
+{
+    fn poll_write(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            OverlayFile::Lower(_) => Poll::Ready(Err(lower_read_only())),
+            OverlayFile::Upper(file) => Pin::new(file).poll_write(cx, buf)
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            OverlayFile::Lower(_) => Poll::Ready(Ok(())),
+            OverlayFile::Upper(file) => Pin::new(file).poll_flush(cx)
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            OverlayFile::Lower(_) => Poll::Ready(Ok(())),
+            OverlayFile::Upper(file) => Pin::new(file).poll_close(cx)
+        }
+    }
+}
+
+impl<L, U> AsyncSeek for OverlayFile<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: AsyncSeek + Unpin,
+          FileOf<U>: AsyncSeek + Unpin
+{
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        match self.get_mut() {
+            OverlayFile::Lower(file) => Pin::new(file).poll_seek(cx, pos),
+            OverlayFile::Upper(file) => Pin::new(file).poll_seek(cx, pos)
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncWriteExt};
+
+    use super::*;
+    use crate::mem_fs::MemFs;
+
+    #[test]
+    fn lower_files_are_only_read() {
+        block_on(async {
+            let lower = MemFs::new();
+            lower.open_options()
+                 .write(true)
+                 .create(true)
+                 .open("/f")
+                 .await
+                 .unwrap()
+                 .write_all(b"lower")
+                 .await
+                 .unwrap();
+            let upper = MemFs::new();
+            let fs = OverlayFs::new(lower, upper.clone());
+
+            let mut file =
+                fs.open_options().read(true).open("/f").await.unwrap();
+            assert!(matches!(file, OverlayFile::Lower(_)));
+            let err = file.write_all(b"x").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            let err = file.set_len(0).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+            let err = fs.open_options()
+                        .write(true)
+                        .create_new(true)
+                        .open("/f")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+            let err = fs.open_options()
+                        .write(true)
+                        .open("/missing")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+            let file = fs.open_options()
+                         .write(true)
+                         .truncate(true)
+                         .open("/f")
+                         .await
+                         .unwrap();
+            assert!(matches!(file, OverlayFile::Upper(_)));
+            assert_eq!(upper.metadata("/f").await.unwrap().len(), 0);
+        });
+    }
+}
@@ -0,0 +1,711 @@
+//! [`OverlayFs`] merges a read-only lower filesystem with a writable upper
+//! one.
+
+use std::{
+    collections::HashSet,
+    ffi::{OsStr, OsString},
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_lite::{AsyncWriteExt, StreamExt};
+
+use super::{
+    OverlayDirBuilder, OverlayDirEntry, OverlayOpenOptions, OverlayReadDir
+};
+use crate::{
+    layer::FileOf, AsyncDirBuilderTrait, AsyncDirEntryTrait,
+    AsyncFileBuilderTrait, AsyncFsTrait, AsyncSymLinkTrait, Metadata,
+    Permissions
+};
+
+/// The prefix of the names of whiteouts.
+const WHITEOUT: &str = ".wh.";
+
+/// The name of the file that marks a directory in the upper filesystem as
+/// opaque.
+const OPAQUE: &str = ".wh..wh..opq";
+
+pub(super) fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such file or directory")
+}
+
+pub(super) fn already_exists() -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, "file exists")
+}
+
+fn not_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "not a directory")
+}
+
+fn is_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "is a directory")
+}
+
+fn not_empty() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "directory not empty")
+}
+
+fn cross_device() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "cross-device link")
+}
+
+fn root() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput,
+                   "the root directory can't be removed or renamed")
+}
+
+/// Returns true if `name` is reserved for whiteouts and opaque markers.
+fn is_reserved(name: &OsStr) -> bool {
+    name.to_string_lossy().starts_with(WHITEOUT)
+}
+
+/// Returns the path of the whiteout that hides `path`, which mustn't be the
+/// root.
+fn whiteout(path: &Path) -> PathBuf {
+    let mut name = OsString::from(WHITEOUT);
+    name.push(path.file_name().unwrap_or_default());
+    path.with_file_name(name)
+}
+
+/// Turns `path` into an absolute path without `.` or `..` components.
+pub(super) fn normalize(path: &Path) -> io::Result<PathBuf> {
+    let mut normal = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "path prefixes aren't supported"))
+            }
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            Component::Normal(name) if is_reserved(name) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "names starting with `.wh.` are \
+                                           reserved for whiteouts"))
+            }
+            Component::Normal(name) => normal.push(name)
+        }
+    }
+    Ok(normal)
+}
+
+/// The filesystem that a path was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Layer {
+    Lower,
+    Upper
+}
+
+/// A filesystem that stacks a writable filesystem on a read-only one.
+///
+/// See the [module documentation][1] for how the two are combined.
+/// [`AsyncSymLinkTrait`] is implemented if the upper filesystem implements it.
+///
+/// [1]: super
+pub struct OverlayFs<L, U> {
+    lower: Arc<L>,
+    upper: Arc<U>
+}
+
+impl<L, U> OverlayFs<L, U> {
+    /// Stacks `upper` on top of `lower`.
+    pub fn new(lower: L, upper: U) -> Self {
+        OverlayFs { lower: Arc::new(lower),
+                    upper: Arc::new(upper) }
+    }
+
+    /// Returns a reference to the lower filesystem.
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// Returns a reference to the upper filesystem.
+    pub fn upper(&self) -> &U {
+        &self.upper
+    }
+}
+
+impl<L, U> Clone for OverlayFs<L, U> {
+    fn clone(&self) -> Self {
+        OverlayFs { lower: Arc::clone(&self.lower),
+                    upper: Arc::clone(&self.upper) }
+    }
+}
+
+impl<L, U> std::fmt::Debug for OverlayFs<L, U>
+    where L: std::fmt::Debug,
+          U: std::fmt::Debug
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OverlayFs")
+         .field("lower", &self.lower)
+         .field("upper", &self.upper)
+         .finish()
+    }
+}
+
+impl<L, U> OverlayFs<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: AsyncRead + Unpin,
+          FileOf<U>: AsyncWrite + Unpin
+{
+    /// Returns true if anything, even a dangling symlink, exists at `path` in
+    /// the upper filesystem.
+    async fn in_upper(&self, path: &Path) -> bool {
+        self.upper.symlink_metadata(path).await.is_ok()
+    }
+
+    /// Returns true if nothing in the upper filesystem hides `path` in the
+    /// lower one.  `path` must be normalized.
+    async fn lower_visible(&self, path: &Path) -> bool {
+        let mut prefix = PathBuf::from("/");
+        let mut names = path.components().skip(1).peekable();
+        while let Some(name) = names.next() {
+            if self.in_upper(&prefix.join(OPAQUE)).await {
+                return false;
+            }
+            prefix.push(name);
+            if self.in_upper(&whiteout(&prefix)).await {
+                return false;
+            }
+            if names.peek().is_some() {
+                match self.upper.symlink_metadata(&prefix).await {
+                    Ok(metadata) if !metadata.is_dir() => return false,
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns true if `path` exists in the lower filesystem and isn't hidden.
+    async fn in_lower(&self, path: &Path) -> bool {
+        self.lower_visible(path).await
+        && self.lower.symlink_metadata(path).await.is_ok()
+    }
+
+    /// Returns the filesystem that `path` is found in, if any.  `path` must be
+    /// normalized.
+    pub(super) async fn find(&self, path: &Path) -> Option<Layer> {
+        if self.in_upper(path).await {
+            Some(Layer::Upper)
+        } else if self.in_lower(path).await {
+            Some(Layer::Lower)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the metadata of `path` in `layer`.
+    async fn stat(&self,
+                  layer: Layer,
+                  path: &Path,
+                  follow: bool)
+                  -> io::Result<Metadata> {
+        match (layer, follow) {
+            (Layer::Lower, true) => self.lower.metadata(path).await,
+            (Layer::Lower, false) => self.lower.symlink_metadata(path).await,
+            (Layer::Upper, true) => self.upper.metadata(path).await,
+            (Layer::Upper, false) => self.upper.symlink_metadata(path).await
+        }
+    }
+
+    /// Creates the parent directories of `path` in the upper filesystem, if
+    /// they only exist in the lower one.
+    pub(super) async fn copy_up_parents(&self, path: &Path) -> io::Result<()> {
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            None => return Ok(())
+        };
+        let mut prefix = PathBuf::from("/");
+        for name in parent.components().skip(1) {
+            prefix.push(name);
+            if self.in_upper(&prefix).await {
+                continue;
+            }
+            if !self.in_lower(&prefix).await
+               || !self.lower.metadata(&prefix).await?.is_dir()
+            {
+                return Err(not_found());
+            }
+            self.upper.dir_builder().create(&prefix).await?;
+        }
+        Ok(())
+    }
+
+    /// Copies `path`, which must only be found in the lower filesystem, up
+    /// into the upper one.
+    pub(super) async fn copy_up(&self, path: &Path) -> io::Result<()> {
+        self.copy_up_parents(path).await?;
+        if self.lower.metadata(path).await?.is_dir() {
+            return self.upper.dir_builder().create(path).await;
+        }
+        let mut src = self.lower.open_options().read(true).open(path).await?;
+        let mut dst = self.upper
+                          .open_options()
+                          .write(true)
+                          .create_new(true)
+                          .open(path)
+                          .await?;
+        futures_lite::io::copy(&mut src, &mut dst).await?;
+        dst.close().await
+    }
+
+    /// Tidies up after something new was placed at `path` in the upper
+    /// filesystem: removes the whiteout for it, and marks it as opaque if it
+    /// is a directory that would otherwise be merged with a lower one.
+    pub(super) async fn settle(&self,
+                               path: &Path,
+                               dir: bool)
+                               -> io::Result<()> {
+        let whiteout = whiteout(path);
+        if self.in_upper(&whiteout).await {
+            self.upper.remove_file(&whiteout).await?;
+        }
+        if dir && self.in_lower(path).await {
+            self.upper
+                .open_options()
+                .write(true)
+                .create(true)
+                .open(path.join(OPAQUE))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Hides `path` in the lower filesystem behind a whiteout.
+    async fn hide(&self, path: &Path) -> io::Result<()> {
+        self.copy_up_parents(path).await?;
+        self.upper
+            .open_options()
+            .write(true)
+            .create(true)
+            .open(whiteout(path))
+            .await?;
+        Ok(())
+    }
+
+    /// Creates the directory `path`, which must be normalized.  If `exist_ok`
+    /// is true, a directory that already exists isn't an error.
+    pub(super) async fn create_dir(&self,
+                                   path: &Path,
+                                   exist_ok: bool)
+                                   -> io::Result<()> {
+        if let Some(layer) = self.find(path).await {
+            return if exist_ok && self.stat(layer, path, true).await?.is_dir() {
+                Ok(())
+            } else {
+                Err(already_exists())
+            };
+        }
+        self.copy_up_parents(path).await?;
+        self.upper.dir_builder().create(path).await?;
+        self.settle(path, true).await
+    }
+
+    /// Returns the merged entries of the directory `path`, which must be
+    /// normalized.
+    async fn entries(&self,
+                     path: &Path)
+                     -> io::Result<Vec<OverlayDirEntry<L, U>>> {
+        let mut entries = Vec::new();
+        let merge = match self.find(path).await.ok_or_else(not_found)? {
+            Layer::Upper => {
+                let mut names = HashSet::new();
+                let mut opaque = false;
+                let mut upper = self.upper.read_dir(path).await?;
+                while let Some(entry) = upper.next().await {
+                    let entry = entry?;
+                    let name = entry.file_name().await;
+                    if name == OPAQUE {
+                        opaque = true;
+                    } else if !is_reserved(&name) {
+                        entries.push(OverlayDirEntry::Upper(entry));
+                    }
+                    names.insert(name);
+                }
+                (!opaque
+                 && self.in_lower(path).await
+                 && self.lower.metadata(path).await?.is_dir()).then(|| names)
+            }
+            Layer::Lower => Some(HashSet::new())
+        };
+
+        if let Some(names) = merge {
+            let mut lower = self.lower.read_dir(path).await?;
+            while let Some(entry) = lower.next().await {
+                let entry = entry?;
+                let name = entry.file_name().await;
+                let mut whiteout = OsString::from(WHITEOUT);
+                whiteout.push(&name);
+                if !names.contains(&name) && !names.contains(&whiteout) {
+                    entries.push(OverlayDirEntry::Lower(entry));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Removes the directory `path`, found in `layer`, along with everything
+    /// in it.
+    async fn remove_tree(&self, path: &Path, layer: Layer) -> io::Result<()> {
+        let lower = self.in_lower(path).await;
+        if layer == Layer::Upper {
+            self.upper.remove_dir_all(path).await?;
+        }
+        if lower {
+            self.hide(path).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<L, U> AsyncFsTrait for OverlayFs<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait,
+          FileOf<L>: AsyncRead + Unpin,
+          FileOf<U>: AsyncRead + AsyncWrite + Unpin
+{
+    type DirBuilder = OverlayDirBuilder<L, U>;
+    type DirEntry = OverlayDirEntry<L, U>;
+    type FileBuilder = OverlayOpenOptions<L, U>;
+    type ReadDir = OverlayReadDir<L, U>;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        OverlayDirBuilder::new(self.clone())
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        OverlayOpenOptions::new(self.clone())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        match self.find(&path).await.ok_or_else(not_found)? {
+            Layer::Lower => self.lower.canonicalize(path).await,
+            Layer::Upper => self.upper.canonicalize(path).await
+        }
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = normalize(src.as_ref())?;
+        let dst = normalize(dst.as_ref())?;
+        if src.parent().is_none() || dst.parent().is_none() {
+            return Err(root());
+        }
+        let layer = self.find(&src).await.ok_or_else(not_found)?;
+        let dir = self.stat(layer, &src, false).await?.is_dir();
+        let src_lower = self.in_lower(&src).await;
+        if dir && src_lower {
+            return Err(cross_device());
+        }
+        if src == dst {
+            return Ok(());
+        }
+
+        if let Some(dst_layer) = self.find(&dst).await {
+            let dst_dir = self.stat(dst_layer, &dst, false).await?.is_dir();
+            match (dir, dst_dir) {
+                (true, false) => return Err(not_a_directory()),
+                (false, true) => return Err(is_a_directory()),
+                (true, true) if !self.entries(&dst).await?.is_empty() => {
+                    return Err(not_empty())
+                }
+                (true, true) if dst_layer == Layer::Upper => {
+                    self.upper.remove_dir_all(&dst).await?
+                }
+                _ => {}
+            }
+        }
+        if layer == Layer::Lower {
+            self.copy_up(&src).await?;
+        }
+        self.copy_up_parents(&dst).await?;
+        self.upper.rename(&src, &dst).await?;
+        self.settle(&dst, dir).await?;
+        if src_lower {
+            self.hide(&src).await?;
+        }
+        Ok(())
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        if self.find(&path).await.ok_or_else(not_found)? == Layer::Lower {
+            self.copy_up(&path).await?;
+        }
+        self.upper.set_permissions(path, perm).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = normalize(src.as_ref())?;
+        let dst = normalize(dst.as_ref())?;
+        if self.find(&src).await.ok_or_else(not_found)? == Layer::Lower {
+            self.copy_up(&src).await?;
+        }
+        if self.find(&dst).await.is_some() {
+            return Err(already_exists());
+        }
+        self.copy_up_parents(&dst).await?;
+        self.upper.hard_link(&src, &dst).await?;
+        self.settle(&dst, false).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        match self.find(&path).await.ok_or_else(not_found)? {
+            Layer::Lower => self.lower.read_link(path).await,
+            Layer::Upper => self.upper.read_link(path).await
+        }
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        let layer = self.find(&path).await.ok_or_else(not_found)?;
+        self.stat(layer, &path, false).await
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        let layer = self.find(&path).await.ok_or_else(not_found)?;
+        self.stat(layer, &path, true).await
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let mut src = self.open_options().read(true).open(src).await?;
+        let mut dst = self.open_options()
+                          .write(true)
+                          .create(true)
+                          .truncate(true)
+                          .open(dst)
+                          .await?;
+        let copied = futures_lite::io::copy(&mut src, &mut dst).await?;
+        dst.close().await?;
+        Ok(copied)
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        let layer = self.find(&path).await.ok_or_else(not_found)?;
+        if self.stat(layer, &path, false).await?.is_dir() {
+            return Err(is_a_directory());
+        }
+        let lower = self.in_lower(&path).await;
+        if layer == Layer::Upper {
+            self.upper.remove_file(&path).await?;
+        }
+        if lower {
+            self.hide(&path).await?;
+        }
+        Ok(())
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        Ok(OverlayReadDir::new(self.entries(&path).await?))
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        if path.parent().is_none() {
+            return Err(root());
+        }
+        let layer = self.find(&path).await.ok_or_else(not_found)?;
+        if !self.stat(layer, &path, false).await?.is_dir() {
+            return Err(not_a_directory());
+        }
+        if !self.entries(&path).await?.is_empty() {
+            return Err(not_empty());
+        }
+        self.remove_tree(&path, layer).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = normalize(path.as_ref())?;
+        if path.parent().is_none() {
+            return Err(root());
+        }
+        let layer = self.find(&path).await.ok_or_else(not_found)?;
+        if !self.stat(layer, &path, false).await?.is_dir() {
+            return Err(not_a_directory());
+        }
+        self.remove_tree(&path, layer).await
+    }
+}
+
+#[async_trait]
+impl<L, U> AsyncSymLinkTrait for OverlayFs<L, U>
+    where L: AsyncFsTrait,
+          U: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<L>: AsyncRead + Unpin,
+          FileOf<U>: AsyncRead + AsyncWrite + Unpin
+{
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = normalize(src.as_ref())?;
+        if self.find(&src).await.is_some() {
+            return Err(already_exists());
+        }
+        self.copy_up_parents(&src).await?;
+        self.upper.symlink(&src, dst).await?;
+        self.settle(&src, false).await
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt};
+
+    use super::*;
+    use crate::mem_fs::MemFs;
+
+    async fn write(fs: &MemFs, path: &str, contents: &str) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(contents.as_bytes()).await.unwrap();
+    }
+
+    async fn read<F>(fs: &F, path: &str) -> String
+        where F: AsyncFsTrait,
+              FileOf<F>: AsyncRead + Unpin
+    {
+        let mut file = fs.open_options().read(true).open(path).await.unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).await.unwrap();
+        contents
+    }
+
+    async fn overlay() -> (MemFs, MemFs, OverlayFs<MemFs, MemFs>) {
+        let lower = MemFs::new();
+        lower.dir_builder()
+             .recursive(true)
+             .create("/dir/sub")
+             .await
+             .unwrap();
+        write(&lower, "/dir/a", "lower").await;
+        write(&lower, "/dir/sub/b", "lower").await;
+        let upper = MemFs::new();
+        (lower.clone(), upper.clone(), OverlayFs::new(lower, upper))
+    }
+
+    #[test]
+    fn writes_copy_up() {
+        block_on(async {
+            let (lower, upper, fs) = overlay().await;
+            assert_eq!(read(&fs, "/dir/a").await, "lower");
+            assert!(upper.metadata("/dir").await.is_err());
+
+            let mut file =
+                fs.open_options().append(true).open("/dir/a").await.unwrap();
+            file.write_all(b"+upper").await.unwrap();
+            assert_eq!(read(&fs, "/dir/a").await, "lower+upper");
+            assert_eq!(read(&upper, "/dir/a").await, "lower+upper");
+            assert_eq!(read(&lower, "/dir/a").await, "lower");
+
+            fs.copy("/dir/sub/b", "/dir/c").await.unwrap();
+            assert_eq!(read(&upper, "/dir/c").await, "lower");
+            assert!(upper.metadata("/dir/sub").await.is_err());
+        });
+    }
+
+    #[test]
+    fn removals_leave_whiteouts() {
+        block_on(async {
+            let (lower, upper, fs) = overlay().await;
+            fs.remove_file("/dir/a").await.unwrap();
+            let err = fs.metadata("/dir/a").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(upper.metadata("/dir/.wh.a").await.is_ok());
+            assert!(lower.metadata("/dir/a").await.is_ok());
+
+            let err = fs.remove_dir("/dir").await.unwrap_err();
+            assert_eq!(err.to_string(), "directory not empty");
+            fs.remove_dir_all("/dir").await.unwrap();
+            assert!(fs.metadata("/dir/sub/b").await.is_err());
+
+            // A new directory doesn't bring back the old contents.
+            fs.dir_builder().create("/dir").await.unwrap();
+            assert!(fs.metadata("/dir/sub").await.is_err());
+            assert!(fs.read_dir("/dir").await.unwrap().next().await.is_none());
+            write(&upper, "/dir/x", "").await;
+            fs.remove_dir_all("/dir").await.unwrap();
+            assert!(upper.metadata("/dir").await.is_err());
+            assert!(upper.metadata("/.wh.dir").await.is_ok());
+
+            let err = fs.metadata("/.wh.dir").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn renames() {
+        block_on(async {
+            let (lower, _upper, fs) = overlay().await;
+            fs.rename("/dir/a", "/a").await.unwrap();
+            assert_eq!(read(&fs, "/a").await, "lower");
+            assert!(fs.metadata("/dir/a").await.is_err());
+            assert!(lower.metadata("/dir/a").await.is_ok());
+
+            let err = fs.rename("/dir/sub", "/sub").await.unwrap_err();
+            assert_eq!(err.to_string(), "cross-device link");
+
+            fs.dir_builder().create("/new").await.unwrap();
+            fs.rename("/a", "/new/a").await.unwrap();
+            fs.rename("/new", "/renamed").await.unwrap();
+            assert_eq!(read(&fs, "/renamed/a").await, "lower");
+        });
+    }
+}
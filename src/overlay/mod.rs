@@ -0,0 +1,55 @@
+//! A union filesystem that stacks a writable filesystem on a read-only one.
+//!
+//! [`OverlayFs`] works like Linux's overlayfs.  It combines two filesystems
+//! that share a namespace: a *lower* filesystem that is only ever read, and an
+//! *upper* filesystem that receives every change.  Where both contain the same
+//! path, the upper one wins, except that directories present in both are
+//! merged.
+//!
+//! * Reads fall through to the lower filesystem for paths that aren't in the
+//!   upper one.
+//! * Opening a lower file for writing, or changing its permissions, first
+//!   *copies it up*: its parent directories are created in the upper
+//!   filesystem, and its contents are copied there.  Metadata other than the
+//!   contents isn't copied, and a lower symlink is copied up as a regular file
+//!   holding its target's contents.
+//! * Removing a path that exists in the lower filesystem leaves a *whiteout*
+//!   in the upper one, an empty file named `.wh.` followed by the removed
+//!   name.  Whiteouts hide the lower path from then on.  A directory created
+//!   where a lower one was removed is marked *opaque* by an empty
+//!   `.wh..wh..opq` file inside it, so that the old contents stay hidden.
+//! * [`read_dir()`][1] merges the entries of both filesystems, leaving out
+//!   whiteouts, opaque markers, and the lower entries they hide.
+//!
+//! Names starting with `.wh.` are reserved for this bookkeeping, and paths
+//! that contain them are refused with [`ErrorKind::InvalidInput`][2].  Paths
+//! are normalized lexically before they are looked up, with `..` removing the
+//! component before it, and relative paths are taken to start at the root.
+//! Symlinks are followed by whichever filesystem holds them.
+//!
+//! As in overlayfs, renaming a directory that exists in the lower filesystem
+//! fails with a cross-device error (of kind [`ErrorKind::Other`][3]), since
+//! that would mean copying the whole tree up; callers are expected to fall
+//! back to copying, as they would between filesystems.
+//!
+//! The lower filesystem's files must implement [`AsyncRead`][4], and the
+//! upper filesystem's files [`AsyncRead`][4] and [`AsyncWrite`][5].
+//!
+//! This module is only available when the `overlay` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait::read_dir
+//! [2]: std::io::ErrorKind::InvalidInput
+//! [3]: std::io::ErrorKind::Other
+//! [4]: crate::AsyncRead
+//! [5]: crate::AsyncWrite
+
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{OverlayDirBuilder, OverlayDirEntry, OverlayReadDir};
+#[doc(inline)]
+pub use file::{OverlayFile, OverlayOpenOptions};
+#[doc(inline)]
+pub use fs::OverlayFs;
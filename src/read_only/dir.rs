@@ -0,0 +1,66 @@
+//! [`ReadOnlyDirBuilder`] refuses to create directories in a
+//! [`ReadOnlyFs`][1].
+//!
+//! [1]: super::ReadOnlyFs
+
+use std::{io, path::Path};
+
+use async_trait::async_trait;
+
+use super::fs::read_only;
+use crate::AsyncDirBuilderTrait;
+
+/// A directory builder that never creates anything.
+///
+/// [`create()`][1] always fails with [`ErrorKind::PermissionDenied`][2].
+///
+/// [1]: AsyncDirBuilderTrait::create
+/// [2]: io::ErrorKind::PermissionDenied
+#[derive(Debug, Clone, Default)]
+pub struct ReadOnlyDirBuilder {
+    recursive: bool
+}
+
+impl ReadOnlyDirBuilder {
+    pub(super) fn new() -> Self {
+        ReadOnlyDirBuilder::default()
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for ReadOnlyDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    #[test]
+    fn nothing_is_created() {
+        let mut builder = ReadOnlyDirBuilder::new();
+        builder.recursive(true);
+        let err = block_on(builder.create("/a/b")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,261 @@
+//! [`ReadOnlyOpenOptions`] opens [`ReadOnlyFile`]s in a [`ReadOnlyFs`][1].
+//!
+//! [1]: super::ReadOnlyFs
+
+use std::{
+    fmt,
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncBufRead, AsyncRead, AsyncSeek};
+
+use super::fs::read_only;
+use crate::{
+    layer::{FileOf, OpenFlags},
+    AsyncFileBuilderTrait, AsyncFileTrait, AsyncFsTrait, Metadata, Permissions
+};
+
+/// Options for opening a [`ReadOnlyFile`].
+///
+/// Only [`read()`][1] is passed on to the wrapped filesystem.  Opening a file
+/// with any of the other options set fails with
+/// [`ErrorKind::PermissionDenied`][2], whether or not the file exists.
+///
+/// [1]: AsyncFileBuilderTrait::read
+/// [2]: io::ErrorKind::PermissionDenied
+pub struct ReadOnlyOpenOptions<F>
+    where F: AsyncFsTrait
+{
+    inner: F::FileBuilder,
+    flags: OpenFlags
+}
+
+impl<F> ReadOnlyOpenOptions<F> where F: AsyncFsTrait
+{
+    pub(super) fn new(inner: F::FileBuilder) -> Self {
+        ReadOnlyOpenOptions { inner,
+                              flags: OpenFlags::default() }
+    }
+}
+
+impl<F> fmt::Debug for ReadOnlyOpenOptions<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadOnlyOpenOptions")
+         .field("inner", &self.inner)
+         .field("flags", &self.flags)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFileBuilderTrait for ReadOnlyOpenOptions<F> where F: AsyncFsTrait
+{
+    type File = ReadOnlyFile<F>;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.inner.read(read);
+        self.flags.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.flags.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.flags.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.flags.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.flags.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.flags.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        let OpenFlags { write,
+                        append,
+                        truncate,
+                        create,
+                        create_new,
+                        .. } = self.flags;
+        if write || append || truncate || create || create_new {
+            return Err(read_only());
+        }
+        let inner = self.inner.open(path).await?;
+        Ok(ReadOnlyFile { inner })
+    }
+}
+
+/// An open file in a [`ReadOnlyFs`][1].
+///
+/// This implements [`AsyncRead`], [`AsyncBufRead`], and [`AsyncSeek`]
+/// whenever the wrapped file does, but never [`AsyncWrite`][2].
+/// [`set_len()`][3] and [`set_permissions()`][4] always fail with
+/// [`ErrorKind::PermissionDenied`][5].
+///
+/// [1]: super::ReadOnlyFs
+/// [2]: crate::AsyncWrite
+/// [3]: AsyncFileTrait::set_len
+/// [4]: AsyncFileTrait::set_permissions
+/// [5]: io::ErrorKind::PermissionDenied
+pub struct ReadOnlyFile<F>
+    where F: AsyncFsTrait
+{
+    inner: FileOf<F>
+}
+
+impl<F> fmt::Debug for ReadOnlyFile<F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadOnlyFile")
+         .field("inner", &self.inner)
+         .finish()
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFileTrait for ReadOnlyFile<F> where F: AsyncFsTrait
+{
+    async fn sync_all(&self) -> io::Result<()> {
+        self.inner.sync_all().await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.inner.sync_data().await
+    }
+
+    async fn set_len(&self, _size: u64) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        self.inner.metadata().await
+    }
+
+    async fn set_permissions(&self, _perm: Permissions) -> io::Result<()> {
+        Err(read_only())
+    }
+}
+
+impl<F> AsyncRead for ReadOnlyFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncRead + Unpin
+{
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<F> AsyncBufRead for ReadOnlyFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncBufRead + Unpin
+{
+    fn poll_fill_buf(self: Pin<&mut Self>,
+                     cx: &mut Context<'_>)
+                     -> Poll<io::Result<&[u8]>> {
+        Pin::new(&mut self.get_mut().inner).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.get_mut().inner).consume(amt)
+    }
+}
+
+impl<F> AsyncSeek for ReadOnlyFile<F>
+    where F: AsyncFsTrait,
+          FileOf<F>: AsyncSeek + Unpin
+{
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.get_mut().inner).poll_seek(cx, pos)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{layer::FsLayer, mem_fs::MemFs, read_only::ReadOnlyLayer};
+
+    #[test]
+    fn files_open_for_reading_only() {
+        block_on(async {
+            let mem = MemFs::new();
+            let mut file = mem.open_options()
+                              .write(true)
+                              .create(true)
+                              .open("/f")
+                              .await
+                              .unwrap();
+            file.write_all(b"contents").await.unwrap();
+
+            let fs = ReadOnlyLayer::new().layer(mem);
+            let mut file =
+                fs.open_options().read(true).open("/f").await.unwrap();
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, "contents");
+            let err = file.set_len(0).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+            for options in [OpenFlags { write: true,
+                                        ..OpenFlags::default() },
+                            OpenFlags { append: true,
+                                        ..OpenFlags::default() },
+                            OpenFlags { truncate: true,
+                                        ..OpenFlags::default() },
+                            OpenFlags { create: true,
+                                        ..OpenFlags::default() },
+                            OpenFlags { create_new: true,
+                                        ..OpenFlags::default() }]
+            {
+                let err = fs.open_options()
+                            .read(true)
+                            .write(options.write)
+                            .append(options.append)
+                            .truncate(options.truncate)
+                            .create(options.create)
+                            .create_new(options.create_new)
+                            .open("/new")
+                            .await
+                            .unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            }
+            assert!(fs.metadata("/new").await.is_err());
+        });
+    }
+}
@@ -0,0 +1,241 @@
+//! [`ReadOnlyLayer`] wraps a filesystem in a [`ReadOnlyFs`].
+
+use std::{
+    io,
+    path::{Path, PathBuf}
+};
+
+use async_trait::async_trait;
+
+use super::{ReadOnlyDirBuilder, ReadOnlyOpenOptions};
+use crate::{
+    layer::FsLayer, AsyncFsTrait, AsyncSymLinkTrait, Metadata, Permissions
+};
+
+/// The error returned for every operation that would change the filesystem.
+pub(super) fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "filesystem is read-only")
+}
+
+/// A layer that makes a filesystem read-only.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOnlyLayer {
+    _private: ()
+}
+
+impl ReadOnlyLayer {
+    /// Creates a new layer.
+    pub fn new() -> Self {
+        ReadOnlyLayer::default()
+    }
+}
+
+impl<F> FsLayer<F> for ReadOnlyLayer where F: AsyncFsTrait
+{
+    type Fs = ReadOnlyFs<F>;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        ReadOnlyFs::new(inner)
+    }
+}
+
+/// A filesystem that can be read, but not changed.
+///
+/// Queries are passed on to the wrapped filesystem, and directory streams and
+/// entries are the wrapped filesystem's own, since they can't change
+/// anything.  [`rename()`][1], [`set_permissions()`][2], [`hard_link()`][3],
+/// [`copy()`][4], [`remove_file()`][5], [`remove_dir()`][6],
+/// [`remove_dir_all()`][7], and [`symlink()`][8] all fail with
+/// [`ErrorKind::PermissionDenied`][9] without calling the wrapped filesystem.
+/// `symlink()` is refused even if the wrapped filesystem doesn't support
+/// symlinks.
+///
+/// There is no way back to the wrapped filesystem, so code that is handed a
+/// `ReadOnlyFs` can't get around it by unwrapping it:
+///
+/// ```compile_fail
+/// use async_fs_traits::{read_only::ReadOnlyFs, AsyncFsTrait};
+///
+/// fn unwrap<F: AsyncFsTrait>(fs: ReadOnlyFs<F>) -> F {
+///     fs.into_inner()
+/// }
+///
+/// fn reach<F: AsyncFsTrait>(fs: &ReadOnlyFs<F>) -> &F {
+///     fs.get_ref()
+/// }
+/// ```
+///
+/// [1]: AsyncFsTrait::rename
+/// [2]: AsyncFsTrait::set_permissions
+/// [3]: AsyncFsTrait::hard_link
+/// [4]: AsyncFsTrait::copy
+/// [5]: AsyncFsTrait::remove_file
+/// [6]: AsyncFsTrait::remove_dir
+/// [7]: AsyncFsTrait::remove_dir_all
+/// [8]: AsyncSymLinkTrait::symlink
+/// [9]: io::ErrorKind::PermissionDenied
+#[derive(Debug, Clone)]
+pub struct ReadOnlyFs<F> {
+    inner: F
+}
+
+impl<F> ReadOnlyFs<F> {
+    /// Wraps `inner`.
+    pub fn new(inner: F) -> Self {
+        ReadOnlyFs { inner }
+    }
+}
+
+#[async_trait]
+impl<F> AsyncFsTrait for ReadOnlyFs<F> where F: AsyncFsTrait
+{
+    type DirBuilder = ReadOnlyDirBuilder;
+    type DirEntry = F::DirEntry;
+    type FileBuilder = ReadOnlyOpenOptions<F>;
+    type ReadDir = F::ReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        ReadOnlyDirBuilder::new()
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        ReadOnlyOpenOptions::new(self.inner.open_options())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.canonicalize(path).await
+    }
+
+    async fn rename<P, Q>(&self, _src: P, _dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn set_permissions<P>(&self,
+                                _path: P,
+                                _perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn hard_link<P, Q>(&self, _src: P, _dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.read_link(path).await
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.symlink_metadata(path).await
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.metadata(path).await
+    }
+
+    async fn copy<P, Q>(&self, _src: P, _dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn remove_file<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.read_dir(path).await
+    }
+
+    async fn remove_dir<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn remove_dir_all<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+}
+
+#[async_trait]
+impl<F> AsyncSymLinkTrait for ReadOnlyFs<F> where F: AsyncFsTrait
+{
+    async fn symlink<P, Q>(&self, _src: P, _dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{mem_fs::MemFs, AsyncDirBuilderTrait};
+
+    #[test]
+    fn mutations_are_refused_and_queries_forwarded() {
+        block_on(async {
+            let mem = MemFs::new();
+            mem.dir_builder().create("/dir").await.unwrap();
+            let fs = ReadOnlyLayer::new().layer(mem);
+
+            let denied = [fs.rename("/dir", "/moved").await,
+                          fs.set_permissions("/dir", Permissions::new(true))
+                            .await,
+                          fs.hard_link("/dir", "/link").await,
+                          fs.copy("/dir", "/copy").await.map(drop),
+                          fs.remove_file("/dir").await,
+                          fs.remove_dir("/dir").await,
+                          fs.remove_dir_all("/dir").await,
+                          fs.symlink("/sym", "/dir").await,
+                          fs.dir_builder().create("/new").await];
+            for result in denied {
+                let err = result.unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            }
+
+            assert!(fs.metadata("/dir").await.unwrap().is_dir());
+            assert!(!fs.metadata("/dir")
+                       .await
+                       .unwrap()
+                       .permissions()
+                       .readonly());
+        });
+    }
+}
@@ -0,0 +1,38 @@
+//! A layer that makes any filesystem read-only.
+//!
+//! [`ReadOnlyLayer`] wraps a filesystem in a [`ReadOnlyFs`], which refuses
+//! every operation that could change the filesystem with
+//! [`ErrorKind::PermissionDenied`][1], before the wrapped filesystem ever sees
+//! it.  Files can only be opened for reading, and [`ReadOnlyFile`] doesn't
+//! implement [`AsyncWrite`][2] at all, so code that is handed a `ReadOnlyFs`
+//! can't even be written to try:
+//!
+//! ```compile_fail
+//! use async_fs_traits::{
+//!     read_only::ReadOnlyFs, AsyncFileBuilderTrait, AsyncFsTrait
+//! };
+//! use futures_lite::AsyncWriteExt;
+//!
+//! async fn write<F>(fs: &ReadOnlyFs<F>) -> std::io::Result<()>
+//!     where F: AsyncFsTrait
+//! {
+//!     let mut file = fs.open_options().read(true).open("/f").await?;
+//!     file.write_all(b"nope").await // `ReadOnlyFile` isn't `AsyncWrite`
+//! }
+//! ```
+//!
+//! This module is only available when the `read-only` feature is enabled.
+//!
+//! [1]: std::io::ErrorKind::PermissionDenied
+//! [2]: crate::AsyncWrite
+
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::ReadOnlyDirBuilder;
+#[doc(inline)]
+pub use file::{ReadOnlyFile, ReadOnlyOpenOptions};
+#[doc(inline)]
+pub use fs::{ReadOnlyFs, ReadOnlyLayer};
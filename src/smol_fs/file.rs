@@ -0,0 +1,242 @@
+//! [`SmolOpenOptions`] opens [`SmolFile`]s through [`async_fs`].
+
+use std::{
+    io::{self, IoSlice, IoSliceMut, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use crate::{
+    metadata::to_std_permissions, AsyncFileBuilderTrait, AsyncFileTrait,
+    Metadata, Permissions
+};
+
+/// Options for opening a [`SmolFile`].
+///
+/// This is a thin wrapper around [`async_fs::OpenOptions`].  Every option
+/// starts out set to `false`.
+#[derive(Debug, Clone)]
+pub struct SmolOpenOptions {
+    inner: async_fs::OpenOptions
+}
+
+impl SmolOpenOptions {
+    /// Creates a blank set of options.
+    pub fn new() -> Self {
+        SmolOpenOptions { inner: async_fs::OpenOptions::new() }
+    }
+}
+
+impl Default for SmolOpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<async_fs::OpenOptions> for SmolOpenOptions {
+    fn from(inner: async_fs::OpenOptions) -> Self {
+        SmolOpenOptions { inner }
+    }
+}
+
+#[async_trait]
+impl AsyncFileBuilderTrait for SmolOpenOptions {
+    type File = SmolFile;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.inner.read(read);
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.inner.write(write);
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.inner.append(append);
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.inner.truncate(truncate);
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.inner.create(create);
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.inner.create_new(create_new);
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.inner.open(path.as_ref()).await?.into())
+    }
+}
+
+/// An open file, as seen through [`async_fs`].
+///
+/// This wraps an [`async_fs::File`], forwarding the [`futures_io`]
+/// traits to it unchanged.  The original file can be recovered with
+/// [`into_inner()`][1].
+///
+/// [1]: SmolFile::into_inner
+#[derive(Debug)]
+pub struct SmolFile {
+    inner: async_fs::File
+}
+
+impl SmolFile {
+    /// Returns a reference to the wrapped async-fs file.
+    pub fn get_ref(&self) -> &async_fs::File {
+        &self.inner
+    }
+
+    /// Unwraps this file, returning the wrapped async-fs file.
+    pub fn into_inner(self) -> async_fs::File {
+        self.inner
+    }
+}
+
+impl From<async_fs::File> for SmolFile {
+    fn from(inner: async_fs::File) -> Self {
+        SmolFile { inner }
+    }
+}
+
+#[async_trait]
+impl AsyncFileTrait for SmolFile {
+    async fn sync_all(&self) -> io::Result<()> {
+        self.inner.sync_all().await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.inner.sync_data().await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.inner.set_len(size).await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.inner.metadata().await?.into())
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        let current = self.inner.metadata().await?.permissions();
+        let std_perm = to_std_permissions(perm, || Ok(current))?;
+        self.inner.set_permissions(std_perm).await
+    }
+}
+
+impl AsyncRead for SmolFile {
+    fn poll_read(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(mut self: Pin<&mut Self>,
+                          cx: &mut Context<'_>,
+                          bufs: &mut [IoSliceMut<'_>])
+                          -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl AsyncWrite for SmolFile {
+    fn poll_write(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(mut self: Pin<&mut Self>,
+                           cx: &mut Context<'_>,
+                           bufs: &[IoSlice<'_>])
+                           -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl AsyncSeek for SmolFile {
+    fn poll_seek(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_seek(cx, pos)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{
+        future::block_on, AsyncReadExt, AsyncSeekExt, AsyncWriteExt
+    };
+
+    use super::*;
+    use crate::{smol_fs::SmolFs, AsyncFsTrait};
+
+    #[test]
+    fn create_new_then_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+
+        block_on(async {
+            let fs = SmolFs::new();
+            let mut file = fs.open_options()
+                             .read(true)
+                             .write(true)
+                             .create_new(true)
+                             .open(&path)
+                             .await
+                             .unwrap();
+            file.write_all(b"hello world").await.unwrap();
+            file.seek(SeekFrom::Start(6)).await.unwrap();
+
+            let mut buf = String::new();
+            file.read_to_string(&mut buf).await.unwrap();
+            assert_eq!(buf, "world");
+
+            let again = fs.open_options()
+                          .write(true)
+                          .create_new(true)
+                          .open(&path)
+                          .await;
+            assert_eq!(again.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        });
+    }
+}
@@ -0,0 +1,223 @@
+//! [`SmolFs`] implements [`AsyncFsTrait`] on top of [`async_fs`].
+
+use std::{
+    io,
+    path::{Path, PathBuf}
+};
+
+use async_trait::async_trait;
+
+use super::{SmolDirBuilder, SmolDirEntry, SmolOpenOptions, SmolReadDir};
+use crate::{
+    metadata::to_std_permissions, AsyncFsTrait, AsyncSymLinkTrait, Metadata,
+    Permissions
+};
+
+/// The operating system's filesystem, as seen through [`async_fs`].
+///
+/// Every method is a thin wrapper around the function of the same name in
+/// [`async_fs`].  Relative paths are resolved against the process's
+/// current working directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SmolFs;
+
+impl SmolFs {
+    /// Creates a handle to the operating system's filesystem.
+    pub fn new() -> Self {
+        SmolFs
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for SmolFs {
+    type DirBuilder = SmolDirBuilder;
+    type DirEntry = SmolDirEntry;
+    type FileBuilder = SmolOpenOptions;
+    type ReadDir = SmolReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        SmolDirBuilder::new()
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        SmolOpenOptions::new()
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        async_fs::canonicalize(path).await
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        async_fs::rename(src.as_ref(), dst.as_ref()).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let current = async_fs::metadata(path).await?.permissions();
+        let std_perm = to_std_permissions(perm, || Ok(current))?;
+        async_fs::set_permissions(path, std_perm).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        async_fs::hard_link(src.as_ref(), dst.as_ref()).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        async_fs::read_link(path).await
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_fs::symlink_metadata(path.as_ref()).await?.into())
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_fs::metadata(path.as_ref()).await?.into())
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        async_fs::copy(src.as_ref(), dst.as_ref()).await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        async_fs::remove_file(path.as_ref()).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        Ok(async_fs::read_dir(path.as_ref()).await?.into())
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        async_fs::remove_dir(path.as_ref()).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        async_fs::remove_dir_all(path.as_ref()).await
+    }
+}
+
+#[async_trait]
+impl AsyncSymLinkTrait for SmolFs {
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        symlink(src.as_ref().to_owned(), dst.as_ref().to_owned()).await
+    }
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// Note that the argument order follows [`AsyncSymLinkTrait::symlink()`],
+/// which is the reverse of the platform-specific functions in async-fs.
+#[cfg(unix)]
+async fn symlink(src: PathBuf, dst: PathBuf) -> io::Result<()> {
+    async_fs::unix::symlink(dst, src).await
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// Windows needs to know whether the target is a directory, so a relative
+/// `dst` is resolved against the parent of `src` to find out.  Targets that
+/// don't exist are linked as files.
+#[cfg(windows)]
+async fn symlink(src: PathBuf, dst: PathBuf) -> io::Result<()> {
+    let target = match src.parent() {
+        Some(parent) => parent.join(&dst),
+        None => dst.clone()
+    };
+    match async_fs::metadata(target).await {
+        Ok(metadata) if metadata.is_dir() => {
+            async_fs::windows::symlink_dir(dst, src).await
+        }
+        _ => async_fs::windows::symlink_file(dst, src).await
+    }
+}
+
+/// Symlinks aren't supported on this platform.
+#[cfg(not(any(unix, windows)))]
+async fn symlink(_src: PathBuf, _dst: PathBuf) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "symlinks are not supported on this platform"))
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    #[test]
+    fn rename_overwrites_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        std::fs::write(&src, b"new").unwrap();
+        std::fs::write(&dst, b"old").unwrap();
+
+        block_on(SmolFs::new().rename(&src, &dst)).unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(std::fs::read(&dst).unwrap(), b"new");
+    }
+
+    #[test]
+    fn symlink_and_set_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        std::fs::write(&target, b"12345").unwrap();
+
+        let fs = SmolFs::new();
+        block_on(async {
+            fs.symlink(&link, "target").await.unwrap();
+            assert_eq!(fs.read_link(&link).await.unwrap(),
+                       PathBuf::from("target"));
+            assert_eq!(fs.metadata(&link).await.unwrap().len(), 5);
+
+            fs.set_permissions(&target, Permissions::new(true))
+              .await
+              .unwrap();
+            let metadata = fs.metadata(&target).await.unwrap();
+            assert!(metadata.permissions().readonly());
+        });
+    }
+}
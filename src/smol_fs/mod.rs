@@ -0,0 +1,38 @@
+//! A backend for the operating system's filesystem, built on the
+//! [`async_fs`] crate used by smol.
+//!
+//! async-fs already uses the [`futures_io`] traits for its files, so this
+//! module is mostly a matter of converting between async-fs's types and the
+//! ones used by this crate.  Blocking work is done on the thread pool of the
+//! [`blocking`][1] crate, so these types work with any async runtime, not just
+//! smol.
+//!
+//! The entry point is [`SmolFs`], which implements [`AsyncFsTrait`][2] and
+//! [`AsyncSymLinkTrait`][3].  Everything else in this module is reached
+//! through it:
+//!
+//! - [`SmolOpenOptions`] is returned by [`SmolFs::open_options()`][4], and
+//!   opens [`SmolFile`]s.
+//! - [`SmolDirBuilder`] is returned by [`SmolFs::dir_builder()`][5].
+//! - [`SmolReadDir`] is returned by [`SmolFs::read_dir()`][6], and yields
+//!   [`SmolDirEntry`]s.
+//!
+//! This module is only available when the `smol` feature is enabled.
+//!
+//! [1]: https://docs.rs/blocking
+//! [2]: crate::AsyncFsTrait
+//! [3]: crate::AsyncSymLinkTrait
+//! [4]: crate::AsyncFsTrait::open_options
+//! [5]: crate::AsyncFsTrait::dir_builder
+//! [6]: crate::AsyncFsTrait::read_dir
+
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{SmolDirBuilder, SmolDirEntry, SmolReadDir};
+#[doc(inline)]
+pub use file::{SmolFile, SmolOpenOptions};
+#[doc(inline)]
+pub use fs::SmolFs;
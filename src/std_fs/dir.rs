@@ -0,0 +1,200 @@
+//! Directory types for the operating system's filesystem.
+//!
+//! [`StdDirBuilder`] creates directories, and [`StdReadDir`] streams the
+//! [`StdDirEntry`]s of an existing directory.
+
+use std::{
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use blocking::{unblock, Unblock};
+use futures_core::{ready, Stream};
+
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncReadDirTrait, FileType,
+    Metadata
+};
+
+/// A builder for creating directories on the operating system's filesystem.
+///
+/// This mirrors [`std::fs::DirBuilder`].
+#[derive(Debug, Clone, Default)]
+pub struct StdDirBuilder {
+    recursive: bool,
+
+    #[cfg(unix)]
+    mode: Option<u32>
+}
+
+impl StdDirBuilder {
+    /// Creates a blank set of options.
+    ///
+    /// The [`recursive()`][1] option is initially set to `false`.
+    ///
+    /// [1]: AsyncDirBuilderTrait::recursive
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the mode to create new directories with.
+    ///
+    /// This option defaults to `0o777`, modified by the process's umask.
+    #[cfg(unix)]
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    fn to_std(&self) -> std::fs::DirBuilder {
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(self.recursive);
+
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::DirBuilderExt;
+
+            builder.mode(mode);
+        }
+
+        builder
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for StdDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        let builder = self.to_std();
+        unblock(move || builder.create(path)).await
+    }
+}
+
+/// A stream of entries in a directory on the operating system's filesystem.
+///
+/// The underlying [`std::fs::ReadDir`] iterator is advanced on the
+/// [`blocking`][1] thread pool.
+///
+/// [1]: https://docs.rs/blocking
+pub struct StdReadDir {
+    inner: Unblock<std::fs::ReadDir>
+}
+
+impl StdReadDir {
+    pub(super) fn new(inner: Unblock<std::fs::ReadDir>) -> Self {
+        StdReadDir { inner }
+    }
+}
+
+impl fmt::Debug for StdReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StdReadDir").finish_non_exhaustive()
+    }
+}
+
+impl Stream for StdReadDir {
+    type Item = io::Result<StdDirEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let entry = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        Poll::Ready(entry.map(|entry| entry.map(StdDirEntry::from)))
+    }
+}
+
+impl AsyncReadDirTrait<StdDirEntry> for StdReadDir {}
+
+/// An entry in a directory on the operating system's filesystem.
+///
+/// This wraps a [`std::fs::DirEntry`], so [`path()`][1] and
+/// [`file_name()`][2] never touch the disk.
+///
+/// [1]: AsyncDirEntryTrait::path
+/// [2]: AsyncDirEntryTrait::file_name
+#[derive(Debug, Clone)]
+pub struct StdDirEntry {
+    inner: Arc<std::fs::DirEntry>
+}
+
+impl From<std::fs::DirEntry> for StdDirEntry {
+    fn from(inner: std::fs::DirEntry) -> Self {
+        StdDirEntry { inner: Arc::new(inner) }
+    }
+}
+
+#[async_trait]
+impl AsyncDirEntryTrait for StdDirEntry {
+    async fn path(&self) -> PathBuf {
+        self.inner.path()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        // `std::fs::DirEntry::metadata()` doesn't follow symlinks, but this
+        // trait method is documented to, so go through the path instead.
+        let path = self.inner.path();
+        unblock(move || std::fs::metadata(path).map(Metadata::from)).await
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        let inner = self.inner.clone();
+        unblock(move || inner.file_type().map(FileType::from)).await
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.inner.file_name()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{std_fs::StdFs, AsyncFsTrait};
+
+    #[test]
+    fn recursive_create_and_read_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+
+        let fs = StdFs::new();
+        block_on(async {
+            assert!(fs.dir_builder().create(&nested).await.is_err());
+            fs.dir_builder()
+              .recursive(true)
+              .create(&nested)
+              .await
+              .unwrap();
+
+            let mut entries = fs.read_dir(dir.path()).await.unwrap();
+            let entry = entries.next().await.unwrap().unwrap();
+            assert!(entries.next().await.is_none());
+
+            assert_eq!(entry.file_name().await, OsString::from("a"));
+            assert_eq!(entry.path().await, dir.path().join("a"));
+            assert!(entry.file_type().await.unwrap().is_dir());
+        });
+    }
+}
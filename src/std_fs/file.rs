@@ -0,0 +1,381 @@
+//! [`StdOpenOptions`] opens [`StdFile`]s on the operating system's filesystem.
+
+use std::{
+    fmt,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+use blocking::{unblock, Unblock};
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::AsyncWriteExt;
+
+use crate::{
+    metadata::to_std_permissions, AsyncFileBuilderTrait, AsyncFileTrait,
+    Metadata, Permissions
+};
+
+/// Options for opening a [`StdFile`].
+///
+/// This is a thin wrapper around [`std::fs::OpenOptions`].  Every option
+/// starts out set to `false`.
+#[derive(Debug, Clone)]
+pub struct StdOpenOptions {
+    inner: std::fs::OpenOptions
+}
+
+impl StdOpenOptions {
+    /// Creates a blank set of options.
+    pub fn new() -> Self {
+        StdOpenOptions { inner: std::fs::OpenOptions::new() }
+    }
+}
+
+impl Default for StdOpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<std::fs::OpenOptions> for StdOpenOptions {
+    fn from(inner: std::fs::OpenOptions) -> Self {
+        StdOpenOptions { inner }
+    }
+}
+
+#[async_trait]
+impl AsyncFileBuilderTrait for StdOpenOptions {
+    type File = StdFile;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.inner.read(read);
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.inner.write(write);
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.inner.append(append);
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.inner.truncate(truncate);
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.inner.create(create);
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.inner.create_new(create_new);
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        let options = self.inner.clone();
+        let file = unblock(move || options.open(path)).await?;
+        Ok(StdFile::from(file))
+    }
+}
+
+/// An open file on the operating system's filesystem.
+///
+/// Reads, writes, and seeks are performed on the [`blocking`][1] thread pool,
+/// and are buffered while they are in flight.  Because of this, a read may
+/// have pulled more data from the OS than has been handed back to the caller;
+/// the file keeps track of the logical cursor position and puts the OS cursor
+/// back in the right place before the next write or seek.
+///
+/// Written data may sit in a buffer until the file is flushed.  The methods of
+/// [`AsyncFileTrait`] flush any such data before they do anything else.
+///
+/// [1]: https://docs.rs/blocking
+pub struct StdFile {
+    /// Always accessible reference to the file.
+    file: Arc<std::fs::File>,
+
+    /// Performs blocking I/O operations on a thread pool.
+    unblock: Mutex<Unblock<ArcFile>>,
+
+    /// Logical file cursor, tracked when reading from the file.
+    ///
+    /// This will be set to an error if the file is not seekable.
+    read_pos: Option<io::Result<u64>>,
+
+    /// Set to `true` if the file needs flushing.
+    is_dirty: bool
+}
+
+impl StdFile {
+    /// Puts the OS cursor back to the logical cursor after a read.
+    fn poll_reposition(&mut self,
+                       cx: &mut Context<'_>)
+                       -> Poll<io::Result<()>> {
+        if let Some(Ok(read_pos)) = self.read_pos {
+            let unblock = Pin::new(self.unblock.get_mut());
+            ready!(unblock.poll_seek(cx, SeekFrom::Start(read_pos)))?;
+        }
+        self.read_pos = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl From<std::fs::File> for StdFile {
+    fn from(file: std::fs::File) -> Self {
+        let file = Arc::new(file);
+        let unblock = Mutex::new(Unblock::new(ArcFile(file.clone())));
+        StdFile { file,
+                  unblock,
+                  read_pos: None,
+                  is_dirty: false }
+    }
+}
+
+impl fmt::Debug for StdFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.file.fmt(f)
+    }
+}
+
+#[async_trait]
+impl AsyncFileTrait for StdFile {
+    async fn sync_all(&self) -> io::Result<()> {
+        let mut inner = self.unblock.lock().await;
+        inner.flush().await?;
+        let file = self.file.clone();
+        unblock(move || file.sync_all()).await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        let mut inner = self.unblock.lock().await;
+        inner.flush().await?;
+        let file = self.file.clone();
+        unblock(move || file.sync_data()).await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        let mut inner = self.unblock.lock().await;
+        inner.flush().await?;
+        let file = self.file.clone();
+        unblock(move || file.set_len(size)).await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        let mut inner = self.unblock.lock().await;
+        inner.flush().await?;
+        let file = self.file.clone();
+        unblock(move || file.metadata().map(Metadata::from)).await
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        let mut inner = self.unblock.lock().await;
+        inner.flush().await?;
+        let file = self.file.clone();
+        unblock(move || {
+            let std_perm = to_std_permissions(perm, || {
+                Ok(file.metadata()?.permissions())
+            })?;
+            file.set_permissions(std_perm)
+        }).await
+    }
+}
+
+impl AsyncRead for StdFile {
+    fn poll_read(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        // Before reading begins, remember the current cursor position.
+        if self.read_pos.is_none() {
+            let pos = ready!(self.as_mut().poll_seek(cx, SeekFrom::Current(0)));
+            self.read_pos = Some(pos);
+        }
+
+        let n = ready!(Pin::new(self.unblock.get_mut()).poll_read(cx, buf))?;
+
+        // Update the logical cursor if the file is seekable.
+        if let Some(Ok(pos)) = self.read_pos.as_mut() {
+            *pos += n as u64;
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for StdFile {
+    fn poll_write(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        ready!(self.poll_reposition(cx))?;
+        self.is_dirty = true;
+        Pin::new(self.unblock.get_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        if self.is_dirty {
+            ready!(Pin::new(self.unblock.get_mut()).poll_flush(cx))?;
+            self.is_dirty = false;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Pin::new(self.unblock.get_mut()).poll_close(cx)
+    }
+}
+
+impl AsyncSeek for StdFile {
+    fn poll_seek(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        ready!(self.poll_reposition(cx))?;
+        Pin::new(self.unblock.get_mut()).poll_seek(cx, pos)
+    }
+}
+
+/// A reference counted file that implements the blocking I/O traits, so that
+/// it can be handed to [`Unblock`] while [`StdFile`] keeps its own reference.
+struct ArcFile(Arc<std::fs::File>);
+
+impl Read for ArcFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self.0).read(buf)
+    }
+}
+
+impl Write for ArcFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.0).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.0).flush()
+    }
+}
+
+impl Seek for ArcFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        (&*self.0).seek(pos)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt, AsyncSeekExt};
+
+    use super::*;
+    use crate::{std_fs::StdFs, AsyncFsTrait};
+
+    #[test]
+    fn create_new_fails_on_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, b"").unwrap();
+
+        let result = block_on(StdFs::new().open_options()
+                                          .write(true)
+                                          .create_new(true)
+                                          .open(&path));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn write_read_and_seek_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+
+        block_on(async {
+            let mut file = StdFs::new().open_options()
+                                       .read(true)
+                                       .write(true)
+                                       .create(true)
+                                       .open(&path)
+                                       .await
+                                       .unwrap();
+            file.write_all(b"hello world").await.unwrap();
+            file.seek(SeekFrom::Start(6)).await.unwrap();
+
+            let mut buf = String::new();
+            file.read_to_string(&mut buf).await.unwrap();
+            assert_eq!(buf, "world");
+
+            file.sync_all().await.unwrap();
+            assert_eq!(file.metadata().await.unwrap().len(), 11);
+        });
+    }
+
+    #[test]
+    fn set_len_zero_fills_and_keeps_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+
+        block_on(async {
+            let mut file = StdFs::new().open_options()
+                                       .read(true)
+                                       .write(true)
+                                       .create(true)
+                                       .open(&path)
+                                       .await
+                                       .unwrap();
+            file.write_all(b"abc").await.unwrap();
+            file.set_len(6).await.unwrap();
+            assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 3);
+
+            file.seek(SeekFrom::Start(0)).await.unwrap();
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(buf, b"abc\0\0\0");
+        });
+    }
+
+    #[test]
+    fn metadata_sees_buffered_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+
+        block_on(async {
+            let mut file = StdFs::new().open_options()
+                                       .write(true)
+                                       .create(true)
+                                       .open(&path)
+                                       .await
+                                       .unwrap();
+            let chunk = [1; 64 << 10];
+            for i in 1..=64 {
+                file.write_all(&chunk).await.unwrap();
+                let len = file.metadata().await.unwrap().len();
+                assert_eq!(len, i * chunk.len() as u64);
+            }
+        });
+    }
+}
@@ -0,0 +1,265 @@
+//! [`StdFs`] implements [`AsyncFsTrait`] on the operating system's filesystem.
+
+use std::{
+    io,
+    path::{Path, PathBuf}
+};
+
+use async_trait::async_trait;
+use blocking::{unblock, Unblock};
+
+use super::{StdDirBuilder, StdDirEntry, StdOpenOptions, StdReadDir};
+use crate::{
+    metadata::to_std_permissions, AsyncFsTrait, AsyncSymLinkTrait, Metadata,
+    Permissions
+};
+
+/// The operating system's filesystem.
+///
+/// Every method is a thin wrapper around the function of the same name in
+/// [`std::fs`], run on the [`blocking`][1] thread pool.  Relative paths are
+/// resolved against the process's current working directory, exactly as they
+/// would be by [`std::fs`].
+///
+/// [1]: https://docs.rs/blocking
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StdFs;
+
+impl StdFs {
+    /// Creates a handle to the operating system's filesystem.
+    pub fn new() -> Self {
+        StdFs
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for StdFs {
+    type DirBuilder = StdDirBuilder;
+    type DirEntry = StdDirEntry;
+    type FileBuilder = StdOpenOptions;
+    type ReadDir = StdReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        StdDirBuilder::new()
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        StdOpenOptions::new()
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        unblock(move || std::fs::canonicalize(path)).await
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = src.as_ref().to_owned();
+        let dst = dst.as_ref().to_owned();
+        unblock(move || std::fs::rename(src, dst)).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        unblock(move || {
+            let std_perm = to_std_permissions(perm, || {
+                Ok(std::fs::metadata(&path)?.permissions())
+            })?;
+            std::fs::set_permissions(path, std_perm)
+        }).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = src.as_ref().to_owned();
+        let dst = dst.as_ref().to_owned();
+        unblock(move || std::fs::hard_link(src, dst)).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        unblock(move || std::fs::read_link(path)).await
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        let metadata = unblock(move || std::fs::symlink_metadata(path)).await?;
+        Ok(metadata.into())
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        let metadata = unblock(move || std::fs::metadata(path)).await?;
+        Ok(metadata.into())
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = src.as_ref().to_owned();
+        let dst = dst.as_ref().to_owned();
+        unblock(move || std::fs::copy(src, dst)).await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        unblock(move || std::fs::remove_file(path)).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        let inner = unblock(move || std::fs::read_dir(path)).await?;
+        Ok(StdReadDir::new(Unblock::new(inner)))
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        unblock(move || std::fs::remove_dir(path)).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref().to_owned();
+        unblock(move || std::fs::remove_dir_all(path)).await
+    }
+}
+
+#[async_trait]
+impl AsyncSymLinkTrait for StdFs {
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        let src = src.as_ref().to_owned();
+        let dst = dst.as_ref().to_owned();
+        unblock(move || symlink(&src, &dst)).await
+    }
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// Note that the argument order follows [`AsyncSymLinkTrait::symlink()`],
+/// which is the reverse of the platform-specific functions in the standard
+/// library.
+#[cfg(unix)]
+fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(dst, src)
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// Windows needs to know whether the target is a directory, so a relative
+/// `dst` is resolved against the parent of `src` to find out.  Targets that
+/// don't exist are linked as files.
+#[cfg(windows)]
+fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    let target = match src.parent() {
+        Some(parent) => parent.join(dst),
+        None => dst.to_owned()
+    };
+    if std::fs::metadata(target).map(|m| m.is_dir())
+                                .unwrap_or(false)
+    {
+        std::os::windows::fs::symlink_dir(dst, src)
+    } else {
+        std::os::windows::fs::symlink_file(dst, src)
+    }
+}
+
+/// Symlinks aren't supported on this platform.
+#[cfg(not(any(unix, windows)))]
+fn symlink(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "symlinks are not supported on this platform"))
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    #[test]
+    fn rename_overwrites_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        std::fs::write(&src, b"new").unwrap();
+        std::fs::write(&dst, b"old").unwrap();
+
+        block_on(StdFs::new().rename(&src, &dst)).unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(std::fs::read(&dst).unwrap(), b"new");
+    }
+
+    #[test]
+    fn metadata_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        std::fs::write(&target, b"12345").unwrap();
+
+        let fs = StdFs::new();
+        block_on(async {
+            fs.symlink(&link, "target").await.unwrap();
+
+            assert_eq!(fs.read_link(&link).await.unwrap(),
+                       PathBuf::from("target"));
+            assert!(fs.symlink_metadata(&link).await.unwrap().is_symlink());
+
+            let metadata = fs.metadata(&link).await.unwrap();
+            assert!(metadata.is_file());
+            assert_eq!(metadata.len(), 5);
+        });
+    }
+
+    #[test]
+    fn remove_dir_rejects_non_empty_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(sub.join("file"), b"").unwrap();
+
+        let fs = StdFs::new();
+        block_on(async {
+            assert!(fs.remove_dir(&sub).await.is_err());
+            fs.remove_dir_all(&sub).await.unwrap();
+        });
+        assert!(!sub.exists());
+    }
+}
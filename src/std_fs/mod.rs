@@ -0,0 +1,37 @@
+//! A backend for the operating system's filesystem, built on [`std::fs`].
+//!
+//! The functions in [`std::fs`] are blocking, so every call made through the
+//! types in this module is offloaded onto the thread pool provided by the
+//! [`blocking`][1] crate.  That thread pool doesn't depend on any particular
+//! async runtime, so the types in this module can be used with tokio,
+//! async-std, smol, or anything else that is able to poll a future.
+//!
+//! The entry point is [`StdFs`], which implements [`AsyncFsTrait`][2] and
+//! [`AsyncSymLinkTrait`][3].  Everything else in this module is reached through
+//! it:
+//!
+//! - [`StdOpenOptions`] is returned by [`StdFs::open_options()`][4], and opens
+//!   [`StdFile`]s.
+//! - [`StdDirBuilder`] is returned by [`StdFs::dir_builder()`][5].
+//! - [`StdReadDir`] is returned by [`StdFs::read_dir()`][6], and yields
+//!   [`StdDirEntry`]s.
+//!
+//! This module is only available when the `std-fs` feature is enabled.
+//!
+//! [1]: https://docs.rs/blocking
+//! [2]: crate::AsyncFsTrait
+//! [3]: crate::AsyncSymLinkTrait
+//! [4]: crate::AsyncFsTrait::open_options
+//! [5]: crate::AsyncFsTrait::dir_builder
+//! [6]: crate::AsyncFsTrait::read_dir
+
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{StdDirBuilder, StdDirEntry, StdReadDir};
+#[doc(inline)]
+pub use file::{StdFile, StdOpenOptions};
+#[doc(inline)]
+pub use fs::StdFs;
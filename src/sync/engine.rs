@@ -0,0 +1,508 @@
+//! [`sync_tree()`], and what brings one directory tree in line with
+//! another.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf}
+};
+
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_lite::io::AsyncWriteExt;
+
+use super::{Action, Compare, SyncAction, SyncOptions, SyncReport};
+use crate::{
+    layer::FileOf,
+    tree::{list, same_contents, same_permissions},
+    AsyncDirBuilderTrait, AsyncFileBuilderTrait, AsyncFsTrait,
+    AsyncSymLinkTrait, FileType, Metadata, Permissions
+};
+
+/// Syncs `dst` in `dst_fs` to `src` in `src_fs` with the default
+/// [`SyncOptions`], and returns what was done.
+///
+/// Files are compared by their sizes and modification times, entries that
+/// the source doesn't have are kept, and permissions are carried over.
+pub async fn sync_tree<S, D, P, Q>(src_fs: &S,
+                                   src: P,
+                                   dst_fs: &D,
+                                   dst: Q)
+                                   -> io::Result<SyncReport>
+    where S: AsyncFsTrait,
+          D: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<S>: AsyncRead + Unpin,
+          FileOf<D>: AsyncRead + AsyncWrite + Unpin,
+          P: AsRef<Path>,
+          Q: AsRef<Path>
+{
+    SyncOptions::new().sync_tree(src_fs, src, dst_fs, dst).await
+}
+
+/// A directory that is in both trees, or that is being created.
+struct Dir {
+    src: PathBuf,
+    dst: PathBuf,
+    path: PathBuf,
+
+    /// Whether the directory already exists in the destination tree.
+    exists: bool
+}
+
+struct Syncer<'a, S, D> {
+    src_fs: &'a S,
+    dst_fs: &'a D,
+    options: &'a SyncOptions,
+    report: SyncReport,
+
+    /// Directories whose permissions are set once everything below them has
+    /// been synced.
+    dirs: Vec<(PathBuf, Permissions)>
+}
+
+impl<S, D> Syncer<'_, S, D>
+    where S: AsyncFsTrait,
+          D: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<S>: AsyncRead + Unpin,
+          FileOf<D>: AsyncRead + AsyncWrite + Unpin
+{
+    fn record(&mut self, path: &Path, file_type: FileType, action: Action) {
+        self.report.actions.push(SyncAction { path: path.to_owned(),
+                                              file_type,
+                                              action });
+    }
+
+    /// Returns the type of what is at `path` in the destination tree, if
+    /// anything is.
+    async fn dst_type(&self, path: &Path) -> io::Result<Option<FileType>> {
+        match self.dst_fs.symlink_metadata(path).await {
+            Ok(metadata) => Ok(Some(metadata.file_type())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Syncs a single entry, and returns it if it is a directory whose
+    /// entries need syncing next.
+    async fn sync(&mut self,
+                  dir: Dir,
+                  src_type: FileType,
+                  dst_type: Option<FileType>)
+                  -> io::Result<Option<Dir>> {
+        match src_type {
+            FileType::Dir | FileType::File | FileType::Symlink => {}
+            // Special files are left alone.
+            _ => return Ok(None)
+        }
+        match dst_type {
+            None => return self.create(dir, src_type, Action::Create).await,
+            Some(dst_type) if dst_type != src_type => {
+                self.remove(&dir.dst, dst_type).await?;
+                return self.create(dir, src_type, Action::Replace).await;
+            }
+            Some(_) => {}
+        }
+        match src_type {
+            FileType::Dir => {
+                let ours = self.src_fs.metadata(&dir.src).await?;
+                let theirs = self.dst_fs.metadata(&dir.dst).await?;
+                self.sync_permissions(&dir, &ours, &theirs).await?;
+                return Ok(Some(dir));
+            }
+            FileType::File => {
+                let ours = self.src_fs.metadata(&dir.src).await?;
+                let theirs = self.dst_fs.metadata(&dir.dst).await?;
+                if self.changed(&dir, &ours, &theirs).await? {
+                    if !self.options.dry_run {
+                        self.dst_fs.remove_file(&dir.dst).await?;
+                    }
+                    self.copy_file(&dir, &ours).await?;
+                    self.record(&dir.path, src_type, Action::Update);
+                } else {
+                    self.sync_permissions(&dir, &ours, &theirs).await?;
+                }
+            }
+            _ => {
+                let ours = self.src_fs.read_link(&dir.src).await?;
+                if ours != self.dst_fs.read_link(&dir.dst).await? {
+                    if !self.options.dry_run {
+                        self.dst_fs.remove_file(&dir.dst).await?;
+                        self.dst_fs.symlink(&dir.dst, ours).await?;
+                    }
+                    self.record(&dir.path, src_type, Action::Update);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Copies an entry that isn't in the destination tree.
+    async fn create(&mut self,
+                    dir: Dir,
+                    src_type: FileType,
+                    action: Action)
+                    -> io::Result<Option<Dir>> {
+        self.record(&dir.path, src_type, action);
+        match src_type {
+            FileType::Dir => {
+                let metadata = self.src_fs.metadata(&dir.src).await?;
+                if !self.options.dry_run {
+                    self.dst_fs.dir_builder().create(&dir.dst).await?;
+                    if self.options.permissions {
+                        self.dirs
+                            .push((dir.dst.clone(), metadata.permissions()));
+                    }
+                }
+                return Ok(Some(Dir { exists: false,
+                                     ..dir }));
+            }
+            FileType::File => {
+                let metadata = self.src_fs.metadata(&dir.src).await?;
+                self.copy_file(&dir, &metadata).await?;
+            }
+            _ => {
+                let points_to = self.src_fs.read_link(&dir.src).await?;
+                if !self.options.dry_run {
+                    self.dst_fs.symlink(&dir.dst, points_to).await?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes the entry at `path` in the destination tree.
+    async fn remove(&self, path: &Path, file_type: FileType) -> io::Result<()> {
+        match (self.options.dry_run, file_type) {
+            (true, _) => Ok(()),
+            (false, FileType::Dir) => self.dst_fs.remove_dir_all(path).await,
+            (false, _) => self.dst_fs.remove_file(path).await
+        }
+    }
+
+    /// Copies a file whose copy doesn't exist, or has been removed.
+    async fn copy_file(&mut self,
+                       dir: &Dir,
+                       metadata: &Metadata)
+                       -> io::Result<()> {
+        self.report.bytes += metadata.len();
+        if self.options.dry_run {
+            return Ok(());
+        }
+        let mut src =
+            self.src_fs.open_options().read(true).open(&dir.src).await?;
+        let mut dst = self.dst_fs
+                          .open_options()
+                          .write(true)
+                          .create_new(true)
+                          .open(&dir.dst)
+                          .await?;
+        futures_lite::io::copy(&mut src, &mut dst).await?;
+        dst.close().await?;
+        if self.options.permissions {
+            self.dst_fs
+                .set_permissions(&dir.dst, metadata.permissions())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the file at `dir.src` differs from its copy.
+    async fn changed(&self,
+                     dir: &Dir,
+                     ours: &Metadata,
+                     theirs: &Metadata)
+                     -> io::Result<bool> {
+        if ours.len() != theirs.len() {
+            return Ok(true);
+        }
+        if self.options.compare == Compare::Metadata {
+            return Ok(match (ours.modified(), theirs.modified()) {
+                (Ok(ours), Ok(theirs)) => ours > theirs,
+                _ => false
+            });
+        }
+        let mut src =
+            self.src_fs.open_options().read(true).open(&dir.src).await?;
+        let mut dst =
+            self.dst_fs.open_options().read(true).open(&dir.dst).await?;
+        Ok(!same_contents(&mut src, &mut dst).await?)
+    }
+
+    /// Gives the copy of an unchanged entry the permissions of its source,
+    /// if they differ.
+    async fn sync_permissions(&mut self,
+                              dir: &Dir,
+                              ours: &Metadata,
+                              theirs: &Metadata)
+                              -> io::Result<()> {
+        let permissions = ours.permissions();
+        if !self.options.permissions
+           || same_permissions(permissions, theirs.permissions())
+        {
+            return Ok(());
+        }
+        self.record(&dir.path, ours.file_type(), Action::SetPermissions);
+        match (self.options.dry_run, ours.is_dir()) {
+            (true, _) => {}
+            (false, true) => self.dirs.push((dir.dst.clone(), permissions)),
+            (false, false) => {
+                self.dst_fs.set_permissions(&dir.dst, permissions).await?
+            }
+        }
+        Ok(())
+    }
+
+    /// Syncs the entries of a directory, and returns the directories among
+    /// them.
+    async fn sync_dir(&mut self, dir: &Dir) -> io::Result<Vec<Dir>> {
+        let ours = list(self.src_fs, &dir.src).await?;
+        let theirs = match dir.exists {
+            true => list(self.dst_fs, &dir.dst).await?,
+            false => BTreeMap::new()
+        };
+        if self.options.delete {
+            for (name, dst_type) in &theirs {
+                if !ours.contains_key(name) {
+                    self.remove(&dir.dst.join(name), *dst_type).await?;
+                    self.record(&dir.path.join(name),
+                                *dst_type,
+                                Action::Delete);
+                }
+            }
+        }
+        let mut below = Vec::new();
+        for (name, src_type) in ours {
+            let entry = Dir { src: dir.src.join(&name),
+                              dst: dir.dst.join(&name),
+                              path: dir.path.join(&name),
+                              exists: true };
+            let dst_type = theirs.get(&name).copied();
+            if let Some(dir) = self.sync(entry, src_type, dst_type).await? {
+                below.push(dir);
+            }
+        }
+        Ok(below)
+    }
+
+    /// Gives the directories that were synced their permissions, deepest
+    /// first, so that read-only ones don't get in the way of the others.
+    async fn finish(&mut self) -> io::Result<()> {
+        for (dir, permissions) in self.dirs.drain(..).rev() {
+            self.dst_fs.set_permissions(dir, permissions).await?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) async fn run<S, D>(options: &SyncOptions,
+                              src_fs: &S,
+                              src: &Path,
+                              dst_fs: &D,
+                              dst: &Path)
+                              -> io::Result<SyncReport>
+    where S: AsyncFsTrait,
+          D: AsyncFsTrait + AsyncSymLinkTrait,
+          FileOf<S>: AsyncRead + Unpin,
+          FileOf<D>: AsyncRead + AsyncWrite + Unpin
+{
+    let mut syncer = Syncer { src_fs,
+                              dst_fs,
+                              options,
+                              report: SyncReport::default(),
+                              dirs: Vec::new() };
+    // The root is always followed, as in a walk.
+    let src_type = src_fs.metadata(src).await?.file_type();
+    let dst_type = syncer.dst_type(dst).await?;
+    let root = Dir { src: src.to_owned(),
+                     dst: dst.to_owned(),
+                     path: PathBuf::new(),
+                     exists: true };
+    let mut stack = Vec::new();
+    stack.extend(syncer.sync(root, src_type, dst_type).await?);
+    while let Some(dir) = stack.pop() {
+        let below = syncer.sync_dir(&dir).await?;
+        stack.extend(below.into_iter().rev());
+    }
+    syncer.finish().await?;
+    Ok(syncer.report)
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, io::AsyncReadExt};
+
+    use super::*;
+    use crate::mem_fs::MemFs;
+
+    async fn write(fs: &MemFs, path: &str, contents: &[u8]) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create(true)
+                         .truncate(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(contents).await.unwrap();
+        file.close().await.unwrap();
+    }
+
+    async fn read(fs: &MemFs, path: &str) -> Vec<u8> {
+        let mut file = fs.open_options().read(true).open(path).await.unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).await.unwrap();
+        contents
+    }
+
+    async fn source() -> MemFs {
+        let fs = MemFs::new();
+        fs.dir_builder()
+          .recursive(true)
+          .create("/out/obj")
+          .await
+          .unwrap();
+        write(&fs, "/out/app", b"binary").await;
+        write(&fs, "/out/obj/main.o", b"object").await;
+        fs.symlink("/out/latest", "app").await.unwrap();
+        fs
+    }
+
+    fn summary(report: &SyncReport) -> Vec<(String, Action)> {
+        report.actions
+              .iter()
+              .map(|done| (done.path.display().to_string(), done.action))
+              .collect()
+    }
+
+    fn actions(list: &[(&str, Action)]) -> Vec<(String, Action)> {
+        list.iter()
+            .map(|(path, action)| (path.to_string(), *action))
+            .collect()
+    }
+
+    #[test]
+    fn only_changes_are_copied() {
+        block_on(async {
+            let (src, dst) = (source().await, MemFs::new());
+            let report = sync_tree(&src, "/out", &dst, "/cache").await.unwrap();
+            assert_eq!(summary(&report),
+                       actions(&[("", Action::Create),
+                                 ("app", Action::Create),
+                                 ("latest", Action::Create),
+                                 ("obj", Action::Create),
+                                 ("obj/main.o", Action::Create)]));
+            assert_eq!(report.bytes, 12);
+            assert_eq!(read(&dst, "/cache/obj/main.o").await, b"object");
+            assert!(sync_tree(&src, "/out", &dst, "/cache").await
+                                                           .unwrap()
+                                                           .is_empty());
+
+            write(&src, "/out/app", b"binary").await;
+            write(&src, "/out/obj/main.o", b"bigger object").await;
+            src.remove_file("/out/latest").await.unwrap();
+            src.symlink("/out/latest", "obj/main.o").await.unwrap();
+            let report = sync_tree(&src, "/out", &dst, "/cache").await.unwrap();
+            assert_eq!(summary(&report),
+                       actions(&[("app", Action::Update),
+                                 ("latest", Action::Update),
+                                 ("obj/main.o", Action::Update)]));
+            assert_eq!(read(&dst, "/cache/obj/main.o").await, b"bigger object");
+            assert_eq!(dst.read_link("/cache/latest").await.unwrap(),
+                       Path::new("obj/main.o"));
+        });
+    }
+
+    #[test]
+    fn contents_can_be_compared() {
+        block_on(async {
+            let (src, dst) = (source().await, MemFs::new());
+            sync_tree(&src, "/out", &dst, "/cache").await.unwrap();
+            // Same size, and the copy looks newer.
+            write(&src, "/out/app", b"BINARY").await;
+            write(&dst, "/cache/app", b"binary").await;
+            assert!(sync_tree(&src, "/out", &dst, "/cache").await
+                                                           .unwrap()
+                                                           .is_empty());
+
+            let report =
+                SyncOptions::new().compare(Compare::Contents)
+                                  .sync_tree(&src, "/out", &dst, "/cache")
+                                  .await
+                                  .unwrap();
+            assert_eq!(summary(&report), actions(&[("app", Action::Update)]));
+            assert_eq!(read(&dst, "/cache/app").await, b"BINARY");
+        });
+    }
+
+    #[test]
+    fn extraneous_entries_can_be_deleted() {
+        block_on(async {
+            let (src, dst) = (source().await, MemFs::new());
+            sync_tree(&src, "/out", &dst, "/cache").await.unwrap();
+            dst.dir_builder().create("/cache/stale").await.unwrap();
+            write(&dst, "/cache/stale/x", b"x").await;
+            write(&dst, "/cache/obj/old.o", b"old").await;
+            assert!(sync_tree(&src, "/out", &dst, "/cache").await
+                                                           .unwrap()
+                                                           .is_empty());
+
+            let mut options = SyncOptions::new();
+            let dry_run = options.delete(true)
+                                 .dry_run(true)
+                                 .sync_tree(&src, "/out", &dst, "/cache")
+                                 .await
+                                 .unwrap();
+            assert_eq!(summary(&dry_run),
+                       actions(&[("stale", Action::Delete),
+                                 ("obj/old.o", Action::Delete)]));
+            assert!(dst.metadata("/cache/stale/x").await.is_ok());
+
+            let report = options.dry_run(false)
+                                .sync_tree(&src, "/out", &dst, "/cache")
+                                .await
+                                .unwrap();
+            assert_eq!(report, dry_run);
+            assert!(dst.metadata("/cache/stale").await.is_err());
+            assert!(dst.metadata("/cache/obj/old.o").await.is_err());
+        });
+    }
+
+    #[test]
+    fn types_and_permissions_are_synced() {
+        block_on(async {
+            let (src, dst) = (source().await, MemFs::new());
+            sync_tree(&src, "/out", &dst, "/cache").await.unwrap();
+            dst.remove_dir_all("/cache/obj").await.unwrap();
+            write(&dst, "/cache/obj", b"not a directory").await;
+            src.set_permissions("/out/app", Permissions::from_mode(0o755))
+               .await
+               .unwrap();
+
+            let report =
+                SyncOptions::new().dry_run(true)
+                                  .sync_tree(&src, "/out", &dst, "/cache")
+                                  .await
+                                  .unwrap();
+            let expected = actions(&[("app", Action::SetPermissions),
+                                     ("obj", Action::Replace),
+                                     ("obj/main.o", Action::Create)]);
+            assert_eq!(summary(&report), expected);
+            assert!(dst.metadata("/cache/obj").await.unwrap().is_file());
+
+            let report = sync_tree(&src, "/out", &dst, "/cache").await.unwrap();
+            assert_eq!(summary(&report), expected);
+            assert_eq!(report.actions[1].file_type, FileType::Dir);
+            let app = dst.metadata("/cache/app").await.unwrap();
+            assert_eq!(app.permissions().mode().map(|mode| mode & 0o777),
+                       Some(0o755));
+            assert_eq!(read(&dst, "/cache/obj/main.o").await, b"object");
+        });
+    }
+}
@@ -0,0 +1,45 @@
+//! One-way syncs of directory trees, from one filesystem to another.
+//!
+//! [`sync_tree()`] and [`SyncOptions::sync_tree()`] bring a destination tree
+//! in line with a source tree, on any two filesystems, in the manner of
+//! `rsync`.  Only what changed is copied: files are compared by their
+//! [`metadata()`][1], or by their contents, and directories entry by entry,
+//! using each entry's [`file_type()`][2] so that symlinks and directories
+//! are told apart without being followed.  Entries that the source tree
+//! doesn't have can be deleted, and a dry run reports what would be done
+//! without doing it:
+//!
+//! ```
+//! # #[cfg(feature = "mem-fs")]
+//! # {
+//! use async_fs_traits::{mem_fs::MemFs, sync::sync_tree, AsyncDirBuilderTrait,
+//!                       AsyncFsTrait};
+//! use futures_lite::future::block_on;
+//!
+//! let (disk, cache) = (MemFs::new(), MemFs::new());
+//! block_on(async {
+//!     disk.dir_builder().recursive(true).create("/target/debug").await?;
+//!     let report = sync_tree(&disk, "/target", &cache, "/target").await?;
+//!     assert_eq!(report.actions.len(), 2);
+//!     assert!(sync_tree(&disk, "/target", &cache, "/target").await?
+//!                                                            .is_empty());
+//!     std::io::Result::Ok(())
+//! });
+//! # }
+//! ```
+//!
+//! This module is only available when the `sync` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait::metadata
+//! [2]: crate::AsyncDirEntryTrait::file_type
+
+mod engine;
+mod options;
+mod report;
+
+#[doc(inline)]
+pub use engine::sync_tree;
+#[doc(inline)]
+pub use options::{Compare, SyncOptions};
+#[doc(inline)]
+pub use report::{Action, SyncAction, SyncReport};
@@ -0,0 +1,158 @@
+//! [`SyncOptions`] configures how one directory tree is synced to another.
+
+use std::{io, path::Path};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use super::{engine, SyncReport};
+use crate::{layer::FileOf, AsyncFsTrait, AsyncSymLinkTrait};
+
+/// How files are compared to their copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compare {
+    /// Compares sizes and modification times.
+    ///
+    /// Copies get the time they were made as their modification time, so a
+    /// file counts as changed if its size differs from its copy's, or if it
+    /// was modified after its copy.  Only sizes are compared if either time
+    /// is unknown.
+    Metadata,
+
+    /// Compares sizes, and then contents, which finds every change, but reads
+    /// every file on both sides.
+    Contents
+}
+
+impl Default for Compare {
+    fn default() -> Self {
+        Compare::Metadata
+    }
+}
+
+/// Options for syncing one directory tree to another.
+///
+/// The options start out comparing files by their metadata, keeping what
+/// the source doesn't have, carrying permissions over, and making changes.
+///
+/// ```
+/// # #[cfg(feature = "mem-fs")]
+/// # {
+/// use async_fs_traits::{
+///     mem_fs::MemFs,
+///     sync::{Action, SyncOptions},
+///     AsyncDirBuilderTrait, AsyncFsTrait
+/// };
+/// use futures_lite::future::block_on;
+///
+/// let (disk, cache) = (MemFs::new(), MemFs::new());
+/// block_on(async {
+///     disk.dir_builder().create("/target").await?;
+///     let report = SyncOptions::new()
+///         .delete(true)
+///         .dry_run(true)
+///         .sync_tree(&disk, "/target", &cache, "/target")
+///         .await?;
+///     for done in report.actions(Action::Create) {
+///         println!("would create {}", done.path.display());
+///     }
+///     std::io::Result::Ok(())
+/// });
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub(super) compare: Compare,
+    pub(super) delete: bool,
+    pub(super) dry_run: bool,
+    pub(super) permissions: bool
+}
+
+impl SyncOptions {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        SyncOptions { compare: Compare::Metadata,
+                      delete: false,
+                      dry_run: false,
+                      permissions: true }
+    }
+
+    /// Sets how files are compared to their copies.
+    pub fn compare(&mut self, compare: Compare) -> &mut Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Deletes the entries of the destination tree that the source tree
+    /// doesn't have.
+    pub fn delete(&mut self, delete: bool) -> &mut Self {
+        self.delete = delete;
+        self
+    }
+
+    /// Only reports what would be done, without changing anything.
+    pub fn dry_run(&mut self, dry_run: bool) -> &mut Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Gives files and directories the same permissions as the ones they
+    /// are synced from.
+    pub fn permissions(&mut self, permissions: bool) -> &mut Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Syncs `dst` in `dst_fs` to `src` in `src_fs`, so that it holds the
+    /// same entries, and returns what was done.
+    ///
+    /// Directories are compared entry by entry, using the file types of
+    /// their entries without following symlinks.  Files are copied if they
+    /// changed, symlinks are recreated if they point somewhere else, and an
+    /// entry of another type than its source is replaced.  Special files,
+    /// such as devices or named pipes, are left alone.
+    ///
+    /// The first error stops the sync, and is returned; whatever was done
+    /// before it is left in place.
+    pub async fn sync_tree<S, D, P, Q>(&self,
+                                       src_fs: &S,
+                                       src: P,
+                                       dst_fs: &D,
+                                       dst: Q)
+                                       -> io::Result<SyncReport>
+        where S: AsyncFsTrait,
+              D: AsyncFsTrait + AsyncSymLinkTrait,
+              FileOf<S>: AsyncRead + Unpin,
+              FileOf<D>: AsyncRead + AsyncWrite + Unpin,
+              P: AsRef<Path>,
+              Q: AsRef<Path>
+    {
+        engine::run(self, src_fs, src.as_ref(), dst_fs, dst.as_ref()).await
+    }
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions::new()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_start_out_keeping_everything() {
+        let options = SyncOptions::default();
+        assert_eq!(options.compare, Compare::Metadata);
+        assert!(!options.delete && !options.dry_run && options.permissions);
+    }
+}
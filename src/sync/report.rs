@@ -0,0 +1,94 @@
+//! [`SyncReport`] lists what a sync did, or would do.
+
+use std::path::PathBuf;
+
+use crate::FileType;
+
+/// What a sync does to an entry of the destination tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Copies an entry that the destination doesn't have.
+    Create,
+
+    /// Copies a file over a changed copy of it, or points a symlink
+    /// somewhere else.
+    Update,
+
+    /// Removes an entry of another type, and copies the entry in its place.
+    Replace,
+
+    /// Removes an entry that the source doesn't have.
+    Delete,
+
+    /// Gives an entry whose contents are unchanged new permissions.
+    SetPermissions
+}
+
+/// Something that a sync did, or would do.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyncAction {
+    /// The path of the entry, relative to the roots of both trees.  The roots
+    /// themselves have an empty path.
+    pub path: PathBuf,
+
+    /// The type of the entry in the source tree, or in the destination tree
+    /// if it is deleted.
+    pub file_type: FileType,
+
+    /// What is done to the entry.
+    pub action: Action
+}
+
+/// What a sync did, or would do in a [dry run][1].
+///
+/// [1]: super::SyncOptions::dry_run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SyncReport {
+    /// Everything that was done, with each directory before its contents.
+    pub actions: Vec<SyncAction>,
+
+    /// The number of bytes of file contents copied.
+    pub bytes: u64
+}
+
+impl SyncReport {
+    /// Returns `true` if the trees were already in sync.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Returns the actions of the given kind.
+    pub fn actions(&self, action: Action) -> impl Iterator<Item = &SyncAction> {
+        self.actions
+            .iter()
+            .filter(move |done| done.action == action)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_are_picked_out_by_kind() {
+        let mut report = SyncReport::default();
+        assert!(report.is_empty());
+        for (path, action) in [("a", Action::Create), ("b", Action::Delete)] {
+            report.actions.push(SyncAction { path: PathBuf::from(path),
+                                             file_type: FileType::File,
+                                             action });
+        }
+        let deleted = report.actions(Action::Delete).collect::<Vec<_>>();
+        assert_eq!(deleted, [&report.actions[1]]);
+        assert!(!report.is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+//! [`TarReadDir`] lists the directories of a [`TarFs`][1], and
+//! [`TarDirBuilder`] refuses to create new ones.
+//!
+//! [1]: super::TarFs
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    vec
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+
+use super::fs::read_only;
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncReadDirTrait, FileType,
+    Metadata
+};
+
+/// A directory builder that never creates anything.
+///
+/// [`create()`][1] always fails with [`ErrorKind::PermissionDenied`][2].
+///
+/// [1]: AsyncDirBuilderTrait::create
+/// [2]: io::ErrorKind::PermissionDenied
+#[derive(Debug, Clone, Default)]
+pub struct TarDirBuilder {
+    recursive: bool
+}
+
+impl TarDirBuilder {
+    pub(super) fn new() -> Self {
+        TarDirBuilder::default()
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for TarDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+}
+
+/// A stream of the entries in a directory of a [`TarFs`][1], in order of
+/// their names.
+///
+/// [1]: super::TarFs
+#[derive(Debug)]
+pub struct TarReadDir {
+    entries: vec::IntoIter<TarDirEntry>
+}
+
+impl TarReadDir {
+    pub(super) fn new(entries: Vec<TarDirEntry>) -> Self {
+        TarReadDir { entries: entries.into_iter() }
+    }
+}
+
+impl Stream for TarReadDir {
+    type Item = io::Result<TarDirEntry>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 _cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().entries.next().map(Ok))
+    }
+}
+
+impl AsyncReadDirTrait<TarDirEntry> for TarReadDir {}
+
+/// An entry in a directory of a [`TarFs`][1].
+///
+/// Archives never change, so everything about the entry is recorded when the
+/// directory is read.  Like [`std::fs::DirEntry::metadata()`],
+/// [`metadata()`][2] describes a symlink itself rather than its target.
+///
+/// [1]: super::TarFs
+/// [2]: AsyncDirEntryTrait::metadata
+#[derive(Debug, Clone)]
+pub struct TarDirEntry {
+    path: PathBuf,
+    name: OsString,
+    metadata: Metadata
+}
+
+impl TarDirEntry {
+    pub(super) fn new(path: PathBuf,
+                      name: OsString,
+                      metadata: Metadata)
+                      -> Self {
+        TarDirEntry { path,
+                      name,
+                      metadata }
+    }
+}
+
+#[async_trait]
+impl AsyncDirEntryTrait for TarDirEntry {
+    async fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.metadata.file_type())
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{
+        tar_fs::{header::tests::Builder, TarFs},
+        AsyncFsTrait
+    };
+
+    #[test]
+    fn entries_are_listed_in_order() {
+        block_on(async {
+            let bytes = Builder::new().file("top/b", b"")
+                                      .symlink("top/a", "b")
+                                      .dir("top/c/")
+                                      .finish();
+            let fs = TarFs::from_bytes(bytes).await.unwrap();
+            let mut entries = Vec::new();
+            let mut stream = fs.read_dir("top").await.unwrap();
+            while let Some(entry) = stream.next().await {
+                let entry = entry.unwrap();
+                entries.push((entry.path().await,
+                              entry.file_type().await.unwrap()));
+            }
+            assert_eq!(entries,
+                       [(PathBuf::from("top/a"), FileType::Symlink),
+                        (PathBuf::from("top/b"), FileType::File),
+                        (PathBuf::from("top/c"), FileType::Dir)]);
+
+            let err = fs.read_dir("/top/b").await.unwrap_err();
+            assert_eq!(err.to_string(), "not a directory");
+        });
+    }
+}
@@ -0,0 +1,249 @@
+//! [`TarOpenOptions`] opens [`TarFile`]s in a [`TarFs`].
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek};
+
+use super::{fs::read_only, TarFs};
+use crate::{
+    archive::{is_a_directory, Kind, Region},
+    layer::OpenFlags,
+    AsyncFileBuilderTrait, AsyncFileTrait, Metadata, Permissions
+};
+
+/// Options for opening a [`TarFile`].
+///
+/// Files can only be opened for reading.  Asking for write access, or for a
+/// file to be created or truncated, fails with
+/// [`ErrorKind::PermissionDenied`][1].
+///
+/// [1]: std::io::ErrorKind::PermissionDenied
+#[derive(Debug, Clone)]
+pub struct TarOpenOptions {
+    fs: TarFs,
+    flags: OpenFlags
+}
+
+impl TarOpenOptions {
+    pub(super) fn new(fs: TarFs) -> Self {
+        TarOpenOptions { fs,
+                         flags: OpenFlags::default() }
+    }
+}
+
+#[async_trait]
+impl AsyncFileBuilderTrait for TarOpenOptions {
+    type File = TarFile;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.flags.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.flags.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.flags.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.flags.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.flags.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.flags.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        let OpenFlags { read,
+                        write,
+                        append,
+                        truncate,
+                        create,
+                        create_new } = self.flags;
+        if write || append || truncate || create || create_new {
+            return Err(read_only());
+        }
+        if !read {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "at least one of read, write, or \
+                                       append access is required"));
+        }
+        let (_, entry) = self.fs.index().resolve(path.as_ref(), true)?;
+        match entry.kind {
+            Kind::File { data, len } => {
+                let data = Region::new(self.fs.source().clone(), data, len);
+                Ok(TarFile { metadata: entry.metadata(),
+                             data })
+            }
+            Kind::Dir { .. } => Err(is_a_directory()),
+            _ => Err(io::Error::new(io::ErrorKind::Other, "not a regular file"))
+        }
+    }
+}
+
+/// A file in a [`TarFs`], open for reading.
+///
+/// `TarFile` implements [`AsyncRead`] and [`AsyncSeek`], so any part of a
+/// file in an uncompressed archive can be read without reading what comes
+/// before it.
+#[derive(Debug)]
+pub struct TarFile {
+    metadata: Metadata,
+    data: Region
+}
+
+#[async_trait]
+impl AsyncFileTrait for TarFile {
+    async fn sync_all(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn set_len(&self, _size: u64) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+
+    async fn set_permissions(&self, _perm: Permissions) -> io::Result<()> {
+        Err(read_only())
+    }
+}
+
+impl AsyncRead for TarFile {
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().data).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for TarFile {
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.get_mut().data).poll_seek(cx, pos)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{
+        future::block_on, io::Cursor, AsyncReadExt, AsyncSeekExt
+    };
+
+    use super::*;
+    use crate::{tar_fs::header::tests::Builder, AsyncFsTrait};
+
+    fn archive() -> Vec<u8> {
+        let data = (0..2000u32).map(|i| i as u8).collect::<Vec<_>>();
+        Builder::new().file("first", b"padding")
+                      .file("data", &data)
+                      .symlink("link", "data")
+                      .dir("dir")
+                      .finish()
+    }
+
+    async fn read_at(file: &mut TarFile, pos: u64, len: usize) -> Vec<u8> {
+        file.seek(SeekFrom::Start(pos)).await.unwrap();
+        let mut buf = vec![0; len];
+        let read = file.read(&mut buf).await.unwrap();
+        buf.truncate(read);
+        buf
+    }
+
+    #[test]
+    fn reads_seek_into_the_archive() {
+        block_on(async {
+            let fs = TarFs::new(Cursor::new(archive())).await.unwrap();
+            let mut file =
+                fs.open_options().read(true).open("/link").await.unwrap();
+            assert_eq!(file.metadata().await.unwrap().len(), 2000);
+            assert_eq!(read_at(&mut file, 1000, 4).await, [232, 233, 234, 235]);
+            assert_eq!(read_at(&mut file, 1998, 10).await, [206, 207]);
+            assert_eq!(read_at(&mut file, 2000, 10).await, []);
+
+            let mut all = Vec::new();
+            file.seek(SeekFrom::End(-2000)).await.unwrap();
+            file.read_to_end(&mut all).await.unwrap();
+            assert_eq!(all.len(), 2000);
+            assert!(all.iter().enumerate().all(|(i, &b)| b == i as u8));
+            assert!(file.seek(SeekFrom::Current(-2001)).await.is_err());
+        });
+    }
+
+    #[test]
+    fn only_regular_files_open_for_reading() {
+        block_on(async {
+            let fs = TarFs::from_bytes(archive()).await.unwrap();
+            let err = fs.open_options()
+                        .read(true)
+                        .write(true)
+                        .open("/data")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            let err =
+                fs.open_options().read(true).open("/dir").await.unwrap_err();
+            assert_eq!(err.to_string(), "is a directory");
+            let file =
+                fs.open_options().read(true).open("/first").await.unwrap();
+            let err = file.set_len(0).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        });
+    }
+
+    #[cfg(feature = "tar-gz")]
+    #[test]
+    fn compressed_archives_are_read_into_memory() {
+        use std::io::Write;
+
+        use flate2::{write::GzEncoder, Compression};
+
+        block_on(async {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+            encoder.write_all(&archive()).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let fs = TarFs::from_gz(compressed.as_slice()).await.unwrap();
+            let mut file =
+                fs.open_options().read(true).open("/data").await.unwrap();
+            assert_eq!(read_at(&mut file, 256, 3).await, [0, 1, 2]);
+        });
+    }
+}
@@ -0,0 +1,263 @@
+//! [`TarFs`] serves the entries of a tar archive.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc
+};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek};
+use futures_lite::io::Cursor;
+
+use super::{
+    header::read_index, TarDirBuilder, TarDirEntry, TarOpenOptions, TarReadDir
+};
+use crate::{
+    archive::{Index, Kind, Source},
+    AsyncFsTrait, Metadata, Permissions
+};
+
+/// The error returned for every operation that would change an archive.
+pub(super) fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   "tar archives are read-only")
+}
+
+/// A read-only filesystem that serves the entries of a tar archive.
+///
+/// The archive's headers are read once, when it is opened, into an index of
+/// every entry.  After that, metadata and directory listings are answered from
+/// the index, and reads go straight to the data of the file being read.
+///
+/// Cloning a `TarFs` is cheap, and the clones share the index and the
+/// archive.
+#[derive(Debug, Clone)]
+pub struct TarFs {
+    index: Arc<Index>,
+    source: Source
+}
+
+impl TarFs {
+    /// Opens the uncompressed archive that starts at the current position of
+    /// `reader`.
+    ///
+    /// Files are read by seeking `reader` to their data, so the whole archive
+    /// never has to be held in memory.  Reads from different files take turns
+    /// using `reader`.
+    pub async fn new<R>(mut reader: R) -> io::Result<Self>
+        where R: AsyncRead + AsyncSeek + Send + Unpin + 'static
+    {
+        let index = read_index(&mut reader).await?;
+        Ok(TarFs { index: Arc::new(index),
+                   source: Source::reader(reader) })
+    }
+
+    /// Opens an uncompressed archive that is already in memory.
+    pub async fn from_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        let mut cursor = Cursor::new(bytes);
+        let index = read_index(&mut cursor).await?;
+        Ok(TarFs { index: Arc::new(index),
+                   source: Source::Memory(Arc::new(cursor.into_inner())) })
+    }
+
+    /// Opens a gzip-compressed archive, such as a `.tar.gz` file, by
+    /// decompressing all of `reader` into memory.
+    ///
+    /// This is only available when the `tar-gz` feature is enabled.
+    #[cfg(feature = "tar-gz")]
+    pub async fn from_gz<R>(mut reader: R) -> io::Result<Self>
+        where R: AsyncRead + Unpin
+    {
+        use std::io::Read;
+
+        use flate2::read::MultiGzDecoder;
+        use futures_lite::AsyncReadExt;
+
+        let mut compressed = Vec::new();
+        reader.read_to_end(&mut compressed).await?;
+        let mut bytes = Vec::new();
+        MultiGzDecoder::new(compressed.as_slice()).read_to_end(&mut bytes)?;
+        TarFs::from_bytes(bytes).await
+    }
+
+    pub(super) fn index(&self) -> &Index {
+        &self.index
+    }
+
+    pub(super) fn source(&self) -> &Source {
+        &self.source
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for TarFs {
+    type DirBuilder = TarDirBuilder;
+    type FileBuilder = TarOpenOptions;
+    type DirEntry = TarDirEntry;
+    type ReadDir = TarReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        TarDirBuilder::new()
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        TarOpenOptions::new(self.clone())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.index.resolve(path.as_ref(), true)?.0)
+    }
+
+    async fn rename<P, Q>(&self, _src: P, _dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn set_permissions<P>(&self,
+                                _path: P,
+                                _perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn hard_link<P, Q>(&self, _src: P, _dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        match &self.index.resolve(path.as_ref(), false)?.1.kind {
+            Kind::Symlink(target) => Ok(target.clone()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    "not a symbolic link"))
+        }
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.index.resolve(path.as_ref(), false)?.1.metadata())
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.index.resolve(path.as_ref(), true)?.1.metadata())
+    }
+
+    async fn copy<P, Q>(&self, _src: P, _dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn remove_file<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let (dir, _) = self.index.resolve(path, true)?;
+        let entries = self.index
+                          .children(&dir)?
+                          .map(|(name, entry)| {
+                              TarDirEntry::new(path.join(name),
+                                               name.clone(),
+                                               entry.metadata())
+                          })
+                          .collect();
+        Ok(TarReadDir::new(entries))
+    }
+
+    async fn remove_dir<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+
+    async fn remove_dir_all<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(read_only())
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{tar_fs::header::tests::Builder, AsyncDirBuilderTrait};
+
+    fn fs() -> TarFs {
+        let bytes = Builder::new().file("docs/readme", b"hello")
+                                  .symlink("latest", "docs/readme")
+                                  .hard_link("copy", "docs/readme")
+                                  .finish();
+        block_on(TarFs::from_bytes(bytes)).unwrap()
+    }
+
+    #[test]
+    fn links_are_exposed() {
+        block_on(async {
+            let fs = fs();
+            assert_eq!(fs.read_link("/latest").await.unwrap(),
+                       Path::new("docs/readme"));
+            assert!(fs.symlink_metadata("/latest").await.unwrap().is_symlink());
+            assert_eq!(fs.metadata("/latest").await.unwrap().len(), 5);
+            assert_eq!(fs.canonicalize("latest").await.unwrap(),
+                       Path::new("/docs/readme"));
+
+            let copy = fs.symlink_metadata("/copy").await.unwrap();
+            let readme = fs.symlink_metadata("/docs/readme").await.unwrap();
+            assert!(copy.is_file());
+            assert_eq!(copy.ino(), readme.ino());
+            assert_eq!(copy.nlink(), Some(2));
+            let err = fs.read_link("/copy").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn changes_are_refused() {
+        block_on(async {
+            let fs = fs();
+            let denied = |result: io::Result<()>| {
+                assert_eq!(result.unwrap_err().kind(),
+                           io::ErrorKind::PermissionDenied)
+            };
+            denied(fs.rename("/copy", "/moved").await);
+            denied(fs.remove_file("/copy").await);
+            denied(fs.remove_dir_all("/docs").await);
+            denied(fs.hard_link("/copy", "/again").await);
+            denied(fs.dir_builder().create("/new").await);
+            denied(fs.copy("/copy", "/again").await.map(drop));
+            assert!(fs.metadata("/copy").await.is_ok());
+        });
+    }
+}
@@ -0,0 +1,455 @@
+//! [`read_index()`] reads the headers of a tar archive into an [`Index`].
+//!
+//! The parser understands the ustar format, GNU long names and long link
+//! names, and the `path`, `linkpath`, `size`, `mtime`, `uid`, and `gid`
+//! records of pax extended headers.  Global pax headers are skipped.
+
+use std::{
+    io::{self, SeekFrom},
+    ops::Range,
+    path::PathBuf
+};
+
+use futures_io::AsyncRead;
+use futures_lite::{AsyncReadExt, AsyncSeekExt};
+
+use crate::{
+    archive::{
+        archive_path, invalid_data, os_string, truncated, Archive, Attributes,
+        Index, Kind
+    },
+    FileType
+};
+
+/// The size of a header, and the unit that entry data is padded to.
+const BLOCK: u64 = 512;
+
+/// The largest GNU long name or pax header that is read into memory.
+const MAX_EXTENSION: u64 = 1 << 20;
+
+/// Returns the bytes of a header field, up to the first NUL.
+fn field(block: &[u8], range: Range<usize>) -> &[u8] {
+    let field = &block[range];
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+/// Parses a numeric header field, which is either octal text or, if the high
+/// bit of the first byte is set, a big-endian base-256 number.
+fn number(block: &[u8], range: Range<usize>) -> io::Result<u64> {
+    let field = &block[range];
+    if field[0] & 0x80 != 0 {
+        let mut value = u64::from(field[0] & 0x7f);
+        for &byte in &field[1..] {
+            value = value.checked_mul(256)
+                         .ok_or_else(|| invalid_data("tar number overflows"))?
+                    | u64::from(byte);
+        }
+        return Ok(value);
+    }
+    let text = field.iter()
+                    .copied()
+                    .skip_while(|&b| b == b' ')
+                    .take_while(|&b| b != 0 && b != b' ')
+                    .collect::<Vec<_>>();
+    if text.is_empty() {
+        return Ok(0);
+    }
+    std::str::from_utf8(&text).ok()
+                              .and_then(|text| {
+                                  u64::from_str_radix(text, 8).ok()
+                              })
+                              .ok_or_else(|| invalid_data("invalid tar number"))
+}
+
+/// Checks a header against its checksum, which is the sum of its bytes with
+/// the checksum field itself counted as spaces.
+fn verify(block: &[u8]) -> io::Result<()> {
+    let expected = number(block, 148..156)?;
+    let actual = block.iter()
+                      .enumerate()
+                      .map(|(i, &b)| match (148..156).contains(&i) {
+                          true => u64::from(b' '),
+                          false => u64::from(b)
+                      })
+                      .sum::<u64>();
+    match expected == actual {
+        true => Ok(()),
+        false => Err(invalid_data("tar header checksum mismatch"))
+    }
+}
+
+/// Returns the name in a header, joining the ustar prefix if there is one.
+fn header_name(block: &[u8]) -> Vec<u8> {
+    let name = field(block, 0..100);
+    // GNU headers have "ustar  \0" here, and use the prefix for other things.
+    if &block[257..263] != b"ustar\0" {
+        return name.to_vec();
+    }
+    let prefix = field(block, 345..500);
+    if prefix.is_empty() {
+        return name.to_vec();
+    }
+    let mut joined = prefix.to_vec();
+    joined.push(b'/');
+    joined.extend_from_slice(name);
+    joined
+}
+
+/// Rounds `size` up to a whole number of blocks.
+fn padded(size: u64) -> u64 {
+    (size + BLOCK - 1) / BLOCK * BLOCK
+}
+
+/// The records of pax extended headers that apply to the next entry.
+#[derive(Debug, Default)]
+struct Pax {
+    path: Option<Vec<u8>>,
+    linkpath: Option<Vec<u8>>,
+    size: Option<u64>,
+    mtime: Option<u64>,
+    uid: Option<u64>,
+    gid: Option<u64>
+}
+
+impl Pax {
+    /// Reads the `<length> <key>=<value>\n` records of an extended header.
+    fn merge(&mut self, mut data: &[u8]) -> io::Result<()> {
+        let invalid = || invalid_data("invalid pax extended header");
+        let decimal = |value: &[u8]| {
+            std::str::from_utf8(value).ok()
+                                      .and_then(|value| {
+                                          // Fractional seconds are dropped.
+                                          let value = value.split('.').next()?;
+                                          value.parse::<u64>().ok()
+                                      })
+                                      .ok_or_else(invalid)
+        };
+        while !data.iter().all(|&b| b == 0) {
+            let space =
+                data.iter().position(|&b| b == b' ').ok_or_else(invalid)?;
+            let len = decimal(&data[..space])? as usize;
+            if len <= space || len > data.len() || data[len - 1] != b'\n' {
+                return Err(invalid());
+            }
+            let record = &data[space + 1..len - 1];
+            let equals =
+                record.iter().position(|&b| b == b'=').ok_or_else(invalid)?;
+            let (key, value) = (&record[..equals], &record[equals + 1..]);
+            match key {
+                b"path" => self.path = Some(value.to_vec()),
+                b"linkpath" => self.linkpath = Some(value.to_vec()),
+                b"size" => self.size = Some(decimal(value)?),
+                b"mtime" => self.mtime = Some(decimal(value)?),
+                b"uid" => self.uid = Some(decimal(value)?),
+                b"gid" => self.gid = Some(decimal(value)?),
+                _ => {}
+            }
+            data = &data[len..];
+        }
+        Ok(())
+    }
+}
+
+/// Reads the headers of the archive that starts at the current position of
+/// `reader`, seeking over the data of its entries.
+pub(super) async fn read_index<R>(reader: &mut R) -> io::Result<Index>
+    where R: Archive
+{
+    let mut index = Index::new();
+    let mut offset = reader.seek(SeekFrom::Current(0)).await?;
+    let mut block = [0; BLOCK as usize];
+    let mut long_name = None;
+    let mut long_link = None;
+    let mut pax = Pax::default();
+    loop {
+        if !read_block(reader, &mut block).await? {
+            break;
+        }
+        offset += BLOCK;
+        if block.iter().all(|&b| b == 0) {
+            break;
+        }
+        verify(&block)?;
+        let size = number(&block, 124..136)?;
+        let flag = block[156];
+        if let b'L' | b'K' | b'x' | b'g' = flag {
+            if size > MAX_EXTENSION {
+                return Err(invalid_data("tar extended header is too \
+                                         large"));
+            }
+            let mut data = vec![0; padded(size) as usize];
+            reader.read_exact(&mut data).await?;
+            offset += data.len() as u64;
+            data.truncate(size as usize);
+            match flag {
+                b'L' => long_name = Some(field(&data, 0..data.len()).to_vec()),
+                b'K' => long_link = Some(field(&data, 0..data.len()).to_vec()),
+                b'x' => pax.merge(&data)?,
+                _ => {}
+            }
+            continue;
+        }
+
+        let pax = std::mem::take(&mut pax);
+        let name = pax.path
+                      .or_else(|| long_name.take())
+                      .unwrap_or_else(|| header_name(&block));
+        let link = pax.linkpath
+                      .or_else(|| long_link.take())
+                      .unwrap_or_else(|| field(&block, 157..257).to_vec());
+        let size = pax.size.unwrap_or(size);
+        let mtime = match pax.mtime {
+            Some(mtime) => mtime,
+            None => number(&block, 136..148)?
+        };
+        let uid = match pax.uid {
+            Some(uid) => uid,
+            None => number(&block, 108..116)?
+        };
+        let gid = match pax.gid {
+            Some(gid) => gid,
+            None => number(&block, 116..124)?
+        };
+        let attributes = Attributes { mode: number(&block, 100..108)? as u32
+                                            & 0o7777,
+                                      uid: Some(uid as u32),
+                                      gid: Some(gid as u32),
+                                      mtime: Some(mtime) };
+        let dir = name.ends_with(b"/");
+        let path = archive_path(&name);
+        let kind = match flag {
+            b'1' => Err(archive_path(&link)),
+            b'2' => Ok(Kind::Symlink(PathBuf::from(os_string(link)))),
+            b'3' => Ok(Kind::Special(FileType::CharDevice)),
+            b'4' => Ok(Kind::Special(FileType::BlockDevice)),
+            b'5' => Ok(Kind::dir()),
+            b'6' => Ok(Kind::Special(FileType::Fifo)),
+            _ if dir => Ok(Kind::dir()),
+            _ => Ok(Kind::File { data: offset,
+                                 len: size })
+        };
+        match kind {
+            Ok(kind) => index.insert(path, kind, attributes)?,
+            Err(target) => index.link(path, &target)?
+        }
+
+        offset += padded(size);
+        reader.seek(SeekFrom::Start(offset)).await?;
+    }
+    index.count_links();
+    Ok(index)
+}
+
+/// Reads one block, returning `false` if the archive ends cleanly before it.
+async fn read_block<R>(reader: &mut R, block: &mut [u8]) -> io::Result<bool>
+    where R: AsyncRead + Unpin
+{
+    let mut filled = 0;
+    while filled < block.len() {
+        match reader.read(&mut block[filled..]).await? {
+            0 if filled == 0 => return Ok(false),
+            0 => return Err(truncated()),
+            read => filled += read
+        }
+    }
+    Ok(true)
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::{
+        path::Path,
+        time::{Duration, UNIX_EPOCH}
+    };
+
+    use futures_lite::{future::block_on, io::Cursor};
+
+    use super::*;
+
+    /// Writes ustar archives for the tests of this module and its siblings.
+    #[derive(Debug, Default)]
+    pub(in super::super) struct Builder {
+        bytes: Vec<u8>
+    }
+
+    impl Builder {
+        pub(in super::super) fn new() -> Self {
+            Builder::default()
+        }
+
+        fn entry(mut self,
+                 name: &str,
+                 flag: u8,
+                 link: &str,
+                 data: &[u8])
+                 -> Self {
+            let mut block = [0; BLOCK as usize];
+            block[..name.len()].copy_from_slice(name.as_bytes());
+            block[100..108].copy_from_slice(b"0000644\0");
+            block[108..116].copy_from_slice(b"0001750\0");
+            block[116..124].copy_from_slice(b"0001750\0");
+            let size = format!("{:011o}\0", data.len());
+            block[124..136].copy_from_slice(size.as_bytes());
+            block[136..148].copy_from_slice(b"07346545000\0");
+            block[156] = flag;
+            block[157..157 + link.len()].copy_from_slice(link.as_bytes());
+            block[257..263].copy_from_slice(b"ustar\0");
+            block[263..265].copy_from_slice(b"00");
+            block[148..156].copy_from_slice(b"        ");
+            let sum = block.iter().map(|&b| u32::from(b)).sum::<u32>();
+            let sum = format!("{:06o}\0 ", sum);
+            block[148..156].copy_from_slice(sum.as_bytes());
+            self.bytes.extend_from_slice(&block);
+            self.bytes.extend_from_slice(data);
+            self.bytes
+                .resize(padded(self.bytes.len() as u64) as usize, 0);
+            self
+        }
+
+        pub(in super::super) fn file(self, name: &str, data: &[u8]) -> Self {
+            self.entry(name, b'0', "", data)
+        }
+
+        pub(in super::super) fn dir(self, name: &str) -> Self {
+            self.entry(name, b'5', "", b"")
+        }
+
+        pub(in super::super) fn symlink(self,
+                                        name: &str,
+                                        target: &str)
+                                        -> Self {
+            self.entry(name, b'2', target, b"")
+        }
+
+        pub(in super::super) fn hard_link(self,
+                                          name: &str,
+                                          target: &str)
+                                          -> Self {
+            self.entry(name, b'1', target, b"")
+        }
+
+        /// Adds a pax extended header for the next entry.
+        fn pax(self, records: &[(&str, &str)]) -> Self {
+            let mut data = String::new();
+            for (key, value) in records {
+                let record = format!(" {}={}\n", key, value);
+                // The length counts its own digits.
+                let mut len = record.len() + 1;
+                while format!("{}{}", len, record).len() != len {
+                    len += 1;
+                }
+                data.push_str(&format!("{}{}", len, record));
+            }
+            self.entry("PaxHeader", b'x', "", data.as_bytes())
+        }
+
+        /// Adds a GNU long name for the next entry.
+        fn long_name(self, name: &str) -> Self {
+            let mut data = name.as_bytes().to_vec();
+            data.push(0);
+            self.entry("././@LongLink", b'L', "", &data)
+        }
+
+        pub(in super::super) fn finish(mut self) -> Vec<u8> {
+            self.bytes.resize(self.bytes.len() + 2 * BLOCK as usize, 0);
+            self.bytes
+        }
+    }
+
+    fn index(bytes: Vec<u8>) -> io::Result<Index> {
+        block_on(read_index(&mut Cursor::new(bytes)))
+    }
+
+    fn file(index: &Index, path: &str) -> (u64, u64) {
+        match index.resolve(Path::new(path), true).unwrap().1.kind {
+            Kind::File { data, len } => (data, len),
+            ref kind => panic!("{} is {:?}", path, kind)
+        }
+    }
+
+    #[test]
+    fn entries_are_indexed() {
+        let index = index(Builder::new().file("./a/b/c.txt", b"hello")
+                                        .dir("d/")
+                                        .symlink("e", "a/b")
+                                        .finish()).unwrap();
+        assert_eq!(file(&index, "/a/b/c.txt"), (512, 5));
+        assert_eq!(file(&index, "/e/c.txt"), (512, 5));
+        let metadata =
+            index.resolve(Path::new("/d"), true).unwrap().1.metadata();
+        assert!(metadata.is_dir());
+        assert_eq!(metadata.permissions().mode(), Some(0o644));
+        assert_eq!(metadata.uid(), Some(1000));
+        assert_eq!(metadata.modified().unwrap(),
+                   UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+    }
+
+    #[test]
+    fn long_names_and_pax_headers_override_the_header() {
+        let long = "x/".repeat(80) + "file";
+        let index = index(Builder::new().long_name(&long)
+                                        .file("truncated", b"1")
+                                        .pax(&[("path", "pax/name"),
+                                               ("mtime", "12.5")])
+                                        .file("ignored", b"22")
+                                        .file("plain", b"333")
+                                        .finish()).unwrap();
+        assert_eq!(file(&index, &format!("/{}", long)).1, 1);
+        assert_eq!(file(&index, "/pax/name").1, 2);
+        assert_eq!(file(&index, "/plain").1, 3);
+        assert!(index.resolve(Path::new("/truncated"), true).is_err());
+        assert!(index.resolve(Path::new("/ignored"), true).is_err());
+        let metadata = index.resolve(Path::new("/pax/name"), true)
+                            .unwrap()
+                            .1
+                            .metadata();
+        assert_eq!(metadata.modified().unwrap(),
+                   UNIX_EPOCH + Duration::from_secs(12));
+    }
+
+    #[test]
+    fn hard_links_are_counted() {
+        let index = index(Builder::new().file("a", b"data")
+                                        .hard_link("b", "a")
+                                        .file("c", b"")
+                                        .finish()).unwrap();
+        assert_eq!(file(&index, "/a"), file(&index, "/b"));
+        let b = index.resolve(Path::new("/b"), false).unwrap().1.metadata();
+        let c = index.resolve(Path::new("/c"), false).unwrap().1.metadata();
+        assert_eq!(b.nlink(), Some(2));
+        assert_eq!(c.nlink(), Some(1));
+    }
+
+    #[test]
+    fn numbers_can_be_base_256() {
+        let mut field = [0u8; 12];
+        field[0] = 0x80;
+        field[10] = 0x01;
+        field[11] = 0x02;
+        assert_eq!(number(&field, 0..12).unwrap(), 0x0102);
+        assert_eq!(number(b"  0755 \0", 0..8).unwrap(), 0o755);
+    }
+
+    #[test]
+    fn corrupt_headers_are_rejected() {
+        let mut bytes = Builder::new().file("a", b"data").finish();
+        bytes[0] = b'b';
+        let err = index(bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = Builder::new().file("a", b"data").finish();
+        bytes.truncate(100);
+        let err = index(bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
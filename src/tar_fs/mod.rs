@@ -0,0 +1,79 @@
+//! A read-only backend that serves the contents of a tar archive.
+//!
+//! [`TarFs`] implements [`AsyncFsTrait`][1] on top of an archive.  Its headers
+//! are read once, when it is opened, into an index of every entry, so
+//! metadata and directory listings never touch the archive again.  Files in
+//! an uncompressed archive are read by seeking to their data, so any part of
+//! any file can be read without reading what comes before it.  Compressed
+//! archives are decompressed into memory first.
+//!
+//! The index understands ustar headers, GNU long names, and pax extended
+//! headers.  Symlinks in the archive are followed just like on a real
+//! filesystem, and their targets are returned by
+//! [`read_link()`][2].  Hard links share the inode number of the entry they
+//! link to, and [`symlink_metadata()`][3] reports how many names it has.  When
+//! a path appears in the archive more than once, the last entry wins, just
+//! like when the archive is extracted.
+//!
+//! Everything else in this module is reached through [`TarFs`]:
+//!
+//! - [`TarOpenOptions`] is returned by [`TarFs::open_options()`][4], and opens
+//!   [`TarFile`]s for reading.
+//! - [`TarDirBuilder`] is returned by [`TarFs::dir_builder()`][5].
+//! - [`TarReadDir`] is returned by [`TarFs::read_dir()`][6], and yields
+//!   [`TarDirEntry`]s.
+//!
+//! Every operation that would change the archive fails with
+//! [`ErrorKind::PermissionDenied`][7].  [`TarFile`] doesn't implement
+//! [`AsyncWrite`][8], so a `TarFs` can be mounted with `mount_read_only()` in
+//! a `MountFs`, but not with `mount()`.
+//!
+//! ```no_run
+//! # #[cfg(feature = "std-fs")]
+//! # {
+//! use async_fs_traits::{
+//!     std_fs::StdFs, tar_fs::TarFs, AsyncFileBuilderTrait, AsyncFsTrait
+//! };
+//! use futures_lite::{future::block_on, AsyncReadExt};
+//!
+//! block_on(async {
+//!     let archive = StdFs::new().open_options()
+//!                               .read(true)
+//!                               .open("backup.tar")
+//!                               .await?;
+//!     let tar = TarFs::new(archive).await?;
+//!     let mut notes = String::new();
+//!     tar.open_options()
+//!        .read(true)
+//!        .open("/home/notes.txt")
+//!        .await?
+//!        .read_to_string(&mut notes)
+//!        .await?;
+//!     std::io::Result::Ok(())
+//! });
+//! # }
+//! ```
+//!
+//! This module is only available when the `tar` feature is enabled.
+//! `TarFs::from_gz()` also needs the `tar-gz` feature.
+//!
+//! [1]: crate::AsyncFsTrait
+//! [2]: crate::AsyncFsTrait::read_link
+//! [3]: crate::AsyncFsTrait::symlink_metadata
+//! [4]: crate::AsyncFsTrait::open_options
+//! [5]: crate::AsyncFsTrait::dir_builder
+//! [6]: crate::AsyncFsTrait::read_dir
+//! [7]: std::io::ErrorKind::PermissionDenied
+//! [8]: crate::AsyncWrite
+
+mod dir;
+mod file;
+mod fs;
+mod header;
+
+#[doc(inline)]
+pub use dir::{TarDirBuilder, TarDirEntry, TarReadDir};
+#[doc(inline)]
+pub use file::{TarFile, TarOpenOptions};
+#[doc(inline)]
+pub use fs::TarFs;
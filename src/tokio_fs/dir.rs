@@ -0,0 +1,175 @@
+//! Directory types for [`tokio::fs`].
+//!
+//! [`TokioDirBuilder`] creates directories, and [`TokioReadDir`] streams the
+//! [`TokioDirEntry`]s of an existing directory.
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::{ready, Stream};
+
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncReadDirTrait, FileType,
+    Metadata
+};
+
+/// A builder for creating directories through [`tokio::fs`].
+///
+/// This is a thin wrapper around [`tokio::fs::DirBuilder`].
+#[derive(Debug, Default)]
+pub struct TokioDirBuilder {
+    inner: tokio::fs::DirBuilder
+}
+
+impl TokioDirBuilder {
+    /// Creates a blank set of options.
+    ///
+    /// The [`recursive()`][1] option is initially set to `false`.
+    ///
+    /// [1]: AsyncDirBuilderTrait::recursive
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the mode to create new directories with.
+    ///
+    /// This option defaults to `0o777`, modified by the process's umask.
+    #[cfg(unix)]
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.inner.mode(mode);
+        self
+    }
+}
+
+impl From<tokio::fs::DirBuilder> for TokioDirBuilder {
+    fn from(inner: tokio::fs::DirBuilder) -> Self {
+        TokioDirBuilder { inner }
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for TokioDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.inner.recursive(recursive);
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.inner.create(path).await
+    }
+}
+
+/// A stream of entries in a directory, as seen through [`tokio::fs`].
+///
+/// This adapts [`tokio::fs::ReadDir`], which isn't a [`Stream`] by itself.
+#[derive(Debug)]
+pub struct TokioReadDir {
+    inner: tokio::fs::ReadDir
+}
+
+impl From<tokio::fs::ReadDir> for TokioReadDir {
+    fn from(inner: tokio::fs::ReadDir) -> Self {
+        TokioReadDir { inner }
+    }
+}
+
+impl Stream for TokioReadDir {
+    type Item = io::Result<TokioDirEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        let entry = ready!(self.inner.poll_next_entry(cx));
+        Poll::Ready(entry.transpose()
+                         .map(|entry| entry.map(TokioDirEntry::from)))
+    }
+}
+
+impl AsyncReadDirTrait<TokioDirEntry> for TokioReadDir {}
+
+/// An entry in a directory, as seen through [`tokio::fs`].
+///
+/// This wraps a [`tokio::fs::DirEntry`], so [`path()`][1] and
+/// [`file_name()`][2] never touch the disk.
+///
+/// [1]: AsyncDirEntryTrait::path
+/// [2]: AsyncDirEntryTrait::file_name
+#[derive(Debug, Clone)]
+pub struct TokioDirEntry {
+    inner: Arc<tokio::fs::DirEntry>
+}
+
+impl From<tokio::fs::DirEntry> for TokioDirEntry {
+    fn from(inner: tokio::fs::DirEntry) -> Self {
+        TokioDirEntry { inner: Arc::new(inner) }
+    }
+}
+
+#[async_trait]
+impl AsyncDirEntryTrait for TokioDirEntry {
+    async fn path(&self) -> PathBuf {
+        self.inner.path()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        // `tokio::fs::DirEntry::metadata()` doesn't follow symlinks, but this
+        // trait method is documented to, so go through the path instead.
+        Ok(tokio::fs::metadata(self.inner.path()).await?.into())
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.inner.file_type().await?.into())
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.inner.file_name()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::StreamExt;
+
+    use super::*;
+    use crate::{tokio_fs::TokioFs, AsyncFsTrait};
+
+    #[tokio::test]
+    async fn recursive_create_and_read_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+
+        let fs = TokioFs::new();
+        assert!(fs.dir_builder().create(&nested).await.is_err());
+        fs.dir_builder()
+          .recursive(true)
+          .create(&nested)
+          .await
+          .unwrap();
+
+        let mut entries = fs.read_dir(dir.path()).await.unwrap();
+        let entry = entries.next().await.unwrap().unwrap();
+        assert!(entries.next().await.is_none());
+
+        assert_eq!(entry.file_name().await, OsString::from("a"));
+        assert_eq!(entry.path().await, dir.path().join("a"));
+        assert!(entry.file_type().await.unwrap().is_dir());
+    }
+}
@@ -0,0 +1,258 @@
+//! [`TokioOpenOptions`] opens [`TokioFile`]s through [`tokio::fs`].
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+use async_trait::async_trait;
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use tokio::io::{
+    AsyncRead as TokioAsyncRead, AsyncSeek as TokioAsyncSeek,
+    AsyncWrite as TokioAsyncWrite, ReadBuf
+};
+
+use crate::{
+    metadata::to_std_permissions, AsyncFileBuilderTrait, AsyncFileTrait,
+    Metadata, Permissions
+};
+
+/// Options for opening a [`TokioFile`].
+///
+/// This is a thin wrapper around [`tokio::fs::OpenOptions`].  Every option
+/// starts out set to `false`.
+#[derive(Debug, Clone)]
+pub struct TokioOpenOptions {
+    inner: tokio::fs::OpenOptions
+}
+
+impl TokioOpenOptions {
+    /// Creates a blank set of options.
+    pub fn new() -> Self {
+        TokioOpenOptions { inner: tokio::fs::OpenOptions::new() }
+    }
+}
+
+impl Default for TokioOpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<tokio::fs::OpenOptions> for TokioOpenOptions {
+    fn from(inner: tokio::fs::OpenOptions) -> Self {
+        TokioOpenOptions { inner }
+    }
+}
+
+#[async_trait]
+impl AsyncFileBuilderTrait for TokioOpenOptions {
+    type File = TokioFile;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.inner.read(read);
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.inner.write(write);
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.inner.append(append);
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.inner.truncate(truncate);
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.inner.create(create);
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.inner.create_new(create_new);
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.inner.open(path).await?.into())
+    }
+}
+
+/// An open file, as seen through [`tokio::fs`].
+///
+/// This wraps a [`tokio::fs::File`] and implements the [`futures_io`]
+/// versions of [`AsyncRead`], [`AsyncWrite`], and [`AsyncSeek`] on top of
+/// tokio's own versions of those traits.  The original file can be recovered
+/// with [`into_inner()`][1].
+///
+/// [1]: TokioFile::into_inner
+#[derive(Debug)]
+pub struct TokioFile {
+    inner: tokio::fs::File,
+
+    /// Set to `true` while a seek started with `start_seek()` hasn't
+    /// completed yet.
+    seek_in_progress: bool
+}
+
+impl TokioFile {
+    /// Returns a reference to the wrapped tokio file.
+    pub fn get_ref(&self) -> &tokio::fs::File {
+        &self.inner
+    }
+
+    /// Unwraps this file, returning the wrapped tokio file.
+    pub fn into_inner(self) -> tokio::fs::File {
+        self.inner
+    }
+}
+
+impl From<tokio::fs::File> for TokioFile {
+    fn from(inner: tokio::fs::File) -> Self {
+        TokioFile { inner,
+                    seek_in_progress: false }
+    }
+}
+
+#[async_trait]
+impl AsyncFileTrait for TokioFile {
+    async fn sync_all(&self) -> io::Result<()> {
+        self.inner.sync_all().await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.inner.sync_data().await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.inner.set_len(size).await
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.inner.metadata().await?.into())
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        let current = self.inner.metadata().await?.permissions();
+        let std_perm = to_std_permissions(perm, || Ok(current))?;
+        self.inner.set_permissions(std_perm).await
+    }
+}
+
+impl AsyncRead for TokioFile {
+    fn poll_read(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        let mut buf = ReadBuf::new(buf);
+        ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+impl AsyncWrite for TokioFile {
+    fn poll_write(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl AsyncSeek for TokioFile {
+    fn poll_seek(mut self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        // Tokio splits seeking into starting the seek and then polling for its
+        // completion.  Only start a new seek if there isn't one in flight
+        // already, since the caller will retry with the same `pos` after being
+        // woken up.
+        if !self.seek_in_progress {
+            Pin::new(&mut self.inner).start_seek(pos)?;
+            self.seek_in_progress = true;
+        }
+        let result = ready!(Pin::new(&mut self.inner).poll_complete(cx));
+        self.seek_in_progress = false;
+        Poll::Ready(result)
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{tokio_fs::TokioFs, AsyncFsTrait};
+
+    #[tokio::test]
+    async fn write_read_and_seek_through_futures_io() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+
+        let mut file = TokioFs::new().open_options()
+                                     .read(true)
+                                     .write(true)
+                                     .create_new(true)
+                                     .open(&path)
+                                     .await
+                                     .unwrap();
+        file.write_all(b"hello world").await.unwrap();
+        file.flush().await.unwrap();
+        file.seek(SeekFrom::Start(6)).await.unwrap();
+
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "world");
+    }
+
+    #[tokio::test]
+    async fn set_len_zero_fills_and_keeps_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+
+        let mut file = TokioFs::new().open_options()
+                                     .read(true)
+                                     .write(true)
+                                     .create(true)
+                                     .open(&path)
+                                     .await
+                                     .unwrap();
+        file.write_all(b"abc").await.unwrap();
+        file.set_len(6).await.unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 3);
+        assert_eq!(file.metadata().await.unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,235 @@
+//! [`TokioFs`] implements [`AsyncFsTrait`] on top of [`tokio::fs`].
+
+use std::{
+    io,
+    path::{Path, PathBuf}
+};
+
+use async_trait::async_trait;
+
+use super::{TokioDirBuilder, TokioDirEntry, TokioOpenOptions, TokioReadDir};
+use crate::{
+    metadata::to_std_permissions, AsyncFsTrait, AsyncSymLinkTrait, Metadata,
+    Permissions
+};
+
+/// The operating system's filesystem, as seen through [`tokio::fs`].
+///
+/// Every method is a thin wrapper around the function of the same name in
+/// [`tokio::fs`].  Relative paths are resolved against the process's current
+/// working directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TokioFs;
+
+impl TokioFs {
+    /// Creates a handle to the operating system's filesystem.
+    pub fn new() -> Self {
+        TokioFs
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for TokioFs {
+    type DirBuilder = TokioDirBuilder;
+    type DirEntry = TokioDirEntry;
+    type FileBuilder = TokioOpenOptions;
+    type ReadDir = TokioReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        TokioDirBuilder::new()
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        TokioOpenOptions::new()
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        tokio::fs::canonicalize(path).await
+    }
+
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        tokio::fs::rename(src, dst).await
+    }
+
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        // This mirrors what `tokio::fs::set_permissions()` does internally,
+        // except that the current permissions may need to be read first.
+        let path = path.as_ref().to_owned();
+        blocking(move || {
+            let std_perm = to_std_permissions(perm, || {
+                Ok(std::fs::metadata(&path)?.permissions())
+            })?;
+            std::fs::set_permissions(path, std_perm)
+        }).await
+    }
+
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        tokio::fs::hard_link(src, dst).await
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        tokio::fs::read_link(path).await
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(tokio::fs::symlink_metadata(path).await?.into())
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(tokio::fs::metadata(path).await?.into())
+    }
+
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        tokio::fs::copy(src, dst).await
+    }
+
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        tokio::fs::remove_file(path).await
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        Ok(tokio::fs::read_dir(path).await?.into())
+    }
+
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        tokio::fs::remove_dir(path).await
+    }
+
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        tokio::fs::remove_dir_all(path).await
+    }
+}
+
+#[async_trait]
+impl AsyncSymLinkTrait for TokioFs {
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        symlink(src.as_ref(), dst.as_ref()).await
+    }
+}
+
+/// Runs a blocking closure on tokio's blocking thread pool.
+async fn blocking<F, T>(f: F) -> io::Result<T>
+    where F: FnOnce() -> io::Result<T> + Send + 'static,
+          T: Send + 'static
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(_) => {
+            Err(io::Error::new(io::ErrorKind::Other, "background task failed"))
+        }
+    }
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// Note that the argument order follows [`AsyncSymLinkTrait::symlink()`],
+/// which is the reverse of the platform-specific functions in tokio.
+#[cfg(unix)]
+async fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    tokio::fs::symlink(dst, src).await
+}
+
+/// Creates a symlink at `src` that points to `dst`.
+///
+/// Windows needs to know whether the target is a directory, so a relative
+/// `dst` is resolved against the parent of `src` to find out.  Targets that
+/// don't exist are linked as files.
+#[cfg(windows)]
+async fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    let target = match src.parent() {
+        Some(parent) => parent.join(dst),
+        None => dst.to_owned()
+    };
+    match tokio::fs::metadata(target).await {
+        Ok(metadata) if metadata.is_dir() => {
+            tokio::fs::symlink_dir(dst, src).await
+        }
+        _ => tokio::fs::symlink_file(dst, src).await
+    }
+}
+
+/// Symlinks aren't supported on this platform.
+#[cfg(not(any(unix, windows)))]
+async fn symlink(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "symlinks are not supported on this platform"))
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rename_overwrites_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        std::fs::write(&src, b"new").unwrap();
+        std::fs::write(&dst, b"old").unwrap();
+
+        TokioFs::new().rename(&src, &dst).await.unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(std::fs::read(&dst).unwrap(), b"new");
+    }
+
+    #[tokio::test]
+    async fn symlink_and_set_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        std::fs::write(&target, b"12345").unwrap();
+
+        let fs = TokioFs::new();
+        fs.symlink(&link, "target").await.unwrap();
+        assert!(fs.symlink_metadata(&link).await.unwrap().is_symlink());
+        assert_eq!(fs.metadata(&link).await.unwrap().len(), 5);
+
+        fs.set_permissions(&target, Permissions::new(true))
+          .await
+          .unwrap();
+        assert!(fs.metadata(&target).await.unwrap().permissions().readonly());
+    }
+}
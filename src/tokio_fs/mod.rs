@@ -0,0 +1,38 @@
+//! A backend for the operating system's filesystem, built on [`tokio::fs`].
+//!
+//! Tokio defines its own `AsyncRead`, `AsyncWrite`, and `AsyncSeek` traits,
+//! which are different from the [`futures_io`] traits that this crate uses.
+//! [`TokioFile`] bridges the two, so a file opened through this module can be
+//! handed to any code that is written against the [`futures_io`] traits.
+//!
+//! The entry point is [`TokioFs`], which implements [`AsyncFsTrait`][1] and
+//! [`AsyncSymLinkTrait`][2].  Everything else in this module is reached
+//! through it:
+//!
+//! - [`TokioOpenOptions`] is returned by [`TokioFs::open_options()`][3], and
+//!   opens [`TokioFile`]s.
+//! - [`TokioDirBuilder`] is returned by [`TokioFs::dir_builder()`][4].
+//! - [`TokioReadDir`] is returned by [`TokioFs::read_dir()`][5], and yields
+//!   [`TokioDirEntry`]s.
+//!
+//! As with [`tokio::fs`] itself, the types in this module must be used from
+//! within a tokio runtime.
+//!
+//! This module is only available when the `tokio` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait
+//! [2]: crate::AsyncSymLinkTrait
+//! [3]: crate::AsyncFsTrait::open_options
+//! [4]: crate::AsyncFsTrait::dir_builder
+//! [5]: crate::AsyncFsTrait::read_dir
+
+mod dir;
+mod file;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{TokioDirBuilder, TokioDirEntry, TokioReadDir};
+#[doc(inline)]
+pub use file::{TokioFile, TokioOpenOptions};
+#[doc(inline)]
+pub use fs::TokioFs;
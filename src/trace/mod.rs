@@ -0,0 +1,35 @@
+//! A layer that traces every filesystem operation with [`tracing`].
+//!
+//! [`TraceLayer`] wraps a filesystem in a [`TraceFs`], which emits a span for
+//! each call made through any trait in the family: every [`AsyncFsTrait`][1]
+//! method, directory and file creation, every [`AsyncFileTrait`][2] method,
+//! every poll of a file's read, write, flush, close, and seek, and every
+//! directory entry.  Nothing else about the operations changes, so this layer
+//! can be dropped into a production stack to debug its I/O.
+//!
+//! Spans for whole operations are emitted at the `DEBUG` level, while spans for
+//! individual polls and directory entry accessors are emitted at `TRACE`.  Each
+//! span is named after the operation, and carries the following fields when
+//! they apply:
+//!
+//! - `path`, or `src` and `dst`: the paths that were passed in.  Spans for
+//!   open files carry the path the file was opened with.
+//! - `bytes`: the number of bytes read, written, or copied.
+//! - `capacity`, `size`, `pos`, `offset`, `flags`, `recursive`, `readonly`:
+//!   the other arguments and results of the operation.
+//! - `elapsed_us`: how long the operation took, in microseconds.  For an
+//!   `async` operation this includes the time spent waiting.
+//! - `pending`: `true` if a poll returned [`Poll::Pending`][3].
+//! - `error`: the [`ErrorKind`][4] of a failed operation.
+//!
+//! This module is only available when the `tracing` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait
+//! [2]: crate::AsyncFileTrait
+//! [3]: std::task::Poll::Pending
+//! [4]: std::io::ErrorKind
+
+mod tracer;
+
+#[doc(inline)]
+pub use tracer::{TraceFs, TraceLayer, Tracer};
@@ -0,0 +1,610 @@
+//! [`Tracer`] is the middleware behind [`TraceLayer`].
+
+use std::{
+    ffi::OsString,
+    future::Future,
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use tracing::{
+    debug_span,
+    field::{display, Empty},
+    trace_span, Instrument, Span
+};
+
+use crate::{
+    layer::{FileOf, FsLayer, FsMiddleware, Layered, OpenFlags},
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncFileBuilderTrait,
+    AsyncFileTrait, AsyncFsTrait, AsyncSymLinkTrait, FileType, Metadata,
+    Permissions
+};
+
+/// A filesystem wrapped by a [`TraceLayer`].
+pub type TraceFs<F> = Layered<Tracer, F>;
+
+/// An [`FsLayer`] that wraps filesystems in a [`TraceFs`].
+#[derive(Debug, Clone, Default)]
+pub struct TraceLayer {
+    tracer: Arc<Tracer>
+}
+
+impl TraceLayer {
+    /// Creates a new tracing layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<F> FsLayer<F> for TraceLayer where F: AsyncFsTrait
+{
+    type Fs = TraceFs<F>;
+
+    fn layer(&self, inner: F) -> Self::Fs {
+        Layered::from_arc(inner, self.tracer.clone())
+    }
+}
+
+/// The [`FsMiddleware`] that emits a span for every operation.
+///
+/// See the [module documentation][1] for the spans and their fields.
+///
+/// [1]: super
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct Tracer;
+
+/// Runs `future` inside `span`, then records how long it took and how it
+/// ended.
+async fn traced<T, Fut>(span: Span, future: Fut) -> io::Result<T>
+    where Fut: Future<Output = io::Result<T>>
+{
+    let start = Instant::now();
+    let result = future.instrument(span.clone()).await;
+    finish(&span, start, &result);
+    result
+}
+
+/// Records the latency of an operation that started at `start` and its error
+/// kind, if it failed.
+fn finish<T>(span: &Span, start: Instant, result: &io::Result<T>) {
+    span.record("elapsed_us", start.elapsed().as_micros() as u64);
+    if let Err(error) = result {
+        span.record("error", tracing::field::debug(error.kind()));
+    }
+}
+
+/// Like [`finish()`], for the result of a poll.
+fn finish_poll<T>(span: &Span, start: Instant, poll: &Poll<io::Result<T>>) {
+    match poll {
+        Poll::Ready(result) => finish(span, start, result),
+        Poll::Pending => {
+            span.record("elapsed_us", start.elapsed().as_micros() as u64);
+            span.record("pending", true);
+        }
+    }
+}
+
+#[async_trait]
+impl<F> FsMiddleware<F> for Tracer where F: AsyncFsTrait
+{
+    async fn canonicalize(&self, fs: &F, path: &Path) -> io::Result<PathBuf> {
+        let span = debug_span!("canonicalize",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.canonicalize(path)).await
+    }
+
+    async fn rename(&self, fs: &F, src: &Path, dst: &Path) -> io::Result<()> {
+        let span = debug_span!("rename",
+                               src = %src.display(),
+                               dst = %dst.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.rename(src, dst)).await
+    }
+
+    async fn set_permissions(&self,
+                             fs: &F,
+                             path: &Path,
+                             perm: Permissions)
+                             -> io::Result<()> {
+        let span = debug_span!("set_permissions",
+                               path = %path.display(),
+                               readonly = perm.readonly(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.set_permissions(path, perm)).await
+    }
+
+    async fn hard_link(&self,
+                       fs: &F,
+                       src: &Path,
+                       dst: &Path)
+                       -> io::Result<()> {
+        let span = debug_span!("hard_link",
+                               src = %src.display(),
+                               dst = %dst.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.hard_link(src, dst)).await
+    }
+
+    async fn read_link(&self, fs: &F, path: &Path) -> io::Result<PathBuf> {
+        let span = debug_span!("read_link",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.read_link(path)).await
+    }
+
+    async fn symlink_metadata(&self,
+                              fs: &F,
+                              path: &Path)
+                              -> io::Result<Metadata> {
+        let span = debug_span!("symlink_metadata",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.symlink_metadata(path)).await
+    }
+
+    async fn metadata(&self, fs: &F, path: &Path) -> io::Result<Metadata> {
+        let span = debug_span!("metadata",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.metadata(path)).await
+    }
+
+    async fn copy(&self, fs: &F, src: &Path, dst: &Path) -> io::Result<u64> {
+        let span = debug_span!("copy",
+                               src = %src.display(),
+                               dst = %dst.display(),
+                               bytes = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        let result = traced(span.clone(), fs.copy(src, dst)).await;
+        if let Ok(bytes) = result {
+            span.record("bytes", bytes);
+        }
+        result
+    }
+
+    async fn remove_file(&self, fs: &F, path: &Path) -> io::Result<()> {
+        let span = debug_span!("remove_file",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.remove_file(path)).await
+    }
+
+    async fn read_dir(&self, fs: &F, path: &Path) -> io::Result<F::ReadDir> {
+        let span = debug_span!("read_dir",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.read_dir(path)).await
+    }
+
+    async fn remove_dir(&self, fs: &F, path: &Path) -> io::Result<()> {
+        let span = debug_span!("remove_dir",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.remove_dir(path)).await
+    }
+
+    async fn remove_dir_all(&self, fs: &F, path: &Path) -> io::Result<()> {
+        let span = debug_span!("remove_dir_all",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.remove_dir_all(path)).await
+    }
+
+    async fn symlink(&self, fs: &F, src: &Path, dst: &Path) -> io::Result<()>
+        where F: AsyncSymLinkTrait
+    {
+        let span = debug_span!("symlink",
+                               src = %src.display(),
+                               dst = %dst.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, fs.symlink(src, dst)).await
+    }
+
+    async fn create_dir(&self,
+                        builder: &F::DirBuilder,
+                        path: &Path,
+                        recursive: bool)
+                        -> io::Result<()> {
+        let span = debug_span!("create_dir",
+                               path = %path.display(),
+                               recursive,
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, builder.create(path)).await
+    }
+
+    async fn open(&self,
+                  builder: &F::FileBuilder,
+                  path: &Path,
+                  flags: OpenFlags)
+                  -> io::Result<FileOf<F>> {
+        let span = debug_span!("open",
+                               path = %path.display(),
+                               ?flags,
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, builder.open(path)).await
+    }
+
+    async fn sync_all(&self, file: &FileOf<F>, path: &Path) -> io::Result<()> {
+        let span = debug_span!("sync_all",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, file.sync_all()).await
+    }
+
+    async fn sync_data(&self, file: &FileOf<F>, path: &Path) -> io::Result<()> {
+        let span = debug_span!("sync_data",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, file.sync_data()).await
+    }
+
+    async fn set_len(&self,
+                     file: &FileOf<F>,
+                     path: &Path,
+                     size: u64)
+                     -> io::Result<()> {
+        let span = debug_span!("set_len",
+                               path = %path.display(),
+                               size,
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, file.set_len(size)).await
+    }
+
+    async fn file_metadata(&self,
+                           file: &FileOf<F>,
+                           path: &Path)
+                           -> io::Result<Metadata> {
+        let span = debug_span!("file_metadata",
+                               path = %path.display(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, file.metadata()).await
+    }
+
+    async fn file_set_permissions(&self,
+                                  file: &FileOf<F>,
+                                  path: &Path,
+                                  perm: Permissions)
+                                  -> io::Result<()> {
+        let span = debug_span!("file_set_permissions",
+                               path = %path.display(),
+                               readonly = perm.readonly(),
+                               elapsed_us = Empty,
+                               error = Empty);
+        traced(span, file.set_permissions(perm)).await
+    }
+
+    fn poll_read(&self,
+                 file: Pin<&mut FileOf<F>>,
+                 path: &Path,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>>
+        where FileOf<F>: AsyncRead
+    {
+        let span = trace_span!("poll_read",
+                               path = %path.display(),
+                               capacity = buf.len(),
+                               bytes = Empty,
+                               pending = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        let start = Instant::now();
+        let poll = span.in_scope(|| file.poll_read(cx, buf));
+        finish_poll(&span, start, &poll);
+        if let Poll::Ready(Ok(bytes)) = poll {
+            span.record("bytes", bytes);
+        }
+        poll
+    }
+
+    fn poll_write(&self,
+                  file: Pin<&mut FileOf<F>>,
+                  path: &Path,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>>
+        where FileOf<F>: AsyncWrite
+    {
+        let span = trace_span!("poll_write",
+                               path = %path.display(),
+                               capacity = buf.len(),
+                               bytes = Empty,
+                               pending = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        let start = Instant::now();
+        let poll = span.in_scope(|| file.poll_write(cx, buf));
+        finish_poll(&span, start, &poll);
+        if let Poll::Ready(Ok(bytes)) = poll {
+            span.record("bytes", bytes);
+        }
+        poll
+    }
+
+    fn poll_flush(&self,
+                  file: Pin<&mut FileOf<F>>,
+                  path: &Path,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>>
+        where FileOf<F>: AsyncWrite
+    {
+        let span = trace_span!("poll_flush",
+                               path = %path.display(),
+                               pending = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        let start = Instant::now();
+        let poll = span.in_scope(|| file.poll_flush(cx));
+        finish_poll(&span, start, &poll);
+        poll
+    }
+
+    fn poll_close(&self,
+                  file: Pin<&mut FileOf<F>>,
+                  path: &Path,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>>
+        where FileOf<F>: AsyncWrite
+    {
+        let span = trace_span!("poll_close",
+                               path = %path.display(),
+                               pending = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        let start = Instant::now();
+        let poll = span.in_scope(|| file.poll_close(cx));
+        finish_poll(&span, start, &poll);
+        poll
+    }
+
+    fn poll_seek(&self,
+                 file: Pin<&mut FileOf<F>>,
+                 path: &Path,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>>
+        where FileOf<F>: AsyncSeek
+    {
+        let span = trace_span!("poll_seek",
+                               path = %path.display(),
+                               ?pos,
+                               offset = Empty,
+                               pending = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        let start = Instant::now();
+        let poll = span.in_scope(|| file.poll_seek(cx, pos));
+        finish_poll(&span, start, &poll);
+        if let Poll::Ready(Ok(offset)) = poll {
+            span.record("offset", offset);
+        }
+        poll
+    }
+
+    fn poll_next_entry(&self,
+                       read_dir: Pin<&mut F::ReadDir>,
+                       cx: &mut Context<'_>)
+                       -> Poll<Option<io::Result<F::DirEntry>>> {
+        let span = trace_span!("poll_next_entry",
+                               done = Empty,
+                               pending = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        let start = Instant::now();
+        let poll = span.in_scope(|| read_dir.poll_next(cx));
+        match &poll {
+            Poll::Ready(Some(result)) => finish(&span, start, result),
+            Poll::Ready(None) => {
+                finish::<()>(&span, start, &Ok(()));
+                span.record("done", true);
+            }
+            Poll::Pending => {
+                finish_poll::<()>(&span, start, &Poll::Pending);
+            }
+        }
+        poll
+    }
+
+    async fn entry_path(&self, entry: &F::DirEntry) -> PathBuf {
+        let span = trace_span!("entry_path", elapsed_us = Empty);
+        let start = Instant::now();
+        let path = entry.path().instrument(span.clone()).await;
+        span.record("elapsed_us", start.elapsed().as_micros() as u64);
+        path
+    }
+
+    async fn entry_metadata(&self,
+                            entry: &F::DirEntry)
+                            -> io::Result<Metadata> {
+        let span = trace_span!("entry_metadata",
+                               path = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        if !span.is_disabled() {
+            span.record("path", display(entry.path().await.display()));
+        }
+        traced(span, entry.metadata()).await
+    }
+
+    async fn entry_file_type(&self,
+                             entry: &F::DirEntry)
+                             -> io::Result<FileType> {
+        let span = trace_span!("entry_file_type",
+                               path = Empty,
+                               elapsed_us = Empty,
+                               error = Empty);
+        if !span.is_disabled() {
+            span.record("path", display(entry.path().await.display()));
+        }
+        traced(span, entry.file_type()).await
+    }
+
+    async fn entry_file_name(&self, entry: &F::DirEntry) -> OsString {
+        let span = trace_span!("entry_file_name", elapsed_us = Empty);
+        let start = Instant::now();
+        let name = entry.file_name().instrument(span.clone()).await;
+        span.record("elapsed_us", start.elapsed().as_micros() as u64);
+        name
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use std::{collections::HashMap, fmt, sync::Mutex};
+
+    use futures_lite::{
+        future::block_on, AsyncReadExt, AsyncWriteExt, StreamExt
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata as TracingMetadata, Subscriber
+    };
+
+    use super::*;
+    use crate::{layer::FsStackBuilder, mem_fs::MemFs};
+
+    /// The name and fields of a span, as recorded by [`Recorder`].
+    type Recorded = (&'static str, HashMap<&'static str, String>);
+
+    /// A subscriber that remembers every span and the fields recorded on it.
+    #[derive(Default)]
+    struct Recorder {
+        spans: Mutex<Vec<Recorded>>
+    }
+
+    struct Fields<'a>(&'a mut HashMap<&'static str, String>);
+
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &TracingMetadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+            let mut fields = HashMap::new();
+            attrs.record(&mut Fields(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((attrs.metadata().name(), fields));
+            span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let (_, fields) = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut Fields(fields));
+        }
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn spans_carry_paths_bytes_and_errors() {
+        let recorder = Arc::new(Recorder::default());
+        let fs = FsStackBuilder::new().layer(TraceLayer::new())
+                                      .build(MemFs::new());
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            block_on(async {
+                let mut file = fs.open_options()
+                                 .read(true)
+                                 .write(true)
+                                 .create(true)
+                                 .open("/file")
+                                 .await
+                                 .unwrap();
+                file.write_all(b"hello").await.unwrap();
+                file.set_len(3).await.unwrap();
+                drop(file);
+
+                let mut contents = Vec::new();
+                fs.open_options()
+                  .read(true)
+                  .open("/file")
+                  .await
+                  .unwrap()
+                  .read_to_end(&mut contents)
+                  .await
+                  .unwrap();
+                assert!(fs.metadata("/missing").await.is_err());
+
+                let mut entries = fs.read_dir("/").await.unwrap();
+                let entry = entries.next().await.unwrap().unwrap();
+                assert!(entry.metadata().await.unwrap().is_file());
+                assert!(entry.file_type().await.unwrap().is_file());
+            });
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        let find = |name: &str| {
+            spans.iter()
+                 .filter(|(span, _)| *span == name)
+                 .map(|(_, fields)| fields)
+                 .collect::<Vec<_>>()
+        };
+
+        let opens = find("open");
+        assert_eq!(opens.len(), 2);
+        assert_eq!(opens[0]["path"], "/file");
+        assert!(opens[0].contains_key("elapsed_us"));
+
+        let writes = find("poll_write");
+        assert_eq!(writes[0]["bytes"], "5");
+        assert_eq!(find("set_len")[0]["size"], "3");
+        let reads = find("poll_read");
+        assert_eq!(reads[0]["bytes"], "3");
+        assert_eq!(reads[0]["path"], "/file");
+
+        let metadata = find("metadata");
+        assert_eq!(metadata[0]["error"], "NotFound");
+        assert_eq!(find("entry_metadata")[0]["path"], "/file");
+        assert_eq!(find("entry_file_type")[0]["path"], "/file");
+    }
+}
@@ -3,25 +3,22 @@
 //! Implement this trait on your file system if you wish it to provide a way of
 //! creating new directories.
 
-#[doc(no_inline)]
-pub use std::fs::{FileType, Metadata, Permissions};
 use std::{io, path::Path};
 
 use async_trait::async_trait;
 
+#[doc(no_inline)]
+pub use crate::metadata::{FileType, Metadata, Permissions};
+
 /// A builder for creating directories with configurable options.
+///
+/// Builders are obtained from the filesystem they operate on via
+/// [`AsyncFsTrait::dir_builder()`][1], which allows the builder to carry
+/// whatever state the filesystem needs in order to create directories.
+///
+/// [1]: super::AsyncFsTrait::dir_builder
 #[async_trait]
-pub trait AsyncDirBuilderTrait:
-    std::fmt::Debug + std::default::Default
-{
-    /// Creates a blank set of options.
-    ///
-    /// The [`recursive()`][1] option is initially set to `false`.
-    ///
-    /// [1]: `AsyncDirBuilderTrait::recursive()`
-    async fn new<T>() -> T
-        where T: AsyncDirBuilderTrait;
-
+pub trait AsyncDirBuilderTrait: std::fmt::Debug + Send + Sync {
     /// Sets the option for recursive mode.
     ///
     /// When set to `true`, this option means all parent directories should be
@@ -29,8 +26,7 @@ pub trait AsyncDirBuilderTrait:
     /// same permissions as the final directory.
     ///
     /// This option is initially set to `false`.
-    async fn recursive<T>(self, recursive: bool) -> T
-        where T: AsyncDirBuilderTrait;
+    fn recursive(&mut self, recursive: bool) -> &mut Self;
 
     /// Creates a directory with the configured options.
     ///
@@ -45,8 +41,8 @@ pub trait AsyncDirBuilderTrait:
     /// * The current process lacks permissions to create the directory or its
     ///   missing parents.
     /// * Some other I/O error occurred.
-    async fn create<P>(self, path: P) -> io::Result<()>
-        where P: AsRef<Path>;
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send;
 }
 
 //  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
@@ -9,19 +9,22 @@
 //! exist; they are intended to be returned by iterators *quickly* and
 //! *cheaply*.  Keep this in mind when implementing this trait.
 
-#[doc(no_inline)]
-pub use std::fs::{FileType, Metadata, Permissions};
 use std::{ffi::OsString, io, path::PathBuf};
 
 use async_trait::async_trait;
 
+#[doc(no_inline)]
+pub use crate::metadata::{FileType, Metadata, Permissions};
+
 /// An entry in a directory.
 ///
 /// A stream of entries in a directory is returned by[`read_dir()`][1].
 ///
 /// [1]: super::AsyncFsTrait::read_dir
 #[async_trait]
-pub trait AsyncDirEntryTrait: std::fmt::Debug + std::clone::Clone {
+pub trait AsyncDirEntryTrait:
+    std::fmt::Debug + std::clone::Clone + Send + Sync
+{
     /// Returns the full path to this entry.
     ///
     /// The full path is created by joining the original path passed to
@@ -8,11 +8,11 @@
 //! expected to follow the standard rust builder idioms, providing sensible
 //! defaults, and catching bad combinations of options.
 
-pub use std::fs::{FileType, Metadata, Permissions};
-use std::{future::Future, io, path::Path};
+use std::{io, path::Path};
 
 use async_trait::async_trait;
 
+pub use crate::metadata::{FileType, Metadata, Permissions};
 #[doc(no_inline)]
 use crate::AsyncFileTrait;
 
@@ -27,26 +27,32 @@ use crate::AsyncFileTrait;
 /// [`create`][5] a file if it doesn't exist yet, or to always create a new
 /// file with[`create_new`][6].
 ///
+/// Builders are obtained from the filesystem they operate on via
+/// [`AsyncFsTrait::open_options()`][7], and start out with all options set to
+/// `false`.  The option setters take `&mut self` and return `&mut Self` so
+/// that they can be chained in the same way as [`std::fs::OpenOptions`]; only
+/// [`open`][8] is asynchronous, and it resolves to the builder's own
+/// [`File`][9] type.
+///
 /// [1]: AsyncFileBuilderTrait::read()
 /// [2]: AsyncFileBuilderTrait::write()
 /// [3]: AsyncFileBuilderTrait::append()
 /// [4]: AsyncFileBuilderTrait::truncate()
 /// [5]: AsyncFileBuilderTrait::create()
 /// [6]: AsyncFileBuilderTrait::create_new()
+/// [7]: super::AsyncFsTrait::open_options
+/// [8]: AsyncFileBuilderTrait::open()
+/// [9]: AsyncFileBuilderTrait::File
 #[async_trait]
-pub trait AsyncFileBuilderTrait: std::default::Default {
-    /// Creates a blank set of options.
-    ///
-    /// All options are initially set to `false`.
-    async fn new<T>() -> T
-        where T: AsyncFileBuilderTrait;
+pub trait AsyncFileBuilderTrait: std::fmt::Debug + Send + Sync {
+    /// The type of file that is opened by this builder.
+    type File: AsyncFileTrait;
 
     /// Configures the option for read mode.
     ///
     /// When set to `true`, this option means the file will be readable after
     /// opening.
-    async fn read<T>(self, read: bool) -> T
-        where T: AsyncFileBuilderTrait;
+    fn read(&mut self, read: bool) -> &mut Self;
 
     /// Configures the option for write mode.
     ///
@@ -55,16 +61,14 @@ pub trait AsyncFileBuilderTrait: std::default::Default {
     ///
     /// If the file already exists, write calls on it will overwrite the
     /// previous contents without truncating it.
-    async fn write<T>(self, write: bool) -> T
-        where T: AsyncFileBuilderTrait;
+    fn write(&mut self, write: bool) -> &mut Self;
 
     /// Configures the option for append mode.
     ///
     /// When set to `true`, this option means the file will be writable after
     /// opening and the file cursor will be moved to the end of file before
     /// every write operation.
-    async fn append<T>(self, append: bool) -> T
-        where T: AsyncFileBuilderTrait;
+    fn append(&mut self, append: bool) -> &mut Self;
 
     /// Configures the option for truncating the previous file.
     ///
@@ -75,8 +79,7 @@ pub trait AsyncFileBuilderTrait: std::default::Default {
     ///
     /// [1]: AsyncFileBuilderTrait::write()
     /// [2]: AsyncFileBuilderTrait::append()
-    async fn truncate<T>(self, truncate: bool) -> T
-        where T: AsyncFileBuilderTrait;
+    fn truncate(&mut self, truncate: bool) -> &mut Self;
 
     /// Configures the option for creating a new file if it doesn't exist.
     ///
@@ -88,8 +91,7 @@ pub trait AsyncFileBuilderTrait: std::default::Default {
     ///
     /// [1]: AsyncFileBuilderTrait::write()
     /// [2]: AsyncFileBuilderTrait::append()
-    async fn create<T>(self, create: bool) -> T
-        where T: AsyncFileBuilderTrait;
+    fn create(&mut self, create: bool) -> &mut Self;
 
     /// Configures the option for creating a new file or failing if it already
     /// exists.
@@ -102,8 +104,7 @@ pub trait AsyncFileBuilderTrait: std::default::Default {
     ///
     /// [1]: AsyncFileBuilderTrait::write()
     /// [2]: AsyncFileBuilderTrait::append()
-    async fn create_new<T>(self, create_new: bool) -> T
-        where T: AsyncFileBuilderTrait;
+    fn create_new(&mut self, create_new: bool) -> &mut Self;
 
     /// Opens a file with the configured options.
     ///
@@ -130,9 +131,8 @@ pub trait AsyncFileBuilderTrait: std::default::Default {
     /// [`truncate`]: `AsyncFileBuilderTrait::truncate()`
     /// [`create`]: `AsyncFileBuilderTrait::create()`
     /// [`create_new`]: `AsyncFileBuilderTrait::create_new()`
-    async fn open<P, T>(self, path: P) -> dyn Future<Output = io::Result<T>>
-        where P: AsRef<Path>,
-              T: AsyncFileTrait;
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send;
 }
 
 //  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
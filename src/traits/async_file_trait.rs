@@ -21,11 +21,12 @@
 //! depend on [`futures_io::AsyncRead`], [`futures_io::AsyncWrite`], or
 //! [`futures_io::AsyncSeek`].
 
-pub use std::fs::{FileType, Metadata, Permissions};
 use std::io;
 
 use async_trait::async_trait;
 
+pub use crate::metadata::{FileType, Metadata, Permissions};
+
 /// An open file on the filesystem.
 ///
 /// Depending on what options the file was opened with, this type can be used
@@ -44,7 +45,7 @@ use async_trait::async_trait;
 /// [2]: self::AsyncFileTrait::sync_all()
 /// [3]: self::AsyncFileTrait::sync_data()
 #[async_trait]
-pub trait AsyncFileTrait: std::fmt::Debug {
+pub trait AsyncFileTrait: std::fmt::Debug + Send + Sync {
     /// Synchronizes OS-internal buffered contents and metadata to disk.
     ///
     /// This function will ensure that all in-memory data reaches the
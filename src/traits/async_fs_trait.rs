@@ -3,12 +3,13 @@
 //! There are objects that can be viewed as file systems that are not normally
 //! considered to be file systems.  Simple examples include things such as
 //! archive and trees.  For this reason, this crate provides the
-//! [`AsyncFsTrait`] trait, which defines a set of associated functions that
-//! would normally be free functions.  See the documentation for each to
-//! understand what they do.
+//! [`AsyncFsTrait`] trait, which defines a set of methods that would normally
+//! be free functions.  Because the methods take `&self`, an implementor is
+//! free to carry whatever state it needs (an archive handle, a root directory,
+//! a connection, an in-memory tree, etc.), and several instances of the same
+//! filesystem type can be used side by side.  See the documentation for each
+//! method to understand what it does.
 
-#[doc(no_inline)]
-pub use std::fs::{FileType, Metadata, Permissions};
 use std::{
     io,
     path::{Path, PathBuf}
@@ -18,18 +19,50 @@ use async_trait::async_trait;
 
 #[doc(no_inline)]
 use super::AsyncDirEntryTrait;
-use super::AsyncReadDirTrait;
+use super::{AsyncDirBuilderTrait, AsyncFileBuilderTrait, AsyncReadDirTrait};
+#[doc(no_inline)]
+pub use crate::metadata::{FileType, Metadata, Permissions};
 
 /// [`AsyncFsTrait`] is a trait for file systems as a whole.
 ///
 /// There are objects that can be viewed as file systems that are not normally
 /// considered to be file systems.  Simple examples include things such as
 /// archive and trees.  For this reason, this crate provides the
-/// [`AsyncFsTrait`] trait, which defines a set of associated functions that
-/// would normally be free functions.  See the documentation for each to
-/// understand what they do.
+/// [`AsyncFsTrait`] trait, which defines a set of methods that would normally
+/// be free functions.  See the documentation for each to understand what they
+/// do.
 #[async_trait]
-pub trait AsyncFsTrait {
+pub trait AsyncFsTrait: std::fmt::Debug + Send + Sync {
+    /// The type of builder returned by [`dir_builder()`][1].
+    ///
+    /// [1]: AsyncFsTrait::dir_builder
+    type DirBuilder: AsyncDirBuilderTrait;
+
+    /// Returns a builder for creating directories on this filesystem.
+    ///
+    /// The builder starts out with all of its options set to their defaults,
+    /// as described in [`AsyncDirBuilderTrait`].
+    fn dir_builder(&self) -> Self::DirBuilder;
+
+    /// The type of builder returned by [`open_options()`][1].
+    ///
+    /// [1]: AsyncFsTrait::open_options
+    type FileBuilder: AsyncFileBuilderTrait;
+
+    /// Returns a builder for opening files on this filesystem.
+    ///
+    /// The builder starts out with all of its options set to `false`, as
+    /// described in [`AsyncFileBuilderTrait`].
+    fn open_options(&self) -> Self::FileBuilder;
+
+    /// The type of entry yielded by [`Self::ReadDir`].
+    type DirEntry: AsyncDirEntryTrait;
+
+    /// The type of stream returned by [`read_dir()`][1].
+    ///
+    /// [1]: AsyncFsTrait::read_dir
+    type ReadDir: AsyncReadDirTrait<Self::DirEntry>;
+
     /// Returns the canonical form of a path.
     ///
     /// The returned path is in absolute form with all intermediate components
@@ -42,8 +75,8 @@ pub trait AsyncFsTrait {
     /// * `path` does not point to an existing file or directory.
     /// * A non-final component in `path` is not a directory.
     /// * Some other I/O error occurred.
-    async fn canonicalize<P>(path: P) -> io::Result<PathBuf>
-        where P: AsRef<Path>;
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send;
 
     /// Renames a file or directory to a new location.
     ///
@@ -58,9 +91,9 @@ pub trait AsyncFsTrait {
     /// * `src` and `dst` are on different filesystems.
     /// * The current process lacks permissions to do the rename operation.
     /// * Some other I/O error occurred.
-    async fn rename<P, Q>(src: P, dst: Q) -> io::Result<()>
-        where P: AsRef<Path>,
-              Q: AsRef<Path>;
+    async fn rename<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send;
 
     /// Changes the permissions of a file or directory.
     ///
@@ -72,8 +105,11 @@ pub trait AsyncFsTrait {
     /// * The current process lacks permissions to change attributes on the
     ///   file or directory.
     /// * Some other I/O error occurred.
-    async fn set_permissions<P>(path: P, perm: Permissions) -> io::Result<()>
-        where P: AsRef<Path>;
+    async fn set_permissions<P>(&self,
+                                path: P,
+                                perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send;
 
     /// Creates a hard link on the filesystem.
     ///
@@ -87,9 +123,9 @@ pub trait AsyncFsTrait {
     ///
     /// * `src` does not point to an existing file.
     /// * Some other I/O error occurred.
-    async fn hard_link<P, Q>(src: P, dst: Q) -> io::Result<()>
-        where P: AsRef<Path>,
-              Q: AsRef<Path>;
+    async fn hard_link<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send;
 
     /// Reads a symbolic link and returns the path it points to.
     ///
@@ -99,8 +135,8 @@ pub trait AsyncFsTrait {
     ///
     /// * `path` does not point to an existing link.
     /// * Some other I/O error occurred.
-    async fn read_link<P>(path: P) -> io::Result<PathBuf>
-        where P: AsRef<Path>;
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send;
 
     /// Reads metadata for a path without following symbolic links.
     ///
@@ -116,8 +152,8 @@ pub trait AsyncFsTrait {
     /// * Some other I/O error occurred.
     ///
     /// [1]: AsyncFsTrait::metadata
-    async fn symlink_metadata<P>(path: P) -> io::Result<Metadata>
-        where P: AsRef<Path>;
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send;
 
     /// Reads metadata for a path.
     ///
@@ -134,8 +170,8 @@ pub trait AsyncFsTrait {
     /// * Some other I/O error occurred.
     ///
     /// [1]: AsyncFsTrait::symlink_metadata
-    async fn metadata<P>(path: P) -> io::Result<Metadata>
-        where P: AsRef<Path>;
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send;
 
     /// Copies a file to a new location.
     ///
@@ -153,9 +189,9 @@ pub trait AsyncFsTrait {
     /// * `src` does not point to an existing file.
     /// * The current process lacks permissions to read `src` or write `dst`.
     /// * Some other I/O error occurred.
-    async fn copy<P, Q>(src: P, dst: Q) -> io::Result<u64>
-        where P: AsRef<Path>,
-              Q: AsRef<Path>;
+    async fn copy<P, Q>(&self, src: P, dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send;
 
     /// Removes a file.
     ///
@@ -166,14 +202,14 @@ pub trait AsyncFsTrait {
     /// * `path` does not point to an existing file.
     /// * The current process lacks permissions to remove the file.
     /// * Some other I/O error occurred.
-    async fn remove_file<P>(path: P) -> io::Result<()>
-        where P: AsRef<Path>;
+    async fn remove_file<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send;
 
     /// Returns a stream of entries in a directory.
     ///
-    /// The stream yields items of type
-    /// [`io::Result`]`<`[`AsyncDirEntryTrait`]`>`. Note that I/O errors can
-    /// occur while reading from the stream.
+    /// The stream is this filesystem's own [`ReadDir`][1] type, and yields
+    /// items of type [`io::Result`]`<`[`Self::DirEntry`][2]`>`. Note that I/O
+    /// errors can occur while reading from the stream.
     ///
     /// # Errors
     ///
@@ -183,10 +219,11 @@ pub trait AsyncFsTrait {
     /// * The current process lacks permissions to read the contents of the
     ///   directory.
     /// * Some other I/O error occurred.
-    async fn read_dir<P, T, U>(path: P) -> io::Result<T>
-        where P: AsRef<Path>,
-              T: AsyncReadDirTrait<U>,
-              U: AsyncDirEntryTrait;
+    ///
+    /// [1]: AsyncFsTrait::ReadDir
+    /// [2]: AsyncFsTrait::DirEntry
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send;
 
     /// Removes an empty directory.
     ///
@@ -203,8 +240,8 @@ pub trait AsyncFsTrait {
     /// * Some other I/O error occurred.
     ///
     /// [1]: super::AsyncFsTrait::remove_dir_all()
-    async fn remove_dir<P>(path: P) -> io::Result<()>
-        where P: AsRef<Path>;
+    async fn remove_dir<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send;
 
     /// Removes a directory and all of its contents.
     ///
@@ -215,8 +252,8 @@ pub trait AsyncFsTrait {
     /// * `path` is not an existing and empty directory.
     /// * The current process lacks permissions to remove the directory.
     /// * Some other I/O error occurred.
-    async fn remove_dir_all<P>(path: P) -> io::Result<()>
-        where P: AsRef<Path>;
+    async fn remove_dir_all<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send;
 }
 
 //  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
@@ -7,7 +7,6 @@
 //!
 //! [1]: super::AsyncFsTrait::read_dir
 
-pub use std::fs::{FileType, Metadata, Permissions};
 use std::io;
 
 use async_trait::async_trait;
@@ -15,6 +14,7 @@ use async_trait::async_trait;
 #[doc(no_inline)]
 use super::AsyncDirEntryTrait;
 use super::Stream;
+pub use crate::metadata::{FileType, Metadata, Permissions};
 
 /// A stream of entries in a directory.
 ///
@@ -23,10 +23,15 @@ use super::Stream;
 /// [`super::AsyncDirEntryTrait`] can then retrieve information like entry's
 /// path or metadata.
 ///
+/// Each filesystem names its own stream type through
+/// [`AsyncFsTrait::ReadDir`][2].  The stream is required to be [`Unpin`] so
+/// that generic code can poll it without having to pin it first.
+///
 /// [1]: super::AsyncFsTrait::read_dir
+/// [2]: super::AsyncFsTrait::ReadDir
 #[async_trait]
 pub trait AsyncReadDirTrait<T>:
-    std::fmt::Debug + Stream<Item = io::Result<T>>
+    std::fmt::Debug + Send + Unpin + Stream<Item = io::Result<T>>
     where T: AsyncDirEntryTrait
 {
 }
@@ -21,8 +21,14 @@ use async_trait::async_trait;
 /// symlink for the given location and connection.  If it isn't, then an
 /// appropriate error must be returned.  It is always valid to implement this
 /// trait but then always return an error.
+///
+/// This trait is normally implemented on the same type that implements
+/// [`AsyncFsTrait`][1], so that symlinks are created within that particular
+/// filesystem instance.
+///
+/// [1]: super::AsyncFsTrait
 #[async_trait]
-pub trait AsyncSymLinkTrait: std::fmt::Debug {
+pub trait AsyncSymLinkTrait: std::fmt::Debug + Send + Sync {
     /// Creates a symlink at `src` that points to `dst`.
     ///
     /// The symlink itself will be located at the path `src`.  The path at `dst`
@@ -34,9 +40,9 @@ pub trait AsyncSymLinkTrait: std::fmt::Debug {
     /// the file without deleting the symlink, the symlink will no longer be
     /// valid.  Your file system will need to be able to handle this case
     /// without crashing.
-    async fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(src: P,
-                                                     dst: Q)
-                                                     -> io::Result<()>;
+    async fn symlink<P, Q>(&self, src: P, dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send;
 }
 
 //  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
@@ -7,9 +7,10 @@
 //! capabilities that the original filesystem authors may not have considered
 //! when developing their own code.
 //!
-//! In particular, the return types of the traits are never `Self`; instead,
-//! the return types are generic types with trait bounds on them.  This allows
-//! the return type to be anything, which may be useful in certain situations.
+//! In particular, the types that the traits hand back (opened files, builders,
+//! and so on) are associated types chosen by the implementor, with trait bounds
+//! on them.  This allows the returned type to be anything the implementor
+//! needs, while still letting generic code name it.
 //!
 //! That said, all of the types that are returned are concrete; `&dyn Foo` is
 //! never used.  There are several reasons for this:
@@ -0,0 +1,67 @@
+//! What compares the entries of two trees.
+
+use std::{collections::BTreeMap, ffi::OsString, io, path::Path};
+
+use futures_io::AsyncRead;
+use futures_lite::{io::AsyncReadExt, StreamExt};
+
+use super::CHUNK;
+use crate::{AsyncDirEntryTrait, AsyncFsTrait, FileType, Permissions};
+
+/// Returns `true` if `a` and `b` grant the same access.
+pub(crate) fn same_permissions(a: Permissions, b: Permissions) -> bool {
+    match (a.mode(), b.mode()) {
+        (Some(a), Some(b)) => a & 0o7777 == b & 0o7777,
+        _ => a.readonly() == b.readonly()
+    }
+}
+
+/// Reads `a` and `b` side by side, and returns `true` if they hold the same
+/// bytes.
+pub(crate) async fn same_contents<A, B>(a: &mut A,
+                                        b: &mut B)
+                                        -> io::Result<bool>
+    where A: AsyncRead + Unpin,
+          B: AsyncRead + Unpin
+{
+    let (mut ours, mut theirs) = (vec![0; CHUNK], vec![0; CHUNK]);
+    loop {
+        let len = fill(a, &mut ours).await?;
+        if fill(b, &mut theirs).await? != len || ours[..len] != theirs[..len] {
+            return Ok(false);
+        }
+        if len == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Reads from `reader` until `buf` is full or the end is reached, and
+/// returns how much was read.
+async fn fill<R>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize>
+    where R: AsyncRead + Unpin
+{
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]).await? {
+            0 => break,
+            len => filled += len
+        }
+    }
+    Ok(filled)
+}
+
+/// Returns the names and types of the entries of the directory `path`.
+pub(crate) async fn list<F>(fs: &F,
+                            path: &Path)
+                            -> io::Result<BTreeMap<OsString, FileType>>
+    where F: AsyncFsTrait
+{
+    let mut entries = BTreeMap::new();
+    let mut dir = fs.read_dir(path).await?;
+    while let Some(entry) = dir.next().await {
+        let entry = entry?;
+        entries.insert(entry.file_name().await, entry.file_type().await?);
+    }
+    Ok(entries)
+}
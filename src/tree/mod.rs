@@ -0,0 +1,15 @@
+//! Shared machinery for the modules that copy, compare, or sync whole
+//! directory trees.
+//!
+//! Files are read a [`CHUNK`] at a time.  When the `diff` or `sync` feature
+//! is enabled, there is also what compares the entries of two trees: their
+//! listings, contents, and permissions.
+
+#[cfg(any(feature = "diff", feature = "sync"))]
+mod compare;
+
+#[cfg(any(feature = "diff", feature = "sync"))]
+pub(crate) use compare::{list, same_contents, same_permissions};
+
+/// How much of a file is read at a time.
+pub(crate) const CHUNK: usize = 64 * 1024;
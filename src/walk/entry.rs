@@ -0,0 +1,164 @@
+//! [`WalkEntry`] is an entry found while walking a directory tree.
+
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf}
+};
+
+use async_trait::async_trait;
+
+use crate::{AsyncDirEntryTrait, FileType, Metadata};
+
+/// Where a [`WalkEntry`] came from.
+#[derive(Debug, Clone)]
+pub(super) enum Source<E> {
+    /// The root of the walk, with its metadata.
+    Root(Metadata),
+
+    /// An entry of a directory that was read during the walk.
+    Entry(E)
+}
+
+/// An entry found by a [`Walk`][1].
+///
+/// `WalkEntry` wraps the directory entries of the filesystem being walked.
+/// Its path, name, and file type are recorded when its directory is read, so
+/// they can be had without awaiting anything, which is what
+/// [sorting][2] and [filtering][3] need.  It also implements
+/// [`AsyncDirEntryTrait`], so that it can be used wherever the filesystem's
+/// own entries can.
+///
+/// As with the wrapped entries, [`file_type()`][4] describes a symlink
+/// itself, even when the walk follows it; [`is_dir()`][5] tells whether the
+/// walk descends into the entry.
+///
+/// [1]: super::Walk
+/// [2]: super::WalkOptions::sort_by
+/// [3]: super::WalkOptions::filter_entry
+/// [4]: WalkEntry::file_type
+/// [5]: WalkEntry::is_dir
+#[derive(Debug, Clone)]
+pub struct WalkEntry<E> {
+    pub(super) path: PathBuf,
+    pub(super) name: OsString,
+    pub(super) depth: usize,
+    pub(super) file_type: FileType,
+
+    /// Whether the entry is a directory, or a symlink to one that is
+    /// followed.
+    pub(super) dir: bool,
+    pub(super) source: Source<E>
+}
+
+impl<E> WalkEntry<E> {
+    /// Returns the path of this entry: the root of the walk joined with the
+    /// names of the directories below it.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the name of this entry.  For the root of the walk, this is the
+    /// last component of its path, or the whole path if it has none.
+    pub fn file_name(&self) -> &OsStr {
+        &self.name
+    }
+
+    /// Returns the type of this entry, without following symlinks.
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns how far below the root of the walk this entry is.  The root
+    /// itself is at depth 0, and the entries directly inside it at depth 1.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns `true` if this entry is a directory, or a symlink to one that
+    /// the walk follows.
+    pub fn is_dir(&self) -> bool {
+        self.dir
+    }
+
+    /// Returns the directory entry that this entry wraps, or `None` for the
+    /// root of the walk, which doesn't have one.
+    pub fn dir_entry(&self) -> Option<&E> {
+        match &self.source {
+            Source::Root(_) => None,
+            Source::Entry(entry) => Some(entry)
+        }
+    }
+}
+
+#[async_trait]
+impl<E> AsyncDirEntryTrait for WalkEntry<E> where E: AsyncDirEntryTrait
+{
+    async fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        match &self.source {
+            Source::Root(metadata) => Ok(metadata.clone()),
+            Source::Entry(entry) => entry.metadata().await
+        }
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.file_type)
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::{future::block_on, StreamExt};
+
+    use super::*;
+    use crate::{
+        mem_fs::MemFs, walk::walk_dir, AsyncDirBuilderTrait, AsyncFsTrait,
+        AsyncSymLinkTrait
+    };
+
+    #[test]
+    fn entries_describe_themselves() {
+        block_on(async {
+            let fs = MemFs::new();
+            fs.dir_builder().create("/dir").await.unwrap();
+            fs.symlink("/dir/link", "/dir").await.unwrap();
+            let entries = walk_dir(&fs, "/dir").map(Result::unwrap)
+                                               .collect::<Vec<_>>()
+                                               .await;
+
+            let root = &entries[0];
+            assert_eq!((root.depth(), root.is_dir()), (0, true));
+            assert!(root.dir_entry().is_none());
+            assert_eq!(root.file_name(), "dir");
+            assert!(root.metadata().await.unwrap().is_dir());
+
+            let link = &entries[1];
+            assert_eq!((link.depth(), link.is_dir()), (1, false));
+            assert!(link.dir_entry().is_some());
+            assert_eq!(link.path(), Path::new("/dir/link"));
+            assert_eq!(link.file_type(), FileType::Symlink);
+            assert_eq!(AsyncDirEntryTrait::path(link).await,
+                       PathBuf::from("/dir/link"));
+            assert_eq!(AsyncDirEntryTrait::file_type(link).await.unwrap(),
+                       FileType::Symlink);
+            assert!(link.metadata().await.unwrap().is_dir());
+        });
+    }
+}
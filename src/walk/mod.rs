@@ -0,0 +1,49 @@
+//! Recursive walks over the directory trees of any filesystem.
+//!
+//! [`walk_dir()`] and [`WalkOptions::walk()`] return a [`Walk`], which
+//! streams a [`WalkEntry`] for the root of the walk and for everything below
+//! it.  Walks are built only on [`read_dir()`][1] and the methods of
+//! [`AsyncDirEntryTrait`][2], so every backend in this crate, and every
+//! layer around them, can be walked the same way:
+//!
+//! ```
+//! # #[cfg(feature = "mem-fs")]
+//! # {
+//! use async_fs_traits::{mem_fs::MemFs, walk::walk_dir};
+//! use futures_lite::{future::block_on, StreamExt};
+//!
+//! let fs = MemFs::new();
+//! block_on(async {
+//!     let mut walk = walk_dir(&fs, "/");
+//!     while let Some(entry) = walk.next().await {
+//!         let entry = entry?;
+//!         println!("{:indent$}{}",
+//!                  "",
+//!                  entry.file_name().to_string_lossy(),
+//!                  indent = entry.depth() * 2);
+//!     }
+//!     std::io::Result::Ok(())
+//! });
+//! # }
+//! ```
+//!
+//! [`WalkOptions`] limits how deep the walk goes, follows symlinks while
+//! guarding against loops, sorts the entries of each directory, yields
+//! directories after their contents, prunes subtrees, and reads several
+//! directories at once.
+//!
+//! This module is only available when the `walk` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait::read_dir
+//! [2]: crate::AsyncDirEntryTrait
+
+mod entry;
+mod options;
+mod walker;
+
+#[doc(inline)]
+pub use entry::WalkEntry;
+#[doc(inline)]
+pub use options::WalkOptions;
+#[doc(inline)]
+pub use walker::{walk_dir, Walk};
@@ -0,0 +1,202 @@
+//! [`WalkOptions`] configures how a directory tree is walked.
+
+use std::{cmp::Ordering, fmt, path::Path, sync::Arc};
+
+use super::{walker::Walker, Walk, WalkEntry};
+use crate::AsyncFsTrait;
+
+type Compare<E> =
+    Arc<dyn Fn(&WalkEntry<E>, &WalkEntry<E>) -> Ordering + Send + Sync>;
+type Filter<E> = Arc<dyn Fn(&WalkEntry<E>) -> bool + Send + Sync>;
+
+/// Options for walking a directory tree.
+///
+/// The options start out walking the whole tree, in pre-order, without
+/// following symlinks, and reading one directory at a time.  Entries come in
+/// the order that [`read_dir()`][1] yields them, unless a sort order is set.
+///
+/// ```
+/// # #[cfg(feature = "mem-fs")]
+/// # {
+/// use async_fs_traits::{mem_fs::MemFs, walk::WalkOptions};
+/// use futures_lite::{future::block_on, StreamExt};
+///
+/// let fs = MemFs::new();
+/// let mut walk = WalkOptions::new().max_depth(2)
+///                                  .sort_by_file_name()
+///                                  .filter_entry(|e| e.file_name() != ".git")
+///                                  .walk(&fs, "/");
+/// block_on(async {
+///     while let Some(entry) = walk.next().await {
+///         println!("{}", entry?.path().display());
+///     }
+///     std::io::Result::Ok(())
+/// });
+/// # }
+/// ```
+///
+/// [1]: AsyncFsTrait::read_dir
+pub struct WalkOptions<E> {
+    pub(super) min_depth: usize,
+    pub(super) max_depth: usize,
+    pub(super) follow_links: bool,
+    pub(super) contents_first: bool,
+    pub(super) max_concurrency: usize,
+    pub(super) compare: Option<Compare<E>>,
+    pub(super) filter: Option<Filter<E>>
+}
+
+impl<E> WalkOptions<E> {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        WalkOptions { min_depth: 0,
+                      max_depth: usize::MAX,
+                      follow_links: false,
+                      contents_first: false,
+                      max_concurrency: 1,
+                      compare: None,
+                      filter: None }
+    }
+
+    /// Only yields entries at least `depth` levels below the root.  The root
+    /// is at depth 0.  Shallower directories are still walked.
+    pub fn min_depth(&mut self, depth: usize) -> &mut Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Doesn't descend more than `depth` levels below the root, so a depth of
+    /// 0 only yields the root itself.
+    pub fn max_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Descends into symlinks that point to directories.
+    ///
+    /// A symlink that leads back to a directory that is already being walked
+    /// yields an error of kind [`ErrorKind::Other`][1] instead of being walked
+    /// again.  Directories are told apart by their inode and device numbers,
+    /// or by their canonical paths on filesystems that don't report inodes.
+    ///
+    /// [1]: std::io::ErrorKind::Other
+    pub fn follow_links(&mut self, follow_links: bool) -> &mut Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Yields the contents of each directory before the directory itself,
+    /// which is what removing a tree needs.
+    pub fn contents_first(&mut self, contents_first: bool) -> &mut Self {
+        self.contents_first = contents_first;
+        self
+    }
+
+    /// Reads up to `max_concurrency` directories at once.
+    ///
+    /// Directories that the walk will reach soon are read ahead, while the
+    /// entries before them are yielded.  The entries still come in the same
+    /// order.  A value of 0 is treated as 1.
+    pub fn max_concurrency(&mut self, max_concurrency: usize) -> &mut Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Sorts the entries of each directory with `compare`.
+    pub fn sort_by<F>(&mut self, compare: F) -> &mut Self
+        where F: Fn(&WalkEntry<E>, &WalkEntry<E>) -> Ordering
+                  + Send
+                  + Sync
+                  + 'static
+    {
+        self.compare = Some(Arc::new(compare));
+        self
+    }
+
+    /// Sorts the entries of each directory by name.
+    pub fn sort_by_file_name(&mut self) -> &mut Self {
+        self.sort_by(|a, b| a.name.cmp(&b.name))
+    }
+
+    /// Skips every entry for which `filter` returns `false`, along with
+    /// everything below it, so that whole subtrees are pruned without being
+    /// read.
+    ///
+    /// `filter` is only called for entries at or below the
+    /// [minimum depth][1].
+    ///
+    /// [1]: WalkOptions::min_depth
+    pub fn filter_entry<F>(&mut self, filter: F) -> &mut Self
+        where F: Fn(&WalkEntry<E>) -> bool + Send + Sync + 'static
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Walks the tree below `root` in `fs`.
+    pub fn walk<'a, F, P>(&self, fs: &'a F, root: P) -> Walk<'a, F>
+        where F: AsyncFsTrait<DirEntry = E>,
+              P: AsRef<Path>
+    {
+        Walker::new(fs, self.clone(), root.as_ref().to_owned()).into_stream()
+    }
+
+    /// Returns `true` if `entry` passes the filter.
+    pub(super) fn keep(&self, entry: &WalkEntry<E>) -> bool {
+        entry.depth < self.min_depth
+        || self.filter.as_ref().map_or(true, |filter| filter(entry))
+    }
+}
+
+impl<E> Clone for WalkOptions<E> {
+    fn clone(&self) -> Self {
+        WalkOptions { min_depth: self.min_depth,
+                      max_depth: self.max_depth,
+                      follow_links: self.follow_links,
+                      contents_first: self.contents_first,
+                      max_concurrency: self.max_concurrency,
+                      compare: self.compare.clone(),
+                      filter: self.filter.clone() }
+    }
+}
+
+impl<E> Default for WalkOptions<E> {
+    fn default() -> Self {
+        WalkOptions::new()
+    }
+}
+
+impl<E> fmt::Debug for WalkOptions<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkOptions")
+         .field("min_depth", &self.min_depth)
+         .field("max_depth", &self.max_depth)
+         .field("follow_links", &self.follow_links)
+         .field("contents_first", &self.contents_first)
+         .field("max_concurrency", &self.max_concurrency)
+         .field("sorted", &self.compare.is_some())
+         .field("filtered", &self.filter.is_some())
+         .finish()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_concurrency_reads_one_directory() {
+        let mut options = WalkOptions::<()>::new();
+        assert_eq!(options.max_concurrency(0).max_concurrency, 1);
+        assert_eq!(options.max_concurrency(4).max_concurrency, 4);
+    }
+}
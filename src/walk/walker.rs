@@ -0,0 +1,546 @@
+//! [`Walker`] walks a directory tree, and [`Walk`] streams what it finds.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    vec
+};
+
+use futures_core::Stream;
+use futures_lite::{future::poll_fn, stream, StreamExt};
+
+use super::{entry::Source, WalkEntry, WalkOptions};
+use crate::{AsyncDirEntryTrait, AsyncFsTrait, FileType};
+
+type Item<F> = io::Result<WalkEntry<<F as AsyncFsTrait>::DirEntry>>;
+
+type ListFuture<'a, E> =
+    Pin<Box<dyn Future<Output = io::Result<Listing<E>>> + Send + 'a>>;
+
+/// Walks the tree below `root` in `fs` with the default [`WalkOptions`].
+///
+/// The root comes first, followed by everything below it, with each
+/// directory before its contents.  Symlinks are yielded, but not followed,
+/// except when the root itself is one.
+pub fn walk_dir<F, P>(fs: &F, root: P) -> Walk<'_, F>
+    where F: AsyncFsTrait,
+          P: AsRef<Path>
+{
+    WalkOptions::new().walk(fs, root)
+}
+
+fn walks_back(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::Other,
+                   format!("{} leads back to a directory that is already \
+                            being walked",
+                           path.display()))
+}
+
+/// What tells directories apart, to find symlinks that lead back to one
+/// that is already being walked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Id {
+    Inode(Option<u64>, u64),
+    Path(PathBuf)
+}
+
+impl Id {
+    async fn of<F>(fs: &F, path: &Path) -> io::Result<Self>
+        where F: AsyncFsTrait
+    {
+        let metadata = fs.metadata(path).await?;
+        match metadata.ino() {
+            Some(ino) => Ok(Id::Inode(metadata.dev(), ino)),
+            None => Ok(Id::Path(fs.canonicalize(path).await?))
+        }
+    }
+}
+
+/// The contents of a directory.
+struct Listing<E> {
+    /// Only known when symlinks are followed.
+    id: Option<Id>,
+    entries: Vec<WalkEntry<E>>
+}
+
+/// A directory that is being walked.
+struct Frame<E> {
+    path: PathBuf,
+    depth: usize,
+    id: Option<Id>,
+
+    /// The entries that are still to be walked, once the directory has been
+    /// read.
+    entries: Option<vec::IntoIter<WalkEntry<E>>>,
+
+    /// The directory itself, when it comes after its contents.
+    dir: Option<WalkEntry<E>>
+}
+
+pub(super) struct Walker<'a, F>
+    where F: AsyncFsTrait
+{
+    fs: &'a F,
+    options: WalkOptions<F::DirEntry>,
+
+    /// The root, until the walk starts.
+    root: Option<PathBuf>,
+
+    /// The directories from the root down to the one being walked.
+    stack: Vec<Frame<F::DirEntry>>,
+
+    /// Directories that are being read.
+    reading: Vec<(PathBuf, ListFuture<'a, F::DirEntry>)>,
+
+    /// Directories that were read ahead, but haven't been reached yet.
+    read: HashMap<PathBuf, io::Result<Listing<F::DirEntry>>>,
+
+    /// Directories that could be read ahead, with their depths, in the order
+    /// that the walk reaches them.
+    ahead: VecDeque<(PathBuf, usize)>
+}
+
+impl<'a, F> Walker<'a, F> where F: AsyncFsTrait
+{
+    pub(super) fn new(fs: &'a F,
+                      options: WalkOptions<F::DirEntry>,
+                      root: PathBuf)
+                      -> Self {
+        Walker { fs,
+                 options,
+                 root: Some(root),
+                 stack: Vec::new(),
+                 reading: Vec::new(),
+                 read: HashMap::new(),
+                 ahead: VecDeque::new() }
+    }
+
+    pub(super) fn into_stream(self) -> Walk<'a, F> {
+        let stream = stream::unfold(self, |mut walker| async move {
+            let item = walker.next().await?;
+            Some((item, walker))
+        });
+        Walk { stream: Box::pin(stream) }
+    }
+
+    async fn next(&mut self) -> Option<Item<F>> {
+        if let Some(root) = self.root.take() {
+            let root = match Self::root_entry(self.fs, root).await {
+                Ok(root) => root,
+                Err(err) => return Some(Err(err))
+            };
+            if !self.options.keep(&root) {
+                return None;
+            }
+            if let Some(root) = self.enter(root) {
+                return Some(Ok(root));
+            }
+        }
+        // Directories that are being read ahead only make progress when they
+        // are polled.
+        poll_fn(|cx| {
+            self.poll_reading(cx);
+            Poll::Ready(())
+        }).await;
+        loop {
+            let frame = self.stack.last_mut()?;
+            let entries = match &mut frame.entries {
+                Some(entries) => entries,
+                None => {
+                    let (path, depth) = (frame.path.clone(), frame.depth);
+                    let listing =
+                        poll_fn(|cx| self.poll_listing(cx, &path, depth)).await;
+                    match self.open(listing) {
+                        Ok(()) => continue,
+                        Err(err) => return Some(Err(err))
+                    }
+                }
+            };
+            match entries.next() {
+                Some(entry) => {
+                    if let Some(entry) = self.enter(entry) {
+                        return Some(Ok(entry));
+                    }
+                }
+                None => {
+                    if let Some(dir) = self.stack.pop().and_then(|f| f.dir) {
+                        return Some(Ok(dir));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Describes the root, following it if it is a symlink.
+    async fn root_entry(fs: &F,
+                        root: PathBuf)
+                        -> io::Result<WalkEntry<F::DirEntry>> {
+        let file_type = fs.symlink_metadata(&root).await?.file_type();
+        let metadata = fs.metadata(&root).await?;
+        let name = match root.file_name() {
+            Some(name) => name.to_owned(),
+            None => root.clone().into_os_string()
+        };
+        Ok(WalkEntry { path: root,
+                       name,
+                       depth: 0,
+                       file_type,
+                       dir: metadata.is_dir(),
+                       source: Source::Root(metadata) })
+    }
+
+    /// Starts walking below `entry` if it is a directory, and returns it if
+    /// it should be yielded now.
+    fn enter(&mut self,
+             entry: WalkEntry<F::DirEntry>)
+             -> Option<WalkEntry<F::DirEntry>> {
+        let yielded = entry.depth >= self.options.min_depth;
+        if !entry.dir || entry.depth >= self.options.max_depth {
+            return yielded.then(|| entry);
+        }
+        let (path, depth) = (entry.path.clone(), entry.depth);
+        let (now, later) = match (yielded, self.options.contents_first) {
+            (false, _) => (None, None),
+            (true, false) => (Some(entry), None),
+            (true, true) => (None, Some(entry))
+        };
+        self.stack.push(Frame { path,
+                                depth,
+                                id: None,
+                                entries: None,
+                                dir: later });
+        now
+    }
+
+    /// Starts walking the entries of the directory at the top of the stack,
+    /// which was just read.
+    fn open(&mut self,
+            listing: io::Result<Listing<F::DirEntry>>)
+            -> io::Result<()> {
+        let frame = self.stack.last_mut().expect("a directory was read");
+        // If anything goes wrong, the directory is walked as if it were empty.
+        frame.entries = Some(Vec::new().into_iter());
+        let Listing { id, mut entries } = listing?;
+        let (frame, ancestors) =
+            self.stack.split_last_mut().expect("a directory was read");
+        if id.is_some() && ancestors.iter().any(|ancestor| ancestor.id == id) {
+            return Err(walks_back(&frame.path));
+        }
+        entries.retain(|entry| self.options.keep(entry));
+        if let Some(compare) = &self.options.compare {
+            entries.sort_by(|a, b| compare(a, b));
+        }
+        if self.options.max_concurrency > 1 {
+            let below =
+                entries.iter()
+                       .filter(|entry| {
+                           entry.dir && entry.depth < self.options.max_depth
+                       });
+            for entry in below.rev() {
+                self.ahead.push_front((entry.path.clone(), entry.depth));
+            }
+        }
+        frame.id = id;
+        frame.entries = Some(entries.into_iter());
+        Ok(())
+    }
+
+    /// Reads the directory at `path`.
+    fn list(&self, path: PathBuf, depth: usize) -> ListFuture<'a, F::DirEntry> {
+        let fs = self.fs;
+        let follow_links = self.options.follow_links;
+        Box::pin(async move {
+            let id = match follow_links {
+                true => Some(Id::of(fs, &path).await?),
+                false => None
+            };
+            let mut dir = fs.read_dir(&path).await?;
+            let mut entries = Vec::new();
+            while let Some(entry) = dir.next().await {
+                let entry = entry?;
+                let file_type = entry.file_type().await?;
+                let is_dir = match file_type {
+                    FileType::Dir => true,
+                    // A dangling symlink is yielded, but not walked.
+                    FileType::Symlink if follow_links => {
+                        entry.metadata()
+                             .await
+                             .map_or(false, |metadata| metadata.is_dir())
+                    }
+                    _ => false
+                };
+                entries.push(WalkEntry { path: entry.path().await,
+                                         name: entry.file_name().await,
+                                         depth: depth + 1,
+                                         file_type,
+                                         dir: is_dir,
+                                         source: Source::Entry(entry) });
+            }
+            Ok(Listing { id, entries })
+        })
+    }
+
+    /// Polls every directory that is being read, and starts reading ahead as
+    /// far as the concurrency limit allows.  Returns `true` if any of them
+    /// finished.
+    fn poll_reading(&mut self, cx: &mut Context<'_>) -> bool {
+        let mut finished = false;
+        loop {
+            while self.reading.len() + self.read.len()
+                  < self.options.max_concurrency
+            {
+                match self.ahead.pop_front() {
+                    Some((path, depth)) => {
+                        let future = self.list(path.clone(), depth);
+                        self.reading.push((path, future));
+                    }
+                    None => break
+                }
+            }
+            let mut progress = false;
+            let mut i = 0;
+            while i < self.reading.len() {
+                match Future::poll(self.reading[i].1.as_mut(), cx) {
+                    Poll::Ready(result) => {
+                        let (path, _) = self.reading.swap_remove(i);
+                        self.read.insert(path, result);
+                        progress = true;
+                    }
+                    Poll::Pending => i += 1
+                }
+            }
+            if !progress {
+                return finished;
+            }
+            finished = true;
+        }
+    }
+
+    /// Waits for the directory at `path` to be read, reading ahead in the
+    /// meantime.
+    fn poll_listing(&mut self,
+                    cx: &mut Context<'_>,
+                    path: &Path,
+                    depth: usize)
+                    -> Poll<io::Result<Listing<F::DirEntry>>> {
+        let started =
+            self.read.contains_key(path)
+            || self.reading.iter().any(|(reading, _)| reading == path);
+        if !started {
+            self.ahead.retain(|(ahead, _)| ahead != path);
+            let future = self.list(path.to_owned(), depth);
+            self.reading.push((path.to_owned(), future));
+        }
+        loop {
+            let finished = self.poll_reading(cx);
+            if let Some(listing) = self.read.remove(path) {
+                return Poll::Ready(listing);
+            }
+            if !finished {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// A stream of the entries of a directory tree, returned by [`walk_dir()`]
+/// and [`WalkOptions::walk()`].
+///
+/// An error reading a directory is yielded in place of its contents, and the
+/// walk carries on with the next entry.
+pub struct Walk<'a, F>
+    where F: AsyncFsTrait
+{
+    stream: Pin<Box<dyn Stream<Item = Item<F>> + Send + 'a>>
+}
+
+impl<F> Stream for Walk<'_, F> where F: AsyncFsTrait
+{
+    type Item = Item<F>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        self.get_mut().stream.as_mut().poll_next(cx)
+    }
+}
+
+impl<F> fmt::Debug for Walk<'_, F> where F: AsyncFsTrait
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Walk").finish_non_exhaustive()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{
+        mem_fs::MemFs, AsyncDirBuilderTrait, AsyncFileBuilderTrait,
+        AsyncSymLinkTrait
+    };
+
+    async fn tree() -> MemFs {
+        let fs = MemFs::new();
+        for dir in ["/t/b/y", "/t/a", "/t/c"] {
+            fs.dir_builder().recursive(true).create(dir).await.unwrap();
+        }
+        for file in ["/t/b/x", "/t/b/y/z", "/t/a/w", "/t/file"] {
+            fs.open_options()
+              .write(true)
+              .create_new(true)
+              .open(file)
+              .await
+              .unwrap();
+        }
+        fs
+    }
+
+    async fn paths(walk: Walk<'_, MemFs>) -> Vec<String> {
+        walk.map(|entry| entry.unwrap().path().to_string_lossy().into_owned())
+            .collect()
+            .await
+    }
+
+    fn sorted() -> WalkOptions<<MemFs as AsyncFsTrait>::DirEntry> {
+        let mut options = WalkOptions::new();
+        options.sort_by_file_name();
+        options
+    }
+
+    #[test]
+    fn trees_are_walked_in_order() {
+        block_on(async {
+            let fs = tree().await;
+            assert_eq!(paths(sorted().walk(&fs, "/t")).await,
+                       ["/t", "/t/a", "/t/a/w", "/t/b", "/t/b/x", "/t/b/y",
+                        "/t/b/y/z", "/t/c", "/t/file"]);
+            let walk = sorted().contents_first(true).walk(&fs, "/t");
+            assert_eq!(paths(walk).await,
+                       ["/t/a/w", "/t/a", "/t/b/x", "/t/b/y/z", "/t/b/y",
+                        "/t/b", "/t/c", "/t/file", "/t"]);
+
+            let mut unsorted = paths(walk_dir(&fs, "/t")).await;
+            unsorted.sort();
+            let mut expected = paths(sorted().walk(&fs, "/t")).await;
+            expected.sort();
+            assert_eq!(unsorted, expected);
+        });
+    }
+
+    #[test]
+    fn depths_and_filters_limit_the_walk() {
+        block_on(async {
+            let fs = tree().await;
+            let walk = sorted().min_depth(1).max_depth(1).walk(&fs, "/t");
+            assert_eq!(paths(walk).await, ["/t/a", "/t/b", "/t/c", "/t/file"]);
+            let walk = sorted().min_depth(2).walk(&fs, "/t");
+            assert_eq!(paths(walk).await,
+                       ["/t/a/w", "/t/b/x", "/t/b/y", "/t/b/y/z"]);
+
+            let walk = sorted().filter_entry(|entry| entry.file_name() != "b")
+                               .walk(&fs, "/t");
+            assert_eq!(paths(walk).await,
+                       ["/t", "/t/a", "/t/a/w", "/t/c", "/t/file"]);
+            let walk = sorted().filter_entry(|_| false).walk(&fs, "/t");
+            assert!(paths(walk).await.is_empty());
+        });
+    }
+
+    #[test]
+    fn symlinks_are_followed_without_looping() {
+        block_on(async {
+            let fs = tree().await;
+            fs.symlink("/t/c/up", "/t").await.unwrap();
+            fs.symlink("/t/c/to_a", "../a").await.unwrap();
+            fs.symlink("/t/c/dangling", "/nowhere").await.unwrap();
+
+            let walk = sorted().min_depth(2).max_depth(2).walk(&fs, "/t");
+            assert_eq!(paths(walk).await,
+                       ["/t/a/w",
+                        "/t/b/x",
+                        "/t/b/y",
+                        "/t/c/dangling",
+                        "/t/c/to_a",
+                        "/t/c/up"]);
+
+            let mut walk = sorted().follow_links(true).walk(&fs, "/t/c");
+            let mut found = Vec::new();
+            let mut errors = 0;
+            while let Some(entry) = walk.next().await {
+                match entry {
+                    Ok(entry) => found.push(entry.path().to_owned()),
+                    Err(_) => errors += 1
+                }
+            }
+            assert_eq!(found,
+                       ["/t/c",
+                        "/t/c/dangling",
+                        "/t/c/to_a",
+                        "/t/c/to_a/w",
+                        "/t/c/up",
+                        "/t/c/up/a",
+                        "/t/c/up/a/w",
+                        "/t/c/up/b",
+                        "/t/c/up/b/x",
+                        "/t/c/up/b/y",
+                        "/t/c/up/b/y/z",
+                        "/t/c/up/c",
+                        "/t/c/up/file"].map(PathBuf::from));
+            assert_eq!(errors, 1);
+        });
+    }
+
+    #[test]
+    fn directories_are_read_ahead_in_order() {
+        block_on(async {
+            let fs = tree().await;
+            for n in 0..20 {
+                let dir = format!("/t/many/{:02}/sub", n);
+                fs.dir_builder().recursive(true).create(dir).await.unwrap();
+            }
+            let expected = paths(sorted().walk(&fs, "/t")).await;
+            for concurrency in [2, 4, 64] {
+                let walk =
+                    sorted().max_concurrency(concurrency).walk(&fs, "/t");
+                assert_eq!(paths(walk).await, expected);
+            }
+            let mut options = sorted();
+            options.contents_first(true).max_depth(2);
+            let expected = paths(options.walk(&fs, "/t")).await;
+            let walk = options.max_concurrency(4).walk(&fs, "/t");
+            assert_eq!(paths(walk).await, expected);
+        });
+    }
+
+    #[test]
+    fn errors_are_yielded_in_place() {
+        block_on(async {
+            let fs = tree().await;
+            let mut walk = walk_dir(&fs, "/missing");
+            let err = walk.next().await.unwrap().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(walk.next().await.is_none());
+
+            let mut walk = walk_dir(&fs, "/t/file");
+            assert_eq!(walk.next().await.unwrap().unwrap().depth(), 0);
+            assert!(walk.next().await.is_none());
+        });
+    }
+}
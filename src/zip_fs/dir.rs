@@ -0,0 +1,187 @@
+//! [`ZipReadDir`] lists the directories of a [`ZipFs`], and
+//! [`ZipDirBuilder`] adds new ones to an archive that is being written.
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    vec
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+
+use super::ZipFs;
+use crate::{
+    AsyncDirBuilderTrait, AsyncDirEntryTrait, AsyncReadDirTrait, FileType,
+    Metadata
+};
+
+/// A builder for directories in a [`ZipFs`].
+///
+/// Directories can only be added to an archive that is being written, and
+/// [`create()`][1] fails with [`ErrorKind::PermissionDenied`][2] in one that
+/// was opened for reading.  Like files, directories can't be added while a
+/// file is being written.
+///
+/// [1]: AsyncDirBuilderTrait::create
+/// [2]: io::ErrorKind::PermissionDenied
+#[derive(Debug, Clone)]
+pub struct ZipDirBuilder {
+    fs: ZipFs,
+    recursive: bool
+}
+
+impl ZipDirBuilder {
+    pub(super) fn new(fs: ZipFs) -> Self {
+        ZipDirBuilder { fs,
+                        recursive: false }
+    }
+}
+
+#[async_trait]
+impl AsyncDirBuilderTrait for ZipDirBuilder {
+    fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    async fn create<P>(&self, path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        self.fs.create_dir(path.as_ref(), self.recursive).await
+    }
+}
+
+/// A stream of the entries in a directory of a [`ZipFs`], in order of their
+/// names.
+#[derive(Debug)]
+pub struct ZipReadDir {
+    entries: vec::IntoIter<ZipDirEntry>
+}
+
+impl ZipReadDir {
+    pub(super) fn new(entries: Vec<ZipDirEntry>) -> Self {
+        ZipReadDir { entries: entries.into_iter() }
+    }
+}
+
+impl Stream for ZipReadDir {
+    type Item = io::Result<ZipDirEntry>;
+
+    fn poll_next(self: Pin<&mut Self>,
+                 _cx: &mut Context<'_>)
+                 -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().entries.next().map(Ok))
+    }
+}
+
+impl AsyncReadDirTrait<ZipDirEntry> for ZipReadDir {}
+
+/// An entry in a directory of a [`ZipFs`].
+///
+/// Entries never change once they are in an archive, so everything about the
+/// entry is recorded when the directory is read.  Like
+/// [`std::fs::DirEntry::metadata()`], [`metadata()`][1] describes a symlink
+/// itself rather than its target.
+///
+/// [1]: AsyncDirEntryTrait::metadata
+#[derive(Debug, Clone)]
+pub struct ZipDirEntry {
+    path: PathBuf,
+    name: OsString,
+    metadata: Metadata
+}
+
+impl ZipDirEntry {
+    pub(super) fn new(path: PathBuf,
+                      name: OsString,
+                      metadata: Metadata)
+                      -> Self {
+        ZipDirEntry { path,
+                      name,
+                      metadata }
+    }
+}
+
+#[async_trait]
+impl AsyncDirEntryTrait for ZipDirEntry {
+    async fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+
+    async fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.metadata.file_type())
+    }
+
+    async fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, AsyncWriteExt, StreamExt};
+
+    use super::*;
+    use crate::{
+        zip_fs::fs::tests::Buffer, AsyncFileBuilderTrait, AsyncFsTrait
+    };
+
+    async fn list(fs: &ZipFs, path: &str) -> Vec<(PathBuf, FileType)> {
+        let mut entries = Vec::new();
+        let mut stream = fs.read_dir(path).await.unwrap();
+        while let Some(entry) = stream.next().await {
+            let entry = entry.unwrap();
+            entries.push((entry.path().await,
+                          entry.file_type().await.unwrap()));
+        }
+        entries
+    }
+
+    #[test]
+    fn entries_are_listed_in_order() {
+        block_on(async {
+            let buffer = Buffer::default();
+            let fs = ZipFs::create(buffer.clone()).await.unwrap();
+            fs.dir_builder()
+              .recursive(true)
+              .create("/top/c")
+              .await
+              .unwrap();
+            for name in ["/top/b", "/top/a"] {
+                let mut file = fs.open_options()
+                                 .write(true)
+                                 .create_new(true)
+                                 .open(name)
+                                 .await
+                                 .unwrap();
+                file.close().await.unwrap();
+            }
+            let expected = [(PathBuf::from("top/a"), FileType::File),
+                            (PathBuf::from("top/b"), FileType::File),
+                            (PathBuf::from("top/c"), FileType::Dir)];
+            assert_eq!(list(&fs, "top").await, expected);
+
+            let fs = ZipFs::from_bytes(buffer.bytes()).await.unwrap();
+            assert_eq!(list(&fs, "top").await, expected);
+            let err = fs.read_dir("/top/b").await.unwrap_err();
+            assert_eq!(err.to_string(), "not a directory");
+        });
+    }
+}
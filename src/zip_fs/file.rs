@@ -0,0 +1,683 @@
+//! [`ZipOpenOptions`] opens [`ZipFile`]s in a [`ZipFs`].
+
+use std::{
+    fmt,
+    io::{self, Read, SeekFrom, Write},
+    mem,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::SystemTime
+};
+
+use async_lock::{Mutex as AsyncMutex, MutexGuardArc};
+use async_trait::async_trait;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression, Crc};
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::{future::poll_fn, AsyncSeekExt, AsyncWriteExt};
+
+use super::{
+    format::{
+        local_header_len, Record, DEFLATED, FLAG_ENCRYPTED, LOCAL_HEADER_LEN,
+        STORED, S_IFREG
+    },
+    fs::{busy, entry_name, read_only, written, Output},
+    ZipFs
+};
+use crate::{
+    archive::{invalid_data, is_a_directory, not_found, Kind, Region, Source},
+    layer::OpenFlags,
+    AsyncFileBuilderTrait, AsyncFileTrait, FileType, Metadata, Permissions
+};
+
+/// The mode of the files that are written to an archive.
+const FILE_MODE: u32 = 0o644;
+
+fn not_opened_for(access: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   format!("file not opened for {}", access))
+}
+
+fn finished() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "zip entry is already finished")
+}
+
+/// Returns the data of the entry described by `record`, decompressing it
+/// into memory unless it is stored.
+pub(super) async fn entry_data(source: &Source,
+                               record: &Record)
+                               -> io::Result<Region> {
+    if record.flags & FLAG_ENCRYPTED != 0 {
+        return Err(io::Error::new(io::ErrorKind::Unsupported,
+                                  "encrypted zip entries aren't supported"));
+    }
+    let header = source.read_at(record.offset, LOCAL_HEADER_LEN as usize)
+                       .await?;
+    let start = record.offset + local_header_len(&header)?;
+    match record.method {
+        STORED if record.compressed == record.len => {
+            Ok(Region::new(source.clone(), start, record.len))
+        }
+        STORED => Err(invalid_data("stored zip entry has two lengths")),
+        DEFLATED => {
+            let compressed = usize::try_from(record.compressed).map_err(|_| {
+                                 invalid_data("zip entry is too large")
+                             })?;
+            let compressed = source.read_at(start, compressed).await?;
+            let mut data = Vec::new();
+            DeflateDecoder::new(compressed.as_slice()).take(record.len + 1)
+                                                      .read_to_end(&mut data)?;
+            let mut crc = Crc::new();
+            crc.update(&data);
+            if data.len() as u64 != record.len || crc.sum() != record.crc {
+                return Err(invalid_data("zip entry doesn't match its \
+                                         checksum"));
+            }
+            Ok(Region::new(Source::Memory(Arc::new(data)), 0, record.len))
+        }
+        _ => Err(io::Error::new(io::ErrorKind::Unsupported,
+                                "unsupported zip compression method"))
+    }
+}
+
+/// Options for opening a [`ZipFile`].
+///
+/// In an archive that was opened for reading, files can only be opened for
+/// reading, and asking for write access, or for a file to be created or
+/// truncated, fails with [`ErrorKind::PermissionDenied`][1].
+///
+/// In an archive that is being written, files can only be created, with
+/// [`create_new()`][2] or [`create()`][3], and opened for writing.  Only one
+/// file can be written at a time; opening another one before the first is
+/// finished fails with [`ErrorKind::Other`][4].
+///
+/// [1]: std::io::ErrorKind::PermissionDenied
+/// [2]: AsyncFileBuilderTrait::create_new
+/// [3]: AsyncFileBuilderTrait::create
+/// [4]: std::io::ErrorKind::Other
+#[derive(Debug, Clone)]
+pub struct ZipOpenOptions {
+    fs: ZipFs,
+    flags: OpenFlags
+}
+
+impl ZipOpenOptions {
+    pub(super) fn new(fs: ZipFs) -> Self {
+        ZipOpenOptions { fs,
+                         flags: OpenFlags::default() }
+    }
+
+    async fn open_for_reading(&self,
+                              source: &Source,
+                              path: &Path)
+                              -> io::Result<ZipFile> {
+        let OpenFlags { read,
+                        write,
+                        append,
+                        truncate,
+                        create,
+                        create_new } = self.flags;
+        if write || append || truncate || create || create_new {
+            return Err(read_only());
+        }
+        if !read {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "at least one of read, write, or \
+                                       append access is required"));
+        }
+        let (metadata, record) = {
+            let contents = self.fs.contents();
+            let (_, entry) = contents.index.resolve(path, true)?;
+            match entry.kind {
+                Kind::File { data, .. } => {
+                    (entry.metadata(), contents.records[data as usize].clone())
+                }
+                Kind::Dir { .. } => return Err(is_a_directory()),
+                _ => {
+                    return Err(io::Error::new(io::ErrorKind::Other,
+                                              "not a regular file"))
+                }
+            }
+        };
+        let data = entry_data(source, &record).await?;
+        Ok(ZipFile { metadata,
+                     inner: Inner::Read(data) })
+    }
+
+    async fn create_entry(&self,
+                          output: &Arc<AsyncMutex<Output>>,
+                          path: &Path)
+                          -> io::Result<ZipFile> {
+        let OpenFlags { read,
+                        write,
+                        append,
+                        create,
+                        create_new,
+                        .. } = self.flags;
+        if read {
+            return Err(io::Error::new(io::ErrorKind::Unsupported,
+                                      "zip entries can't be read while the \
+                                       archive is being written"));
+        }
+        if !write && !append {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "at least one of read, write, or \
+                                       append access is required"));
+        }
+        let mut output = output.try_lock_arc().ok_or_else(busy)?;
+        let path =
+            match (self.fs.contents().new_entry(path), create || create_new) {
+                (Ok(path), true) => path,
+                (Ok(_), false) => return Err(not_found()),
+                (Err(err), _)
+                    if err.kind() == io::ErrorKind::AlreadyExists
+                       && !create_new =>
+                {
+                    return Err(written())
+                }
+                (Err(err), _) => return Err(err)
+            };
+        let now = SystemTime::now();
+        let record = Record::new(entry_name(&path, false),
+                                 DEFLATED,
+                                 S_IFREG | FILE_MODE,
+                                 output.end,
+                                 now);
+        let header = record.local_header();
+        let end = output.end;
+        output.writer.seek(SeekFrom::Start(end)).await?;
+        output.writer.write_all(&header).await?;
+        let metadata =
+            Metadata::new(FileType::File, 0)
+                .with_permissions(Permissions::from_mode(FILE_MODE))
+                .with_modified(now);
+        let encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        let entry = EntryWriter { fs: self.fs.clone(),
+                                  output: Some(output),
+                                  record,
+                                  header_len: header.len() as u64,
+                                  encoder,
+                                  crc: Crc::new(),
+                                  pending: Vec::new(),
+                                  end: None };
+        Ok(ZipFile { metadata,
+                     inner: Inner::Write(AsyncMutex::new(entry)) })
+    }
+}
+
+#[async_trait]
+impl AsyncFileBuilderTrait for ZipOpenOptions {
+    type File = ZipFile;
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.flags.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.flags.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.flags.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.flags.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.flags.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.flags.create_new = create_new;
+        self
+    }
+
+    async fn open<P>(&self, path: P) -> io::Result<Self::File>
+        where P: AsRef<Path> + Send
+    {
+        match (self.fs.source(), self.fs.output()) {
+            (Some(source), _) => {
+                self.open_for_reading(source, path.as_ref()).await
+            }
+            (None, Some(output)) => {
+                self.create_entry(output, path.as_ref()).await
+            }
+            (None, None) => unreachable!("archives are read or written")
+        }
+    }
+}
+
+/// A file of a [`ZipFs`] that is being written.
+struct EntryWriter {
+    fs: ZipFs,
+
+    /// The archive's output, held until the entry is finished.
+    output: Option<MutexGuardArc<Output>>,
+    record: Record,
+    header_len: u64,
+    encoder: DeflateEncoder<Vec<u8>>,
+    crc: Crc,
+
+    /// Bytes that are ready to go to the output.
+    pending: Vec<u8>,
+
+    /// Where the new central directory starts, once the entry's data and the
+    /// central directory are all pending.
+    end: Option<u64>
+}
+
+impl EntryWriter {
+    /// Writes every pending byte to the output.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let output = self.output.as_mut().ok_or_else(finished)?;
+        while !self.pending.is_empty() {
+            let n = match Pin::new(&mut output.writer).poll_write(cx,
+                                                                  &self.pending)
+            {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()))
+                }
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            };
+            self.pending.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_write(&mut self,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        if self.end.is_some() {
+            return Poll::Ready(Err(finished()));
+        }
+        match self.poll_drain(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending
+        }
+        self.encoder.write_all(buf)?;
+        self.crc.update(buf);
+        self.record.len += buf.len() as u64;
+        self.take_compressed();
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.output.is_none() {
+            return Poll::Ready(Ok(()));
+        }
+        match self.poll_drain(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other
+        }
+        let output = self.output.as_mut().expect("not finished");
+        Pin::new(&mut output.writer).poll_flush(cx)
+    }
+
+    /// Moves what the encoder has compressed so far to the pending bytes.
+    fn take_compressed(&mut self) {
+        let compressed = mem::take(self.encoder.get_mut());
+        self.record.compressed += compressed.len() as u64;
+        self.pending.extend(compressed);
+    }
+
+    /// Writes the rest of the entry and a new central directory, and then
+    /// lets other entries be written.
+    fn poll_finish(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.output.is_none() {
+            return Poll::Ready(Ok(()));
+        }
+        let end = match self.end {
+            Some(end) => end,
+            None => {
+                self.encoder.try_finish()?;
+                self.take_compressed();
+                self.record.crc = self.crc.sum();
+                let descriptor = self.record.data_descriptor()?;
+                let end = self.record.offset
+                          + self.header_len
+                          + self.record.compressed
+                          + descriptor.len() as u64;
+                let mut records = self.fs.contents().records.clone();
+                records.push(self.record.clone());
+                self.pending.extend(descriptor);
+                self.pending
+                    .extend(super::format::central_directory(&records, end)?);
+                self.end = Some(end);
+                end
+            }
+        };
+        match self.poll_flush(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other
+        }
+        let mut output = self.output.take().expect("not finished");
+        output.end = end;
+        drop(output);
+        Poll::Ready(self.fs.contents().add_file(self.record.clone()))
+    }
+}
+
+impl fmt::Debug for EntryWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryWriter")
+         .field("record", &self.record)
+         .field("finished", &self.output.is_none())
+         .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+enum Inner {
+    Read(Region),
+    Write(AsyncMutex<EntryWriter>)
+}
+
+/// A file in a [`ZipFs`], open either for reading or for writing.
+///
+/// A file open for reading implements [`AsyncRead`] and [`AsyncSeek`].  Any
+/// part of a stored file can be read without reading what comes before it,
+/// while compressed files are decompressed into memory when they are opened.
+///
+/// A file open for writing is compressed with deflate as it is written, and
+/// can't be seeked anywhere but its end.  The entry is finished, and the
+/// archive's central directory written, when the file is closed with
+/// [`close()`][1] or synced with [`sync_all()`][2]; after that, nothing more
+/// can be written to it.  A file that is dropped before it is finished is
+/// left out of the archive, but the archive isn't valid again until another
+/// entry is written.
+///
+/// [1]: futures_lite::AsyncWriteExt::close
+/// [2]: AsyncFileTrait::sync_all
+#[derive(Debug)]
+pub struct ZipFile {
+    metadata: Metadata,
+    inner: Inner
+}
+
+impl ZipFile {
+    fn refuse(&self) -> io::Error {
+        match self.inner {
+            Inner::Read(_) => read_only(),
+            Inner::Write(_) => written()
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncFileTrait for ZipFile {
+    async fn sync_all(&self) -> io::Result<()> {
+        match &self.inner {
+            Inner::Read(_) => Ok(()),
+            Inner::Write(entry) => {
+                let mut entry = entry.lock().await;
+                poll_fn(|cx| entry.poll_finish(cx)).await
+            }
+        }
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        match &self.inner {
+            Inner::Read(_) => Ok(()),
+            Inner::Write(entry) => {
+                let mut entry = entry.lock().await;
+                poll_fn(|cx| entry.poll_flush(cx)).await
+            }
+        }
+    }
+
+    async fn set_len(&self, _size: u64) -> io::Result<()> {
+        Err(self.refuse())
+    }
+
+    async fn metadata(&self) -> io::Result<Metadata> {
+        match &self.inner {
+            Inner::Read(_) => Ok(self.metadata.clone()),
+            Inner::Write(entry) => {
+                let len = entry.lock().await.record.len;
+                Ok(self.metadata.clone().with_len(len))
+            }
+        }
+    }
+
+    async fn set_permissions(&self, _perm: Permissions) -> io::Result<()> {
+        Err(self.refuse())
+    }
+}
+
+impl AsyncRead for ZipFile {
+    fn poll_read(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 buf: &mut [u8])
+                 -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().inner {
+            Inner::Read(data) => Pin::new(data).poll_read(cx, buf),
+            Inner::Write(_) => Poll::Ready(Err(not_opened_for("reading")))
+        }
+    }
+}
+
+impl AsyncWrite for ZipFile {
+    fn poll_write(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>,
+                  buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().inner {
+            Inner::Read(_) => Poll::Ready(Err(not_opened_for("writing"))),
+            Inner::Write(entry) => entry.get_mut().poll_write(cx, buf)
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        match &mut self.get_mut().inner {
+            Inner::Read(_) => Poll::Ready(Ok(())),
+            Inner::Write(entry) => entry.get_mut().poll_flush(cx)
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>,
+                  cx: &mut Context<'_>)
+                  -> Poll<io::Result<()>> {
+        match &mut self.get_mut().inner {
+            Inner::Read(_) => Poll::Ready(Ok(())),
+            Inner::Write(entry) => entry.get_mut().poll_finish(cx)
+        }
+    }
+}
+
+impl AsyncSeek for ZipFile {
+    fn poll_seek(self: Pin<&mut Self>,
+                 cx: &mut Context<'_>,
+                 pos: SeekFrom)
+                 -> Poll<io::Result<u64>> {
+        let entry = match &mut self.get_mut().inner {
+            Inner::Read(data) => return Pin::new(data).poll_seek(cx, pos),
+            Inner::Write(entry) => entry.get_mut()
+        };
+        // Only the position that the next write goes to can be reported.
+        let len = entry.record.len;
+        match pos {
+            SeekFrom::Start(pos) if pos == len => Poll::Ready(Ok(len)),
+            SeekFrom::End(0) | SeekFrom::Current(0) => Poll::Ready(Ok(len)),
+            _ => {
+                let error = io::Error::new(io::ErrorKind::Unsupported,
+                                           "zip entries are written in order");
+                Poll::Ready(Err(error))
+            }
+        }
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{
+        future::block_on, io::Cursor, AsyncReadExt, AsyncSeekExt
+    };
+
+    use super::*;
+    use crate::{
+        zip_fs::fs::tests::Buffer, AsyncDirBuilderTrait, AsyncFsTrait
+    };
+
+    async fn write(fs: &ZipFs, path: &str, data: &[u8]) {
+        let mut file = fs.open_options()
+                         .write(true)
+                         .create_new(true)
+                         .open(path)
+                         .await
+                         .unwrap();
+        file.write_all(data).await.unwrap();
+        file.close().await.unwrap();
+    }
+
+    async fn read(fs: &ZipFs, path: &str) -> Vec<u8> {
+        let mut file = fs.open_options().read(true).open(path).await.unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).await.unwrap();
+        data
+    }
+
+    #[test]
+    fn written_archives_read_back() {
+        block_on(async {
+            let buffer = Buffer::default();
+            let fs = ZipFs::create(buffer.clone()).await.unwrap();
+            let data =
+                (0..100_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            fs.dir_builder().create("/docs").await.unwrap();
+            write(&fs, "/docs/big", &data).await;
+            write(&fs, "/empty", b"").await;
+            assert_eq!(fs.metadata("/docs/big").await.unwrap().len(), 100_000);
+
+            let fs = ZipFs::new(Cursor::new(buffer.bytes())).await.unwrap();
+            assert_eq!(read(&fs, "/docs/big").await, data);
+            assert_eq!(read(&fs, "/empty").await, b"");
+            assert_eq!(fs.metadata("/docs/big").await.unwrap().len(), 100_000);
+        });
+    }
+
+    #[test]
+    fn entries_are_finished_once() {
+        block_on(async {
+            let buffer = Buffer::default();
+            let fs = ZipFs::create(buffer.clone()).await.unwrap();
+            let mut file = fs.open_options()
+                             .write(true)
+                             .create_new(true)
+                             .open("/file")
+                             .await
+                             .unwrap();
+            let err = fs.open_options()
+                        .write(true)
+                        .create_new(true)
+                        .open("/other")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.to_string(), "another zip entry is being written");
+
+            file.write_all(b"hello").await.unwrap();
+            assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 5);
+            assert!(file.seek(SeekFrom::Start(0)).await.is_err());
+            assert_eq!(file.metadata().await.unwrap().len(), 5);
+            file.sync_all().await.unwrap();
+            assert!(file.write_all(b"more").await.is_err());
+            file.close().await.unwrap();
+
+            let err = fs.open_options()
+                        .write(true)
+                        .create_new(true)
+                        .open("/file")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+            let err = fs.open_options()
+                        .write(true)
+                        .create(true)
+                        .open("/file")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+            let fs = ZipFs::from_bytes(buffer.bytes()).await.unwrap();
+            assert_eq!(read(&fs, "/file").await, b"hello");
+        });
+    }
+
+    #[test]
+    fn dropped_entries_are_left_out() {
+        block_on(async {
+            let buffer = Buffer::default();
+            let fs = ZipFs::create(buffer.clone()).await.unwrap();
+            let mut file = fs.open_options()
+                             .write(true)
+                             .create_new(true)
+                             .open("/dropped")
+                             .await
+                             .unwrap();
+            file.write_all(&[1; 10_000]).await.unwrap();
+            drop(file);
+            write(&fs, "/kept", b"kept").await;
+
+            let fs = ZipFs::from_bytes(buffer.bytes()).await.unwrap();
+            assert!(fs.metadata("/dropped").await.is_err());
+            assert_eq!(read(&fs, "/kept").await, b"kept");
+        });
+    }
+
+    #[test]
+    fn stored_entries_are_seekable() {
+        block_on(async {
+            // A stored entry, as written by `zip -0`.
+            let data = (0..=255u8).collect::<Vec<_>>();
+            let mut crc = Crc::new();
+            crc.update(&data);
+            let mut record = Record::new(b"data".to_vec(),
+                                         STORED,
+                                         S_IFREG | 0o600,
+                                         0,
+                                         SystemTime::UNIX_EPOCH);
+            record.crc = crc.sum();
+            record.compressed = 256;
+            record.len = 256;
+            let mut bytes = record.local_header();
+            bytes.extend(&data);
+            let end = bytes.len() as u64;
+            bytes.extend(super::super::format::central_directory(&[record],
+                                                                 end).unwrap());
+
+            let fs = ZipFs::new(Cursor::new(bytes)).await.unwrap();
+            let mut file =
+                fs.open_options().read(true).open("/data").await.unwrap();
+            let metadata = file.metadata().await.unwrap();
+            assert_eq!(metadata.permissions().mode(), Some(0o600));
+            file.seek(SeekFrom::Start(200)).await.unwrap();
+            let mut buf = [0; 3];
+            file.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [200, 201, 202]);
+            let err = file.write_all(b"no").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        });
+    }
+}
@@ -0,0 +1,535 @@
+//! Reading and writing the records of a zip archive.
+//!
+//! Archives are read through their central directory, including the zip64
+//! extensions for large archives.  Archives are written with a data
+//! descriptor after every file, so entries can be streamed without knowing
+//! their size up front, but without zip64, so every entry and the whole
+//! archive must stay under 4 GiB.
+
+use std::{
+    io::{self, SeekFrom},
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+use futures_lite::{AsyncReadExt, AsyncSeekExt};
+
+use crate::{
+    archive::{invalid_data, Archive},
+    FileType
+};
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+
+/// The size of the fixed part of a local header.
+pub(super) const LOCAL_HEADER_LEN: u64 = 30;
+
+/// The size of an end of central directory record without a comment.
+const END_LEN: u64 = 22;
+
+/// Extra field holding the 64-bit sizes and offset of an entry.
+const ZIP64_EXTRA: u16 = 0x0001;
+
+/// Extra field holding Unix timestamps.
+const TIMESTAMP_EXTRA: u16 = 0x5455;
+
+/// Entries whose data follows the header uncompressed.
+pub(super) const STORED: u16 = 0;
+
+/// Entries whose data is compressed with deflate.
+pub(super) const DEFLATED: u16 = 8;
+
+/// The entry is encrypted.
+pub(super) const FLAG_ENCRYPTED: u16 = 0x0001;
+
+/// The sizes and CRC follow the data in a data descriptor.
+const FLAG_DESCRIPTOR: u16 = 0x0008;
+
+/// The name is UTF-8.
+const FLAG_UTF8: u16 = 0x0800;
+
+/// The host system of "version made by" that stores Unix modes in the
+/// external attributes.
+const UNIX: u16 = 3;
+
+/// The zip version needed to extract what this module writes.
+const VERSION: u16 = 20;
+
+/// The largest size or offset that fits without zip64.
+const MAX_32: u64 = 0xffff_ffff;
+
+const S_IFMT: u32 = 0o170_000;
+pub(super) const S_IFREG: u32 = 0o100_000;
+pub(super) const S_IFDIR: u32 = 0o040_000;
+const S_IFLNK: u32 = 0o120_000;
+const S_IFCHR: u32 = 0o020_000;
+const S_IFBLK: u32 = 0o060_000;
+const S_IFIFO: u32 = 0o010_000;
+const S_IFSOCK: u32 = 0o140_000;
+
+/// The MS-DOS directory attribute.
+const DOS_DIR: u32 = 0x10;
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::Other,
+                   "zip entries and archives of 4 GiB or more can't be \
+                    written")
+}
+
+/// A little-endian reader over a byte slice.
+struct Fields<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid_data("zip record is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let low = u64::from(self.u32()?);
+        let high = u64::from(self.u32()?);
+        Ok(high << 32 | low)
+    }
+}
+
+/// Converts an MS-DOS date and time, taken to be UTC, to seconds since the
+/// Unix epoch.
+fn from_dos(date: u16, time: u16) -> u64 {
+    let year = i64::from(date >> 9) + 1980;
+    let month = i64::from((date >> 5) & 0xf).clamp(1, 12);
+    let day = i64::from(date & 0x1f).max(1);
+    // Days from civil, from Howard Hinnant's date algorithms.
+    let (y, m) = match month <= 2 {
+        true => (year - 1, month + 9),
+        false => (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let seconds = i64::from(time >> 11) * 3600
+                  + i64::from((time >> 5) & 0x3f) * 60
+                  + i64::from(time & 0x1f) * 2;
+    (days * 86_400 + seconds).max(0) as u64
+}
+
+/// Converts seconds since the Unix epoch to an MS-DOS date and time in UTC,
+/// clamped to the years that MS-DOS dates can hold.
+fn to_dos(secs: u64) -> (u16, u16) {
+    let secs = secs.clamp(315_532_800, 4_354_819_198) as i64;
+    let (days, seconds) = (secs / 86_400, secs % 86_400);
+    // Civil from days, from Howard Hinnant's date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let date = (year - 1980) << 9 | month << 5 | day;
+    let time = (seconds / 3600) << 11
+               | (seconds % 3600 / 60) << 5
+               | (seconds % 60 / 2);
+    (date as u16, time as u16)
+}
+
+/// An entry of the central directory.
+#[derive(Debug, Clone)]
+pub(super) struct Record {
+    /// The name, exactly as it is stored.
+    pub(super) name: Vec<u8>,
+    pub(super) flags: u16,
+    pub(super) method: u16,
+    pub(super) crc: u32,
+    pub(super) compressed: u64,
+    pub(super) len: u64,
+
+    /// Where the entry's local header starts.
+    pub(super) offset: u64,
+
+    /// The Unix mode, including the file type bits, if the archive has one.
+    pub(super) mode: Option<u32>,
+
+    /// Whether MS-DOS attributes mark the entry as a directory.
+    dos_dir: bool,
+
+    /// Seconds since the Unix epoch.
+    pub(super) mtime: u64
+}
+
+impl Record {
+    /// Describes a new entry that is about to be written at `offset`.
+    pub(super) fn new(name: Vec<u8>,
+                      method: u16,
+                      mode: u32,
+                      offset: u64,
+                      mtime: SystemTime)
+                      -> Self {
+        let mtime = mtime.duration_since(UNIX_EPOCH)
+                         .unwrap_or(Duration::ZERO)
+                         .as_secs();
+        Record { name,
+                 flags: FLAG_UTF8
+                        | match method {
+                            STORED => 0,
+                            _ => FLAG_DESCRIPTOR
+                        },
+                 method,
+                 crc: 0,
+                 compressed: 0,
+                 len: 0,
+                 offset,
+                 mode: Some(mode),
+                 dos_dir: false,
+                 mtime }
+    }
+
+    pub(super) fn is_dir(&self) -> bool {
+        match self.mode {
+            Some(mode) if mode & S_IFMT != 0 => mode & S_IFMT == S_IFDIR,
+            _ => self.dos_dir || self.name.ends_with(b"/")
+        }
+    }
+
+    pub(super) fn is_symlink(&self) -> bool {
+        matches!(self.mode, Some(mode) if mode & S_IFMT == S_IFLNK)
+    }
+
+    /// Returns the type of a device, FIFO, or socket, which has no data.
+    pub(super) fn special(&self) -> Option<FileType> {
+        match self.mode? & S_IFMT {
+            S_IFCHR => Some(FileType::CharDevice),
+            S_IFBLK => Some(FileType::BlockDevice),
+            S_IFIFO => Some(FileType::Fifo),
+            S_IFSOCK => Some(FileType::Socket),
+            _ => None
+        }
+    }
+
+    /// Parses one entry of the central directory, and returns the number of
+    /// bytes it took.
+    fn parse(bytes: &[u8]) -> io::Result<(Self, usize)> {
+        let mut fields = Fields { bytes };
+        if fields.u32()? != CENTRAL_HEADER {
+            return Err(invalid_data("invalid zip central directory"));
+        }
+        let made_by = fields.u16()?;
+        let _needed = fields.u16()?;
+        let flags = fields.u16()?;
+        let method = fields.u16()?;
+        let time = fields.u16()?;
+        let date = fields.u16()?;
+        let crc = fields.u32()?;
+        let mut compressed = u64::from(fields.u32()?);
+        let mut len = u64::from(fields.u32()?);
+        let name_len = usize::from(fields.u16()?);
+        let extra_len = usize::from(fields.u16()?);
+        let comment_len = usize::from(fields.u16()?);
+        let _disk = fields.u16()?;
+        let _internal = fields.u16()?;
+        let external = fields.u32()?;
+        let mut offset = u64::from(fields.u32()?);
+        let name = fields.take(name_len)?.to_vec();
+        let mut extra = Fields { bytes: fields.take(extra_len)? };
+        fields.take(comment_len)?;
+
+        let mut mtime = from_dos(date, time);
+        while extra.bytes.len() >= 4 {
+            let id = extra.u16()?;
+            let size = usize::from(extra.u16()?);
+            let mut data = Fields { bytes: extra.take(size)? };
+            match id {
+                // Only the fields that overflowed are present, in this order.
+                ZIP64_EXTRA => {
+                    for field in [&mut len, &mut compressed, &mut offset] {
+                        if *field == MAX_32 {
+                            *field = data.u64()?;
+                        }
+                    }
+                }
+                // The flags say which times follow, the mtime first.
+                TIMESTAMP_EXTRA
+                    if data.bytes.len() >= 5 && data.bytes[0] & 1 != 0 =>
+                {
+                    data.take(1)?;
+                    mtime = u64::from(data.u32()?);
+                }
+                _ => {}
+            }
+        }
+
+        let mode = match made_by >> 8 {
+            UNIX => Some(external >> 16),
+            _ => None
+        };
+        let record = Record { name,
+                              flags,
+                              method,
+                              crc,
+                              compressed,
+                              len,
+                              offset,
+                              mode,
+                              dos_dir: external & DOS_DIR != 0,
+                              mtime };
+        Ok((record, bytes.len() - fields.bytes.len()))
+    }
+
+    /// Returns the local header that starts the entry.
+    pub(super) fn local_header(&self) -> Vec<u8> {
+        let (date, time) = to_dos(self.mtime);
+        let mut header = Vec::with_capacity(30 + self.name.len());
+        header.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&self.flags.to_le_bytes());
+        header.extend_from_slice(&self.method.to_le_bytes());
+        header.extend_from_slice(&time.to_le_bytes());
+        header.extend_from_slice(&date.to_le_bytes());
+        // The CRC and sizes are in the data descriptor, or are all zero.
+        header.extend_from_slice(&[0; 12]);
+        header.extend_from_slice(&(self.name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&self.name);
+        header
+    }
+
+    /// Returns the data descriptor that follows the entry's data.
+    pub(super) fn data_descriptor(&self) -> io::Result<Vec<u8>> {
+        if self.len >= MAX_32 || self.compressed >= MAX_32 {
+            return Err(too_large());
+        }
+        let mut descriptor = Vec::with_capacity(16);
+        descriptor.extend_from_slice(&DATA_DESCRIPTOR.to_le_bytes());
+        descriptor.extend_from_slice(&self.crc.to_le_bytes());
+        descriptor.extend_from_slice(&(self.compressed as u32).to_le_bytes());
+        descriptor.extend_from_slice(&(self.len as u32).to_le_bytes());
+        Ok(descriptor)
+    }
+
+    fn write_central(&self, out: &mut Vec<u8>) -> io::Result<()> {
+        if self.len >= MAX_32
+           || self.compressed >= MAX_32
+           || self.offset >= MAX_32
+        {
+            return Err(too_large());
+        }
+        let (date, time) = to_dos(self.mtime);
+        let mode = self.mode.unwrap_or(S_IFREG | 0o644);
+        let external = mode << 16
+                       | match mode & S_IFMT == S_IFDIR {
+                           true => DOS_DIR,
+                           false => 0
+                       };
+        out.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        out.extend_from_slice(&(UNIX << 8 | VERSION).to_le_bytes());
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.flags.to_le_bytes());
+        out.extend_from_slice(&self.method.to_le_bytes());
+        out.extend_from_slice(&time.to_le_bytes());
+        out.extend_from_slice(&date.to_le_bytes());
+        out.extend_from_slice(&self.crc.to_le_bytes());
+        out.extend_from_slice(&(self.compressed as u32).to_le_bytes());
+        out.extend_from_slice(&(self.len as u32).to_le_bytes());
+        out.extend_from_slice(&(self.name.len() as u16).to_le_bytes());
+        // The extended timestamp keeps the full resolution of the mtime.
+        out.extend_from_slice(&9u16.to_le_bytes());
+        out.extend_from_slice(&[0; 6]);
+        out.extend_from_slice(&external.to_le_bytes());
+        out.extend_from_slice(&(self.offset as u32).to_le_bytes());
+        out.extend_from_slice(&self.name);
+        out.extend_from_slice(&TIMESTAMP_EXTRA.to_le_bytes());
+        out.extend_from_slice(&5u16.to_le_bytes());
+        out.push(1);
+        out.extend_from_slice(&(self.mtime.min(MAX_32) as u32).to_le_bytes());
+        Ok(())
+    }
+}
+
+/// Returns the central directory for `records`, followed by the end of
+/// central directory record, to be written at `offset`.
+pub(super) fn central_directory(records: &[Record],
+                                offset: u64)
+                                -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for record in records {
+        record.write_central(&mut out)?;
+    }
+    let count = u16::try_from(records.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::Other,
+                                   "zip archives of more than 65535 entries \
+                                    can't be written")
+                })?;
+    if offset + out.len() as u64 >= MAX_32 {
+        return Err(too_large());
+    }
+    let size = out.len() as u32;
+    out.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(&(offset as u32).to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    Ok(out)
+}
+
+/// Reads the central directory of the archive in `reader`.
+pub(super) async fn read_central_directory<R>(reader: &mut R)
+                                              -> io::Result<Vec<Record>>
+    where R: Archive
+{
+    let end = reader.seek(SeekFrom::End(0)).await?;
+    let tail_len = end.min(END_LEN + u64::from(u16::MAX));
+    let mut tail = vec![0; tail_len as usize];
+    reader.seek(SeekFrom::Start(end - tail_len)).await?;
+    reader.read_exact(&mut tail).await?;
+    let signature = END_OF_CENTRAL_DIRECTORY.to_le_bytes();
+    let at = (0..=tail.len().saturating_sub(END_LEN as usize))
+                .rev()
+                .find(|&i| tail[i..].starts_with(&signature))
+                .ok_or_else(|| invalid_data("not a zip archive"))?;
+    let mut fields = Fields { bytes: &tail[at + 4..] };
+    fields.take(6)?;
+    let mut count = u64::from(fields.u16()?);
+    let mut size = u64::from(fields.u32()?);
+    let mut offset = u64::from(fields.u32()?);
+
+    // A zip64 locator sits right before the end of central directory record.
+    let position = end - tail_len + at as u64;
+    if position >= 20 && at >= 20 {
+        let mut locator = Fields { bytes: &tail[at - 20..at] };
+        if locator.u32()? == ZIP64_LOCATOR {
+            locator.u32()?;
+            let mut record = [0; 56];
+            reader.seek(SeekFrom::Start(locator.u64()?)).await?;
+            reader.read_exact(&mut record).await?;
+            let mut fields = Fields { bytes: &record };
+            if fields.u32()? != ZIP64_END_OF_CENTRAL_DIRECTORY {
+                return Err(invalid_data("invalid zip64 end of central \
+                                         directory"));
+            }
+            fields.take(20)?;
+            fields.u64()?;
+            count = fields.u64()?;
+            size = fields.u64()?;
+            offset = fields.u64()?;
+        }
+    }
+
+    let size = usize::try_from(size).map_err(|_| {
+                   invalid_data("zip central directory is too large")
+               })?;
+    let mut directory = vec![0; size];
+    reader.seek(SeekFrom::Start(offset)).await?;
+    reader.read_exact(&mut directory).await?;
+    let mut records = Vec::new();
+    let mut rest = directory.as_slice();
+    for _ in 0..count {
+        let (record, len) = Record::parse(rest)?;
+        records.push(record);
+        rest = &rest[len..];
+    }
+    Ok(records)
+}
+
+/// Reads the length of the local header at the start of `header`, which must
+/// hold at least [`LOCAL_HEADER_LEN`] bytes.
+pub(super) fn local_header_len(header: &[u8]) -> io::Result<u64> {
+    let mut fields = Fields { bytes: header };
+    if fields.u32()? != LOCAL_HEADER {
+        return Err(invalid_data("invalid zip local header"));
+    }
+    fields.take(22)?;
+    let name_len = u64::from(fields.u16()?);
+    let extra_len = u64::from(fields.u16()?);
+    Ok(LOCAL_HEADER_LEN + name_len + extra_len)
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, io::Cursor};
+
+    use super::*;
+
+    #[test]
+    fn dos_times_round_trip() {
+        // 2021-03-04 05:06:08 UTC.
+        let secs = 1_614_834_368;
+        let (date, time) = to_dos(secs);
+        assert_eq!(date, (41 << 9) | (3 << 5) | 4);
+        assert_eq!(time, (5 << 11) | (6 << 5) | 4);
+        assert_eq!(from_dos(date, time), secs);
+        assert_eq!(from_dos(0x21, 0), 315_532_800);
+    }
+
+    #[test]
+    fn central_directories_round_trip() {
+        let mut file = Record::new(b"dir/file".to_vec(),
+                                   DEFLATED,
+                                   S_IFREG | 0o600,
+                                   0,
+                                   UNIX_EPOCH + Duration::from_secs(1_000));
+        file.crc = 0x1234_5678;
+        file.compressed = 10;
+        file.len = 20;
+        let dir = Record::new(b"dir/".to_vec(),
+                              STORED,
+                              S_IFDIR | 0o755,
+                              100,
+                              UNIX_EPOCH);
+        let mut bytes = vec![0; 200];
+        bytes.extend(central_directory(&[file, dir], 200).unwrap());
+
+        let records =
+            block_on(read_central_directory(&mut Cursor::new(bytes))).unwrap();
+        assert_eq!(records.len(), 2);
+        let (file, dir) = (&records[0], &records[1]);
+        assert_eq!(file.name, b"dir/file");
+        assert_eq!((file.crc, file.compressed, file.len),
+                   (0x1234_5678, 10, 20));
+        assert_eq!((file.method, file.mode), (DEFLATED, Some(S_IFREG | 0o600)));
+        assert_eq!(file.mtime, 1_000);
+        assert!(!file.is_dir() && !file.is_symlink());
+        assert_eq!(dir.offset, 100);
+        assert!(dir.is_dir());
+    }
+
+    #[test]
+    fn garbage_is_not_an_archive() {
+        let mut garbage = Cursor::new(vec![7; 100]);
+        let err = block_on(read_central_directory(&mut garbage)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,600 @@
+//! [`ZipFs`] serves the entries of a zip archive, or writes a new one.
+
+use std::{
+    fmt,
+    io::{self, SeekFrom},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::SystemTime
+};
+
+use async_lock::Mutex as AsyncMutex;
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_lite::{io::Cursor, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::{
+    file::entry_data,
+    format::{
+        central_directory, read_central_directory, Record, STORED, S_IFDIR
+    },
+    ZipDirBuilder, ZipDirEntry, ZipOpenOptions, ZipReadDir
+};
+use crate::{
+    archive::{
+        already_exists, archive_path, not_a_directory, os_bytes, os_string,
+        Attributes, Index, Kind, Source
+    },
+    AsyncFsTrait, Metadata, Permissions
+};
+
+/// The error returned for every operation that would change an archive that
+/// was opened for reading.
+pub(super) fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "zip archive is read-only")
+}
+
+/// The error returned for every operation that would change an entry that is
+/// already in an archive being written.
+pub(super) fn written() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied,
+                   "zip entries can't be changed once written")
+}
+
+pub(super) fn busy() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "another zip entry is being written")
+}
+
+/// Returns the name that `path`, an absolute path without symlinks, is stored
+/// under in an archive.
+pub(super) fn entry_name(path: &Path, dir: bool) -> Vec<u8> {
+    let mut name = Vec::new();
+    for component in path.components() {
+        if let Component::Normal(component) = component {
+            if !name.is_empty() {
+                name.push(b'/');
+            }
+            name.extend(os_bytes(component));
+        }
+    }
+    if dir {
+        name.push(b'/');
+    }
+    name
+}
+
+/// A seekable writer of an archive.
+pub(super) trait Sink: AsyncWrite + AsyncSeek + Send + Unpin {}
+
+impl<T> Sink for T where T: AsyncWrite + AsyncSeek + Send + Unpin {}
+
+/// Where a new archive is being written.
+pub(super) struct Output {
+    pub(super) writer: Box<dyn Sink>,
+
+    /// Where the central directory starts, and so where the next entry goes.
+    pub(super) end: u64
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Output")
+         .field("end", &self.end)
+         .finish_non_exhaustive()
+    }
+}
+
+/// The entries of an archive.
+#[derive(Debug)]
+pub(super) struct Contents {
+    pub(super) index: Index,
+
+    /// The central directory.  The data of a regular file in the index is the
+    /// number of its record.
+    pub(super) records: Vec<Record>
+}
+
+impl Contents {
+    /// Adds `record` to the central directory, and to the index as `kind`.
+    pub(super) fn add(&mut self, record: Record, kind: Kind) -> io::Result<()> {
+        let default = match record.is_dir() {
+            true => 0o755,
+            false => 0o644
+        };
+        let mode = record.mode
+                         .map(|mode| mode & 0o7777)
+                         .filter(|&mode| mode != 0)
+                         .unwrap_or(default);
+        let attributes = Attributes { mode,
+                                      uid: None,
+                                      gid: None,
+                                      mtime: Some(record.mtime) };
+        self.index
+            .insert(archive_path(&record.name), kind, attributes)?;
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Adds `record`, which describes a regular file.
+    pub(super) fn add_file(&mut self, record: Record) -> io::Result<()> {
+        let kind = Kind::File { data: self.records.len() as u64,
+                                len: record.len };
+        self.add(record, kind)
+    }
+
+    /// Checks that nothing is at `path` yet, but that its parent is a
+    /// directory, and returns the path without symlinks.
+    pub(super) fn new_entry(&self, path: &Path) -> io::Result<PathBuf> {
+        // Only the root and `..` have no name, and both exist.
+        let name = path.file_name().ok_or_else(already_exists)?;
+        let parent = path.parent().unwrap_or_else(|| Path::new("/"));
+        let (parent, entry) = self.index.resolve(parent, true)?;
+        if !matches!(entry.kind, Kind::Dir { .. }) {
+            return Err(not_a_directory());
+        }
+        let path = parent.join(name);
+        match self.index.resolve(&path, false) {
+            Ok(_) => Err(already_exists()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(path),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Returns the directories that must be created for `path` to be one.
+    fn new_dirs(&self,
+                path: &Path,
+                recursive: bool)
+                -> io::Result<Vec<PathBuf>> {
+        if !recursive {
+            return Ok(vec![self.new_entry(path)?]);
+        }
+        // Archives that are being written never hold symlinks, so the path
+        // can be resolved lexically.
+        let path = archive_path(&os_bytes(path.as_os_str()));
+        let mut missing = Vec::new();
+        for dir in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            match self.index.resolve(dir, true) {
+                Ok((_, entry)) if matches!(entry.kind, Kind::Dir { .. }) => {}
+                Ok(_) if dir == path => return Err(already_exists()),
+                Ok(_) => return Err(not_a_directory()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    missing.push(dir.to_owned())
+                }
+                Err(err) => return Err(err)
+            }
+        }
+        Ok(missing)
+    }
+}
+
+#[derive(Debug)]
+enum Mode {
+    /// An existing archive that is being read.
+    Read(Source),
+
+    /// A new archive that is being written.
+    Write(Arc<AsyncMutex<Output>>)
+}
+
+#[derive(Debug)]
+struct Shared {
+    contents: Mutex<Contents>,
+    mode: Mode
+}
+
+/// A filesystem that serves the entries of a zip archive, or writes a new
+/// one.
+///
+/// An archive is either opened for reading, with [`new()`][1] or
+/// [`from_bytes()`][2], or created with [`create()`][3].  Either way,
+/// metadata and directory listings are answered from the archive's central
+/// directory, which is read once when an archive is opened, and kept up to
+/// date as a new one is written.
+///
+/// Cloning a `ZipFs` is cheap, and the clones share the archive.
+///
+/// [1]: ZipFs::new
+/// [2]: ZipFs::from_bytes
+/// [3]: ZipFs::create
+#[derive(Debug, Clone)]
+pub struct ZipFs {
+    shared: Arc<Shared>
+}
+
+impl ZipFs {
+    /// Opens the archive in `reader` for reading.
+    ///
+    /// Stored files are read by seeking `reader` to their data, so the whole
+    /// archive never has to be held in memory.  Reads from different files
+    /// take turns using `reader`.
+    pub async fn new<R>(mut reader: R) -> io::Result<Self>
+        where R: AsyncRead + AsyncSeek + Send + Unpin + 'static
+    {
+        let records = read_central_directory(&mut reader).await?;
+        ZipFs::open(records, Source::reader(reader)).await
+    }
+
+    /// Opens an archive that is already in memory for reading.
+    pub async fn from_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        let mut cursor = Cursor::new(bytes);
+        let records = read_central_directory(&mut cursor).await?;
+        let source = Source::Memory(Arc::new(cursor.into_inner()));
+        ZipFs::open(records, source).await
+    }
+
+    async fn open(records: Vec<Record>, source: Source) -> io::Result<Self> {
+        let mut contents = Contents { index: Index::new(),
+                                      records: Vec::new() };
+        for record in records {
+            if record.is_dir() {
+                contents.add(record, Kind::dir())?;
+            } else if record.is_symlink() {
+                let mut target = Vec::new();
+                entry_data(&source, &record).await?
+                                            .read_to_end(&mut target)
+                                            .await?;
+                let target = PathBuf::from(os_string(target));
+                contents.add(record, Kind::Symlink(target))?;
+            } else if let Some(file_type) = record.special() {
+                contents.add(record, Kind::Special(file_type))?;
+            } else {
+                contents.add_file(record)?;
+            }
+        }
+        Ok(ZipFs::with_mode(contents, Mode::Read(source)))
+    }
+
+    /// Starts a new, empty archive at the current position of `writer`.
+    ///
+    /// Files are added by opening them with
+    /// [`create_new()`][1], and directories with [`dir_builder()`][2].
+    /// Entries are written one at a time, and the central directory is
+    /// written again after each one, so that `writer` always holds a
+    /// complete archive between entries.
+    ///
+    /// [1]: crate::AsyncFileBuilderTrait::create_new
+    /// [2]: AsyncFsTrait::dir_builder
+    pub async fn create<W>(mut writer: W) -> io::Result<Self>
+        where W: AsyncWrite + AsyncSeek + Send + Unpin + 'static
+    {
+        let end = writer.seek(SeekFrom::Current(0)).await?;
+        writer.write_all(&central_directory(&[], end)?).await?;
+        writer.flush().await?;
+        let contents = Contents { index: Index::new(),
+                                  records: Vec::new() };
+        let output = Output { writer: Box::new(writer),
+                              end };
+        let mode = Mode::Write(Arc::new(AsyncMutex::new(output)));
+        Ok(ZipFs::with_mode(contents, mode))
+    }
+
+    fn with_mode(contents: Contents, mode: Mode) -> Self {
+        ZipFs { shared: Arc::new(Shared { contents: Mutex::new(contents),
+                                          mode }) }
+    }
+
+    pub(super) fn contents(&self) -> MutexGuard<'_, Contents> {
+        self.shared
+            .contents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the archive's bytes, if it was opened for reading.
+    pub(super) fn source(&self) -> Option<&Source> {
+        match &self.shared.mode {
+            Mode::Read(source) => Some(source),
+            Mode::Write(_) => None
+        }
+    }
+
+    /// Returns where the archive is written, if it is being written.
+    pub(super) fn output(&self) -> Option<&Arc<AsyncMutex<Output>>> {
+        match &self.shared.mode {
+            Mode::Read(_) => None,
+            Mode::Write(output) => Some(output)
+        }
+    }
+
+    /// Returns the error for an operation that would change an entry.
+    pub(super) fn refuse(&self) -> io::Error {
+        match self.shared.mode {
+            Mode::Read(_) => read_only(),
+            Mode::Write(_) => written()
+        }
+    }
+
+    /// Writes a directory entry for `path`, and for every directory above it
+    /// that is missing if `recursive` is set.
+    pub(super) async fn create_dir(&self,
+                                   path: &Path,
+                                   recursive: bool)
+                                   -> io::Result<()> {
+        let output = self.output().ok_or_else(read_only)?;
+        let mut output = output.try_lock_arc().ok_or_else(busy)?;
+        let (dirs, mut records) = {
+            let contents = self.contents();
+            (contents.new_dirs(path, recursive)?, contents.records.clone())
+        };
+        if dirs.is_empty() {
+            return Ok(());
+        }
+        let now = SystemTime::now();
+        let count = records.len();
+        let mut end = output.end;
+        let mut bytes = Vec::new();
+        for dir in dirs {
+            let record = Record::new(entry_name(&dir, true),
+                                     STORED,
+                                     S_IFDIR | 0o755,
+                                     end,
+                                     now);
+            let header = record.local_header();
+            end += header.len() as u64;
+            bytes.extend(header);
+            records.push(record);
+        }
+        bytes.extend(central_directory(&records, end)?);
+
+        let start = output.end;
+        output.writer.seek(SeekFrom::Start(start)).await?;
+        output.writer.write_all(&bytes).await?;
+        output.writer.flush().await?;
+        output.end = end;
+        let mut contents = self.contents();
+        for record in records.drain(count..) {
+            contents.add(record, Kind::dir())?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AsyncFsTrait for ZipFs {
+    type DirBuilder = ZipDirBuilder;
+    type FileBuilder = ZipOpenOptions;
+    type DirEntry = ZipDirEntry;
+    type ReadDir = ZipReadDir;
+
+    fn dir_builder(&self) -> Self::DirBuilder {
+        ZipDirBuilder::new(self.clone())
+    }
+
+    fn open_options(&self) -> Self::FileBuilder {
+        ZipOpenOptions::new(self.clone())
+    }
+
+    async fn canonicalize<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.contents().index.resolve(path.as_ref(), true)?.0)
+    }
+
+    async fn rename<P, Q>(&self, _src: P, _dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(self.refuse())
+    }
+
+    async fn set_permissions<P>(&self,
+                                _path: P,
+                                _perm: Permissions)
+                                -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(self.refuse())
+    }
+
+    async fn hard_link<P, Q>(&self, _src: P, _dst: Q) -> io::Result<()>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(self.refuse())
+    }
+
+    async fn read_link<P>(&self, path: P) -> io::Result<PathBuf>
+        where P: AsRef<Path> + Send
+    {
+        match &self.contents().index.resolve(path.as_ref(), false)?.1.kind {
+            Kind::Symlink(target) => Ok(target.clone()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    "not a symbolic link"))
+        }
+    }
+
+    async fn symlink_metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.contents()
+               .index
+               .resolve(path.as_ref(), false)?
+               .1
+               .metadata())
+    }
+
+    async fn metadata<P>(&self, path: P) -> io::Result<Metadata>
+        where P: AsRef<Path> + Send
+    {
+        Ok(self.contents()
+               .index
+               .resolve(path.as_ref(), true)?
+               .1
+               .metadata())
+    }
+
+    async fn copy<P, Q>(&self, _src: P, _dst: Q) -> io::Result<u64>
+        where P: AsRef<Path> + Send,
+              Q: AsRef<Path> + Send
+    {
+        Err(self.refuse())
+    }
+
+    async fn remove_file<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(self.refuse())
+    }
+
+    async fn read_dir<P>(&self, path: P) -> io::Result<Self::ReadDir>
+        where P: AsRef<Path> + Send
+    {
+        let path = path.as_ref();
+        let contents = self.contents();
+        let (dir, _) = contents.index.resolve(path, true)?;
+        let entries = contents.index
+                              .children(&dir)?
+                              .map(|(name, entry)| {
+                                  ZipDirEntry::new(path.join(name),
+                                                   name.clone(),
+                                                   entry.metadata())
+                              })
+                              .collect();
+        Ok(ZipReadDir::new(entries))
+    }
+
+    async fn remove_dir<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(self.refuse())
+    }
+
+    async fn remove_dir_all<P>(&self, _path: P) -> io::Result<()>
+        where P: AsRef<Path> + Send
+    {
+        Err(self.refuse())
+    }
+}
+
+//  ▄▄▄▄▄▄▄▄  ▄▄▄▄▄▄▄▄    ▄▄▄▄    ▄▄▄▄▄▄▄▄    ▄▄▄▄
+//  ▀▀▀██▀▀▀  ██▀▀▀▀▀▀  ▄█▀▀▀▀█   ▀▀▀██▀▀▀  ▄█▀▀▀▀█
+//     ██     ██        ██▄          ██     ██▄
+//     ██     ███████    ▀████▄      ██      ▀████▄
+//     ██     ██             ▀██     ██          ▀██
+//     ██     ██▄▄▄▄▄▄  █▄▄▄▄▄█▀     ██     █▄▄▄▄▄█▀
+//     ▀▀     ▀▀▀▀▀▀▀▀   ▀▀▀▀▀       ▀▀      ▀▀▀▀▀
+//
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll}
+    };
+
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{AsyncDirBuilderTrait, AsyncFileBuilderTrait};
+
+    /// An in-memory archive that can be read back while it is being written.
+    #[derive(Debug, Clone, Default)]
+    pub(in super::super) struct Buffer {
+        cursor: Arc<Mutex<Cursor<Vec<u8>>>>
+    }
+
+    impl Buffer {
+        pub(in super::super) fn bytes(&self) -> Vec<u8> {
+            self.cursor.lock().unwrap().get_ref().clone()
+        }
+    }
+
+    impl AsyncWrite for Buffer {
+        fn poll_write(self: Pin<&mut Self>,
+                      cx: &mut Context<'_>,
+                      buf: &[u8])
+                      -> Poll<io::Result<usize>> {
+            Pin::new(&mut *self.cursor.lock().unwrap()).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>,
+                      _cx: &mut Context<'_>)
+                      -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>,
+                      _cx: &mut Context<'_>)
+                      -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncSeek for Buffer {
+        fn poll_seek(self: Pin<&mut Self>,
+                     cx: &mut Context<'_>,
+                     pos: SeekFrom)
+                     -> Poll<io::Result<u64>> {
+            Pin::new(&mut *self.cursor.lock().unwrap()).poll_seek(cx, pos)
+        }
+    }
+
+    #[test]
+    fn entries_are_named_relative_to_the_root() {
+        assert_eq!(entry_name(Path::new("/a/b"), false), b"a/b");
+        assert_eq!(entry_name(Path::new("/a"), true), b"a/");
+    }
+
+    #[test]
+    fn directories_are_written_with_their_parents() {
+        block_on(async {
+            let buffer = Buffer::default();
+            let fs = ZipFs::create(buffer.clone()).await.unwrap();
+            fs.dir_builder()
+              .recursive(true)
+              .create("/a/b/c")
+              .await
+              .unwrap();
+            fs.dir_builder()
+              .recursive(true)
+              .create("a/b")
+              .await
+              .unwrap();
+            fs.dir_builder().create("/a/d").await.unwrap();
+            let err = fs.dir_builder().create("/a/d").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+            let err = fs.dir_builder().create("/x/y").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(fs.metadata("/a/b/c").await.unwrap().is_dir());
+
+            let fs = ZipFs::from_bytes(buffer.bytes()).await.unwrap();
+            let names = fs.contents()
+                          .records
+                          .iter()
+                          .map(|record| record.name.clone())
+                          .collect::<Vec<_>>();
+            let expected = [&b"a/"[..], b"a/b/", b"a/b/c/", b"a/d/"];
+            assert_eq!(names, expected.map(Vec::from));
+            let metadata = fs.metadata("/a/b").await.unwrap();
+            assert!(metadata.is_dir());
+            assert_eq!(metadata.permissions().mode(), Some(0o755));
+        });
+    }
+
+    #[test]
+    fn changes_are_refused() {
+        block_on(async {
+            let buffer = Buffer::default();
+            let fs = ZipFs::create(buffer.clone()).await.unwrap();
+            fs.dir_builder().create("/dir").await.unwrap();
+            let denied = |result: io::Result<()>, message: &str| {
+                assert_eq!(result.unwrap_err().to_string(), message)
+            };
+            let written = "zip entries can't be changed once written";
+            denied(fs.rename("/dir", "/moved").await, written);
+            denied(fs.remove_dir("/dir").await, written);
+
+            let fs = ZipFs::from_bytes(buffer.bytes()).await.unwrap();
+            let read_only = "zip archive is read-only";
+            denied(fs.remove_dir("/dir").await, read_only);
+            denied(fs.dir_builder().create("/new").await, read_only);
+            let err = fs.open_options()
+                        .write(true)
+                        .create_new(true)
+                        .open("/new")
+                        .await
+                        .unwrap_err();
+            assert_eq!(err.to_string(), read_only);
+        });
+    }
+}
@@ -0,0 +1,80 @@
+//! A backend that reads zip archives, and writes new ones.
+//!
+//! [`ZipFs`] implements [`AsyncFsTrait`][1] on top of an archive.  An
+//! existing archive is opened for reading: its central directory is read once
+//! into an index of every entry, so metadata and directory listings never
+//! touch the archive again.  Stored files are read by seeking to their data,
+//! so any part of them can be read without reading what comes before it,
+//! while deflated files are decompressed into memory when they are opened,
+//! and checked against their CRC.  Symlinks stored with Unix modes are
+//! followed just like on a real filesystem, and zip64 archives are
+//! understood.
+//!
+//! A new archive is written by creating files with
+//! [`create_new()`][2] and writing to them.  Each file is compressed with
+//! deflate as it is written, straight to the archive, so it never has to be
+//! held in memory.  The file is finished, and the archive's central directory
+//! written after it, when the file is closed or synced with
+//! [`sync_all()`][3].  Files are written one at a time, and each entry can't
+//! be changed once it is written.  Archives are written without zip64, so
+//! they must stay under 4 GiB and 65535 entries.
+//!
+//! Everything else in this module is reached through [`ZipFs`]:
+//!
+//! - [`ZipOpenOptions`] is returned by [`ZipFs::open_options()`][4], and opens
+//!   [`ZipFile`]s.
+//! - [`ZipDirBuilder`] is returned by [`ZipFs::dir_builder()`][5].
+//! - [`ZipReadDir`] is returned by [`ZipFs::read_dir()`][6], and yields
+//!   [`ZipDirEntry`]s.
+//!
+//! Every other operation that would change the archive fails with
+//! [`ErrorKind::PermissionDenied`][7].
+//!
+//! ```no_run
+//! # #[cfg(feature = "std-fs")]
+//! # {
+//! use async_fs_traits::{
+//!     std_fs::StdFs, zip_fs::ZipFs, AsyncFileBuilderTrait, AsyncFsTrait
+//! };
+//! use futures_lite::{future::block_on, AsyncWriteExt};
+//!
+//! block_on(async {
+//!     let archive = StdFs::new().open_options()
+//!                               .write(true)
+//!                               .create_new(true)
+//!                               .open("report.zip")
+//!                               .await?;
+//!     let zip = ZipFs::create(archive).await?;
+//!     let mut summary = zip.open_options()
+//!                          .write(true)
+//!                          .create_new(true)
+//!                          .open("/summary.txt")
+//!                          .await?;
+//!     summary.write_all(b"all tests passed").await?;
+//!     summary.close().await?;
+//!     std::io::Result::Ok(())
+//! });
+//! # }
+//! ```
+//!
+//! This module is only available when the `zip` feature is enabled.
+//!
+//! [1]: crate::AsyncFsTrait
+//! [2]: crate::AsyncFileBuilderTrait::create_new
+//! [3]: crate::AsyncFileTrait::sync_all
+//! [4]: crate::AsyncFsTrait::open_options
+//! [5]: crate::AsyncFsTrait::dir_builder
+//! [6]: crate::AsyncFsTrait::read_dir
+//! [7]: std::io::ErrorKind::PermissionDenied
+
+mod dir;
+mod file;
+mod format;
+mod fs;
+
+#[doc(inline)]
+pub use dir::{ZipDirBuilder, ZipDirEntry, ZipReadDir};
+#[doc(inline)]
+pub use file::{ZipFile, ZipOpenOptions};
+#[doc(inline)]
+pub use fs::ZipFs;